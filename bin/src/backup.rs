@@ -0,0 +1,129 @@
+use {
+    crate::prompt::confirm,
+    anyhow::bail,
+    clap::Subcommand,
+    colored::Colorize,
+    grug_db_disk::{BackupDir, BackupInfo, DiskDb},
+    std::{
+        path::PathBuf,
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// How long to wait for a running node to fulfill a backup request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often to check whether a running node has fulfilled a backup request.
+const REQUEST_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Subcommand)]
+pub enum BackupCmd {
+    /// Back up the database at its latest height. If the node is running with
+    /// automatic backups enabled, it takes the backup after committing one of
+    /// its next 10 blocks
+    Create,
+    /// List backups
+    #[command(alias = "ls")]
+    List,
+    /// Restore the database from a backup, replacing the current one. The node
+    /// must be stopped
+    Restore {
+        /// Block height of the backup to restore
+        height: u64,
+        /// Skip confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+impl BackupCmd {
+    pub fn run(self, data_dir: PathBuf, backups_dir: PathBuf) -> anyhow::Result<()> {
+        let backups = BackupDir::new(backups_dir);
+        match self {
+            BackupCmd::Create => create(&backups, data_dir),
+            BackupCmd::List => list(&backups),
+            BackupCmd::Restore { height, yes } => restore(&backups, data_dir, height, yes),
+        }
+    }
+}
+
+fn create(backups: &BackupDir, data_dir: PathBuf) -> anyhow::Result<()> {
+    let info = match DiskDb::open(data_dir) {
+        Ok(db) => backups.create(&db)?,
+        // RocksDB doesn't allow opening the database while the node is using
+        // it. in that case, ask the node to take the backup.
+        Err(err) if err.is_locked() => {
+            println!("Database is in use, requesting a backup from the running node");
+            request(backups)?
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    println!(
+        "Backed up height {} with root hash {:?}",
+        info.version, info.root_hash
+    );
+
+    Ok(())
+}
+
+fn request(backups: &BackupDir) -> anyhow::Result<BackupInfo> {
+    // the node backs up the version it has just committed, which is newer than
+    // any existing backup
+    let latest = backups.list()?.last().map(|info| info.version);
+    backups.request()?;
+
+    let start = Instant::now();
+    while start.elapsed() < REQUEST_TIMEOUT {
+        thread::sleep(REQUEST_POLL_INTERVAL);
+        if let Some(info) = backups.list()?.pop() {
+            if latest.map_or(true, |latest| info.version > latest) {
+                return Ok(info);
+            }
+        }
+    }
+
+    // withdraw the request, so that it isn't fulfilled unexpectedly later
+    backups.take_request();
+
+    bail!(
+        "the node didn't take a backup within {REQUEST_TIMEOUT:?}; is it producing blocks, with \
+         automatic backups enabled?"
+    )
+}
+
+fn list(backups: &BackupDir) -> anyhow::Result<()> {
+    let infos = backups.list()?;
+    if infos.is_empty() {
+        println!("No backup found");
+        return Ok(());
+    }
+
+    for info in infos {
+        println!("{:>10}  {:?}", info.version, info.root_hash);
+    }
+
+    Ok(())
+}
+
+fn restore(backups: &BackupDir, data_dir: PathBuf, height: u64, yes: bool) -> anyhow::Result<()> {
+    let info = backups.info(height)?;
+
+    println!("Root hash at height {height}: {:?}", info.root_hash);
+
+    if !yes {
+        let prompt = format!("Confirm replacing data directory {data_dir:?} with the backup?");
+        if !confirm(prompt.bold())? {
+            println!("🤷 User aborted");
+            return Ok(());
+        }
+    }
+
+    // the backup's root hash is checked before the data directory is replaced
+    backups.restore(height, data_dir)?;
+
+    println!("Restored backup of height {height}");
+
+    Ok(())
+}
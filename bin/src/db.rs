@@ -0,0 +1,111 @@
+use {
+    crate::prompt::confirm,
+    anyhow::{bail, ensure},
+    clap::Subcommand,
+    colored::Colorize,
+    grug_app::Db,
+    grug_db_disk::{DiskDb, Mismatch},
+    std::path::PathBuf,
+};
+
+#[derive(Subcommand)]
+pub enum DbCmd {
+    /// Check that the Merkle tree agrees with the raw key-value pairs
+    Verify {
+        /// Block height to check at [default: latest]
+        #[arg(long)]
+        height: Option<u64>,
+        /// If the check fails, rebuild the Merkle tree from the raw key-value
+        /// pairs. Merkle trees of older heights are discarded
+        #[arg(long)]
+        repair: bool,
+        /// Skip confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+impl DbCmd {
+    pub fn run(self, data_dir: PathBuf) -> anyhow::Result<()> {
+        match self {
+            DbCmd::Verify {
+                height,
+                repair,
+                yes,
+            } => verify(data_dir, height, repair, yes),
+        }
+    }
+}
+
+fn verify(data_dir: PathBuf, height: Option<u64>, repair: bool, yes: bool) -> anyhow::Result<()> {
+    let db = DiskDb::open(data_dir)?;
+
+    // the Merkle tree can only be rebuilt at the latest height
+    if let (true, Some(height)) = (repair, height) {
+        ensure!(
+            db.latest_version() == Some(height),
+            "can only repair at the latest height {:?}",
+            db.latest_version()
+        );
+    }
+
+    let report = db.verify(height)?;
+
+    println!("Height             : {}", report.version);
+    println!("Stored root hash   : {:?}", report.stored_root_hash);
+    println!("Computed root hash : {:?}", report.computed_root_hash);
+
+    for mismatch in &report.mismatches {
+        match mismatch {
+            Mismatch::MissingLeaf { key } => {
+                println!("Missing leaf     : key {}", hex::encode(key));
+            },
+            Mismatch::WrongValueHash {
+                key,
+                expected,
+                found,
+            } => {
+                println!(
+                    "Wrong value hash : key {}, expected {expected}, found {found}",
+                    hex::encode(key)
+                );
+            },
+            Mismatch::ExtraLeaf { key_hash } => {
+                println!("Extra leaf       : key hash {key_hash}");
+            },
+        }
+    }
+
+    if report.is_ok() {
+        println!("Database is consistent");
+        return Ok(());
+    }
+
+    // exit with an error, so that scripts running this can tell the database
+    // needs repairing
+    if !repair {
+        bail!(
+            "state commitment is inconsistent with state storage ({} mismatches); rerun with --repair",
+            report.mismatches.len()
+        );
+    }
+
+    println!(
+        "Database is inconsistent, found {} mismatched keys",
+        report.mismatches.len()
+    );
+
+    if !yes {
+        let prompt = "Confirm rebuilding the Merkle tree? Trees of older heights will be discarded";
+        if !confirm(prompt.bold())? {
+            println!("🤷 User aborted");
+            return Ok(());
+        }
+    }
+
+    let root_hash = db.repair()?;
+
+    println!("Rebuilt Merkle tree with root hash {root_hash:?}");
+
+    Ok(())
+}
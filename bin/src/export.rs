@@ -0,0 +1,41 @@
+use {
+    clap::Parser,
+    grug_app::App,
+    grug_db_disk::DiskDb,
+    grug_vm_wasm::WasmVm,
+    std::{fs, path::PathBuf},
+};
+
+#[derive(Parser)]
+pub struct ExportCmd {
+    /// Block height at which to export the state [default: latest]
+    #[arg(long)]
+    height: Option<u64>,
+
+    /// Write the genesis state to this file instead of printing it to stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+impl ExportCmd {
+    pub fn run(self, data_dir: PathBuf) -> anyhow::Result<()> {
+        let db = DiskDb::open(data_dir)?;
+        let app = App::<DiskDb, WasmVm>::new(db);
+
+        // the output is to be used as the `app_state` in CometBFT's genesis file
+        let state = app.export_state(self.height)?;
+        let json = serde_json::to_string_pretty(&state)?;
+
+        if let Some(path) = self.output {
+            fs::write(&path, json)?;
+            println!(
+                "Exported state at height {} written to {path:?}",
+                state.height
+            );
+        } else {
+            println!("{json}");
+        }
+
+        Ok(())
+    }
+}
@@ -1,15 +1,19 @@
+mod backup;
+mod db;
+mod export;
 mod keys;
 mod prompt;
 mod query;
 mod reset;
+mod rollback;
 mod start;
 mod tendermint;
 mod tx;
 
 use {
     crate::{
-        keys::KeysCmd, query::QueryCmd, reset::ResetCmd, start::StartCmd, tendermint::StatusCmd,
-        tx::TxCmd,
+        backup::BackupCmd, db::DbCmd, export::ExportCmd, keys::KeysCmd, query::QueryCmd,
+        reset::ResetCmd, rollback::RollbackCmd, start::StartCmd, tendermint::StatusCmd, tx::TxCmd,
     },
     anyhow::anyhow,
     clap::Parser,
@@ -21,6 +25,9 @@ use {
 // relative to user home directory (~)
 const DEFAULT_APP_DIR: &str = ".grug";
 
+// relative to the app directory
+const DB_CONFIG_FILE: &str = "db.toml";
+
 #[derive(Parser)]
 #[command(author, version, about, next_display_order = None)]
 struct Cli {
@@ -38,6 +45,17 @@ struct Cli {
 
 #[derive(Parser)]
 enum Command {
+    /// Manage database backups
+    #[command(subcommand, next_display_order = None)]
+    Backup(BackupCmd),
+
+    /// Inspect and maintain the database
+    #[command(subcommand, next_display_order = None)]
+    Db(DbCmd),
+
+    /// Export the chain's state as a genesis state
+    Export(ExportCmd),
+
     /// Manage keys [alias: k]
     #[command(subcommand, next_display_order = None, alias = "k")]
     Keys(KeysCmd),
@@ -46,6 +64,9 @@ enum Command {
     #[command(next_display_order = None, alias = "q")]
     Query(QueryCmd),
 
+    /// Roll back the database to an earlier block height
+    Rollback(RollbackCmd),
+
     /// Start the node
     Start(StartCmd),
 
@@ -77,11 +98,17 @@ async fn main() -> anyhow::Result<()> {
     };
     let data_dir = app_dir.join("data");
     let keys_dir = app_dir.join("keys");
+    let backups_dir = app_dir.join("backups");
+    let db_config_path = app_dir.join(DB_CONFIG_FILE);
 
     match cli.command {
+        Command::Backup(cmd) => cmd.run(data_dir, backups_dir),
+        Command::Db(cmd) => cmd.run(data_dir),
+        Command::Export(cmd) => cmd.run(data_dir),
         Command::Keys(cmd) => cmd.run(keys_dir),
         Command::Query(cmd) => cmd.run().await,
-        Command::Start(cmd) => cmd.run(data_dir).await,
+        Command::Rollback(cmd) => cmd.run(data_dir, db_config_path),
+        Command::Start(cmd) => cmd.run(data_dir, backups_dir, db_config_path).await,
         Command::Status(cmd) => cmd.run().await,
         Command::Tx(cmd) => cmd.run(keys_dir).await,
         Command::UnsafeResetAll(cmd) => cmd.run(data_dir),
@@ -1,10 +1,10 @@
 use {
     crate::prompt::print_json_pretty,
     anyhow::ensure,
-    clap::{Parser, Subcommand},
-    grug_jmt::Proof,
+    clap::{Args, Parser, Subcommand},
     grug_sdk::Client,
-    grug_types::{Addr, Binary, Hash},
+    grug_types::{from_json_value, Addr, Binary, Hash, PageRequest},
+    prost::Message,
     serde::Serialize,
     serde_json::Value,
     std::{fs::File, io::Write, path::PathBuf},
@@ -39,10 +39,8 @@ enum SubCmd {
     Balances {
         /// Account address
         address: Addr,
-        /// Start after this token denomination
-        start_after: Option<String>,
-        /// Maximum number of items to display
-        limit: Option<u32>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Query a token's total supply
     Supply {
@@ -51,19 +49,15 @@ enum SubCmd {
     },
     /// Enumerate all tokens' total supplies
     Supplies {
-        /// Start after this token denomination
-        start_after: Option<String>,
-        /// Maximum number of items to display
-        limit: Option<u32>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Query a Wasm binary code by hash
     Code { hash: Hash },
     /// Enumerate hashes of all Wasm byte codes
     Codes {
-        /// Start after this hash
-        start_after: Option<Hash>,
-        /// Maximum number of items to display
-        limit: Option<u32>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Query metadata of a single account by address
     Account {
@@ -72,10 +66,8 @@ enum SubCmd {
     },
     /// Enumerate metadata of all accounts
     Accounts {
-        /// Start after this address
-        start_after: Option<Addr>,
-        /// Maximum number of items to display
-        limit: Option<u32>,
+        #[command(flatten)]
+        page: PageArgs,
     },
     /// Query the raw value in a contract store by raw key
     WasmRaw {
@@ -111,6 +103,38 @@ enum SubCmd {
     },
 }
 
+#[derive(Args)]
+struct PageArgs {
+    /// Cursor returned by the previous page, in base64 encoding
+    #[arg(long)]
+    cursor: Option<String>,
+    /// Maximum number of items to display
+    #[arg(long)]
+    limit: Option<u32>,
+    /// Also display the total number of items, if there are no more than 10,000
+    #[arg(long, default_value_t = false)]
+    count_total: bool,
+}
+
+impl TryFrom<PageArgs> for PageRequest {
+    type Error = anyhow::Error;
+
+    fn try_from(args: PageArgs) -> anyhow::Result<Self> {
+        // cursors are serialized as base64 strings in query responses, so we
+        // deserialize the input the same way
+        let cursor = args
+            .cursor
+            .map(|cursor| from_json_value::<Binary>(Value::String(cursor)))
+            .transpose()?;
+
+        Ok(PageRequest {
+            cursor,
+            limit: args.limit,
+            count_total: args.count_total,
+        })
+    }
+}
+
 impl QueryCmd {
     pub async fn run(self) -> anyhow::Result<()> {
         let client = Client::connect(&self.node)?;
@@ -123,13 +147,9 @@ impl QueryCmd {
                 let res = client.query_balance(address, denom, self.height).await?;
                 print_json_pretty(res)
             },
-            SubCmd::Balances {
-                address,
-                start_after,
-                limit,
-            } => {
+            SubCmd::Balances { address, page } => {
                 let res = client
-                    .query_balances(address, start_after, limit, self.height)
+                    .query_balances(address, page.try_into()?, self.height)
                     .await?;
                 print_json_pretty(res)
             },
@@ -137,10 +157,8 @@ impl QueryCmd {
                 let res = client.query_supply(denom, self.height).await?;
                 print_json_pretty(res)
             },
-            SubCmd::Supplies { start_after, limit } => {
-                let res = client
-                    .query_supplies(start_after, limit, self.height)
-                    .await?;
+            SubCmd::Supplies { page } => {
+                let res = client.query_supplies(page.try_into()?, self.height).await?;
                 print_json_pretty(res)
             },
             SubCmd::Code { hash } => {
@@ -161,18 +179,16 @@ impl QueryCmd {
 
                 Ok(())
             },
-            SubCmd::Codes { start_after, limit } => {
-                let res = client.query_codes(start_after, limit, self.height).await?;
+            SubCmd::Codes { page } => {
+                let res = client.query_codes(page.try_into()?, self.height).await?;
                 print_json_pretty(res)
             },
             SubCmd::Account { address } => {
                 let res = client.query_account(address, self.height).await?;
                 print_json_pretty(res)
             },
-            SubCmd::Accounts { start_after, limit } => {
-                let res = client
-                    .query_accounts(start_after, limit, self.height)
-                    .await?;
+            SubCmd::Accounts { page } => {
+                let res = client.query_accounts(page.try_into()?, self.height).await?;
                 print_json_pretty(res)
             },
             SubCmd::WasmRaw { contract, key_hex } => {
@@ -194,7 +210,8 @@ impl QueryCmd {
                 struct PrintableQueryStoreResponse {
                     key: String,
                     value: Option<String>,
-                    proof: Option<Proof>,
+                    // ICS-23 commitment proof in protobuf, hex-encoded
+                    proof: Option<String>,
                 }
 
                 let key = hex::decode(&key_hex)?;
@@ -203,7 +220,7 @@ impl QueryCmd {
                 print_json_pretty(PrintableQueryStoreResponse {
                     key: key_hex,
                     value: value.map(hex::encode),
-                    proof,
+                    proof: proof.map(|proof| hex::encode(proof.encode_to_vec())),
                 })
             },
             SubCmd::Tx { hash } => {
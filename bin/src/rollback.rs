@@ -0,0 +1,82 @@
+use {
+    crate::prompt::confirm,
+    anyhow::{bail, ensure},
+    clap::Parser,
+    colored::Colorize,
+    grug_app::Db,
+    grug_db_disk::{DiskDb, DiskDbConfig},
+    grug_types::Hash,
+    std::path::PathBuf,
+};
+
+#[derive(Parser)]
+pub struct RollbackCmd {
+    /// Block height to roll back to
+    #[arg(long)]
+    to_height: u64,
+
+    /// App hash expected at the target height, e.g. as found in the header of
+    /// the next block. Abort if the Merkle root doesn't match it
+    #[arg(long)]
+    app_hash: Option<String>,
+
+    /// Skip confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+impl RollbackCmd {
+    pub fn run(self, data_dir: PathBuf, db_config_path: PathBuf) -> anyhow::Result<()> {
+        // the rollback window is set in the database config, and must be the
+        // same as the node has been running with
+        let db_config = DiskDbConfig::load_or_default(db_config_path)?;
+        let db = DiskDb::open_with_config(data_dir, &db_config)?;
+
+        let Some(latest_height) = db.latest_version() else {
+            bail!("database is empty, nothing to roll back");
+        };
+
+        ensure!(
+            self.to_height < latest_height,
+            "target height {} isn't older than the latest height {latest_height}",
+            self.to_height
+        );
+
+        // the Merkle root at the target height must still be recorded in the
+        // state commitment, and match the app hash the operator expects.
+        let Some(root_hash) = db.root_hash(Some(self.to_height))? else {
+            bail!(
+                "root hash at height {} not found, it may have been pruned",
+                self.to_height
+            );
+        };
+        if let Some(app_hash) = self.app_hash {
+            // CometBFT prints hashes in uppercase, while we use lowercase
+            let app_hash: Hash = app_hash.to_ascii_lowercase().parse()?;
+            ensure!(
+                root_hash == app_hash,
+                "root hash at height {} is {root_hash}, doesn't match the expected {app_hash}",
+                self.to_height
+            );
+        }
+
+        println!("Root hash at height {}: {root_hash}", self.to_height);
+
+        if !self.yes {
+            let prompt = format!(
+                "Confirm rolling back from height {latest_height} to {}?",
+                self.to_height
+            );
+            if !confirm(prompt.bold())? {
+                println!("🤷 User aborted");
+                return Ok(());
+            }
+        }
+
+        db.rollback(self.to_height)?;
+
+        println!("Rolled back to height {}", self.to_height);
+
+        Ok(())
+    }
+}
@@ -1,4 +1,14 @@
-use {clap::Parser, grug_app::App, grug_db_disk::DiskDb, grug_vm_wasm::WasmVm, std::path::PathBuf};
+use {
+    clap::Parser,
+    grug_app::App,
+    grug_db_disk::{AutoCheckpointConfig, DiskDb, DiskDbConfig},
+    grug_vm_wasm::WasmVm,
+    std::{
+        num::{NonZeroU64, NonZeroUsize},
+        path::PathBuf,
+    },
+    tracing::info,
+};
 
 #[derive(Parser)]
 pub struct StartCmd {
@@ -9,14 +19,78 @@ pub struct StartCmd {
     /// Buffer size for reading chunks of incoming data from client
     #[arg(long, default_value = "1048576")]
     read_buf_size: usize,
+
+    /// Write committed blocks to disk in a background thread
+    #[arg(long)]
+    async_commit: bool,
+
+    /// Maximum number of files the database may keep open; -1 means no limit
+    #[arg(long, allow_negative_numbers = true)]
+    db_max_open_files: Option<i32>,
+
+    /// Size, in bytes, of the database's block cache
+    #[arg(long)]
+    db_block_cache_size: Option<usize>,
+
+    /// Size, in bytes, of the database's Merkle tree node cache
+    #[arg(long)]
+    db_node_cache_size: Option<NonZeroUsize>,
+
+    /// Back up the database every this many blocks
+    #[arg(long)]
+    checkpoint_interval: Option<NonZeroU64>,
+
+    /// Number of most recent automatic backups to keep
+    #[arg(long, default_value_t = AutoCheckpointConfig::default_keep_recent())]
+    checkpoint_keep_recent: NonZeroUsize,
 }
 
 impl StartCmd {
-    pub async fn run(self, data_dir: PathBuf) -> anyhow::Result<()> {
+    pub async fn run(
+        self,
+        data_dir: PathBuf,
+        backups_dir: PathBuf,
+        db_config_path: PathBuf,
+    ) -> anyhow::Result<()> {
+        // load DB config from file, then apply overrides from flags
+        let mut db_config = DiskDbConfig::load_or_default(db_config_path)?;
+        if self.async_commit {
+            db_config.async_commit = true;
+        }
+        if let Some(max_open_files) = self.db_max_open_files {
+            db_config.max_open_files = max_open_files;
+        }
+        if let Some(block_cache_size) = self.db_block_cache_size {
+            db_config.block_cache_size = block_cache_size;
+        }
+        if let Some(node_cache_size) = self.db_node_cache_size {
+            db_config.node_cache_size = Some(node_cache_size);
+        }
+        // automatic backups are off unless enabled here or in the config file.
+        // only then can backups be requested while the node is running (see
+        // `grug backup create`).
+        if let Some(interval) = self.checkpoint_interval {
+            db_config.auto_checkpoint = Some(AutoCheckpointConfig {
+                dir: backups_dir,
+                interval: Some(interval),
+                keep_recent: self.checkpoint_keep_recent,
+            });
+        }
+
         // create DB backend
-        let db = DiskDb::open(data_dir)?;
+        let db = DiskDb::open_with_config(data_dir, &db_config)?;
+
+        // start the ABCI server. it runs until the chain halts for an upgrade,
+        // in which case we exit so that the new software can be swapped in.
+        let halt =
+            App::<DiskDb, WasmVm>::new(db).start_abci_server(self.read_buf_size, self.abci_addr)?;
+
+        info!(
+            name = halt.name,
+            height = halt.height,
+            "Upgrade needed. Please restart with the new software"
+        );
 
-        // start the ABCI server
-        Ok(App::<DiskDb, WasmVm>::new(db).start_abci_server(self.read_buf_size, self.abci_addr)?)
+        Ok(())
     }
 }
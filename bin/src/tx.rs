@@ -4,7 +4,7 @@ use {
     clap::{Parser, Subcommand},
     colored::Colorize,
     grug_sdk::{Client, SigningKey, SigningOptions},
-    grug_types::{from_json_slice, Addr, Binary, Coins, Config, Hash, Message},
+    grug_types::{from_json_slice, Addr, Binary, Coins, Config, Hash, Message, Uint64},
     serde::Serialize,
     std::{fs::File, io::Read, path::PathBuf, str::FromStr},
     tendermint_rpc::endpoint::broadcast::tx_sync,
@@ -43,6 +43,16 @@ enum SubCmd {
         /// New configurations as a JSON string
         new_cfg: String,
     },
+    /// Schedule a software upgrade at the given block height
+    ScheduleUpgrade {
+        /// Block height at which the upgrade takes place
+        height: u64,
+        /// Name of the upgrade
+        name: String,
+        /// Additional information about the upgrade
+        #[arg(long)]
+        info: Option<String>,
+    },
     /// Send coins to the given recipient address
     Transfer {
         /// Recipient address
@@ -127,6 +137,11 @@ impl TxCmd {
                 let new_cfg: Config = from_json_slice(new_cfg.as_bytes())?;
                 Message::SetConfig { new_cfg }
             },
+            SubCmd::ScheduleUpgrade { height, name, info } => Message::ScheduleUpgrade {
+                height: Uint64::new(height),
+                name,
+                info,
+            },
             SubCmd::Transfer { to, coins } => {
                 let coins = Coins::from_str(&coins)?;
                 Message::Transfer { to, coins }
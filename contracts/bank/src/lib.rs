@@ -3,8 +3,9 @@ use grug::grug_export;
 use {
     anyhow::bail,
     grug::{
-        grug_derive, Addr, BankQueryMsg, BankQueryResponse, Bound, Coin, Coins, ImmutableCtx, Map,
-        MutableCtx, Order, Response, StdResult, Storage, SudoCtx, TransferMsg, Uint128,
+        grug_derive, Addr, BankQueryMsg, BankQueryResponse, CheckedOps, Coin, Coins, ImmutableCtx,
+        Map, MutableCtx, Page, PageRequest, Response, StdResult, Storage, SudoCtx, TransferMsg,
+        Uint128,
     },
     std::collections::{BTreeMap, HashMap},
 };
@@ -15,9 +16,6 @@ const BALANCES: Map<(&Addr, &str), Uint128> = Map::new("b");
 // denom => supply
 const SUPPLIES: Map<&str, Uint128> = Map::new("s");
 
-// how many items to return in a paginated query by default
-const DEFAULT_PAGE_LIMIT: u32 = 30;
-
 #[grug_derive(serde)]
 pub struct InstantiateMsg {
     pub initial_balances: BTreeMap<Addr, Coins>,
@@ -213,14 +211,12 @@ pub fn bank_query(ctx: ImmutableCtx, msg: BankQueryMsg) -> StdResult<BankQueryRe
         BankQueryMsg::Balance { address, denom } => {
             query_balance(ctx, address, denom).map(BankQueryResponse::Balance)
         },
-        BankQueryMsg::Balances {
-            address,
-            start_after,
-            limit,
-        } => query_balances(ctx, address, start_after, limit).map(BankQueryResponse::Balances),
+        BankQueryMsg::Balances { address, page } => {
+            query_balances(ctx, address, page).map(BankQueryResponse::Balances)
+        },
         BankQueryMsg::Supply { denom } => query_supply(ctx, denom).map(BankQueryResponse::Supply),
-        BankQueryMsg::Supplies { start_after, limit } => {
-            query_supplies(ctx, start_after, limit).map(BankQueryResponse::Supplies)
+        BankQueryMsg::Supplies { page } => {
+            query_supplies(ctx, page).map(BankQueryResponse::Supplies)
         },
     }
 }
@@ -236,18 +232,12 @@ pub fn query_balance(ctx: ImmutableCtx, address: Addr, denom: String) -> StdResu
 pub fn query_balances(
     ctx: ImmutableCtx,
     address: Addr,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<Coins> {
-    let start = start_after
-        .as_ref()
-        .map(|denom| Bound::Exclusive(denom.as_str()));
-    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize;
-    let mut iter = BALANCES
+    page: PageRequest,
+) -> StdResult<Page<Coin>> {
+    BALANCES
         .prefix(&address)
-        .range(ctx.storage, start, None, Order::Ascending)
-        .take(limit);
-    Coins::from_iter_unchecked(&mut iter)
+        .paginate(ctx.storage, page)
+        .map(|page| page.map(|(denom, amount)| Coin { denom, amount }))
 }
 
 pub fn query_supply(ctx: ImmutableCtx, denom: String) -> StdResult<Coin> {
@@ -258,17 +248,8 @@ pub fn query_supply(ctx: ImmutableCtx, denom: String) -> StdResult<Coin> {
     })
 }
 
-pub fn query_supplies(
-    ctx: ImmutableCtx,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<Coins> {
-    let start = start_after
-        .as_ref()
-        .map(|denom| Bound::Exclusive(denom.as_str()));
-    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize;
-    let mut iter = SUPPLIES
-        .range(ctx.storage, start, None, Order::Ascending)
-        .take(limit);
-    Coins::from_iter_unchecked(&mut iter)
+pub fn query_supplies(ctx: ImmutableCtx, page: PageRequest) -> StdResult<Page<Coin>> {
+    SUPPLIES
+        .paginate(ctx.storage, page)
+        .map(|page| page.map(|(denom, amount)| Coin { denom, amount }))
 }
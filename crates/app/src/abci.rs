@@ -2,7 +2,7 @@ use {
     crate::{App, AppError, AppResult, Db, Vm},
     grug_types::{Attribute, BlockInfo, Event, Hash, Timestamp, Uint64, GENESIS_BLOCK_HASH},
     prost::bytes::Bytes,
-    std::{any::type_name, net::ToSocketAddrs},
+    std::{any::type_name, net::ToSocketAddrs, sync::mpsc, thread},
     tendermint_abci::{Application, Error as ABCIError, ServerBuilder},
     tendermint_proto::{
         abci::{
@@ -13,32 +13,99 @@ use {
         crypto::{ProofOp, ProofOps},
         google::protobuf::Timestamp as TmTimestamp,
     },
-    tracing::Value,
+    thiserror::Error,
+    tracing::{error, Value},
 };
 
+type StopSender = mpsc::Sender<Result<UpgradeHalt, ABCIError>>;
+
+/// Signals that the chain has reached the height of a scheduled upgrade, which
+/// the running software doesn't handle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeHalt {
+    pub name: String,
+    pub height: u64,
+}
+
+/// Reasons for the ABCI server to stop, other than an upgrade halt.
+#[derive(Debug, Error)]
+pub enum AbciServerError {
+    #[error(transparent)]
+    Abci(#[from] ABCIError),
+
+    #[error("ABCI server stopped without signaling why")]
+    StoppedUnexpectedly,
+}
+
 impl<DB, VM> App<DB, VM>
 where
     DB: Db + Clone + Send + 'static,
     VM: Vm + Send + 'static,
     AppError: From<DB::Error> + From<VM::Error>,
 {
+    /// Start the ABCI server, and block until the chain halts for an upgrade,
+    /// in which case the upgrade is returned, or the server fails.
+    ///
+    /// `tendermint-abci` offers no way to stop a listening server, so the
+    /// caller is expected to exit the process once this returns, typically so
+    /// that the operator can swap in the new software. Until then, the halted
+    /// block is never responded to, so it isn't processed.
     pub fn start_abci_server(
         self,
         read_buf_size: usize,
         addr: impl ToSocketAddrs + Value,
-    ) -> Result<(), ABCIError> {
-        ServerBuilder::new(read_buf_size).bind(addr, self)?.listen()
+    ) -> Result<UpgradeHalt, AbciServerError> {
+        // the channel through which the server reports that it has stopped,
+        // either because of an upgrade halt, or an error
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let app = AbciApp {
+            app: self,
+            stop_tx: stop_tx.clone(),
+        };
+
+        let server = ServerBuilder::new(read_buf_size).bind(addr, app)?;
+        thread::spawn(move || {
+            if let Err(err) = server.listen() {
+                stop_tx.send(Err(err)).ok();
+            }
+        });
+
+        // all senders are dropped without sending anything only if the server
+        // thread panicked
+        stop_rx
+            .recv()
+            .map_err(|_| AbciServerError::StoppedUnexpectedly)?
+            .map_err(Into::into)
     }
 }
 
-impl<DB, VM> Application for App<DB, VM>
+/// The application served over ABCI: the app, plus the channel through which
+/// it signals an upgrade halt to `App::start_abci_server`.
+struct AbciApp<DB, VM> {
+    app: App<DB, VM>,
+    stop_tx: StopSender,
+}
+
+impl<DB, VM> Clone for AbciApp<DB, VM>
+where
+    DB: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            app: self.app.clone(),
+            stop_tx: self.stop_tx.clone(),
+        }
+    }
+}
+
+impl<DB, VM> Application for AbciApp<DB, VM>
 where
     DB: Db + Clone + Send + 'static,
     VM: Vm + Send + 'static,
     AppError: From<DB::Error> + From<VM::Error>,
 {
     fn info(&self, _req: RequestInfo) -> ResponseInfo {
-        match self.do_info() {
+        match self.app.do_info() {
             Ok((last_block_height, last_block_version)) => ResponseInfo {
                 data: env!("CARGO_PKG_NAME").into(),
                 version: env!("CARGO_PKG_VERSION").into(),
@@ -56,7 +123,10 @@ where
         // always matches block height.
         let block = from_tm_block(0, req.time, None);
 
-        match self.do_init_chain_raw(req.chain_id, block, &req.app_state_bytes) {
+        match self
+            .app
+            .do_init_chain_raw(req.chain_id, block, &req.app_state_bytes)
+        {
             Ok(app_hash) => ResponseInitChain {
                 consensus_params: req.consensus_params,
                 validators: req.validators,
@@ -69,7 +139,7 @@ where
     fn finalize_block(&self, req: RequestFinalizeBlock) -> ResponseFinalizeBlock {
         let block = from_tm_block(req.height, req.time, Some(req.hash));
 
-        match self.do_finalize_block_raw(block, req.txs) {
+        match self.app.do_finalize_block_raw(block, req.txs) {
             Ok((app_hash, events, tx_results)) => ResponseFinalizeBlock {
                 events: events.into_iter().map(to_tm_event).collect(),
                 tx_results: tx_results.into_iter().map(to_tm_tx_result).collect(),
@@ -77,12 +147,28 @@ where
                 consensus_param_updates: None,
                 app_hash: app_hash.into_vec().into(),
             },
+            // the node is running the old software and has reached the height
+            // of a scheduled upgrade. signal the halt to `start_abci_server`,
+            // then abort handling the request, so that the block is never
+            // responded to, and thus isn't processed. this drops the connection,
+            // upon which CometBFT stops too.
+            Err(AppError::UpgradeNeeded { name, height }) => {
+                error!(name, height, "Upgrade needed! Halting");
+                let halt = UpgradeHalt {
+                    name: name.clone(),
+                    height,
+                };
+                if self.stop_tx.send(Ok(halt)).is_err() {
+                    error!("ABCI server has already stopped");
+                }
+                panic!("upgrade `{name}` needed at height {height}");
+            },
             Err(err) => panic!("failed to finalize block: {err}"),
         }
     }
 
     fn commit(&self) -> ResponseCommit {
-        match self.do_commit() {
+        match self.app.do_commit() {
             Ok(()) => {
                 ResponseCommit {
                     retain_height: 0, // TODO: what this means??
@@ -99,22 +185,48 @@ where
         }
     }
 
-    // TODO: From ABCI docs (https://github.com/cometbft/cometbft/blob/main/spec/abci/abci++_methods.md):
+    // the supported paths are:
+    //
+    // - `/app`: `data` is a JSON-encoded `QueryRequest`;
+    // - `/store`: `data` is a raw key in the underlying store;
+    // - `/store/batch`: `data` is a JSON array of raw keys;
+    // - `/store/range`: `data` is a JSON-encoded `StoreRangeRequest`.
     //
-    // > Applications MUST interpret "/store" or any path starting with "/store/"
-    // > as a query by key on the underlying store, in which case a key SHOULD
-    // > be specified in data. Applications SHOULD allow queries over specific
-    // > types like /accounts/... or /votes/....
+    // the proofs come in different formats, as given by the proof op's type:
     //
-    // Currently we're going neither of these. We ignore `path`, `height`, and
-    // `prove` fields, and interpret `data` as a JSON-encoded QueryRequest.
+    // - `/store` returns an ICS-23 proof, encoded in protobuf, of type
+    //   `DB::ICS23_PROOF_TYPE`, for verifiers that only understand ICS-23,
+    //   such as IBC light clients;
+    // - `/app` and `/store/batch` return our own JSON-encoded proofs, of types
+    //   `DB::Proof` and `DB::BatchProof` (as given by `type_name`), which can
+    //   be verified with `grug_jmt::verify_proof` and `verify_batch_proof`.
+    //   ICS-23 has no equivalent of the batch proof, and the `/app` proof is
+    //   consumed by our own client, so we keep the two in the same format.
+    //
+    // to get an ICS-23 proof of a query response, query its storage key under
+    // `/store` instead.
     fn query(&self, req: RequestQuery) -> ResponseQuery {
         match req.path.as_str() {
-            "/app" => match self.do_query_app_raw(&req.data, req.height as u64, req.prove) {
-                Ok(res) => ResponseQuery {
-                    code: 0,
-                    value: res.to_vec().into(),
-                    ..Default::default()
+            "/app" => match self.app.do_query_app_raw(&req.data, req.height as u64, req.prove) {
+                Ok((res, proof)) => {
+                    // if a proof is requested, the query is performed at a
+                    // pinned version, which the client needs to know in order
+                    // to verify the proof.
+                    let height = proof.as_ref().map_or(req.height, |(version, ..)| *version as i64);
+                    let proof_ops = proof.map(|(_, key, proof)| ProofOps {
+                        ops: vec![ProofOp {
+                            r#type: type_name::<DB::Proof>().into(),
+                            key,
+                            data: proof,
+                        }],
+                    });
+                    ResponseQuery {
+                        code: 0,
+                        value: res.into(),
+                        height,
+                        proof_ops,
+                        ..Default::default()
+                    }
                 },
                 Err(err) => ResponseQuery {
                     code: 1,
@@ -123,11 +235,12 @@ where
                     ..Default::default()
                 },
             },
-            "/store" => match self.do_query_store(&req.data, req.height as u64, req.prove) {
+            "/store" => match self.app.do_query_store(&req.data, req.height as u64, req.prove) {
                 Ok((value, proof)) => {
-                    let proof_ops = proof.map(|proof| ProofOps {
+                    let height = proof.as_ref().map_or(req.height, |(version, _)| *version as i64);
+                    let proof_ops = proof.map(|(_, proof)| ProofOps {
                         ops: vec![ProofOp {
-                            r#type: type_name::<DB::Proof>().into(),
+                            r#type: DB::ICS23_PROOF_TYPE.into(),
                             key: req.data.into(),
                             data: proof,
                         }],
@@ -135,7 +248,7 @@ where
                     ResponseQuery {
                         code: 0,
                         value: value.unwrap_or_default().into(),
-                        height: req.height,
+                        height,
                         proof_ops,
                         ..Default::default()
                     }
@@ -147,10 +260,67 @@ where
                     ..Default::default()
                 },
             },
+            // query multiple keys at once, with a single Merkle proof. the keys
+            // are given as a JSON array of at most `MAX_BATCH_PROOF_KEYS` base64
+            // strings, and the values are returned as a JSON array of base64
+            // strings or nulls.
+            "/store/batch" => {
+                match self.app.do_query_store_many_raw(&req.data, req.height as u64, req.prove) {
+                    Ok((values, proof)) => {
+                        let height =
+                            proof.as_ref().map_or(req.height, |(version, _)| *version as i64);
+                        let proof_ops = proof.map(|(_, proof)| ProofOps {
+                            ops: vec![ProofOp {
+                                r#type: type_name::<DB::BatchProof>().into(),
+                                key: req.data.into(),
+                                data: proof,
+                            }],
+                        });
+                        ResponseQuery {
+                            code: 0,
+                            value: values.into(),
+                            height,
+                            proof_ops,
+                            ..Default::default()
+                        }
+                    },
+                    Err(err) => ResponseQuery {
+                        code: 1,
+                        codespace: "store".into(),
+                        log: err.to_string(),
+                        ..Default::default()
+                    },
+                }
+            },
+            // query a range of keys. the request is a JSON-encoded
+            // `StoreRangeRequest`, and the records are returned as a JSON array
+            // of `[key, value]` pairs. Merkle proofs aren't supported.
+            "/store/range" if req.prove => ResponseQuery {
+                code: 1,
+                codespace: "store".into(),
+                log: "Merkle proofs aren't supported for range queries".into(),
+                ..Default::default()
+            },
+            "/store/range" => match self.app.do_query_store_range_raw(&req.data, req.height as u64) {
+                Ok(records) => ResponseQuery {
+                    code: 0,
+                    value: records.into(),
+                    height: req.height,
+                    ..Default::default()
+                },
+                Err(err) => ResponseQuery {
+                    code: 1,
+                    codespace: "store".into(),
+                    log: err.to_string(),
+                    ..Default::default()
+                },
+            },
             unknown => ResponseQuery {
                 code: 1,
                 codespace: "app".into(),
-                log: format!("unknown path `{unknown}`; must be `/app` or `/store`"),
+                log: format!(
+                    "unknown path `{unknown}`; must be `/app`, `/store`, `/store/batch`, or `/store/range`"
+                ),
                 ..Default::default()
             },
         }
@@ -1,15 +1,18 @@
 use {
     crate::{
         do_after_block, do_after_tx, do_before_block, do_before_tx, do_client_create,
-        do_client_freeze, do_client_update, do_execute, do_instantiate, do_migrate, do_set_config,
-        do_transfer, do_upload, query_account, query_accounts, query_balance, query_balances,
-        query_code, query_codes, query_info, query_supplies, query_supply, query_wasm_raw,
-        query_wasm_smart, AppError, AppResult, CacheStore, Db, SharedStore, Vm, CHAIN_ID, CONFIG,
-        LAST_FINALIZED_BLOCK,
+        do_client_freeze, do_client_update, do_execute, do_instantiate, do_migrate,
+        do_schedule_upgrade, do_set_config, do_transfer, do_upgrade, do_upload, export_state,
+        import_state, query_account, query_accounts, query_balance, query_balances, query_code,
+        query_codes, query_info, query_multi, query_supplies, query_supply, query_wasm_raw,
+        query_wasm_raw_range, query_wasm_smart, AppError, AppResult, CacheStore, Db, SharedStore,
+        UpgradeHandler, UpgradeHandlers, Vm, CHAIN_ID, CONFIG, LAST_FINALIZED_BLOCK,
     },
     grug_types::{
-        from_json_slice, hash, to_json_vec, Addr, BlockInfo, Event, GenesisState, Hash, Message,
-        Permission, QueryRequest, QueryResponse, StdResult, Storage, Tx, GENESIS_SENDER,
+        from_json_slice, hash, page_limit, query_storage_key, to_json_vec, Addr, Binary, BlockInfo,
+        Event, Genesis, Hash, Message, Order, Permission, QueryRequest, QueryResponse,
+        RawGenesisState, Record, StdResult, Storage, StoreRangeRequest, Tx, GENESIS_SENDER,
+        MAX_BATCH_PROOF_KEYS,
     },
     std::marker::PhantomData,
     tracing::{debug, info},
@@ -22,6 +25,7 @@ use {
 pub struct App<DB, VM> {
     db: DB,
     vm: PhantomData<VM>,
+    upgrade_handlers: UpgradeHandlers,
 }
 
 impl<DB, VM> App<DB, VM> {
@@ -29,8 +33,16 @@ impl<DB, VM> App<DB, VM> {
         Self {
             db,
             vm: PhantomData,
+            upgrade_handlers: UpgradeHandlers::new(),
         }
     }
+
+    /// Register a handler for the upgrade of the given name. The handler is
+    /// run once, at the beginning of the block at the upgrade height.
+    pub fn add_upgrade_handler(mut self, name: impl Into<String>, handler: UpgradeHandler) -> Self {
+        self.upgrade_handlers.insert(name.into(), handler);
+        self
+    }
 }
 
 // For some reason, using a derive macro `#[derive(Clone)]` on App doesn't work.
@@ -45,6 +57,7 @@ where
         Self {
             db: self.db.clone(),
             vm: PhantomData,
+            upgrade_handlers: self.upgrade_handlers.clone(),
         }
     }
 }
@@ -59,17 +72,17 @@ where
         &self,
         chain_id: String,
         block: BlockInfo,
-        raw_genesis_state: &[u8],
+        raw_genesis: &[u8],
     ) -> AppResult<Hash> {
-        let genesis_state = from_json_slice(raw_genesis_state)?;
-        self.do_init_chain(chain_id, block, genesis_state)
+        let genesis = from_json_slice::<Genesis>(raw_genesis)?;
+        self.do_init_chain(chain_id, block, genesis)
     }
 
     pub fn do_init_chain(
         &self,
         chain_id: String,
         block: BlockInfo,
-        genesis_state: GenesisState,
+        genesis: impl Into<Genesis>,
     ) -> AppResult<Hash> {
         let mut cached = SharedStore::new(CacheStore::new(self.db.state_storage(None), None));
 
@@ -79,18 +92,29 @@ where
             return Err(AppError::incorrect_block_height(0, block.height.number()));
         }
 
-        // save the config and genesis block. some genesis messages may need it
-        CHAIN_ID.save(&mut cached, &chain_id)?;
-        CONFIG.save(&mut cached, &genesis_state.config)?;
-        LAST_FINALIZED_BLOCK.save(&mut cached, &block)?;
-
-        // loop through genesis messages and execute each one.
-        // it's expected that genesis messages should all successfully execute.
-        // if anyone fails, it's fatal error and we abort the genesis.
-        // the developer should examine the error, fix it, and retry.
-        for (idx, msg) in genesis_state.msgs.into_iter().enumerate() {
-            info!(idx, "Processing genesis message");
-            process_msg::<VM>(Box::new(cached.clone()), &block, &GENESIS_SENDER, msg)?;
+        match genesis.into() {
+            Genesis::Msgs(genesis_state) => {
+                // save the config and genesis block. some genesis messages may need it
+                CHAIN_ID.save(&mut cached, &chain_id)?;
+                CONFIG.save(&mut cached, &genesis_state.config)?;
+                LAST_FINALIZED_BLOCK.save(&mut cached, &block)?;
+
+                // loop through genesis messages and execute each one.
+                // it's expected that genesis messages should all successfully execute.
+                // if anyone fails, it's fatal error and we abort the genesis.
+                // the developer should examine the error, fix it, and retry.
+                for (idx, msg) in genesis_state.msgs.into_iter().enumerate() {
+                    info!(idx, "Processing genesis message");
+                    process_msg::<VM>(Box::new(cached.clone()), &block, &GENESIS_SENDER, msg)?;
+                }
+            },
+            Genesis::Raw(raw_genesis_state) => {
+                info!(
+                    exported_chain_id = raw_genesis_state.chain_id,
+                    "Importing raw genesis state"
+                );
+                import_state(&mut cached, &chain_id, &block, raw_genesis_state)?;
+            },
         }
 
         // persist the state changes to disk
@@ -143,7 +167,6 @@ where
         let mut events = vec![];
         let mut tx_results = vec![];
 
-        let cfg = CONFIG.load(&cached)?;
         let last_finalized_block = LAST_FINALIZED_BLOCK.load(&cached)?;
 
         // make sure the new block height is exactly the last finalized height
@@ -156,6 +179,19 @@ where
             ));
         }
 
+        // if an upgrade is scheduled at this block, run the upgrade handler.
+        // if we don't have the handler, meaning this node is running the old
+        // software, this errors and the chain halts.
+        //
+        // we do this before loading the config, as the upgrade may change it.
+        events.extend(do_upgrade(
+            Box::new(cached.share()),
+            &block,
+            &self.upgrade_handlers,
+        )?);
+
+        let cfg = CONFIG.load(&cached)?;
+
         // call begin blockers
         for (idx, contract) in cfg.begin_blockers.iter().enumerate() {
             debug!(
@@ -250,60 +286,109 @@ where
         Ok((version, root_hash))
     }
 
-    pub fn do_query_app_raw(&self, raw_req: &[u8], height: u64, prove: bool) -> AppResult<Vec<u8>> {
+    /// Export the chain's state at the given height, in the form of a raw
+    /// genesis state, which can be used to initialize a new chain.
+    /// If height is unspecified, use the latest height.
+    ///
+    /// Error if the height is newer than the latest height, or if the state at
+    /// that height has been pruned.
+    pub fn export_state(&self, height: Option<u64>) -> AppResult<RawGenesisState> {
+        if let Some(height) = height {
+            let latest = self.db.latest_version().unwrap_or(0);
+            if height > latest {
+                return Err(AppError::height_not_finalized(latest, height));
+            }
+
+            if self.db.root_hash(Some(height))?.is_none() {
+                return Err(AppError::StatePruned { height });
+            }
+        }
+
+        export_state(&self.db.state_storage(height))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn do_query_app_raw(
+        &self,
+        raw_req: &[u8],
+        height: u64,
+        prove: bool,
+    ) -> AppResult<(Vec<u8>, Option<(u64, Vec<u8>, Vec<u8>)>)> {
         let req = from_json_slice(raw_req)?;
-        let res = self.do_query_app(req, height, prove)?;
-        Ok(to_json_vec(&res)?)
+        let (res, proof) = self.do_query_app(req, height, prove)?;
+        // the response is empty if the key being proved doesn't exist, same as
+        // in the `/store` query
+        let res = res.map(|res| to_json_vec(&res)).transpose()?;
+        Ok((res.unwrap_or_default(), proof))
     }
 
+    /// Performs a query of the app's state. Returns two values:
+    /// - the query response; `None` if a proof is requested and the key being
+    ///   proved doesn't exist, in which case the proof is a non-membership
+    ///   proof;
+    /// - if `prove` is true, the version of the state that the query was
+    ///   performed at, the raw key in the state storage that the response was
+    ///   read from, and the Merkle proof (JSON-encoded) of the value under that
+    ///   key; `None` if a proof is not requested.
+    ///
+    /// Only `Code`, `Account`, and `WasmRaw` queries can be proved. See
+    /// `query_storage_key` for how the keys are derived.
+    #[allow(clippy::type_complexity)]
     pub fn do_query_app(
         &self,
         req: QueryRequest,
         height: u64,
         prove: bool,
-    ) -> AppResult<QueryResponse> {
-        if prove {
-            // we can't do merkle proof for smart queries. only raw store query
-            // can be merkle proved.
-            return Err(AppError::ProofNotSupported);
-        }
+    ) -> AppResult<(Option<QueryResponse>, Option<(u64, Vec<u8>, Vec<u8>)>)> {
+        let version = self.query_version(height, prove);
 
-        let version = if height == 0 {
-            // height being zero means unspecified (protobuf doesn't have a null
-            // type) in which case we use the latest version.
-            None
+        // use the state storage at the given version to perform the query
+        let store = self.db.state_storage(version);
+
+        let proof = if prove {
+            // we can't do merkle proof for smart queries. only queries that
+            // read a single key from the store can be merkle proved.
+            let key = query_storage_key(&req).ok_or(AppError::ProofNotSupported)?;
+            let proof = to_json_vec(&self.db.prove(&key, version)?)?;
+            // the pinned version is `None` only if the DB is empty
+            let proof = (version.unwrap_or_default(), key, proof);
+
+            // the key doesn't exist, so the proof is a non-membership proof.
+            // return an empty response instead of performing the query, which
+            // would fail for `Code` and `Account` queries.
+            if store.read(&proof.1).is_none() {
+                return Ok((None, Some(proof)));
+            }
+
+            Some(proof)
         } else {
-            Some(height)
+            None
         };
 
-        // use the state storage at the given version to perform the query
-        let store = self.db.state_storage(version);
         let block = LAST_FINALIZED_BLOCK.load(&store)?;
+        let res = process_query::<VM>(Box::new(store), &block, req)?;
 
-        process_query::<VM>(Box::new(store), &block, req)
+        Ok((Some(res), proof))
     }
 
     /// Performs a raw query of the app's underlying key-value store.
     /// Returns two values:
     /// - the value corresponding to the given key; `None` if the key doesn't exist;
-    /// - the Merkle proof; `None` if a proof is not requested (`prove` is false).
+    /// - the ICS-23 Merkle proof, encoded in protobuf, along with the version
+    ///   it's generated at; `None` if a proof is not requested (`prove` is
+    ///   false).
     #[allow(clippy::type_complexity)]
     pub fn do_query_store(
         &self,
         key: &[u8],
         height: u64,
         prove: bool,
-    ) -> AppResult<(Option<Vec<u8>>, Option<Vec<u8>>)> {
-        let version = if height == 0 {
-            // height being zero means unspecified (protobuf doesn't have a null
-            // type) in which case we use the latest version.
-            None
-        } else {
-            Some(height)
-        };
+    ) -> AppResult<(Option<Vec<u8>>, Option<(u64, Vec<u8>)>)> {
+        let version = self.query_version(height, prove);
 
         let proof = if prove {
-            Some(to_json_vec(&self.db.prove(key, version)?)?)
+            let proof = self.db.prove_ics23(key, version)?;
+            Some((version.unwrap_or_default(), proof))
         } else {
             None
         };
@@ -312,6 +397,115 @@ where
 
         Ok((value, proof))
     }
+
+    /// Same as `do_query_store_many`, but the keys are given as a JSON array,
+    /// and the values are returned as a JSON array.
+    pub fn do_query_store_many_raw(
+        &self,
+        raw_keys: &[u8],
+        height: u64,
+        prove: bool,
+    ) -> AppResult<(Vec<u8>, Option<(u64, Vec<u8>)>)> {
+        let keys: Vec<Binary> = from_json_slice(raw_keys)?;
+        let keys = keys.iter().map(|key| key.as_ref()).collect::<Vec<_>>();
+        let (values, proof) = self.do_query_store_many(&keys, height, prove)?;
+        let values = values
+            .into_iter()
+            .map(|value| value.map(Binary::from))
+            .collect::<Vec<_>>();
+        Ok((to_json_vec(&values)?, proof))
+    }
+
+    /// Performs a raw query of multiple keys in the app's underlying key-value
+    /// store. Same as `do_query_store`, but with a single Merkle proof for all
+    /// the keys. There can be at most `MAX_BATCH_PROOF_KEYS` keys.
+    #[allow(clippy::type_complexity)]
+    pub fn do_query_store_many(
+        &self,
+        keys: &[&[u8]],
+        height: u64,
+        prove: bool,
+    ) -> AppResult<(Vec<Option<Vec<u8>>>, Option<(u64, Vec<u8>)>)> {
+        if keys.len() > MAX_BATCH_PROOF_KEYS {
+            return Err(AppError::too_many_keys(MAX_BATCH_PROOF_KEYS, keys.len()));
+        }
+
+        let version = self.query_version(height, prove);
+
+        let proof = if prove {
+            let proof = to_json_vec(&self.db.prove_many(keys, version)?)?;
+            Some((version.unwrap_or_default(), proof))
+        } else {
+            None
+        };
+
+        let storage = self.db.state_storage(version);
+        let values = keys.iter().map(|key| storage.read(key)).collect();
+
+        Ok((values, proof))
+    }
+
+    /// Same as `do_query_store_range`, but the request is given as a
+    /// JSON-encoded `StoreRangeRequest`, and the records are returned as a JSON
+    /// array of `[key, value]` pairs.
+    pub fn do_query_store_range_raw(&self, raw_req: &[u8], height: u64) -> AppResult<Vec<u8>> {
+        let req: StoreRangeRequest = from_json_slice(raw_req)?;
+        let records = self
+            .do_query_store_range(
+                req.min.as_deref(),
+                req.max.as_deref(),
+                req.order,
+                req.limit,
+                height,
+            )?
+            .into_iter()
+            .map(|(key, value)| (Binary::from(key), Binary::from(value)))
+            .collect::<Vec<_>>();
+        Ok(to_json_vec(&records)?)
+    }
+
+    /// Performs a range query of the app's underlying key-value store. `min` is
+    /// inclusive and `max` exclusive. Returns at most `limit` records, which
+    /// defaults to `DEFAULT_PAGE_LIMIT` and is capped by `MAX_PAGE_LIMIT`.
+    ///
+    /// Merkle proofs aren't supported for range queries.
+    pub fn do_query_store_range(
+        &self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+        limit: Option<u32>,
+        height: u64,
+    ) -> AppResult<Vec<Record>> {
+        let version = self.query_version(height, false);
+
+        let limit = page_limit(limit);
+        let records = self
+            .db
+            .state_storage(version)
+            .scan(min, max, order)
+            .take(limit as usize)
+            .collect();
+
+        Ok(records)
+    }
+
+    // the version at which to perform a query at the given height.
+    //
+    // height being zero means unspecified (protobuf doesn't have a null type)
+    // in which case we use the latest version. if a proof is requested, pin
+    // the version, so that the response and the proof are guaranteed to be
+    // generated from the same state, even if a new block is committed in the
+    // meantime.
+    fn query_version(&self, height: u64, prove: bool) -> Option<u64> {
+        if height != 0 {
+            Some(height)
+        } else if prove {
+            self.db.latest_version()
+        } else {
+            None
+        }
+    }
 }
 
 fn process_tx<S, VM>(storage: S, block: &BlockInfo, tx: Tx) -> AppResult<Vec<Event>>
@@ -372,6 +566,9 @@ where
 {
     match msg {
         Message::SetConfig { new_cfg } => do_set_config(&mut storage, sender, &new_cfg),
+        Message::ScheduleUpgrade { height, name, info } => {
+            do_schedule_upgrade(&mut storage, block, sender, height, name, info)
+        },
         Message::Transfer { to, coins } => {
             do_transfer::<VM>(storage, block, sender.clone(), to, coins, true)
         },
@@ -431,34 +628,40 @@ where
         QueryRequest::Balance { address, denom } => {
             query_balance::<VM>(storage, block, address, denom).map(QueryResponse::Balance)
         },
-        QueryRequest::Balances {
-            address,
-            start_after,
-            limit,
-        } => query_balances::<VM>(storage, block, address, start_after, limit)
-            .map(QueryResponse::Balances),
+        QueryRequest::Balances { address, page } => {
+            query_balances::<VM>(storage, block, address, page).map(QueryResponse::Balances)
+        },
         QueryRequest::Supply { denom } => {
             query_supply::<VM>(storage, block, denom).map(QueryResponse::Supply)
         },
-        QueryRequest::Supplies { start_after, limit } => {
-            query_supplies::<VM>(storage, block, start_after, limit).map(QueryResponse::Supplies)
+        QueryRequest::Supplies { page } => {
+            query_supplies::<VM>(storage, block, page).map(QueryResponse::Supplies)
         },
         QueryRequest::Code { hash } => query_code(&storage, hash).map(QueryResponse::Code),
-        QueryRequest::Codes { start_after, limit } => {
-            query_codes(&storage, start_after, limit).map(QueryResponse::Codes)
-        },
+        QueryRequest::Codes { page } => query_codes(&storage, page).map(QueryResponse::Codes),
         QueryRequest::Account { address } => {
             query_account(&storage, address).map(QueryResponse::Account)
         },
-        QueryRequest::Accounts { start_after, limit } => {
-            query_accounts(&storage, start_after, limit).map(QueryResponse::Accounts)
+        QueryRequest::Accounts { page } => {
+            query_accounts(&storage, page).map(QueryResponse::Accounts)
         },
         QueryRequest::WasmRaw { contract, key } => {
             query_wasm_raw(storage, contract, key).map(QueryResponse::WasmRaw)
         },
+        QueryRequest::WasmRawRange {
+            contract,
+            min,
+            max,
+            page,
+        } => {
+            query_wasm_raw_range(storage, contract, min, max, page).map(QueryResponse::WasmRawRange)
+        },
         QueryRequest::WasmSmart { contract, msg } => {
             query_wasm_smart::<VM>(storage, block, contract, msg).map(QueryResponse::WasmSmart)
         },
+        QueryRequest::Multi(reqs) => {
+            query_multi::<VM>(storage, block, reqs).map(QueryResponse::Multi)
+        },
     }
 }
 
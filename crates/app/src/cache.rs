@@ -1,19 +1,25 @@
 use {
-    grug_types::{Batch, Op, Order, Record, Storage},
-    std::{
-        cmp::Ordering,
-        iter::{self, Peekable},
-        mem,
-        ops::Bound,
-    },
+    grug_types::{Batch, Merged, Op, Order, Record, Storage},
+    std::{iter, mem, ops::Bound},
 };
 
+/// A range of keys, as `(min, max)` bounds. Same as with `Storage::scan`, the
+/// minimum bound is inclusive, and the maximum bound is exclusive.
+type Range = (Option<Vec<u8>>, Option<Vec<u8>>);
+
 /// Adapted from cw-multi-test:
 /// <https://github.com/CosmWasm/cw-multi-test/blob/v0.19.0/src/transactions.rs#L170-L253>
 #[derive(Clone)]
 pub struct CacheStore<S: Clone> {
     base: S,
     pub(crate) pending: Batch,
+    /// Ranges removed from the underlying store. They shadow the records of the
+    /// underlying store, but not pending ops, since pending ops in a range are
+    /// discarded when the range is removed.
+    ///
+    /// Overlapping and adjacent ranges are merged, so the ranges are disjoint
+    /// and sorted, and a key can be looked up by binary search.
+    removed_ranges: Vec<Range>,
 }
 
 impl<S: Clone> CacheStore<S> {
@@ -22,19 +28,89 @@ impl<S: Clone> CacheStore<S> {
         Self {
             base,
             pending: pending.unwrap_or_default(),
+            removed_ranges: Vec::new(),
         }
     }
 
+    fn is_removed(&self, key: &[u8]) -> bool {
+        // the only range that may contain the key is the last one starting at
+        // or before it
+        let index = self
+            .removed_ranges
+            .partition_point(|(min, _)| min.as_deref().map_or(true, |min| min <= key));
+        let Some(index) = index.checked_sub(1) else {
+            return false;
+        };
+
+        let (_, max) = &self.removed_ranges[index];
+        max.as_deref().map_or(true, |max| key < max)
+    }
+
+    fn insert_removed_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        // the existing ranges that overlap or are adjacent to the new one are
+        // contiguous: those after the ones ending before it starts, and before
+        // the ones starting after it ends
+        let start = self
+            .removed_ranges
+            .partition_point(|(_, r_max)| ends_before(r_max.as_deref(), min));
+        let end = self
+            .removed_ranges
+            .partition_point(|(r_min, _)| !ends_before(max, r_min.as_deref()));
+
+        // merge them with the new range. `None` is the lowest minimum bound,
+        // but the highest maximum bound.
+        let mut merged = (min.map(|min| min.to_vec()), max.map(|max| max.to_vec()));
+        if start < end {
+            merged.0 = merged.0.min(self.removed_ranges[start].0.clone());
+            merged.1 = match (merged.1, &self.removed_ranges[end - 1].1) {
+                (Some(max), Some(r_max)) => Some(max.max(r_max.clone())),
+                _ => None,
+            };
+        }
+
+        self.removed_ranges.splice(start..end, [merged]);
+    }
+}
+
+// whether a range with the given exclusive maximum bound ends before one with
+// the given inclusive minimum bound starts, i.e. they neither overlap nor are
+// adjacent. `None` means unbounded.
+fn ends_before(max: Option<&[u8]>, min: Option<&[u8]>) -> bool {
+    match (max, min) {
+        (Some(max), Some(min)) => max < min,
+        _ => false,
+    }
+}
+
+impl<S: Storage + Clone> CacheStore<S> {
     /// Comsume self, do not flush, just return the underlying store and the
     /// pending ops.
+    ///
+    /// Since a batch can only contain ops on individual keys, removed ranges
+    /// are converted into deletions of the records of the underlying store in
+    /// them.
     pub fn disassemble(self) -> (S, Batch) {
-        (self.base, self.pending)
+        let mut pending = self.pending;
+
+        for (min, max) in &self.removed_ranges {
+            for (key, _) in self
+                .base
+                .scan(min.as_deref(), max.as_deref(), Order::Ascending)
+            {
+                pending.entry(key).or_insert(Op::Delete);
+            }
+        }
+
+        (self.base, pending)
     }
-}
 
-impl<S: Storage + Clone> CacheStore<S> {
-    /// Flush pending ops to the underlying store.
+    /// Flush pending ops to the underlying store. Removed ranges are removed
+    /// from the underlying store first, each in one `remove_range` call.
     pub fn commit(&mut self) {
+        for (min, max) in mem::take(&mut self.removed_ranges) {
+            self.base.remove_range(min.as_deref(), max.as_deref());
+        }
+
         let pending = mem::take(&mut self.pending);
         self.base.flush(pending);
     }
@@ -42,7 +118,7 @@ impl<S: Storage + Clone> CacheStore<S> {
     /// Consume self, flush pending ops to the underlying store, return the
     /// underlying store.
     pub fn consume(mut self) -> S {
-        self.base.flush(self.pending);
+        self.commit();
         self.base
     }
 }
@@ -52,6 +128,7 @@ impl<S: Storage + Clone> Storage for CacheStore<S> {
         match self.pending.get(key) {
             Some(Op::Insert(value)) => Some(value.clone()),
             Some(Op::Delete) => None,
+            None if self.is_removed(key) => None,
             None => self.base.read(key),
         }
     }
@@ -68,7 +145,10 @@ impl<S: Storage + Clone> Storage for CacheStore<S> {
             }
         }
 
-        let base = self.base.scan(min, max, order);
+        let base = self
+            .base
+            .scan(min, max, order)
+            .filter(|(key, _)| !self.is_removed(key));
 
         let min = min.map_or(Bound::Unbounded, |bytes| Bound::Included(bytes.to_vec()));
         let max = max.map_or(Bound::Unbounded, |bytes| Bound::Excluded(bytes.to_vec()));
@@ -90,74 +170,34 @@ impl<S: Storage + Clone> Storage for CacheStore<S> {
         self.pending.insert(key.to_vec(), Op::Delete);
     }
 
-    fn flush(&mut self, batch: Batch) {
-        // if we do a.extend(b), while a and b have common keys, the values in b
-        // are chosen. this is exactly what we want.
-        self.pending.extend(batch);
-    }
-}
-
-struct Merged<'a, B, P>
-where
-    B: Iterator<Item = Record>,
-    P: Iterator<Item = (&'a Vec<u8>, &'a Op)>,
-{
-    base: Peekable<B>,
-    pending: Peekable<P>,
-    order: Order,
-}
-
-impl<'a, B, P> Merged<'a, B, P>
-where
-    B: Iterator<Item = Record>,
-    P: Iterator<Item = (&'a Vec<u8>, &'a Op)>,
-{
-    pub fn new(base: B, pending: P, order: Order) -> Self {
-        Self {
-            base: base.peekable(),
-            pending: pending.peekable(),
-            order,
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min >= max {
+                return;
+            }
         }
-    }
 
-    fn take_pending(&mut self) -> Option<Record> {
-        let (key, op) = self.pending.next()?;
-        match op {
-            Op::Insert(value) => Some((key.clone(), value.clone())),
-            Op::Delete => self.next(),
+        // pending ops in the range are simply discarded; records in the range
+        // in the base store are shadowed by the removed range, without having
+        // to iterate them.
+        let min_bound = min.map_or(Bound::Unbounded, |bytes| Bound::Included(bytes.to_vec()));
+        let max_bound = max.map_or(Bound::Unbounded, |bytes| Bound::Excluded(bytes.to_vec()));
+        let pending_keys = self
+            .pending
+            .range((min_bound, max_bound))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in pending_keys {
+            self.pending.remove(&key);
         }
+
+        self.insert_removed_range(min, max);
     }
-}
 
-impl<'a, B, P> Iterator for Merged<'a, B, P>
-where
-    B: Iterator<Item = Record>,
-    P: Iterator<Item = (&'a Vec<u8>, &'a Op)>,
-{
-    type Item = Record;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match (self.base.peek(), self.pending.peek()) {
-            (Some((base_key, _)), Some((pending_key, _))) => {
-                let ordering_raw = base_key.cmp(pending_key);
-                let ordering = match self.order {
-                    Order::Ascending => ordering_raw,
-                    Order::Descending => ordering_raw.reverse(),
-                };
-
-                match ordering {
-                    Ordering::Less => self.base.next(),
-                    Ordering::Equal => {
-                        self.base.next();
-                        self.take_pending()
-                    },
-                    Ordering::Greater => self.take_pending(),
-                }
-            },
-            (None, Some(_)) => self.take_pending(),
-            (Some(_), None) => self.base.next(),
-            (None, None) => None,
-        }
+    fn flush(&mut self, batch: Batch) {
+        // if we do a.extend(b), while a and b have common keys, the values in b
+        // are chosen. this is exactly what we want.
+        self.pending.extend(batch);
     }
 }
 
@@ -213,5 +253,138 @@ mod tests {
         assert_eq!(collect_records(&cached, Order::Descending), merged);
     }
 
+    #[test]
+    fn removing_range_works() {
+        let (mut cached, _) = make_test_case();
+
+        // base    : 1 2 _ 4 5 6 7 _
+        // pending :   D _ D D D D 8
+        // merged  : 1 _ _ _ _ _ _ 8
+        cached.remove_range(Some(&[2]), Some(&[8]));
+        assert_eq!(collect_records(&cached, Order::Ascending), [
+            (vec![1], vec![1]),
+            (vec![8], vec![8]),
+        ]);
+
+        // the removal carries over to the base store once committed
+        let base = cached.consume();
+        assert_eq!(collect_records(&base, Order::Ascending), [
+            (vec![1], vec![1]),
+            (vec![8], vec![8]),
+        ]);
+    }
+
+    #[test]
+    fn removing_range_doesnt_iterate_base() {
+        let mut base = MockStorage::new();
+        for i in 0..100 {
+            base.write(&[i], &[i]);
+        }
+
+        // the range is recorded as a whole, instead of as a tombstone for each
+        // record in it
+        let mut cached = CacheStore::new(base, None);
+        cached.remove_range(Some(&[10]), Some(&[90]));
+        assert!(cached.pending.is_empty());
+        assert_eq!(cached.read(&[9]), Some(vec![9]));
+        assert_eq!(cached.read(&[10]), None);
+        assert_eq!(cached.read(&[89]), None);
+        assert_eq!(cached.read(&[90]), Some(vec![90]));
+
+        // records written into the range afterwards are visible
+        cached.write(&[50], &[255]);
+        assert_eq!(cached.read(&[50]), Some(vec![255]));
+        assert_eq!(
+            cached
+                .scan(Some(&[8]), Some(&[92]), Order::Descending)
+                .collect::<Vec<_>>(),
+            [
+                (vec![91], vec![91]),
+                (vec![90], vec![90]),
+                (vec![50], vec![255]),
+                (vec![9], vec![9]),
+                (vec![8], vec![8]),
+            ]
+        );
+
+        // committed into another cache, the range is removed there as a whole
+        // as well
+        let mut outer = CacheStore::new(cached, None);
+        outer.remove_range(Some(&[0]), Some(&[5]));
+        let mut cached = outer.consume();
+        assert_eq!(cached.removed_ranges.len(), 2);
+        assert_eq!(cached.pending.len(), 1);
+        assert_eq!(cached.read(&[4]), None);
+        assert_eq!(cached.read(&[5]), Some(vec![5]));
+
+        // when disassembled, the ranges are converted into deletions of the
+        // records in the base store
+        cached.remove(&[95]);
+        let (_, batch) = cached.disassemble();
+        assert_eq!(
+            batch.len(),
+            5 + 80 + 1,
+            "5 and 80 records in the ranges, plus the one removed afterwards"
+        );
+        assert_eq!(batch.get(&vec![50]), Some(&Op::Insert(vec![255])));
+        assert_eq!(batch.get(&vec![0]), Some(&Op::Delete));
+        assert_eq!(batch.get(&vec![95]), Some(&Op::Delete));
+    }
+
+    #[test]
+    fn removed_ranges_are_merged() {
+        let mut cached = CacheStore::new(MockStorage::new(), None);
+
+        let range = |min: Option<u8>, max: Option<u8>| (min.map(|b| vec![b]), max.map(|b| vec![b]));
+
+        // disjoint ranges are kept sorted
+        cached.remove_range(Some(&[50]), Some(&[60]));
+        cached.remove_range(Some(&[10]), Some(&[20]));
+        cached.remove_range(Some(&[30]), Some(&[40]));
+        assert_eq!(cached.removed_ranges, [
+            range(Some(10), Some(20)),
+            range(Some(30), Some(40)),
+            range(Some(50), Some(60)),
+        ]);
+
+        // empty ranges are ignored
+        cached.remove_range(Some(&[25]), Some(&[25]));
+        assert_eq!(cached.removed_ranges.len(), 3);
+
+        // adjacent ranges are merged, and so are overlapping ones
+        cached.remove_range(Some(&[20]), Some(&[25]));
+        cached.remove_range(Some(&[35]), Some(&[55]));
+        assert_eq!(cached.removed_ranges, [
+            range(Some(10), Some(25)),
+            range(Some(30), Some(60)),
+        ]);
+
+        // a range containing others replaces them
+        cached.remove_range(Some(&[5]), Some(&[70]));
+        assert_eq!(cached.removed_ranges, [range(Some(5), Some(70))]);
+
+        // unbounded ends take over
+        cached.remove_range(None, Some(&[6]));
+        cached.remove_range(Some(&[80]), None);
+        assert_eq!(cached.removed_ranges, [
+            range(None, Some(70)),
+            range(Some(80), None),
+        ]);
+
+        for (key, removed) in [
+            (0, true),
+            (69, true),
+            (70, false),
+            (79, false),
+            (80, true),
+            (255, true),
+        ] {
+            assert_eq!(cached.is_removed(&[key]), removed);
+        }
+
+        cached.remove_range(Some(&[70]), Some(&[80]));
+        assert_eq!(cached.removed_ranges, [range(None, None)]);
+    }
+
     // TODO: add fuzz test
 }
@@ -1,5 +1,5 @@
 use {
-    grug_types::{Addr, Hash, StdError},
+    grug_types::{Addr, Binary, Hash, StdError},
     thiserror::Error,
 };
 
@@ -14,9 +14,18 @@ pub enum AppError {
     #[error("DB error: {0}")]
     Db(String),
 
-    #[error("Merkle proof is not support for `/app` query; use `/store` instead")]
+    #[error("Merkle proof is only supported for `code`, `account`, and `wasm_raw` queries")]
     ProofNotSupported,
 
+    #[error("Multi query can't contain another multi query")]
+    NestedMultiQuery,
+
+    #[error("Too many queries in a multi query! max: {max}, actual: {actual}")]
+    TooManyQueries { max: usize, actual: usize },
+
+    #[error("Too many keys in a batch query! max: {max}, actual: {actual}")]
+    TooManyKeys { max: usize, actual: usize },
+
     #[error("The sender does not have permission to perform this action")]
     Unauthorized,
 
@@ -29,6 +38,18 @@ pub enum AppError {
     #[error("Sender is not the owner! sender: {sender}, owner: {owner}")]
     NotOwner { sender: Addr, owner: Addr },
 
+    #[error("Height is not yet finalized! latest: {latest}, requested: {height}")]
+    HeightNotFinalized { latest: u64, height: u64 },
+
+    #[error("State at height {height} has been pruned")]
+    StatePruned { height: u64 },
+
+    #[error("Upgrade height is not in the future! current: {current}, upgrade: {height}")]
+    UpgradeHeightNotInFuture { current: u64, height: u64 },
+
+    #[error("Upgrade `{name}` needed at height {height}")]
+    UpgradeNeeded { name: String, height: u64 },
+
     #[error("Admin account is not set")]
     AdminNotSet,
 
@@ -38,9 +59,18 @@ pub enum AppError {
     #[error("Wasm byte code with hash `{hash}` already exists")]
     CodeExists { hash: Hash },
 
+    #[error("Code hash mismatch! expecting: {expect}, actual: {actual}")]
+    CodeHashMismatch { expect: Hash, actual: Hash },
+
     #[error("Account with address `{address}` already exists")]
     AccountExists { address: Addr },
 
+    #[error("Contract storage key is too short to contain an address: {key}")]
+    ContractStorageKeyTooShort { key: String },
+
+    #[error("Page cursor is outside the queried range! cursor: {cursor}")]
+    CursorOutOfRange { cursor: Binary },
+
     #[error("Code hash is not allowed as IBC client: `{code_hash}`")]
     NotAllowedClient { code_hash: Hash },
 }
@@ -50,10 +80,33 @@ impl AppError {
         Self::IncorrectBlockHeight { expect, actual }
     }
 
+    pub fn too_many_queries(max: usize, actual: usize) -> Self {
+        Self::TooManyQueries { max, actual }
+    }
+
+    pub fn too_many_keys(max: usize, actual: usize) -> Self {
+        Self::TooManyKeys { max, actual }
+    }
+
     pub fn not_owner(sender: Addr, owner: Addr) -> Self {
         Self::NotOwner { sender, owner }
     }
 
+    pub fn height_not_finalized(latest: u64, height: u64) -> Self {
+        Self::HeightNotFinalized { latest, height }
+    }
+
+    pub fn upgrade_height_not_in_future(current: u64, height: u64) -> Self {
+        Self::UpgradeHeightNotInFuture { current, height }
+    }
+
+    pub fn upgrade_needed(name: impl Into<String>, height: u64) -> Self {
+        Self::UpgradeNeeded {
+            name: name.into(),
+            height,
+        }
+    }
+
     pub fn not_admin(sender: Addr, admin: Addr) -> Self {
         Self::NotAdmin { sender, admin }
     }
@@ -62,10 +115,24 @@ impl AppError {
         Self::CodeExists { hash }
     }
 
+    pub fn code_hash_mismatch(expect: Hash, actual: Hash) -> Self {
+        Self::CodeHashMismatch { expect, actual }
+    }
+
     pub fn account_exists(address: Addr) -> Self {
         Self::AccountExists { address }
     }
 
+    pub fn cursor_out_of_range(cursor: Binary) -> Self {
+        Self::CursorOutOfRange { cursor }
+    }
+
+    pub fn contract_storage_key_too_short(key: &[u8]) -> Self {
+        Self::ContractStorageKeyTooShort {
+            key: hex::encode(key),
+        }
+    }
+
     pub fn not_allowed_client(code_hash: Hash) -> Self {
         Self::NotAllowedClient { code_hash }
     }
@@ -1,4 +1,4 @@
-use grug_types::{Addr, Attribute, Event, Hash};
+use grug_types::{Addr, Attribute, Event, Hash, Upgrade};
 
 // Event attribute keys emitted by the state machine are prefixed by an
 // underscore. Contracts are not allowed to emit event attributes whose keys are
@@ -32,6 +32,19 @@ pub fn new_set_config_event(sender: &Addr) -> Event {
     Event::new("set_config").add_attribute("sender", sender)
 }
 
+pub fn new_schedule_upgrade_event(sender: &Addr, upgrade: &Upgrade) -> Event {
+    Event::new("schedule_upgrade")
+        .add_attribute("sender", sender)
+        .add_attribute("height", upgrade.height)
+        .add_attribute("name", &upgrade.name)
+}
+
+pub fn new_upgrade_event(upgrade: &Upgrade) -> Event {
+    Event::new("upgrade")
+        .add_attribute("height", upgrade.height)
+        .add_attribute("name", &upgrade.name)
+}
+
 pub fn new_upload_event(code_hash: &Hash, uploader: &Addr) -> Event {
     Event::new("upload")
         .add_attribute("hash", code_hash)
@@ -0,0 +1,103 @@
+use {
+    crate::{
+        AppError, AppResult, ACCOUNTS, CHAIN_ID, CODES, CONFIG, CONTRACT_NAMESPACE,
+        LAST_FINALIZED_BLOCK, NEXT_UPGRADE,
+    },
+    grug_types::{
+        hash, increment_last_byte, Addr, BlockInfo, Hash, Order, RawGenesisState, StdResult,
+        Storage, Uint64,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Write a raw genesis state into the storage.
+///
+/// The chain ID and genesis block are those of the new chain, not the ones of
+/// the chain from which the state was exported. The next upgrade, if any, is
+/// rescheduled to the same number of blocks after the new chain's genesis as
+/// it was after the height of the export.
+pub fn import_state(
+    storage: &mut dyn Storage,
+    chain_id: &str,
+    block: &BlockInfo,
+    state: RawGenesisState,
+) -> AppResult<()> {
+    CHAIN_ID.save(storage, &chain_id.to_string())?;
+    CONFIG.save(storage, &state.config)?;
+    LAST_FINALIZED_BLOCK.save(storage, block)?;
+
+    if let Some(mut upgrade) = state.next_upgrade {
+        let blocks_left = upgrade
+            .height
+            .number()
+            .saturating_sub(state.height.number());
+        upgrade.height = Uint64::new(block.height.number() + blocks_left);
+        NEXT_UPGRADE.save(storage, &upgrade)?;
+    }
+
+    for (code_hash, code) in state.codes {
+        // make sure the code matches its hash, otherwise accounts referencing
+        // this hash would end up running a different code
+        let actual = hash(&code);
+        if actual != code_hash {
+            return Err(AppError::code_hash_mismatch(code_hash, actual));
+        }
+
+        CODES.save(storage, &code_hash, &code.into())?;
+    }
+
+    for (address, account) in state.accounts {
+        ACCOUNTS.save(storage, &address, &account)?;
+    }
+
+    for (address, records) in state.contract_storages {
+        for (key, value) in records {
+            storage.write(&[CONTRACT_NAMESPACE, &address, &key].concat(), &value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the chain's state from the storage, in the form of a raw genesis state.
+pub fn export_state(storage: &dyn Storage) -> AppResult<RawGenesisState> {
+    let chain_id = CHAIN_ID.load(storage)?;
+    let height = LAST_FINALIZED_BLOCK.load(storage)?.height;
+    let config = CONFIG.load(storage)?;
+
+    let codes = CODES
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(code_hash, code)| (code_hash, code.into())))
+        .collect::<StdResult<_>>()?;
+
+    let accounts = ACCOUNTS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+
+    let next_upgrade = NEXT_UPGRADE.may_load(storage)?;
+
+    // contract storage keys are in the format: "wasm" | address | key
+    let mut contract_storages = BTreeMap::<_, BTreeMap<_, _>>::new();
+    let max = increment_last_byte(CONTRACT_NAMESPACE.to_vec());
+    for (k, v) in storage.scan(Some(CONTRACT_NAMESPACE), Some(&max), Order::Ascending) {
+        let rest = &k[CONTRACT_NAMESPACE.len()..];
+        if rest.len() < Hash::LENGTH {
+            return Err(AppError::contract_storage_key_too_short(&k));
+        }
+        let (address, key) = rest.split_at(Hash::LENGTH);
+        contract_storages
+            .entry(Addr::try_from(address)?)
+            .or_default()
+            .insert(key.to_vec().into(), v.into());
+    }
+
+    Ok(RawGenesisState {
+        chain_id,
+        height,
+        config,
+        codes,
+        accounts,
+        contract_storages,
+        next_upgrade,
+    })
+}
@@ -11,6 +11,7 @@ mod cron;
 mod error;
 mod events;
 mod execute;
+mod genesis;
 mod instantiate;
 mod migrate;
 mod prefix;
@@ -21,11 +22,15 @@ mod state;
 mod submessage;
 mod traits;
 mod transfer;
+mod upgrade;
 mod upload;
 mod vm;
 
 pub use crate::{
     app::*, auth::*, cache::*, client::*, config::*, cron::*, error::*, events::*, execute::*,
-    instantiate::*, migrate::*, prefix::*, querier::*, query::*, shared::*, state::*,
-    submessage::*, traits::*, transfer::*, upload::*, vm::*,
+    genesis::*, instantiate::*, migrate::*, prefix::*, querier::*, query::*, shared::*, state::*,
+    submessage::*, traits::*, transfer::*, upgrade::*, upload::*, vm::*,
 };
+
+#[cfg(feature = "abci")]
+pub use crate::abci::*;
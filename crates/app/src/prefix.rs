@@ -1,4 +1,4 @@
-use grug_types::{concat, increment_last_byte, Order, Record, Storage};
+use grug_types::{concat, increment_last_byte, trim, Order, Record, Storage};
 
 #[derive(Clone)]
 pub struct PrefixStore {
@@ -20,6 +20,18 @@ impl PrefixStore {
 
         Self { storage, namespace }
     }
+
+    fn prefixed_bounds(&self, min: Option<&[u8]>, max: Option<&[u8]>) -> (Vec<u8>, Vec<u8>) {
+        let min = match min {
+            Some(bytes) => concat(&self.namespace, bytes),
+            None => self.namespace.to_vec(),
+        };
+        let max = match max {
+            Some(bytes) => concat(&self.namespace, bytes),
+            None => increment_last_byte(self.namespace.to_vec()),
+        };
+        (min, max)
+    }
 }
 
 impl Storage for PrefixStore {
@@ -34,15 +46,15 @@ impl Storage for PrefixStore {
         max: Option<&[u8]>,
         order: Order,
     ) -> Box<dyn Iterator<Item = Record> + 'a> {
-        let min = match min {
-            Some(bytes) => concat(&self.namespace, bytes),
-            None => self.namespace.to_vec(),
-        };
-        let max = match max {
-            Some(bytes) => concat(&self.namespace, bytes),
-            None => increment_last_byte(self.namespace.to_vec()),
-        };
-        self.storage.scan(Some(&min), Some(&max), order)
+        let (min, max) = self.prefixed_bounds(min, max);
+        // strip the namespace, so that the keys are the same as the ones that
+        // were written
+        let namespace = self.namespace.clone();
+        let iter = self
+            .storage
+            .scan(Some(&min), Some(&max), order)
+            .map(move |(k, v)| (trim(&namespace, &k), v));
+        Box::new(iter)
     }
 
     fn write(&mut self, key: &[u8], value: &[u8]) {
@@ -54,4 +66,9 @@ impl Storage for PrefixStore {
         let prefixed_key = concat(&self.namespace, key);
         self.storage.remove(&prefixed_key);
     }
+
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        let (min, max) = self.prefixed_bounds(min, max);
+        self.storage.remove_range(Some(&min), Some(&max));
+    }
 }
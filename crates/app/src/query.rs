@@ -1,18 +1,16 @@
 use {
     crate::{
-        create_vm_instance, load_program, AppError, AppResult, PrefixStore, Vm, ACCOUNTS, CHAIN_ID,
-        CODES, CONFIG, CONTRACT_NAMESPACE, LAST_FINALIZED_BLOCK,
+        create_vm_instance, load_program, process_query, AppError, AppResult, PrefixStore, Vm,
+        ACCOUNTS, CHAIN_ID, CODES, CONFIG, CONTRACT_NAMESPACE, LAST_FINALIZED_BLOCK,
     },
-    grug_storage::Bound,
     grug_types::{
-        AccountResponse, Addr, BankQueryMsg, BankQueryResponse, Binary, BlockInfo, Coin, Coins,
-        Context, Hash, InfoResponse, Json, Order, StdResult, Storage, WasmRawResponse,
-        WasmSmartResponse,
+        extend_one_byte, AccountResponse, Addr, BankQueryMsg, BankQueryResponse, Binary, BlockInfo,
+        Coin, Context, GenericResult, Hash, InfoResponse, Json, Order, Page, PageRequest,
+        QueryRequest, QueryResponse, Storage, WasmRawResponse, WasmSmartResponse, MAX_COUNT_TOTAL,
+        MAX_MULTI_QUERIES,
     },
 };
 
-const DEFAULT_PAGE_LIMIT: u32 = 30;
-
 pub fn query_info(storage: &dyn Storage) -> AppResult<InfoResponse> {
     Ok(InfoResponse {
         chain_id: CHAIN_ID.load(storage)?,
@@ -39,19 +37,23 @@ pub fn query_balances<VM>(
     storage: Box<dyn Storage>,
     block: &BlockInfo,
     address: Addr,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> AppResult<Coins>
+    page: PageRequest,
+) -> AppResult<Page<Coin>>
 where
     VM: Vm,
     AppError: From<VM::Error>,
 {
-    _query_bank::<VM>(storage, block, &BankQueryMsg::Balances {
+    let page = page.capped();
+    let res = _query_bank::<VM>(storage, block, &BankQueryMsg::Balances {
         address,
-        start_after,
-        limit,
-    })
-    .map(|res| res.as_balances())
+        page: page.clone(),
+    })?
+    .as_balances();
+
+    // the bank contract isn't trusted to respect the page limit
+    res.check_limit(&page)?;
+
+    Ok(res)
 }
 
 pub fn query_supply<VM>(
@@ -69,18 +71,22 @@ where
 pub fn query_supplies<VM>(
     storage: Box<dyn Storage>,
     block: &BlockInfo,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> AppResult<Coins>
+    page: PageRequest,
+) -> AppResult<Page<Coin>>
 where
     VM: Vm,
     AppError: From<VM::Error>,
 {
-    _query_bank::<VM>(storage, block, &BankQueryMsg::Supplies {
-        start_after,
-        limit,
-    })
-    .map(|res| res.as_supplies())
+    let page = page.capped();
+    let res = _query_bank::<VM>(storage, block, &BankQueryMsg::Supplies {
+        page: page.clone(),
+    })?
+    .as_supplies();
+
+    // the bank contract isn't trusted to respect the page limit
+    res.check_limit(&page)?;
+
+    Ok(res)
 }
 
 pub fn _query_bank<VM>(
@@ -122,19 +128,8 @@ pub fn query_code(storage: &dyn Storage, hash: Hash) -> AppResult<Binary> {
     Ok(CODES.load(storage, &hash)?.into())
 }
 
-pub fn query_codes(
-    storage: &dyn Storage,
-    start_after: Option<Hash>,
-    limit: Option<u32>,
-) -> AppResult<Vec<Hash>> {
-    let start = start_after.as_ref().map(Bound::exclusive);
-    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
-
-    CODES
-        .keys(storage, start, None, Order::Ascending)
-        .take(limit as usize)
-        .collect::<StdResult<Vec<_>>>()
-        .map_err(Into::into)
+pub fn query_codes(storage: &dyn Storage, page: PageRequest) -> AppResult<Page<Hash>> {
+    CODES.paginate_keys(storage, page).map_err(Into::into)
 }
 
 pub fn query_account(storage: &dyn Storage, address: Addr) -> AppResult<AccountResponse> {
@@ -148,24 +143,15 @@ pub fn query_account(storage: &dyn Storage, address: Addr) -> AppResult<AccountR
 
 pub fn query_accounts(
     storage: &dyn Storage,
-    start_after: Option<Addr>,
-    limit: Option<u32>,
-) -> AppResult<Vec<AccountResponse>> {
-    let start = start_after.as_ref().map(Bound::exclusive);
-    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
-
-    ACCOUNTS
-        .range(storage, start, None, Order::Ascending)
-        .take(limit as usize)
-        .map(|item| {
-            let (address, account) = item?;
-            Ok(AccountResponse {
-                address,
-                code_hash: account.code_hash,
-                admin: account.admin,
-            })
-        })
-        .collect()
+    page: PageRequest,
+) -> AppResult<Page<AccountResponse>> {
+    Ok(ACCOUNTS
+        .paginate(storage, page)?
+        .map(|(address, account)| AccountResponse {
+            address,
+            code_hash: account.code_hash,
+            admin: account.admin,
+        }))
 }
 
 pub fn query_wasm_raw(
@@ -182,6 +168,66 @@ pub fn query_wasm_raw(
     })
 }
 
+/// Return a page of a contract's raw records between `min` and `max`, starting
+/// after the cursor in the request, if any.
+///
+/// The cursor is the last key in the page. It's only returned if there are
+/// more records after it.
+pub fn query_wasm_raw_range(
+    storage: Box<dyn Storage>,
+    contract: Addr,
+    min: Option<Binary>,
+    max: Option<Binary>,
+    page: PageRequest,
+) -> AppResult<Page<(Binary, Binary)>> {
+    let limit = page.effective_limit() as usize;
+    let substore = PrefixStore::new(storage, &[CONTRACT_NAMESPACE, &contract]);
+
+    // count at most `MAX_COUNT_TOTAL` records, so that the cost of the query is
+    // bounded; if there are more, the total is left unknown
+    let total = page
+        .count_total
+        .then(|| {
+            substore
+                .scan(min.as_deref(), max.as_deref(), Order::Ascending)
+                .take(MAX_COUNT_TOTAL as usize + 1)
+                .count() as u64
+        })
+        .filter(|total| *total <= MAX_COUNT_TOTAL);
+
+    // resume right after the cursor. the cursor is supplied by the client, so
+    // make sure it's in the range, otherwise the page could include records
+    // outside of it.
+    let min = match page.cursor {
+        Some(cursor) => {
+            if min.as_ref().is_some_and(|min| cursor < *min)
+                || max.as_ref().is_some_and(|max| cursor >= *max)
+            {
+                return Err(AppError::cursor_out_of_range(cursor));
+            }
+            Some(extend_one_byte(cursor.into()))
+        },
+        None => min.map(Into::into),
+    };
+
+    // fetch one more record than the limit, to find out whether there are
+    // more pages
+    let mut items = substore
+        .scan(min.as_deref(), max.as_deref(), Order::Ascending)
+        .take(limit + 1)
+        .map(|(key, value)| (Binary::from(key), Binary::from(value)))
+        .collect::<Vec<_>>();
+
+    let next = if items.len() > limit {
+        items.truncate(limit);
+        items.last().map(|(key, _)| key.clone())
+    } else {
+        None
+    };
+
+    Ok(Page { items, next, total })
+}
+
 pub fn query_wasm_smart<VM>(
     storage: Box<dyn Storage>,
     block: &BlockInfo,
@@ -216,3 +262,30 @@ where
         data,
     })
 }
+
+pub fn query_multi<VM>(
+    storage: Box<dyn Storage>,
+    block: &BlockInfo,
+    reqs: Vec<QueryRequest>,
+) -> AppResult<Vec<GenericResult<QueryResponse>>>
+where
+    VM: Vm,
+    AppError: From<VM::Error>,
+{
+    if reqs.len() > MAX_MULTI_QUERIES {
+        return Err(AppError::too_many_queries(MAX_MULTI_QUERIES, reqs.len()));
+    }
+
+    // a nested multi query could be used to get around the limit above
+    if reqs.iter().any(|req| matches!(req, QueryRequest::Multi(_))) {
+        return Err(AppError::NestedMultiQuery);
+    }
+
+    // all sub-queries are performed against the same storage, so they see a
+    // consistent snapshot of the state. a failing sub-query doesn't abort the
+    // others; its error is returned in place of the response.
+    Ok(reqs
+        .into_iter()
+        .map(|req| process_query::<VM>(storage.clone(), block, req).into())
+        .collect())
+}
@@ -99,6 +99,10 @@ impl<S: Storage> Storage for SharedStore<S> {
         self.write_access().remove(key)
     }
 
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        self.write_access().remove_range(min, max)
+    }
+
     fn flush(&mut self, batch: Batch) {
         self.write_access().flush(batch)
     }
@@ -1,8 +1,12 @@
 use {
     grug_storage::{Item, Map},
-    grug_types::{Account, Addr, BlockInfo, Config, Hash},
+    grug_types::{
+        Account, Addr, BlockInfo, Config, Hash, Upgrade, ACCOUNTS_NAMESPACE, CODES_NAMESPACE,
+    },
 };
 
+pub use grug_types::CONTRACT_NAMESPACE;
+
 /// A string that identifies the chain
 pub const CHAIN_ID: Item<String> = Item::new("chain_id");
 
@@ -12,12 +16,11 @@ pub const CONFIG: Item<Config> = Item::new("config");
 /// The most recently finalized block
 pub const LAST_FINALIZED_BLOCK: Item<BlockInfo> = Item::new("last_finalized_block");
 
+/// The next scheduled software upgrade, if any
+pub const NEXT_UPGRADE: Item<Upgrade> = Item::new("next_upgrade");
+
 /// Wasm contract byte codes: code_hash => byte_code
-pub const CODES: Map<&Hash, Vec<u8>> = Map::new("code");
+pub const CODES: Map<&Hash, Vec<u8>> = Map::new(CODES_NAMESPACE);
 
 /// Account metadata: address => account
-pub const ACCOUNTS: Map<&Addr, Account> = Map::new("account");
-
-/// Each contract has its own storage space, which we term the "substore".
-/// A key in a contract's substore is prefixed by the word "wasm" + contract address.
-pub const CONTRACT_NAMESPACE: &[u8] = b"wasm";
+pub const ACCOUNTS: Map<&Addr, Account> = Map::new(ACCOUNTS_NAMESPACE);
@@ -42,6 +42,13 @@ pub trait Db {
     /// Type of the Merkle proof. The DB can choose any Merkle tree scheme.
     type Proof: Serialize + DeserializeOwned;
 
+    /// Type of the Merkle proof of multiple keys.
+    type BatchProof: Serialize + DeserializeOwned;
+
+    /// Type of the proof op under which ICS-23 proofs are returned, which tells
+    /// verifiers the proof spec to use, e.g. `ics23:iavl` in the Cosmos SDK.
+    const ICS23_PROOF_TYPE: &'static str;
+
     /// Return the state commitment as an owned, read-only, `Storage` object.
     /// This should be a _Merklized_ KV store that stores _hashed_ keys and _hashed_ values.
     fn state_commitment(&self) -> impl Storage + Clone + 'static;
@@ -66,6 +73,21 @@ pub trait Db {
     /// _membership_ proof; otherwise, it should be a _non-membership_ proof.
     fn prove(&self, key: &[u8], version: Option<u64>) -> Result<Self::Proof, Self::Error>;
 
+    /// Generate an [ICS-23](https://github.com/cosmos/ics23) Merkle proof of
+    /// the given key at the given version, encoded in protobuf, such that it
+    /// can be verified by IBC light clients on other chains.
+    /// If version is unspecified, use the latest version.
+    fn prove_ics23(&self, key: &[u8], version: Option<u64>) -> Result<Vec<u8>, Self::Error>;
+
+    /// Generate a Merkle proof of multiple keys at the given version, proving
+    /// the membership of keys that exist, and non-membership of those that
+    /// don't. If version is unspecified, use the latest version.
+    fn prove_many(
+        &self,
+        keys: &[&[u8]],
+        version: Option<u64>,
+    ) -> Result<Self::BatchProof, Self::Error>;
+
     /// Accept a batch ops (an op is either a DB insertion or a deletion), keep
     /// them in the memory, but do not persist to disk yet; also, increment the
     /// version.
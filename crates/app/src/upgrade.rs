@@ -0,0 +1,119 @@
+use {
+    crate::{
+        new_schedule_upgrade_event, new_upgrade_event, AppError, AppResult, CONFIG, NEXT_UPGRADE,
+    },
+    grug_types::{Addr, BlockInfo, Event, Storage, Uint64, Upgrade},
+    std::collections::BTreeMap,
+    tracing::{info, warn},
+};
+
+/// A function that migrates the host-level state (e.g. the format of `CONFIG`
+/// or the layout of `ACCOUNTS`) from the old software to the new one.
+///
+/// It's run once, at the beginning of the block at the upgrade height, before
+/// any begin blocker or transaction.
+pub type UpgradeHandler = fn(Box<dyn Storage>, &BlockInfo) -> AppResult<()>;
+
+/// Upgrade handlers registered in this software, indexed by upgrade names.
+pub type UpgradeHandlers = BTreeMap<String, UpgradeHandler>;
+
+pub fn do_schedule_upgrade(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+    height: Uint64,
+    name: String,
+    info: Option<String>,
+) -> AppResult<Vec<Event>> {
+    match _do_schedule_upgrade(storage, block, sender, height, name, info) {
+        Ok((events, upgrade)) => {
+            info!(
+                height = upgrade.height.number(),
+                name = upgrade.name,
+                "Scheduled upgrade"
+            );
+            Ok(events)
+        },
+        Err(err) => {
+            warn!(err = err.to_string(), "Failed to schedule upgrade");
+            Err(err)
+        },
+    }
+}
+
+// return the upgrade that is scheduled, for purpose of tracing/logging
+fn _do_schedule_upgrade(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+    height: Uint64,
+    name: String,
+    info: Option<String>,
+) -> AppResult<(Vec<Event>, Upgrade)> {
+    // make sure the sender is authorized to schedule upgrades
+    let cfg = CONFIG.load(storage)?;
+    let Some(owner) = cfg.owner else {
+        return Err(AppError::OwnerNotSet);
+    };
+    if sender != owner {
+        return Err(AppError::not_owner(sender.clone(), owner));
+    }
+
+    // the upgrade must happen at a future block. it can't be the current block
+    // either, since we're already in the middle of processing it.
+    if height.number() <= block.height.number() {
+        return Err(AppError::upgrade_height_not_in_future(
+            block.height.number(),
+            height.number(),
+        ));
+    }
+
+    // save the upgrade. this overwrites the previously scheduled one, if any
+    let upgrade = Upgrade { height, name, info };
+    NEXT_UPGRADE.save(storage, &upgrade)?;
+
+    Ok((vec![new_schedule_upgrade_event(sender, &upgrade)], upgrade))
+}
+
+/// If an upgrade is scheduled at the current block, run the upgrade handler,
+/// or if the handler isn't found (meaning we're running the old software),
+/// return an error, which halts the chain.
+pub fn do_upgrade(
+    mut storage: Box<dyn Storage>,
+    block: &BlockInfo,
+    handlers: &UpgradeHandlers,
+) -> AppResult<Vec<Event>> {
+    let Some(upgrade) = NEXT_UPGRADE.may_load(&storage)? else {
+        return Ok(vec![]);
+    };
+
+    if upgrade.height != block.height {
+        return Ok(vec![]);
+    }
+
+    let Some(handler) = handlers.get(&upgrade.name) else {
+        warn!(
+            height = upgrade.height.number(),
+            name = upgrade.name,
+            info = upgrade.info,
+            "Upgrade needed! Halting"
+        );
+        return Err(AppError::upgrade_needed(
+            upgrade.name,
+            upgrade.height.number(),
+        ));
+    };
+
+    handler(storage.clone(), block)?;
+
+    // remove the upgrade, so that the handler is only run once
+    NEXT_UPGRADE.remove(&mut storage);
+
+    info!(
+        height = upgrade.height.number(),
+        name = upgrade.name,
+        "Completed upgrade"
+    );
+
+    Ok(vec![new_upgrade_event(&upgrade)])
+}
@@ -0,0 +1,378 @@
+use {
+    crate::{DbError, DbResult, DiskDb},
+    grug_app::Db,
+    grug_types::{from_json_slice, to_json_vec, Hash},
+    serde::{Deserialize, Serialize},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Name of the subdirectory where a backup is assembled before it's moved into
+/// place. Not a number, so never mistaken for a backup.
+const INCOMPLETE_DIR: &str = "incomplete";
+
+/// Name of the subdirectory of a backup containing the RocksDB checkpoint.
+const DB_DIR: &str = "db";
+
+/// Name of the file in a backup containing its metadata.
+const INFO_FILE: &str = "info.json";
+
+/// Name of the file whose presence asks the node to back up the database after
+/// the next commit. Not a number, so never mistaken for a backup.
+const REQUEST_FILE: &str = "request";
+
+/// Metadata of a backup.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BackupInfo {
+    /// The latest version in the backup.
+    pub version: u64,
+    /// The Merkle root hash at that version.
+    pub root_hash: Option<Hash>,
+}
+
+/// A directory of database backups, one per version, laid out as:
+///
+/// ```plain
+/// {dir}/{version}/db         RocksDB checkpoint
+/// {dir}/{version}/info.json  metadata of the backup
+/// {dir}/request              present if a backup has been requested
+/// ```
+pub struct BackupDir {
+    dir: PathBuf,
+}
+
+impl BackupDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Back up the database at its latest committed version. If a backup of
+    /// that version already exists, it's replaced, since the version may have
+    /// been rolled back and committed again since.
+    pub fn create(&self, db: &DiskDb) -> DbResult<BackupInfo> {
+        let info = self.prepare(db)?;
+        self.complete(&info)?;
+        Ok(info)
+    }
+
+    // the first half of `create`: checkpoint the database, and write the
+    // metadata of the backup. the backup is assembled elsewhere, and only moved
+    // into place by `complete`, so that a crash midway doesn't leave a broken
+    // backup.
+    pub(crate) fn prepare(&self, db: &DiskDb) -> DbResult<BackupInfo> {
+        let incomplete_dir = self.dir.join(INCOMPLETE_DIR);
+        if incomplete_dir.exists() {
+            fs::remove_dir_all(&incomplete_dir)?;
+        }
+        fs::create_dir_all(&incomplete_dir)?;
+
+        let version = db
+            .checkpoint(incomplete_dir.join(DB_DIR))?
+            .ok_or(DbError::BackupEmptyDb)?;
+        let info = BackupInfo {
+            version,
+            root_hash: db.root_hash(Some(version))?,
+        };
+        fs::write(incomplete_dir.join(INFO_FILE), to_json_vec(&info)?)?;
+
+        Ok(info)
+    }
+
+    // the second half of `create`: move the backup prepared by `prepare` into
+    // place, replacing the existing one of the same version, if any.
+    pub(crate) fn complete(&self, info: &BackupInfo) -> DbResult<()> {
+        let path = self.path(info.version);
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::rename(self.dir.join(INCOMPLETE_DIR), path)?;
+
+        Ok(())
+    }
+
+    /// Ask the node using the database to back it up after one of its next
+    /// commits (see `AutoCheckpointConfig`).
+    /// This is how a running node is backed up, since RocksDB doesn't allow
+    /// another process to open its database meanwhile.
+    pub fn request(&self) -> DbResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(REQUEST_FILE), [])?;
+        Ok(())
+    }
+
+    /// Whether a backup has been requested. The request is cleared, so that
+    /// it's only fulfilled once.
+    pub fn take_request(&self) -> bool {
+        fs::remove_file(self.dir.join(REQUEST_FILE)).is_ok()
+    }
+
+    /// List the backups, in ascending order by version.
+    pub fn list(&self) -> DbResult<Vec<BackupInfo>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut infos = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            if let Some(version) = name.to_str().and_then(|name| name.parse().ok()) {
+                infos.push(self.info(version)?);
+            }
+        }
+        infos.sort_by_key(|info| info.version);
+
+        Ok(infos)
+    }
+
+    /// Load the metadata of the backup of the given version.
+    pub fn info(&self, version: u64) -> DbResult<BackupInfo> {
+        let path = self.path(version);
+        if !path.exists() {
+            return Err(DbError::BackupNotFound { version });
+        }
+
+        Ok(from_json_slice(fs::read(path.join(INFO_FILE))?)?)
+    }
+
+    /// Delete all but the most recent `keep_recent` backups.
+    pub fn prune(&self, keep_recent: usize) -> DbResult<()> {
+        let infos = self.list()?;
+        let num_to_delete = infos.len().saturating_sub(keep_recent);
+        for info in &infos[..num_to_delete] {
+            fs::remove_dir_all(self.path(info.version))?;
+        }
+
+        Ok(())
+    }
+
+    /// Restore the backup of the given version into the data directory,
+    /// replacing the database there, if any.
+    ///
+    /// The backup is first copied next to the data directory and checked:
+    /// its latest version and root hash must match its metadata, and its
+    /// state commitment must agree with its state storage (see
+    /// `DiskDb::verify`). Only then is the existing database replaced, so a
+    /// corrupted backup leaves it intact.
+    pub fn restore(&self, version: u64, data_dir: impl AsRef<Path>) -> DbResult<BackupInfo> {
+        let info = self.info(version)?;
+        let data_dir = data_dir.as_ref();
+
+        let restoring_dir = data_dir.with_extension("restoring");
+        if restoring_dir.exists() {
+            fs::remove_dir_all(&restoring_dir)?;
+        }
+        copy_dir(&self.path(version).join(DB_DIR), &restoring_dir)?;
+
+        // close the database before moving it
+        {
+            let db = DiskDb::open(&restoring_dir)?;
+
+            let found_version = db.latest_version();
+            if found_version != Some(version) {
+                return Err(DbError::BackupVersionMismatch {
+                    expected: version,
+                    found: found_version,
+                });
+            }
+
+            let found_root_hash = db.root_hash(Some(version))?;
+            if found_root_hash != info.root_hash {
+                return Err(DbError::BackupRootHashMismatch {
+                    version,
+                    expected: info.root_hash,
+                    found: found_root_hash,
+                });
+            }
+
+            if !db.verify(Some(version))?.is_ok() {
+                return Err(DbError::BackupCorrupted { version });
+            }
+        }
+
+        if data_dir.exists() {
+            fs::remove_dir_all(data_dir)?;
+        }
+        fs::rename(restoring_dir, data_dir)?;
+
+        Ok(info)
+    }
+
+    fn path(&self, version: u64) -> PathBuf {
+        self.dir.join(version.to_string())
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> DbResult<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let to = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to)?;
+        } else {
+            fs::copy(entry.path(), to)?;
+        }
+    }
+
+    Ok(())
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{AutoCheckpointConfig, DiskDbConfig, TempDataDir},
+        grug_types::{Batch, Op, Storage},
+        std::num::{NonZeroU64, NonZeroUsize},
+    };
+
+    fn commit(db: &DiskDb, counter: &str) -> Option<Hash> {
+        let batch = Batch::from([(b"counter".to_vec(), Op::Insert(counter.as_bytes().to_vec()))]);
+        let (_, root_hash) = db.flush_but_not_commit(batch).unwrap();
+        db.commit().unwrap();
+        // backups are moved into place in the background. wait for them to
+        // finish, so that they're listed.
+        db.wait_for_backup().unwrap();
+        root_hash
+    }
+
+    #[test]
+    fn backing_up_and_restoring() {
+        let path = TempDataDir::new("_grug_disk_db_backup");
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backups = BackupDir::new(backup_dir.path());
+
+        // take a checkpoint every 2 versions, keeping the 2 most recent ones
+        let db = DiskDb::open_with_config(&path, &DiskDbConfig {
+            auto_checkpoint: Some(AutoCheckpointConfig {
+                dir: backup_dir.path().to_path_buf(),
+                interval: NonZeroU64::new(2),
+                keep_recent: NonZeroUsize::new(2).unwrap(),
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut root_hashes = vec![];
+        for version in 0..7 {
+            root_hashes.push(commit(&db, &version.to_string()));
+        }
+
+        // versions 0, 2, 4 and 6 are backed up, 0 and 2 are pruned
+        let infos = backups.list().unwrap();
+        assert_eq!(
+            infos,
+            [4, 6].map(|version| BackupInfo {
+                version,
+                root_hash: root_hashes[version as usize].clone(),
+            })
+        );
+
+        // manually back up the latest version
+        root_hashes.push(commit(&db, "7"));
+        let info = backups.create(&db).unwrap();
+        assert_eq!(info.version, 7);
+        assert_eq!(backups.list().unwrap().len(), 3);
+
+        // the node moves on. version 8 is backed up, and 4 and 6 are pruned
+        root_hashes.push(commit(&db, "8"));
+        let versions = backups.list().unwrap().into_iter().map(|info| info.version);
+        assert_eq!(versions.collect::<Vec<_>>(), [7, 8]);
+
+        // restore the backup of version 7
+        drop(db);
+        let info = backups.restore(7, &path).unwrap();
+        let db = DiskDb::open(&path).unwrap();
+        assert_eq!(db.latest_version(), Some(7));
+        assert_eq!(db.root_hash(None).unwrap(), info.root_hash);
+        assert_eq!(db.state_storage(None).read(b"counter"), Some(b"7".to_vec()));
+        drop(db);
+
+        // tamper with the metadata of a backup. restoring it should fail
+        let tampered = BackupInfo {
+            version: 8,
+            root_hash: root_hashes[7].clone(),
+        };
+        fs::write(
+            backups.path(8).join(INFO_FILE),
+            to_json_vec(&tampered).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            backups.restore(8, &path),
+            Err(DbError::BackupRootHashMismatch { version: 8, .. })
+        ));
+
+        // the existing database is left intact
+        let db = DiskDb::open(&path).unwrap();
+        assert_eq!(db.latest_version(), Some(7));
+
+        // restoring a backup that doesn't exist
+        assert!(matches!(
+            backups.restore(6, &path),
+            Err(DbError::BackupNotFound { version: 6 })
+        ));
+    }
+
+    #[test]
+    fn backing_up_on_request() {
+        let path = TempDataDir::new("_grug_disk_db_backup_on_request");
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backups = BackupDir::new(backup_dir.path());
+
+        // no periodic backups; only on request
+        let db = DiskDb::open_with_config(&path, &DiskDbConfig {
+            auto_checkpoint: Some(AutoCheckpointConfig {
+                dir: backup_dir.path().to_path_buf(),
+                interval: None,
+                keep_recent: NonZeroUsize::new(2).unwrap(),
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        commit(&db, "0");
+        assert!(backups.list().unwrap().is_empty());
+
+        // the request is checked for every 10 versions, so it's fulfilled after
+        // committing version 10, and only once
+        backups.request().unwrap();
+        let mut root_hashes = vec![];
+        for version in 1..=11 {
+            root_hashes.push(commit(&db, &version.to_string()));
+        }
+        assert_eq!(backups.list().unwrap(), [BackupInfo {
+            version: 10,
+            root_hash: root_hashes[9].clone(),
+        }]);
+        assert!(!backups.take_request());
+    }
+
+    #[test]
+    fn failing_to_back_up() {
+        let path = TempDataDir::new("_grug_disk_db_backup_failing");
+        let backup_dir = tempfile::tempdir().unwrap();
+
+        // the backups directory is a file, so backing up fails
+        let not_a_dir = backup_dir.path().join("file");
+        fs::write(&not_a_dir, b"").unwrap();
+
+        let db = DiskDb::open_with_config(&path, &DiskDbConfig {
+            auto_checkpoint: Some(AutoCheckpointConfig {
+                dir: not_a_dir,
+                interval: NonZeroU64::new(1),
+                keep_recent: NonZeroUsize::new(2).unwrap(),
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // the commit succeeds nonetheless, since the version has been written
+        commit(&db, "0");
+        assert_eq!(db.latest_version(), Some(0));
+    }
+}
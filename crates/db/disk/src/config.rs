@@ -0,0 +1,274 @@
+use {
+    crate::DbResult,
+    rocksdb::{BlockBasedOptions, Cache, DBCompressionType, Options},
+    serde::{Deserialize, Serialize},
+    std::{
+        fs,
+        num::{NonZeroU64, NonZeroUsize},
+        path::{Path, PathBuf},
+    },
+};
+
+const KIB: usize = 1024;
+const MIB: usize = 1024 * KIB;
+
+/// Configurations of the RocksDB instance underlying a `DiskDb`.
+///
+/// Typically loaded from a TOML file in the node's home directory. Any field
+/// not found in the file takes its default value. A column family not found in
+/// the file takes the defaults tuned for how it's accessed (see the docs of
+/// `CfConfig::default_state_commitment` and `CfConfig::default_state_storage`),
+/// but one that's found must be given in full; see `CfConfig`.
+///
+/// For references on RocksDB tuning, see:
+/// - <https://github.com/facebook/rocksdb/wiki/RocksDB-Tuning-Guide>
+/// - <https://github.com/sei-protocol/sei-db/blob/main/ss/rocksdb/opts.go#L29-L65>
+/// - <https://github.com/turbofish-org/merk/blob/develop/src/merk/mod.rs#L84-L102>
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiskDbConfig {
+    /// Whether to write committed data to disk in a background thread.
+    /// See `DiskDb::open_async` for details.
+    pub async_commit: bool,
+    /// Maximum number of files RocksDB may keep open. -1 means no limit.
+    pub max_open_files: i32,
+    /// Size, in bytes, of the LRU cache for uncompressed data blocks. The
+    /// cache is shared by all column families.
+    pub block_cache_size: usize,
+    /// Size, in bytes, of the LRU cache for Merkle tree nodes, on top of the
+    /// block cache. This saves the reads and deserialization of nodes that are
+    /// touched block after block, such as those near the root. `None` means
+    /// to not cache nodes.
+    pub node_cache_size: Option<NonZeroUsize>,
+    /// Configurations of the state commitment column family.
+    #[serde(default = "CfConfig::default_state_commitment")]
+    pub state_commitment: CfConfig,
+    /// Configurations of the state storage column family.
+    #[serde(default = "CfConfig::default_state_storage")]
+    pub state_storage: CfConfig,
+    /// Whether and how to back up the database periodically. `None` means to
+    /// not take automatic backups.
+    pub auto_checkpoint: Option<AutoCheckpointConfig>,
+    /// Number of most recent versions that can be rolled back with
+    /// `DiskDb::rollback`. The keys changed in each version are recorded for
+    /// this many versions, and deleted afterwards. 0 (the default) means to
+    /// not record them, in which case rollbacks aren't possible.
+    ///
+    /// Enabling this costs one extra write per version, whose size is that of
+    /// all the keys changed in state storage in that version, and disk space
+    /// for that many versions' worth of keys.
+    pub rollback_window: u64,
+}
+
+impl Default for DiskDbConfig {
+    fn default() -> Self {
+        Self {
+            async_commit: false,
+            max_open_files: -1,
+            block_cache_size: 256 * MIB,
+            node_cache_size: NonZeroUsize::new(64 * MIB),
+            state_commitment: CfConfig::default_state_commitment(),
+            state_storage: CfConfig::default_state_storage(),
+            auto_checkpoint: None,
+            rollback_window: 0,
+        }
+    }
+}
+
+impl DiskDbConfig {
+    /// Load the config from a TOML file.
+    pub fn load(path: impl AsRef<Path>) -> DbResult<Self> {
+        let s = fs::read_to_string(path)?;
+        Ok(toml::from_str(&s)?)
+    }
+
+    /// Load the config from a TOML file if it exists; otherwise, use the
+    /// default config.
+    pub fn load_or_default(path: impl AsRef<Path>) -> DbResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub(crate) fn db_options(&self) -> Options {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        opts.set_max_open_files(self.max_open_files);
+        opts
+    }
+}
+
+/// Configurations of backups taken while the database is in use.
+///
+/// After committing a version that is a multiple of `interval`, a checkpoint of
+/// the database at that version is taken into `dir`, and backups beyond the
+/// most recent `keep_recent` ones are deleted. See `BackupDir` for the layout
+/// of `dir`. Requests made with `BackupDir::request` are checked for every 10
+/// versions, and fulfilled the same way.
+///
+/// The checkpoint is taken as part of the commit, but the backup is moved into
+/// place, and old ones deleted, in a background thread. Use
+/// `DiskDb::wait_for_backup` to wait for it to finish.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AutoCheckpointConfig {
+    pub dir: PathBuf,
+    /// `None` means to only back up on request.
+    #[serde(default)]
+    pub interval: Option<NonZeroU64>,
+    #[serde(default = "AutoCheckpointConfig::default_keep_recent")]
+    pub keep_recent: NonZeroUsize,
+}
+
+impl AutoCheckpointConfig {
+    pub fn default_keep_recent() -> NonZeroUsize {
+        NonZeroUsize::new(3).unwrap()
+    }
+}
+
+/// Configurations of a single column family.
+///
+/// If a column family is specified in the config file, all of its fields must
+/// be provided, except for `bloom_filter_bits_per_key`, which is omitted to
+/// not use a bloom filter. Fields of a column family can't default
+/// individually, as the defaults differ between column families.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CfConfig {
+    /// Size, in bytes, of a single memtable.
+    pub write_buffer_size: usize,
+    /// Algorithm for compressing data blocks.
+    pub compression: Compression,
+    /// Number of bits per key of the bloom filter. `None` means to not use a
+    /// bloom filter.
+    pub bloom_filter_bits_per_key: Option<f64>,
+}
+
+impl CfConfig {
+    /// The state commitment column family stores Merkle tree nodes, which are
+    /// keyed by hashes and read by point lookups. Hashes are incompressible,
+    /// so don't bother compressing them, while a bloom filter saves us from
+    /// reading data blocks for nodes that don't exist.
+    pub fn default_state_commitment() -> Self {
+        Self {
+            write_buffer_size: 64 * MIB,
+            compression: Compression::None,
+            bloom_filter_bits_per_key: Some(10.0),
+        }
+    }
+
+    /// The state storage column family stores raw contract data, which is
+    /// often range scanned, and, being timestamped, keeps many versions of
+    /// the same key. The data is compressible and written in larger volumes.
+    /// Bloom filters don't help range scans, but still do point lookups.
+    pub fn default_state_storage() -> Self {
+        Self {
+            write_buffer_size: 128 * MIB,
+            compression: Compression::Lz4,
+            bloom_filter_bits_per_key: Some(10.0),
+        }
+    }
+
+    pub(crate) fn options(&self, block_cache: &Cache) -> Options {
+        let mut table_opts = BlockBasedOptions::default();
+        table_opts.set_block_cache(block_cache);
+        if let Some(bits_per_key) = self.bloom_filter_bits_per_key {
+            table_opts.set_bloom_filter(bits_per_key, false);
+        }
+
+        let mut opts = Options::default();
+        opts.set_write_buffer_size(self.write_buffer_size);
+        opts.set_compression_type(self.compression.into());
+        opts.set_block_based_table_factory(&table_opts);
+        opts
+    }
+}
+
+/// Compression algorithms supported for data blocks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl From<Compression> for DBCompressionType {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => DBCompressionType::None,
+            Compression::Snappy => DBCompressionType::Snappy,
+            Compression::Lz4 => DBCompressionType::Lz4,
+            Compression::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_config() {
+        // empty file gives the default config
+        let config: DiskDbConfig = toml::from_str("").unwrap();
+        assert_eq!(config, DiskDbConfig::default());
+
+        // fields not provided take the defaults of their own column family
+        let config: DiskDbConfig = toml::from_str(
+            r#"
+            max_open_files = 1000
+            rollback_window = 10
+
+            [state_storage]
+            write_buffer_size = 1048576
+            compression = "zstd"
+            bloom_filter_bits_per_key = 8.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, DiskDbConfig {
+            max_open_files: 1000,
+            rollback_window: 10,
+            state_storage: CfConfig {
+                write_buffer_size: MIB,
+                compression: Compression::Zstd,
+                bloom_filter_bits_per_key: Some(8.0),
+            },
+            ..Default::default()
+        });
+
+        // the bloom filter is on by default, and disabled by omitting it
+        assert!(config.state_commitment.bloom_filter_bits_per_key.is_some());
+        let config: DiskDbConfig = toml::from_str(
+            r#"
+            [state_commitment]
+            write_buffer_size = 1048576
+            compression = "none"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.state_commitment.bloom_filter_bits_per_key, None);
+        assert!(config.state_storage.bloom_filter_bits_per_key.is_some());
+
+        // unknown fields are rejected
+        assert!(toml::from_str::<DiskDbConfig>("max_open_file = 1000").is_err());
+
+        // a column family that's specified must be complete, as its fields
+        // can't take the defaults of a particular column family
+        assert!(toml::from_str::<DiskDbConfig>(
+            r#"
+            [state_commitment]
+            compression = "lz4"
+            "#
+        )
+        .is_err());
+    }
+}
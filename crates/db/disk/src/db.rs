@@ -1,21 +1,33 @@
 use {
-    crate::{DbError, DbResult, U64Comparator, U64Timestamp},
+    crate::{
+        AutoCheckpointConfig, BackupDir, DbError, DbResult, DiskDbConfig, Mismatch, U64Comparator,
+        U64Timestamp, VerifyReport,
+    },
     grug_app::{CacheStore, Db},
-    grug_jmt::{MerkleTree, Proof},
-    grug_types::{hash, Batch, Hash, Op, Order, Record, Storage},
+    grug_jmt::{BatchProof, MerkleTree, NodeCache, Proof, ICS23_PROOF_TYPE},
+    grug_types::{
+        extend_one_byte, from_borsh_slice, hash, to_borsh_vec, Batch, Hash, Merged, MockStorage,
+        Op, Order, Record, Storage,
+    },
+    prost::Message,
     rocksdb::{
-        BoundColumnFamily, DBWithThreadMode, IteratorMode, MultiThreaded, Options, ReadOptions,
-        WriteBatch,
+        checkpoint::Checkpoint, BoundColumnFamily, Cache, DBWithThreadMode, IteratorMode,
+        MultiThreaded, Options, ReadOptions, WriteBatch,
     },
     std::{
+        collections::{BTreeMap, BTreeSet},
+        iter,
+        ops::Bound,
         path::Path,
-        sync::{Arc, RwLock},
+        sync::{Arc, Mutex, PoisonError, RwLock},
+        thread::{self, JoinHandle},
     },
+    tracing::error,
 };
 
 /// We use three column families (CFs) for storing data.
-/// The default family is used for metadata. Currently the only metadata we have
-/// is the latest version.
+/// The default family is used for metadata: the latest version, and the keys
+/// changed in each version.
 const CF_NAME_DEFAULT: &str = "default";
 
 /// The state commitment (SC) family stores Merkle tree nodes, which hold hashed
@@ -38,6 +50,29 @@ const CF_NAME_STATE_STORAGE: &str = "state_storage";
 /// Storage key for the latest version.
 const LATEST_VERSION_KEY: &[u8] = b"latest_version";
 
+/// Storage key for the version the database was most recently rolled back
+/// from, if it hasn't yet caught up with it. See `DiskDb::rollback` for why we
+/// need to keep track of this.
+const ROLLED_BACK_VERSION_KEY: &[u8] = b"rolled_back_version";
+
+/// Prefix of the records of which keys in state storage each version has
+/// changed. A record is `"changeset" | version`, with the version in big endian
+/// so that records are sorted by version, and the Borsh-encoded set of keys as
+/// value. These are used by `DiskDb::rollback` to find the keys to revert, and
+/// are only kept for the versions within the rollback window.
+const CHANGESET_PREFIX: &[u8] = b"changeset";
+
+/// Prefix of the records of which keys in state storage have entries at each
+/// version discarded by a rollback, in the same format as changesets. A record
+/// is deleted once the chain catches up with its version, at which point its
+/// keys become part of that version's changeset.
+const DISCARDED_PREFIX: &[u8] = b"discarded";
+
+/// How often, in versions, to check whether a backup has been requested (see
+/// `BackupDir::request`). Checking touches the file system, which we don't want
+/// to do on every commit.
+const BACKUP_REQUEST_POLL_INTERVAL: u64 = 10;
+
 /// Jellyfish Merkle tree (JMT) using default namespaces.
 const MERKLE_TREE: MerkleTree = MerkleTree::new_default();
 
@@ -61,7 +96,7 @@ const MERKLE_TREE: MerkleTree = MerkleTree::new_default();
 /// Our design mostly resembles Sei's with the differences being that:
 /// - we use a binary Jellyfish Merkle tree (JMT) instead of IAVL;
 /// - we store JMT data in a RocksDB instance, instead of using memory map (mmap);
-/// - we don't have asynchronous commit;
+/// - asynchronous commit is optional (see `DiskDb::open_async`);
 /// - we don't store snapshots or use a WAL to recover the latest state.
 ///
 /// These differences are not because we don't agree with Sei's approach...
@@ -78,9 +113,28 @@ struct DiskDbInner {
     // ideally we want to just use a rocksdb::WriteBatch here, but it's not
     // thread-safe.
     pending_data: RwLock<Option<PendingData>>,
+    // whether to write committed data in a background thread.
+    async_commit: bool,
+    // data that have been committed, but are still being written to the
+    // physical database by the background thread. reads at the latest version
+    // are served from here until the write lands.
+    committing_data: RwLock<Option<Arc<PendingData>>>,
+    // the background thread writing the committing data.
+    commit_thread: Mutex<Option<JoinHandle<DbResult<()>>>>,
+    // held while writing to the physical database, so that a checkpoint is
+    // never taken in the middle of a write.
+    write_lock: Mutex<()>,
+    // whether and how to take checkpoints after committing.
+    auto_checkpoint: Option<AutoCheckpointConfig>,
+    // the background thread taking a checkpoint, if any.
+    backup_thread: Mutex<Option<JoinHandle<()>>>,
+    // recently used Merkle tree nodes, shared across versions.
+    node_cache: Option<NodeCache>,
+    // number of most recent versions whose changesets are kept.
+    rollback_window: u64,
 }
 
-pub(crate) struct PendingData {
+struct PendingData {
     version: u64,
     state_commitment: Batch,
     state_storage: Batch,
@@ -89,21 +143,634 @@ pub(crate) struct PendingData {
 impl DiskDb {
     /// Create a DiskDb instance by opening a physical RocksDB instance.
     pub fn open(data_dir: impl AsRef<Path>) -> DbResult<Self> {
+        Self::open_with_config(data_dir, &DiskDbConfig::default())
+    }
+
+    /// Create a DiskDb instance in asynchronous commit mode.
+    ///
+    /// In this mode, `commit` hands the data over to a background thread that
+    /// writes it to disk, and returns without waiting for the write to land.
+    /// Meanwhile, reads at the latest version are served from the in-memory
+    /// data. If the next version is flushed before the write lands,
+    /// `flush_but_not_commit` blocks until it does.
+    ///
+    /// If the process dies before the write lands, the version is lost, same
+    /// as if the process died between `flush_but_not_commit` and `commit`. The
+    /// consensus engine will replay the block on restart. If the write fails,
+    /// the version is lost as well, and the next `flush_but_not_commit`
+    /// returns the error instead of building on top of it.
+    pub fn open_async(data_dir: impl AsRef<Path>) -> DbResult<Self> {
+        Self::open_with_config(data_dir, &DiskDbConfig {
+            async_commit: true,
+            ..Default::default()
+        })
+    }
+
+    /// Create a DiskDb instance with the given RocksDB tuning and commit mode.
+    pub fn open_with_config(data_dir: impl AsRef<Path>, config: &DiskDbConfig) -> DbResult<Self> {
+        let block_cache = Cache::new_lru_cache(config.block_cache_size);
+
         // note: for default and state commitment CFs, don't enable timestamping;
         // for state storage column family, enable timestamping.
-        let db = DBWithThreadMode::open_cf_with_opts(&new_db_options(), data_dir, [
+        let db = DBWithThreadMode::open_cf_with_opts(&config.db_options(), data_dir, [
             (CF_NAME_DEFAULT, Options::default()),
-            (CF_NAME_STATE_COMMITMENT, Options::default()),
-            (CF_NAME_STATE_STORAGE, new_cf_options_with_ts()),
+            (
+                CF_NAME_STATE_COMMITMENT,
+                config.state_commitment.options(&block_cache),
+            ),
+            (
+                CF_NAME_STATE_STORAGE,
+                with_ts(config.state_storage.options(&block_cache)),
+            ),
         ])?;
 
-        Ok(Self {
+        let db = Self {
             inner: Arc::new(DiskDbInner {
                 db,
                 pending_data: RwLock::new(None),
+                async_commit: config.async_commit,
+                committing_data: RwLock::new(None),
+                commit_thread: Mutex::new(None),
+                write_lock: Mutex::new(()),
+                auto_checkpoint: config.auto_checkpoint.clone(),
+                backup_thread: Mutex::new(None),
+                node_cache: config.node_cache_size.map(NodeCache::new),
+                rollback_window: config.rollback_window,
             }),
+        };
+
+        // if the process was killed between `flush_but_not_commit` and `commit`,
+        // the pending data is lost, and the database is left at the previous
+        // version; the consensus engine then replays the block. since `commit`
+        // writes everything in one atomic batch, the database can't contain
+        // part of a version. we nonetheless check that it doesn't, so that a
+        // corrupted database is caught here instead of producing a wrong app
+        // hash later.
+        if let Some(found_version) = MERKLE_TREE.latest_version(&db.state_commitment())? {
+            if db.latest_version().map_or(true, |v| found_version > v) {
+                return Err(DbError::UncommittedVersionFound { found_version });
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Revert the database to an earlier version, discarding all versions
+    /// after it. Return the root hash at that version.
+    ///
+    /// This is for recovering from a bad block, e.g. one committed with a
+    /// wrong app hash by a buggy binary. Once rolled back, the chain resumes
+    /// from `to_version + 1`.
+    ///
+    /// Only the versions within the rollback window (see
+    /// `DiskDbConfig::rollback_window`) can be rolled back, and the Merkle tree
+    /// at `to_version` must not have been pruned.
+    ///
+    /// In state commitment, nodes newer than `to_version` are simply deleted.
+    /// In state storage this isn't possible: RocksDB can't remove a single
+    /// timestamped entry, and deleting it writes a tombstone, which shadows
+    /// older entries of the same key. Instead, we overwrite the entries at the
+    /// discarded versions, as found from their changesets, with the keys'
+    /// values at `to_version`. Then, until the chain catches up with the
+    /// version it was rolled back from, each commit also writes its ops at the
+    /// later discarded versions where the same keys have entries.
+    pub fn rollback(&self, to_version: u64) -> DbResult<Hash> {
+        self.wait_for_commit()?;
+
+        if self
+            .inner
+            .pending_data
+            .read()
+            .map_err(|_| DbError::PendingDataPoisoned)?
+            .is_some()
+        {
+            return Err(DbError::PendingDataAlreadySet);
+        }
+
+        let latest_version = self.latest_version().unwrap_or(0);
+        if to_version >= latest_version {
+            return Err(DbError::RollbackVersionNotOlder {
+                latest_version,
+                to_version,
+            });
+        }
+
+        // the changesets of older versions have been deleted, so we can't find
+        // the keys to revert
+        if latest_version - to_version > self.inner.rollback_window {
+            return Err(DbError::RollbackBeyondWindow {
+                latest_version,
+                to_version,
+                rollback_window: self.inner.rollback_window,
+            });
+        }
+
+        // the Merkle tree at the target version must be intact, otherwise the
+        // rollback would leave a broken tree
+        let Some(root_hash) = self.root_hash(Some(to_version))? else {
+            return Err(DbError::RollbackRootNotFound { to_version });
+        };
+
+        // the version up to which state storage contains discarded entries.
+        // if the database has been rolled back before and hasn't yet caught
+        // up, this may be greater than the latest version.
+        let rolled_back_version = self
+            .inner
+            .read_version(ROLLED_BACK_VERSION_KEY)
+            .map_or(latest_version, |v| v.max(latest_version));
+
+        // delete nodes newer than the target version from the Merkle tree
+        let mut cache = CacheStore::new(self.state_commitment(), None);
+        self.inner.merkle_tree().rollback(&mut cache, to_version)?;
+        let (_, state_commitment) = cache.disassemble();
+
+        // find the keys that have entries at the discarded versions: those in
+        // the changesets of committed versions, and those left by an earlier
+        // rollback at versions that haven't been caught up with
+        let mut changesets =
+            self.inner
+                .changesets(CHANGESET_PREFIX, to_version + 1, latest_version)?;
+        changesets.extend(self.inner.changesets(
+            DISCARDED_PREFIX,
+            latest_version + 1,
+            rolled_back_version,
+        )?);
+
+        let mut batch = WriteBatch::default();
+
+        let cf = cf_default(&self.inner.db);
+        batch.put_cf(&cf, LATEST_VERSION_KEY, to_version.to_le_bytes());
+        batch.put_cf(
+            &cf,
+            ROLLED_BACK_VERSION_KEY,
+            rolled_back_version.to_le_bytes(),
+        );
+        // the discarded versions' changesets will be recorded again as the
+        // chain catches up
+        batch.delete_range_cf(
+            &cf,
+            changeset_key(CHANGESET_PREFIX, to_version + 1),
+            changeset_key(CHANGESET_PREFIX, latest_version + 1),
+        );
+        for (version, keys) in &changesets {
+            batch.put_cf(
+                &cf,
+                changeset_key(DISCARDED_PREFIX, *version),
+                to_borsh_vec(keys)?,
+            );
+        }
+
+        let cf = cf_state_commitment(&self.inner.db);
+        for (key, op) in state_commitment {
+            if let Op::Insert(value) = op {
+                batch.put_cf(&cf, key, value);
+            } else {
+                batch.delete_cf(&cf, key);
+            }
+        }
+
+        let cf = cf_state_storage(&self.inner.db);
+        let target = self.state_storage(Some(to_version));
+        for (version, keys) in changesets {
+            let ts = U64Timestamp::from(version);
+            for key in keys {
+                if let Some(value) = target.read(&key) {
+                    batch.put_cf_with_ts(&cf, &key, ts, value);
+                } else {
+                    batch.delete_cf_with_ts(&cf, &key, ts);
+                }
+            }
+        }
+
+        {
+            let _guard = self
+                .inner
+                .write_lock
+                .lock()
+                .map_err(|_| DbError::WriteLockPoisoned)?;
+            self.inner.db.write(batch)?;
+        }
+
+        Ok(root_hash)
+    }
+
+    /// Delete Merkle tree nodes that are no longer part of the tree as of the
+    /// given version. Afterwards, proofs can't be generated for versions older
+    /// than it.
+    ///
+    /// The orphaned nodes are deleted one by one, but the records marking them
+    /// as orphaned are contiguous, so they're deleted using RocksDB's range
+    /// deletion.
+    pub fn prune(&self, up_to_version: u64) -> DbResult<()> {
+        self.wait_for_commit()?;
+
+        if self
+            .inner
+            .pending_data
+            .read()
+            .map_err(|_| DbError::PendingDataPoisoned)?
+            .is_some()
+        {
+            return Err(DbError::PendingDataAlreadySet);
+        }
+
+        // all committed data have been written, so there's no committing data
+        let mut store = PruneStore::new(StateCommitment {
+            inner: Arc::clone(&self.inner),
+            committing_data: None,
+        });
+        self.inner
+            .merkle_tree()
+            .prune(&mut store, Some(up_to_version))?;
+
+        let mut batch = WriteBatch::default();
+        let cf = cf_state_commitment(&self.inner.db);
+        for key in store.removed_keys {
+            batch.delete_cf(&cf, key);
+        }
+        for (min, max) in store.removed_ranges {
+            batch.delete_range_cf(&cf, min, max);
+        }
+
+        // versions up to this one can no longer be rolled back to, so their
+        // changesets aren't needed anymore
+        let cf = cf_default(&self.inner.db);
+        batch.delete_range_cf(
+            &cf,
+            changeset_key(CHANGESET_PREFIX, 0),
+            changeset_key(CHANGESET_PREFIX, up_to_version + 1),
+        );
+
+        let _guard = self
+            .inner
+            .write_lock
+            .lock()
+            .map_err(|_| DbError::WriteLockPoisoned)?;
+        Ok(self.inner.db.write(batch)?)
+    }
+
+    /// Check that the state commitment agrees with the state storage at the
+    /// given version. Use the latest version if unspecified.
+    ///
+    /// A Merkle tree is built from scratch out of the raw key-value pairs in
+    /// the state storage. Its root hash is compared with the one stored in the
+    /// state commitment, and each of its leaves with the stored leaves.
+    pub fn verify(&self, version: Option<u64>) -> DbResult<VerifyReport> {
+        let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
+        let mut expected = self.hash_state_storage(version);
+        let (_, computed_root_hash) = build_tree(version, &expected)?;
+        let stored_root_hash = self.root_hash(Some(version))?;
+
+        let mut mismatches = vec![];
+        for leaf in MERKLE_TREE.leaves(&self.state_commitment(), version)? {
+            match expected.remove(&leaf.key_hash) {
+                Some((key, value_hash)) if value_hash != leaf.value_hash => {
+                    mismatches.push(Mismatch::WrongValueHash {
+                        key,
+                        expected: value_hash,
+                        found: leaf.value_hash,
+                    });
+                },
+                Some(_) => {},
+                None => {
+                    mismatches.push(Mismatch::ExtraLeaf {
+                        key_hash: leaf.key_hash,
+                    });
+                },
+            }
+        }
+        for (key, _) in expected.into_values() {
+            mismatches.push(Mismatch::MissingLeaf { key });
+        }
+
+        Ok(VerifyReport {
+            version,
+            stored_root_hash,
+            computed_root_hash,
+            mismatches,
         })
     }
+
+    /// Rebuild the state commitment from the state storage at the latest
+    /// version. Return the new root hash.
+    ///
+    /// The Merkle tree is built from scratch, with all its nodes at the latest
+    /// version. The trees of older versions are discarded, so their root hashes
+    /// and proofs are no longer available, same as if they had been pruned.
+    pub fn repair(&self) -> DbResult<Option<Hash>> {
+        self.wait_for_commit()?;
+
+        if self
+            .inner
+            .pending_data
+            .read()
+            .map_err(|_| DbError::PendingDataPoisoned)?
+            .is_some()
+        {
+            return Err(DbError::PendingDataAlreadySet);
+        }
+
+        let Some(version) = self.latest_version() else {
+            return Ok(None);
+        };
+
+        let hashed = self.hash_state_storage(version);
+        let (nodes, root_hash) = build_tree(version, &hashed)?;
+
+        let mut batch = WriteBatch::default();
+        let cf = cf_state_commitment(&self.inner.db);
+        for (key, _) in self.state_commitment().scan(None, None, Order::Ascending) {
+            batch.delete_cf(&cf, key);
+        }
+        for (key, value) in nodes.scan(None, None, Order::Ascending) {
+            batch.put_cf(&cf, key, value);
+        }
+
+        let _guard = self
+            .inner
+            .write_lock
+            .lock()
+            .map_err(|_| DbError::WriteLockPoisoned)?;
+        self.inner.db.write(batch)?;
+
+        // the nodes have been replaced without going through the cache
+        if let Some(cache) = &self.inner.node_cache {
+            cache.clear();
+        }
+
+        Ok(root_hash)
+    }
+
+    // hash the raw key-value pairs in state storage at the given version, the
+    // same way `MerkleTree::apply_raw` does. return a map from key hashes to
+    // the raw keys and the value hashes.
+    fn hash_state_storage(&self, version: u64) -> BTreeMap<Hash, (Vec<u8>, Hash)> {
+        self.state_storage(Some(version))
+            .scan(None, None, Order::Ascending)
+            .map(|(key, value)| (hash(&key), (key, hash(value))))
+            .collect()
+    }
+
+    /// If a version is being written in the background, block until the write
+    /// lands. Do nothing if not in asynchronous commit mode.
+    pub fn wait_for_commit(&self) -> DbResult<()> {
+        let thread = self
+            .inner
+            .commit_thread
+            .lock()
+            .map_err(|_| DbError::CommitThreadPoisoned)?
+            .take();
+        if let Some(thread) = thread {
+            thread.join().map_err(|_| DbError::CommitThreadPanicked)??;
+        }
+
+        Ok(())
+    }
+
+    /// If a backup is being taken in the background, block until it's done.
+    /// See `AutoCheckpointConfig`.
+    pub fn wait_for_backup(&self) -> DbResult<()> {
+        let thread = self
+            .inner
+            .backup_thread
+            .lock()
+            .map_err(|_| DbError::BackupThreadPoisoned)?
+            .take();
+        if let Some(thread) = thread {
+            thread.join().map_err(|_| DbError::BackupThreadPanicked)?;
+        }
+
+        Ok(())
+    }
+
+    /// Take a RocksDB checkpoint of the database at the given path, which must
+    /// not exist yet. Return the latest version in the checkpoint, or `None` if
+    /// the database is empty.
+    ///
+    /// The checkpoint is a consistent copy of the database that can be opened
+    /// as a `DiskDb` on its own. Where possible, its files are hard links to
+    /// those of the database, so it's cheap to take while the node is running.
+    /// It's never taken in the middle of writing a version, so it contains
+    /// exactly the versions up to the one returned. In asynchronous commit
+    /// mode, that's the latest version whose write has landed.
+    ///
+    /// See `BackupDir` for managing checkpoints as backups.
+    pub fn checkpoint(&self, path: impl AsRef<Path>) -> DbResult<Option<u64>> {
+        let _guard = self
+            .inner
+            .write_lock
+            .lock()
+            .map_err(|_| DbError::WriteLockPoisoned)?;
+        Checkpoint::new(&self.inner.db)?.create_checkpoint(path)?;
+        Ok(self.inner.read_version(LATEST_VERSION_KEY))
+    }
+
+    // if the version is a multiple of the configured interval, or if a backup
+    // has been requested, back up the database, and delete the old backups
+    // beyond the configured retention.
+    //
+    // the checkpoint is taken right away, so that it's of exactly this version,
+    // which is checked. the rest, i.e. moving the backup into place and pruning
+    // old ones, is done in a background thread, so that it doesn't hold up the
+    // next commits. the version has already been durably written, so failing
+    // to back it up isn't fatal: the error is logged, and the node moves on.
+    fn auto_checkpoint(&self, version: u64) {
+        let Some(config) = &self.inner.auto_checkpoint else {
+            return;
+        };
+
+        let backups = BackupDir::new(&config.dir);
+        let scheduled = config
+            .interval
+            .is_some_and(|interval| version % interval.get() == 0);
+        // checking for a request touches the file system, so only do it every
+        // few versions
+        let requested = version % BACKUP_REQUEST_POLL_INTERVAL == 0 && backups.take_request();
+        if !requested && !scheduled {
+            return;
+        }
+
+        // backups are assembled in the same directory, so only take one at a
+        // time. they're normally many versions apart, so the previous one has
+        // long finished by now.
+        if let Err(err) = self.wait_for_backup() {
+            error!(version, %err, "Failed to back up database");
+        }
+
+        let info = backups.prepare(self).and_then(|info| {
+            if info.version != version {
+                return Err(DbError::BackupVersionMismatch {
+                    expected: version,
+                    found: Some(info.version),
+                });
+            }
+            Ok(info)
+        });
+        let info = match info {
+            Ok(info) => info,
+            Err(err) => {
+                error!(version, %err, "Failed to back up database");
+                return;
+            },
+        };
+
+        let keep_recent = config.keep_recent.get();
+        let thread = thread::spawn(move || {
+            if let Err(err) = backups
+                .complete(&info)
+                .and_then(|_| backups.prune(keep_recent))
+            {
+                error!(version, %err, "Failed to back up database");
+            }
+        });
+        *(self
+            .inner
+            .backup_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)) = Some(thread);
+    }
+}
+
+impl DiskDbInner {
+    // the Merkle tree, reading nodes through the node cache if there is one.
+    fn merkle_tree(&self) -> MerkleTree {
+        match &self.node_cache {
+            Some(cache) => MERKLE_TREE.with_cache(cache),
+            None => MERKLE_TREE,
+        }
+    }
+
+    fn read_version(&self, key: &[u8]) -> Option<u64> {
+        let cf = cf_default(&self.db);
+        let bytes = self.db.get_cf(&cf, key).unwrap_or_else(|err| {
+            panic!("failed to read from default column family: {err}");
+        })?;
+        let array = bytes.try_into().unwrap_or_else(|bytes: Vec<u8>| {
+            panic!("version is of incorrect byte length: {}", bytes.len());
+        });
+        Some(u64::from_le_bytes(array))
+    }
+
+    // the sets of keys recorded under the given prefix in the given range of
+    // versions, both ends inclusive, indexed by version
+    fn changesets(
+        &self,
+        prefix: &[u8],
+        min_version: u64,
+        max_version: u64,
+    ) -> DbResult<BTreeMap<u64, BTreeSet<Vec<u8>>>> {
+        if min_version > max_version {
+            return Ok(BTreeMap::new());
+        }
+
+        let min = changeset_key(prefix, min_version);
+        let max = changeset_key(prefix, max_version + 1);
+        let opts = new_read_options(None, Some(&min), Some(&max));
+        self.db
+            .iterator_cf_opt(&cf_default(&self.db), opts, IteratorMode::Start)
+            .map(|item| {
+                let (k, v) = item?;
+                // strip the prefix to get the version
+                let version = u64::from_be_bytes(k[prefix.len()..].try_into().unwrap());
+                Ok((version, from_borsh_slice(v)?))
+            })
+            .collect()
+    }
+
+    fn committing_data(&self) -> Option<Arc<PendingData>> {
+        self.committing_data
+            .read()
+            .unwrap_or_else(|err| {
+                panic!("failed to read committing data: {err}");
+            })
+            .clone()
+    }
+
+    fn write(&self, pending: &PendingData) -> DbResult<()> {
+        let mut batch = WriteBatch::default();
+
+        // set the new version (note: use little endian)
+        let cf = cf_default(&self.db);
+        batch.put_cf(&cf, LATEST_VERSION_KEY, pending.version.to_le_bytes());
+
+        // if the database has been rolled back and hasn't caught up yet, find
+        // the keys that have entries at the discarded versions from this one
+        // on. see `DiskDb::rollback`.
+        let mut discarded = match self.read_version(ROLLED_BACK_VERSION_KEY) {
+            Some(rolled_back_version) => {
+                if rolled_back_version <= pending.version {
+                    batch.delete_cf(&cf, ROLLED_BACK_VERSION_KEY);
+                }
+                self.changesets(DISCARDED_PREFIX, pending.version, rolled_back_version)?
+            },
+            None => BTreeMap::new(),
+        };
+
+        // the keys discarded at this version have entries in it, so they're
+        // part of its changeset, along with the keys it changes
+        let mut changed_keys = discarded.remove(&pending.version).unwrap_or_default();
+        changed_keys.extend(pending.state_storage.keys().cloned());
+        batch.delete_cf(&cf, changeset_key(DISCARDED_PREFIX, pending.version));
+
+        // record the changeset of this version, for rollbacks, and delete the
+        // changesets that have fallen out of the rollback window
+        if self.rollback_window > 0 {
+            batch.put_cf(
+                &cf,
+                changeset_key(CHANGESET_PREFIX, pending.version),
+                to_borsh_vec(&changed_keys)?,
+            );
+        }
+        if let Some(expired_version) = pending.version.checked_sub(self.rollback_window) {
+            batch.delete_range_cf(
+                &cf,
+                changeset_key(CHANGESET_PREFIX, 0),
+                changeset_key(CHANGESET_PREFIX, expired_version + 1),
+            );
+        }
+
+        // writes in state commitment
+        let cf = cf_state_commitment(&self.db);
+        for (key, op) in &pending.state_commitment {
+            if let Op::Insert(value) = op {
+                batch.put_cf(&cf, key, value);
+            } else {
+                batch.delete_cf(&cf, key);
+            }
+        }
+
+        // writes in state storage (note: don't forget timestamping)
+        let cf = cf_state_storage(&self.db);
+        for (key, op) in &pending.state_storage {
+            // also write the op at the later discarded versions where the key
+            // has entries, so that they don't shadow it
+            let later_versions = discarded
+                .iter()
+                .filter(|(_, keys)| keys.contains(key))
+                .map(|(version, _)| *version);
+            for version in iter::once(pending.version).chain(later_versions) {
+                let ts = U64Timestamp::from(version);
+                if let Op::Insert(value) = op {
+                    batch.put_cf_with_ts(&cf, key, ts, value);
+                } else {
+                    batch.delete_cf_with_ts(&cf, key, ts);
+                }
+            }
+        }
+
+        {
+            let _guard = self
+                .write_lock
+                .lock()
+                .map_err(|_| DbError::WriteLockPoisoned)?;
+            self.db.write(batch)?;
+        }
+
+        // the nodes written in this version have landed, so they can be cached
+        self.merkle_tree().cache_nodes(&pending.state_commitment)?;
+
+        Ok(())
+    }
 }
 
 impl Clone for DiskDb {
@@ -115,55 +782,84 @@ impl Clone for DiskDb {
 }
 
 impl Db for DiskDb {
+    type BatchProof = BatchProof;
     type Error = DbError;
     type Proof = Proof;
 
+    const ICS23_PROOF_TYPE: &'static str = ICS23_PROOF_TYPE;
+
     fn state_commitment(&self) -> impl Storage + Clone + 'static {
         StateCommitment {
             inner: Arc::clone(&self.inner),
+            committing_data: self.inner.committing_data(),
         }
     }
 
     fn state_storage(&self, version: Option<u64>) -> impl Storage + Clone + 'static {
+        let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
+        // only the latest version can be still being written
+        let committing_data = self
+            .inner
+            .committing_data()
+            .filter(|data| data.version == version);
+
         StateStorage {
             inner: Arc::clone(&self.inner),
-            version: version.unwrap_or_else(|| self.latest_version().unwrap_or(0)),
+            version,
+            committing_data,
         }
     }
 
     fn latest_version(&self) -> Option<u64> {
-        let cf = cf_default(&self.inner.db);
-        let bytes = self
-            .inner
-            .db
-            .get_cf(&cf, LATEST_VERSION_KEY)
-            .unwrap_or_else(|err| {
-                panic!("failed to read from default column family: {err}");
-            })?;
-        let array = bytes.try_into().unwrap_or_else(|bytes: Vec<u8>| {
-            panic!(
-                "latest version is of incorrect byte length: {}",
-                bytes.len()
-            );
-        });
-        Some(u64::from_le_bytes(array))
+        // a version that is still being written is nonetheless committed
+        if let Some(data) = self.inner.committing_data() {
+            return Some(data.version);
+        }
+
+        self.inner.read_version(LATEST_VERSION_KEY)
     }
 
     fn root_hash(&self, version: Option<u64>) -> DbResult<Option<Hash>> {
         let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
-        Ok(MERKLE_TREE.root_hash(&self.state_commitment(), version)?)
+        let tree = self.inner.merkle_tree();
+        Ok(tree.root_hash(&self.state_commitment(), version)?)
     }
 
     fn prove(&self, key: &[u8], version: Option<u64>) -> DbResult<Proof> {
         let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
-        Ok(MERKLE_TREE.prove(&self.state_commitment(), &hash(key), version)?)
+        let tree = self.inner.merkle_tree();
+        Ok(tree.prove(&self.state_commitment(), &hash(key), version)?)
+    }
+
+    fn prove_ics23(&self, key: &[u8], version: Option<u64>) -> DbResult<Vec<u8>> {
+        let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
+        let tree = self.inner.merkle_tree();
+        let proof = tree.prove_ics23(&self.state_commitment(), &hash(key), version)?;
+        Ok(proof.encode_to_vec())
+    }
+
+    fn prove_many(&self, keys: &[&[u8]], version: Option<u64>) -> DbResult<BatchProof> {
+        let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
+        let key_hashes = keys.iter().map(hash).collect::<Vec<_>>();
+        let tree = self.inner.merkle_tree();
+        Ok(tree.prove_batch(&self.state_commitment(), &key_hashes, version)?)
     }
 
     fn flush_but_not_commit(&self, batch: Batch) -> DbResult<(u64, Option<Hash>)> {
+        // the previous version must have landed, so that the new one is built
+        // on top of it. if its write failed, the error is returned here.
+        self.wait_for_commit()?;
+
         // a write batch must not already exist. if it does, it means a batch
         // has been flushed, but not committed, then a next batch is flusehd,
         // which indicates some error in the ABCI app's logic.
-        if self.inner.pending_data.read()?.is_some() {
+        if self
+            .inner
+            .pending_data
+            .read()
+            .map_err(|_| DbError::PendingDataPoisoned)?
+            .is_some()
+        {
             return Err(DbError::PendingDataAlreadySet);
         }
 
@@ -181,10 +877,15 @@ impl Db for DiskDb {
         // commit hashed KVs to state commitment
         // the DB writes here are kept in the in-memory PendingData
         let mut cache = CacheStore::new(self.state_commitment(), None);
-        let root_hash = MERKLE_TREE.apply_raw(&mut cache, old_version, new_version, &batch)?;
+        let tree = self.inner.merkle_tree();
+        let root_hash = tree.apply_raw(&mut cache, old_version, new_version, &batch)?;
         let (_, pending) = cache.disassemble();
 
-        *(self.inner.pending_data.write()?) = Some(PendingData {
+        *(self
+            .inner
+            .pending_data
+            .write()
+            .map_err(|_| DbError::PendingDataPoisoned)?) = Some(PendingData {
             version: new_version,
             state_commitment: pending,
             state_storage: batch,
@@ -197,92 +898,232 @@ impl Db for DiskDb {
         let pending = self
             .inner
             .pending_data
-            .write()?
+            .write()
+            .map_err(|_| DbError::PendingDataPoisoned)?
             .take()
             .ok_or(DbError::PendingDataNotSet)?;
-        let mut batch = WriteBatch::default();
 
-        // set the new version (note: use little endian)
-        let cf = cf_default(&self.inner.db);
-        batch.put_cf(&cf, LATEST_VERSION_KEY, pending.version.to_le_bytes());
+        if !self.inner.async_commit {
+            self.inner.write(&pending)?;
+            self.auto_checkpoint(pending.version);
+            return Ok(());
+        }
 
-        // writes in state commitment
-        let cf = cf_state_commitment(&self.inner.db);
-        for (key, op) in pending.state_commitment {
-            if let Op::Insert(value) = op {
-                batch.put_cf(&cf, key, value);
-            } else {
-                batch.delete_cf(&cf, key);
+        // back-pressure: if the previous version is still being written, wait
+        // for it to land before handing over this one. it normally has, since
+        // `flush_but_not_commit` waits for it too.
+        self.wait_for_commit()?;
+
+        let pending = Arc::new(pending);
+        *(self
+            .inner
+            .committing_data
+            .write()
+            .map_err(|_| DbError::CommittingDataPoisoned)?) = Some(Arc::clone(&pending));
+
+        let db = self.clone();
+        let thread = thread::spawn(move || {
+            let res = db.inner.write(&pending);
+            // only clear the committing data after the write has landed, so
+            // that reads never miss this version. if the write failed, clear it
+            // all the same, so that reads don't see a version that doesn't
+            // exist; the error is returned by `wait_for_commit`.
+            *(db.inner
+                .committing_data
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)) = None;
+            // nodes of this version may have been cached as they were read from
+            // the committing data, but the version doesn't exist after all
+            if res.is_err() {
+                if let Some(cache) = &db.inner.node_cache {
+                    cache.clear();
+                }
             }
+            res?;
+            db.auto_checkpoint(pending.version);
+            Ok(())
+        });
+        *(self
+            .inner
+            .commit_thread
+            .lock()
+            .map_err(|_| DbError::CommitThreadPoisoned)?) = Some(thread);
+
+        Ok(())
+    }
+}
+
+// ----------------------------- state commitment ------------------------------
+
+pub struct StateCommitment {
+    inner: Arc<DiskDbInner>,
+    committing_data: Option<Arc<PendingData>>,
+}
+
+impl Clone for StateCommitment {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            committing_data: self.committing_data.clone(),
         }
+    }
+}
 
-        // writes in state storage (note: don't forget timestamping)
-        let cf = cf_state_storage(&self.inner.db);
-        let ts = U64Timestamp::from(pending.version);
-        for (key, op) in pending.state_storage {
-            if let Op::Insert(value) = op {
-                batch.put_cf_with_ts(&cf, key, ts, value);
-            } else {
-                batch.delete_cf_with_ts(&cf, key, ts);
-            }
+impl Storage for StateCommitment {
+    fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(op) = self
+            .committing_data
+            .as_ref()
+            .and_then(|data| data.state_commitment.get(key))
+        {
+            return op.clone().into_option();
+        }
+
+        self.inner
+            .db
+            .get_cf(&cf_state_commitment(&self.inner.db), key)
+            .unwrap_or_else(|err| {
+                panic!("failed to read from state commitment: {err}");
+            })
+    }
+
+    fn scan<'a>(
+        &'a self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        let opts = new_read_options(None, min, max);
+        let mode = match order {
+            Order::Ascending => IteratorMode::Start,
+            Order::Descending => IteratorMode::End,
+        };
+        let iter = self
+            .inner
+            .db
+            .iterator_cf_opt(&cf_state_commitment(&self.inner.db), opts, mode)
+            .map(|item| {
+                let (k, v) = item.unwrap_or_else(|err| {
+                    panic!("failed to iterate in state commitment: {err}");
+                });
+                (k.to_vec(), v.to_vec())
+            });
+
+        match &self.committing_data {
+            Some(data) => merge_committing(iter, &data.state_commitment, min, max, order),
+            None => Box::new(iter),
         }
+    }
+
+    fn write(&mut self, _key: &[u8], _value: &[u8]) {
+        unreachable!("write function called on read-only storage");
+    }
 
-        Ok(self.inner.db.write(batch)?)
+    fn remove(&mut self, _key: &[u8]) {
+        unreachable!("write function called on read-only storage");
     }
-}
 
-// ----------------------------- state commitment ------------------------------
+    fn remove_range(&mut self, _min: Option<&[u8]>, _max: Option<&[u8]>) {
+        unreachable!("write function called on read-only storage");
+    }
+}
 
-pub struct StateCommitment {
-    inner: Arc<DiskDbInner>,
+/// A view of the state commitment used for pruning. Removals aren't applied
+/// but collected, so that they can be written to the physical database in one
+/// batch.
+#[derive(Clone)]
+struct PruneStore {
+    base: StateCommitment,
+    removed_keys: Vec<Vec<u8>>,
+    removed_ranges: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
-impl Clone for StateCommitment {
-    fn clone(&self) -> Self {
+impl PruneStore {
+    fn new(base: StateCommitment) -> Self {
         Self {
-            inner: Arc::clone(&self.inner),
+            base,
+            removed_keys: Vec::new(),
+            removed_ranges: Vec::new(),
         }
     }
 }
 
-impl Storage for StateCommitment {
+impl Storage for PruneStore {
     fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
-        self.inner
-            .db
-            .get_cf(&cf_state_commitment(&self.inner.db), key)
-            .unwrap_or_else(|err| {
-                panic!("failed to read from state commitment: {err}");
-            })
+        self.base.read(key)
     }
 
     fn scan<'a>(
         &'a self,
-        _min: Option<&[u8]>,
-        _max: Option<&[u8]>,
-        _order: Order,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
     ) -> Box<dyn Iterator<Item = Record> + 'a> {
-        unimplemented!("this isn't used by the Merkle tree");
+        self.base.scan(min, max, order)
     }
 
     fn write(&mut self, _key: &[u8], _value: &[u8]) {
-        unreachable!("write function called on read-only storage");
+        unreachable!("write function called when pruning");
     }
 
-    fn remove(&mut self, _key: &[u8]) {
-        unreachable!("write function called on read-only storage");
+    fn remove(&mut self, key: &[u8]) {
+        self.removed_keys.push(key.to_vec());
+    }
+
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        // RocksDB's range deletion requires both bounds. in case there's no
+        // max bound, use the one right after the last key in the range.
+        let max = match max {
+            Some(max) => max.to_vec(),
+            None => match self.base.scan(min, None, Order::Descending).next() {
+                Some((key, _)) => extend_one_byte(key),
+                None => return,
+            },
+        };
+
+        self.removed_ranges
+            .push((min.unwrap_or_default().to_vec(), max));
     }
 }
 
+// build a Merkle tree from scratch in memory, with all nodes at the given
+// version. return the storage containing the nodes, and the root hash.
+fn build_tree(
+    version: u64,
+    hashed: &BTreeMap<Hash, (Vec<u8>, Hash)>,
+) -> DbResult<(MockStorage, Option<Hash>)> {
+    let batch = hashed
+        .iter()
+        .map(|(key_hash, (_, value_hash))| (key_hash.clone(), Op::Insert(value_hash.clone())))
+        .collect();
+
+    // the storage is empty, so there is no old root at version 0 to be marked
+    // as orphaned. this is how we get all nodes at the given version.
+    let mut nodes = MockStorage::new();
+    let root_hash = MERKLE_TREE.apply(&mut nodes, 0, version, batch)?;
+
+    Ok((nodes, root_hash))
+}
+
 // ------------------------------- state storage -------------------------------
 
 #[derive(Clone)]
 pub struct StateStorage {
     inner: Arc<DiskDbInner>,
     version: u64,
+    committing_data: Option<Arc<PendingData>>,
 }
 
 impl Storage for StateStorage {
     fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(op) = self
+            .committing_data
+            .as_ref()
+            .and_then(|data| data.state_storage.get(key))
+        {
+            return op.clone().into_option();
+        }
+
         let opts = new_read_options(Some(self.version), None, None);
         self.inner
             .db
@@ -313,7 +1154,11 @@ impl Storage for StateStorage {
                 });
                 (k.to_vec(), v.to_vec())
             });
-        Box::new(iter)
+
+        match &self.committing_data {
+            Some(data) => merge_committing(iter, &data.state_storage, min, max, order),
+            None => Box::new(iter),
+        }
     }
 
     fn write(&mut self, _key: &[u8], _value: &[u8]) {
@@ -324,6 +1169,10 @@ impl Storage for StateStorage {
         unreachable!("write function called on read-only storage");
     }
 
+    fn remove_range(&mut self, _min: Option<&[u8]>, _max: Option<&[u8]>) {
+        unreachable!("write function called on read-only storage");
+    }
+
     fn flush(&mut self, _batch: Batch) {
         unreachable!("write function called on read-only storage");
     }
@@ -331,18 +1180,37 @@ impl Storage for StateStorage {
 
 // ---------------------------------- helpers ----------------------------------
 
-// TODO: rocksdb tuning? see:
-// https://github.com/sei-protocol/sei-db/blob/main/ss/rocksdb/opts.go#L29-L65
-// https://github.com/turbofish-org/merk/blob/develop/src/merk/mod.rs#L84-L102
-fn new_db_options() -> Options {
-    let mut opts = Options::default();
-    opts.create_if_missing(true);
-    opts.create_missing_column_families(true);
-    opts
+/// Merge records from the physical database with the data that's still being
+/// written to it in the background.
+fn merge_committing<'a>(
+    base: impl Iterator<Item = Record> + 'a,
+    committing: &'a Batch,
+    min: Option<&[u8]>,
+    max: Option<&[u8]>,
+    order: Order,
+) -> Box<dyn Iterator<Item = Record> + 'a> {
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Box::new(base);
+        }
+    }
+
+    let min = min.map_or(Bound::Unbounded, |bytes| Bound::Included(bytes.to_vec()));
+    let max = max.map_or(Bound::Unbounded, |bytes| Bound::Excluded(bytes.to_vec()));
+    let committing_raw = committing.range((min, max));
+    let committing: Box<dyn Iterator<Item = _>> = match order {
+        Order::Ascending => Box::new(committing_raw),
+        Order::Descending => Box::new(committing_raw.rev()),
+    };
+
+    Box::new(Merged::new(base, committing, order))
 }
 
-fn new_cf_options_with_ts() -> Options {
-    let mut opts = Options::default();
+fn changeset_key(prefix: &[u8], version: u64) -> Vec<u8> {
+    [prefix, &version.to_be_bytes()].concat()
+}
+
+fn with_ts(mut opts: Options) -> Options {
     // must use a timestamp-enabled comparator
     opts.set_comparator_with_ts(
         U64Comparator::NAME,
@@ -400,6 +1268,7 @@ mod tests {
         grug_jmt::{verify_proof, MembershipProof, NonMembershipProof, ProofNode},
         grug_types::Hash,
         hex_literal::hex,
+        std::{env, process},
     };
 
     // using the same test case as in our rust-rocksdb fork:
@@ -727,4 +1596,662 @@ mod tests {
             .is_ok());
         }
     }
+
+    fn keys(keys: &[&str]) -> BTreeSet<Vec<u8>> {
+        keys.iter().map(|key| key.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn rollback_works() {
+        let path = TempDataDir::new("_grug_db_rollback_works");
+        let store = DiskDb::open_with_config(&path, &DiskDbConfig {
+            rollback_window: 10,
+            ..Default::default()
+        })
+        .unwrap();
+
+        // write the same two batches as in the previous test, plus a third one
+        let batch = Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"trump".to_vec())),
+            (b"jake".to_vec(), Op::Insert(b"shepherd".to_vec())),
+            (b"joe".to_vec(), Op::Insert(b"biden".to_vec())),
+            (b"larry".to_vec(), Op::Insert(b"engineer".to_vec())),
+        ]);
+        store.flush_and_commit(batch).unwrap();
+
+        let batch = Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+            (b"joe".to_vec(), Op::Delete),
+            (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+        ]);
+        store.flush_and_commit(batch).unwrap();
+
+        let batch = Batch::from([(b"jake".to_vec(), Op::Insert(b"paul".to_vec()))]);
+        let (version, _) = store.flush_and_commit(batch).unwrap();
+        assert_eq!(version, 2);
+
+        // can only roll back to an older version
+        assert!(matches!(
+            store.rollback(2),
+            Err(DbError::RollbackVersionNotOlder {
+                latest_version: 2,
+                to_version: 2,
+            })
+        ));
+
+        // the keys to be reverted are found from the changesets, without
+        // scanning the state
+        let changesets = BTreeMap::from([
+            (1, keys(&["donald", "joe", "pumpkin"])),
+            (2, keys(&["jake"])),
+        ]);
+        assert_eq!(
+            store.inner.changesets(CHANGESET_PREFIX, 1, 2).unwrap(),
+            changesets
+        );
+
+        // roll back to version 0. the changesets of the discarded versions
+        // are moved to the discarded records
+        let root_hash = store.rollback(0).unwrap();
+        assert_eq!(root_hash, v0::ROOT_HASH);
+        assert!(store
+            .inner
+            .changesets(CHANGESET_PREFIX, 1, 2)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.inner.changesets(DISCARDED_PREFIX, 1, 2).unwrap(),
+            changesets
+        );
+        assert_eq!(store.latest_version(), Some(0));
+        assert_eq!(store.root_hash(None).unwrap(), Some(v0::ROOT_HASH));
+        assert_eq!(store.root_hash(Some(1)).unwrap(), None);
+        assert_eq!(store.root_hash(Some(2)).unwrap(), None);
+
+        // write a different batch at version 1, then roll back again
+        let batch = Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+            (b"joe".to_vec(), Op::Delete),
+            (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+            (b"jake".to_vec(), Op::Insert(b"lee".to_vec())),
+        ]);
+        let (version, root_hash) = store.flush_and_commit(batch).unwrap();
+        assert_eq!(version, 1);
+        assert_ne!(root_hash, Some(v1::ROOT_HASH));
+
+        // the keys discarded at version 1 are now part of its changeset, and
+        // those discarded at version 2 are still pending
+        assert_eq!(
+            store.inner.changesets(CHANGESET_PREFIX, 1, 1).unwrap(),
+            BTreeMap::from([(1, keys(&["donald", "jake", "joe", "pumpkin"]))])
+        );
+        assert_eq!(
+            store.inner.changesets(DISCARDED_PREFIX, 1, 2).unwrap(),
+            BTreeMap::from([(2, keys(&["jake"]))])
+        );
+
+        let root_hash = store.rollback(0).unwrap();
+        assert_eq!(root_hash, v0::ROOT_HASH);
+
+        // replaying the original batch at version 1 should give the same root
+        let batch = Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+            (b"joe".to_vec(), Op::Delete),
+            (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+        ]);
+        let (version, root_hash) = store.flush_and_commit(batch).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(root_hash, Some(v1::ROOT_HASH));
+
+        let batch = Batch::from([(b"larry".to_vec(), Op::Insert(b"page".to_vec()))]);
+        let (version, _) = store.flush_and_commit(batch).unwrap();
+        assert_eq!(version, 2);
+
+        // the database has caught up, so nothing is left discarded
+        assert!(store
+            .inner
+            .changesets(DISCARDED_PREFIX, 0, 2)
+            .unwrap()
+            .is_empty());
+
+        // none of the data discarded by the rollbacks should be visible. in
+        // particular, donald's value at version 2 must not be shadowed by the
+        // entry that the rollback left there.
+        for (version, key, value) in [
+            (0, "jake", Some("shepherd")),
+            (0, "larry", Some("engineer")),
+            (1, "donald", Some("duck")),
+            (1, "jake", Some("shepherd")),
+            (1, "joe", None),
+            (1, "larry", Some("engineer")),
+            (1, "pumpkin", Some("cat")),
+            (2, "donald", Some("duck")),
+            (2, "jake", Some("shepherd")),
+            (2, "joe", None),
+            (2, "larry", Some("page")),
+            (2, "pumpkin", Some("cat")),
+        ] {
+            let found_value = store.state_storage(Some(version)).read(key.as_bytes());
+            assert_eq!(
+                found_value
+                    .map(|bz| String::from_utf8(bz).unwrap())
+                    .as_deref(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn pruning_works() {
+        let path = TempDataDir::new("_grug_db_pruning_works");
+        let store = DiskDb::open_with_config(&path, &DiskDbConfig {
+            rollback_window: 10,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let batch = Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"trump".to_vec())),
+            (b"jake".to_vec(), Op::Insert(b"shepherd".to_vec())),
+            (b"joe".to_vec(), Op::Insert(b"biden".to_vec())),
+            (b"larry".to_vec(), Op::Insert(b"engineer".to_vec())),
+        ]);
+        store.flush_and_commit(batch).unwrap();
+
+        let batch = Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+            (b"joe".to_vec(), Op::Delete),
+            (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+        ]);
+        store.flush_and_commit(batch).unwrap();
+
+        store.prune(1).unwrap();
+
+        // version 0 is gone, while version 1 is intact and can be proven
+        assert_eq!(store.root_hash(Some(0)).unwrap(), None);
+        assert_eq!(store.root_hash(Some(1)).unwrap(), Some(v1::ROOT_HASH));
+        assert!(store.prove(b"donald", Some(1)).is_ok());
+        assert!(store.verify(Some(1)).unwrap().is_ok());
+
+        // nothing is marked as orphaned anymore. the orphans are stored under
+        // the namespace "o", prefixed by its length.
+        assert!(store
+            .state_commitment()
+            .scan(Some(b"\0\x01o"), Some(b"\0\x01p"), Order::Ascending)
+            .next()
+            .is_none());
+
+        // the changesets of the pruned versions are gone too
+        assert!(store
+            .inner
+            .changesets(CHANGESET_PREFIX, 0, 1)
+            .unwrap()
+            .is_empty());
+
+        // the pruned version can't be rolled back to
+        assert!(matches!(
+            store.rollback(0),
+            Err(DbError::RollbackRootNotFound { to_version: 0 })
+        ));
+        assert_eq!(store.latest_version(), Some(1));
+        assert_eq!(store.root_hash(None).unwrap(), Some(v1::ROOT_HASH));
+    }
+
+    #[test]
+    fn rollback_window_works() {
+        let path = TempDataDir::new("_grug_db_rollback_window_works");
+        let store = DiskDb::open_with_config(&path, &DiskDbConfig {
+            rollback_window: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for value in ["trump", "duck", "biden", "engineer"] {
+            let batch = Batch::from([(b"donald".to_vec(), Op::Insert(value.as_bytes().to_vec()))]);
+            store.flush_and_commit(batch).unwrap();
+        }
+
+        // only the changesets of the last 2 versions are kept
+        assert_eq!(
+            store.inner.changesets(CHANGESET_PREFIX, 0, 3).unwrap(),
+            BTreeMap::from([(2, keys(&["donald"])), (3, keys(&["donald"]))])
+        );
+
+        // so at most 2 versions can be rolled back
+        assert!(matches!(
+            store.rollback(0),
+            Err(DbError::RollbackBeyondWindow {
+                latest_version: 3,
+                to_version: 0,
+                rollback_window: 2,
+            })
+        ));
+        store.rollback(1).unwrap();
+        assert_eq!(store.latest_version(), Some(1));
+        assert_eq!(
+            store.state_storage(None).read(b"donald"),
+            Some(b"duck".to_vec())
+        );
+    }
+
+    #[test]
+    fn rollback_disabled_by_default() {
+        let path = TempDataDir::new("_grug_db_rollback_disabled_by_default");
+        let store = DiskDb::open(&path).unwrap();
+
+        for value in ["trump", "duck"] {
+            let batch = Batch::from([(b"donald".to_vec(), Op::Insert(value.as_bytes().to_vec()))]);
+            store.flush_and_commit(batch).unwrap();
+        }
+
+        // no changesets are recorded, so nothing can be rolled back
+        assert!(store
+            .inner
+            .changesets(CHANGESET_PREFIX, 0, 1)
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            store.rollback(0),
+            Err(DbError::RollbackBeyondWindow {
+                latest_version: 1,
+                to_version: 0,
+                rollback_window: 0,
+            })
+        ));
+    }
+
+    #[test]
+    fn async_commit_works() {
+        let path = TempDataDir::new("_grug_db_async_commit_works");
+        let store = DiskDb::open_async(&path).unwrap();
+
+        let (_, root_hash) = store
+            .flush_and_commit(Batch::from([
+                (b"donald".to_vec(), Op::Insert(b"trump".to_vec())),
+                (b"jake".to_vec(), Op::Insert(b"shepherd".to_vec())),
+                (b"joe".to_vec(), Op::Insert(b"biden".to_vec())),
+                (b"larry".to_vec(), Op::Insert(b"engineer".to_vec())),
+            ]))
+            .unwrap();
+        assert_eq!(root_hash, Some(v0::ROOT_HASH));
+        store.wait_for_commit().unwrap();
+
+        // flush version 1 and hand it over as if it's being written, without
+        // actually writing it. reads at the latest version should be served
+        // from the in-memory data.
+        store
+            .flush_but_not_commit(Batch::from([
+                (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+                (b"joe".to_vec(), Op::Delete),
+                (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+            ]))
+            .unwrap();
+        let pending = store.inner.pending_data.write().unwrap().take().unwrap();
+        *store.inner.committing_data.write().unwrap() = Some(Arc::new(pending));
+
+        assert_eq!(store.latest_version(), Some(1));
+        assert_eq!(store.root_hash(None).unwrap(), Some(v1::ROOT_HASH));
+        assert_eq!(
+            store
+                .state_storage(None)
+                .scan(None, None, Order::Ascending)
+                .collect::<Vec<_>>(),
+            [
+                ("donald", "duck"),
+                ("jake", "shepherd"),
+                ("larry", "engineer"),
+                ("pumpkin", "cat"),
+            ]
+            .map(|(k, v)| (k.as_bytes().to_vec(), v.as_bytes().to_vec()))
+        );
+        assert_eq!(store.state_storage(None).read(b"joe"), None);
+
+        // older versions are unaffected
+        assert_eq!(
+            store.state_storage(Some(0)).read(b"joe"),
+            Some(b"biden".to_vec())
+        );
+
+        // write it for real this time, then commit version 2, which is written
+        // in the background.
+        let pending = store.inner.committing_data.write().unwrap().take().unwrap();
+        store.inner.write(&pending).unwrap();
+        store
+            .flush_and_commit(Batch::from([(b"jake".to_vec(), Op::Delete)]))
+            .unwrap();
+        assert_eq!(store.latest_version(), Some(2));
+        assert_eq!(store.state_storage(None).read(b"jake"), None);
+
+        // once the write lands, the data should be found on disk
+        store.wait_for_commit().unwrap();
+        assert!(store.inner.committing_data().is_none());
+        drop(store);
+
+        let store = DiskDb::open(&path).unwrap();
+        assert_eq!(store.latest_version(), Some(2));
+        assert_eq!(store.state_storage(None).read(b"jake"), None);
+        assert_eq!(
+            store.state_storage(Some(1)).read(b"jake"),
+            Some(b"shepherd".to_vec())
+        );
+    }
+
+    #[test]
+    fn async_commit_failing() {
+        let path = TempDataDir::new("_grug_db_async_commit_failing");
+        let store = DiskDb::open_async(&path).unwrap();
+
+        store
+            .flush_and_commit(Batch::from([(
+                b"donald".to_vec(),
+                Op::Insert(b"trump".to_vec()),
+            )]))
+            .unwrap();
+        store.wait_for_commit().unwrap();
+
+        // poison the write lock, so that the background write fails
+        let inner = Arc::clone(&store.inner);
+        thread::spawn(move || {
+            let _guard = inner.write_lock.lock().unwrap();
+            panic!("poisoning the write lock");
+        })
+        .join()
+        .unwrap_err();
+
+        store
+            .flush_and_commit(Batch::from([(
+                b"donald".to_vec(),
+                Op::Insert(b"duck".to_vec()),
+            )]))
+            .unwrap();
+
+        // the error is returned by the next flush, rather than a version being
+        // built on top of the lost one
+        assert!(store
+            .flush_but_not_commit(Batch::from([(b"joe".to_vec(), Op::Delete)]))
+            .is_err());
+
+        // the committing data is cleared, so reads don't see the lost version
+        assert!(store.inner.committing_data().is_none());
+        assert_eq!(store.latest_version(), Some(0));
+        assert_eq!(
+            store.state_storage(None).read(b"donald"),
+            Some(b"trump".to_vec())
+        );
+    }
+
+    /// Environment variable telling the test process to act as the node, and
+    /// at which step to crash.
+    const CRASH_STEP_ENV: &str = "GRUG_TEST_CRASH_STEP";
+
+    /// Environment variable telling the node process where its data is.
+    const CRASH_DATA_DIR_ENV: &str = "GRUG_TEST_CRASH_DATA_DIR";
+
+    /// Exit code of the node process when it crashes as instructed, so that
+    /// we can tell it from a crash for any other reason.
+    const CRASH_EXIT_CODE: i32 = 42;
+
+    /// Commit version 0, then process version 1, crashing at the given step.
+    ///
+    /// The process is terminated with `process::exit`, which doesn't run any
+    /// destructor, so RocksDB doesn't get a chance to shut down gracefully,
+    /// same as if the node was killed.
+    fn run_node_until_crash(data_dir: &str, step: &str) -> ! {
+        let crash_at = |current_step: &str| {
+            if current_step == step {
+                process::exit(CRASH_EXIT_CODE);
+            }
+        };
+
+        let store = DiskDb::open(data_dir).unwrap();
+        store
+            .flush_and_commit(Batch::from([
+                (b"donald".to_vec(), Op::Insert(b"trump".to_vec())),
+                (b"jake".to_vec(), Op::Insert(b"shepherd".to_vec())),
+                (b"joe".to_vec(), Op::Insert(b"biden".to_vec())),
+                (b"larry".to_vec(), Op::Insert(b"engineer".to_vec())),
+            ]))
+            .unwrap();
+
+        crash_at("before_flush");
+
+        store
+            .flush_but_not_commit(Batch::from([
+                (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+                (b"joe".to_vec(), Op::Delete),
+                (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+            ]))
+            .unwrap();
+
+        crash_at("after_flush");
+
+        store.commit().unwrap();
+
+        crash_at("after_commit");
+
+        unreachable!("unknown crash step: {step}");
+    }
+
+    // kill the node at each step of processing a block, then restart it. the
+    // database should be either at the previous version, in which case the
+    // block is to be replayed, or at the new version; never in between.
+    #[test]
+    fn recovering_from_crash() {
+        // if we're the node process, run the node and crash as instructed
+        if let Ok(step) = env::var(CRASH_STEP_ENV) {
+            let data_dir = env::var(CRASH_DATA_DIR_ENV).unwrap();
+            run_node_until_crash(&data_dir, &step);
+        }
+
+        // otherwise, spawn a node process for each step. the node process runs
+        // this same test, so find the test's full path, which doesn't include
+        // the crate name.
+        let (_, module_path) = module_path!().split_once("::").unwrap();
+        let test_name = format!("{module_path}::recovering_from_crash");
+
+        for (step, version, root_hash) in [
+            ("before_flush", 0, v0::ROOT_HASH),
+            ("after_flush", 0, v0::ROOT_HASH),
+            ("after_commit", 1, v1::ROOT_HASH),
+        ] {
+            let path = TempDataDir::new(&format!("_grug_db_recovering_from_crash_{step}"));
+            let status = process::Command::new(env::current_exe().unwrap())
+                .args(["--exact", &test_name, "--test-threads", "1"])
+                .env(CRASH_STEP_ENV, step)
+                .env(CRASH_DATA_DIR_ENV, AsRef::<Path>::as_ref(&&path))
+                .stdout(process::Stdio::null())
+                .status()
+                .unwrap();
+            assert_eq!(status.code(), Some(CRASH_EXIT_CODE));
+
+            // restart the node. the recovered app hash should be that of the
+            // last committed version.
+            let store = DiskDb::open(&path).unwrap();
+            assert_eq!(store.latest_version(), Some(version));
+            assert_eq!(store.root_hash(None).unwrap(), Some(root_hash));
+
+            // if the block was lost, replay it. it should result in the same
+            // app hash as if the crash never happened.
+            if version == 0 {
+                let (version, root_hash) = store
+                    .flush_and_commit(Batch::from([
+                        (b"donald".to_vec(), Op::Insert(b"duck".to_vec())),
+                        (b"joe".to_vec(), Op::Delete),
+                        (b"pumpkin".to_vec(), Op::Insert(b"cat".to_vec())),
+                    ]))
+                    .unwrap();
+                assert_eq!(version, 1);
+                assert_eq!(root_hash, Some(v1::ROOT_HASH));
+            }
+
+            for (key, value) in [
+                ("donald", Some("duck")),
+                ("jake", Some("shepherd")),
+                ("joe", None),
+                ("larry", Some("engineer")),
+                ("pumpkin", Some("cat")),
+            ] {
+                let found_value = store.state_storage(None).read(key.as_bytes());
+                assert_eq!(
+                    found_value
+                        .map(|bz| String::from_utf8(bz).unwrap())
+                        .as_deref(),
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn detecting_uncommitted_data() {
+        let path = TempDataDir::new("_grug_db_detecting_uncommitted_data");
+
+        // commit version 0, then write a node of version 1 directly into state
+        // commitment, as if only part of version 1 was written
+        {
+            let store = DiskDb::open(&path).unwrap();
+            store
+                .flush_and_commit(Batch::from([(
+                    b"donald".to_vec(),
+                    Op::Insert(b"trump".to_vec()),
+                )]))
+                .unwrap();
+            store.flush_but_not_commit(Batch::new()).unwrap();
+
+            let pending = store.inner.pending_data.write().unwrap().take().unwrap();
+            let cf = cf_state_commitment(&store.inner.db);
+            for (key, op) in pending.state_commitment {
+                if let Op::Insert(value) = op {
+                    store.inner.db.put_cf(&cf, key, value).unwrap();
+                }
+            }
+        }
+
+        assert!(matches!(
+            DiskDb::open(&path),
+            Err(DbError::UncommittedVersionFound { found_version: 1 })
+        ));
+    }
+
+    #[test]
+    fn detecting_locked_database() {
+        let path = TempDataDir::new("_grug_db_detecting_locked_database");
+        let _store = DiskDb::open(&path).unwrap();
+
+        // the database can't be opened again while it's in use
+        assert!(DiskDb::open(&path).is_err_and(|err| err.is_locked()));
+
+        // other errors aren't mistaken for it
+        let not_a_dir = tempfile::NamedTempFile::new().unwrap();
+        assert!(DiskDb::open(not_a_dir.path()).is_err_and(|err| !err.is_locked()));
+    }
+
+    #[test]
+    fn reporting_poisoned_locks() {
+        let path = TempDataDir::new("_grug_db_reporting_poisoned_locks");
+        let store = DiskDb::open(&path).unwrap();
+
+        // poison the locks by panicking while holding them
+        let inner = Arc::clone(&store.inner);
+        thread::spawn(move || {
+            let _pending_data = inner.pending_data.write().unwrap();
+            let _write_lock = inner.write_lock.lock().unwrap();
+            let _commit_thread = inner.commit_thread.lock().unwrap();
+            let _backup_thread = inner.backup_thread.lock().unwrap();
+            panic!("panicking while holding the locks");
+        })
+        .join()
+        .unwrap_err();
+
+        // each lock is reported as such
+        assert!(matches!(
+            store.flush_but_not_commit(Batch::new()),
+            Err(DbError::PendingDataPoisoned)
+        ));
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            store.checkpoint(checkpoint_dir.path().join("checkpoint")),
+            Err(DbError::WriteLockPoisoned)
+        ));
+        assert!(matches!(
+            store.wait_for_commit(),
+            Err(DbError::CommitThreadPoisoned)
+        ));
+        assert!(matches!(
+            store.wait_for_backup(),
+            Err(DbError::BackupThreadPoisoned)
+        ));
+    }
+
+    #[test]
+    fn verifying_and_repairing() {
+        let path = TempDataDir::new("_grug_db_verifying_and_repairing");
+        let store = DiskDb::open(&path).unwrap();
+
+        store
+            .flush_and_commit(Batch::from([
+                (b"donald".to_vec(), Op::Insert(b"trump".to_vec())),
+                (b"jake".to_vec(), Op::Insert(b"shepherd".to_vec())),
+            ]))
+            .unwrap();
+        store
+            .flush_and_commit(Batch::from([
+                (b"joe".to_vec(), Op::Insert(b"biden".to_vec())),
+                (b"larry".to_vec(), Op::Insert(b"engineer".to_vec())),
+            ]))
+            .unwrap();
+
+        // the database is consistent at both versions
+        for version in [None, Some(0), Some(1)] {
+            let report = store.verify(version).unwrap();
+            assert!(report.is_ok());
+            assert_eq!(report.computed_root_hash, store.root_hash(version).unwrap());
+        }
+
+        // corrupt the state storage at version 1: change a value, delete a key,
+        // and add a key
+        let cf = cf_state_storage(&store.inner.db);
+        let ts = U64Timestamp::from(1);
+        let mut batch = WriteBatch::default();
+        batch.put_cf_with_ts(&cf, b"larry", ts, b"fisherman");
+        batch.delete_cf_with_ts(&cf, b"jake", ts);
+        batch.put_cf_with_ts(&cf, b"pumpkin", ts, b"cat");
+        store.inner.db.write(batch).unwrap();
+
+        let report = store.verify(None).unwrap();
+        assert!(!report.is_ok());
+        assert_ne!(report.computed_root_hash, report.stored_root_hash);
+        assert_eq!(report.mismatches.len(), 3);
+        assert!(report.mismatches.contains(&Mismatch::WrongValueHash {
+            key: b"larry".to_vec(),
+            expected: hash("fisherman"),
+            found: hash("engineer"),
+        }));
+        assert!(report.mismatches.contains(&Mismatch::ExtraLeaf {
+            key_hash: hash("jake"),
+        }));
+        assert!(report.mismatches.contains(&Mismatch::MissingLeaf {
+            key: b"pumpkin".to_vec(),
+        }));
+
+        // version 0 isn't affected
+        assert!(store.verify(Some(0)).unwrap().is_ok());
+
+        // rebuild the state commitment. it should now agree with state storage,
+        // while trees of older versions are gone
+        let root_hash = store.repair().unwrap();
+        assert_eq!(root_hash, report.computed_root_hash);
+        assert_eq!(store.root_hash(None).unwrap(), root_hash);
+        assert_eq!(store.root_hash(Some(0)).unwrap(), None);
+        assert!(store.verify(None).unwrap().is_ok());
+
+        // the chain can move on from the rebuilt tree
+        store
+            .flush_and_commit(Batch::from([(
+                b"jake".to_vec(),
+                Op::Insert(b"shepherd".to_vec()),
+            )]))
+            .unwrap();
+        assert!(store.verify(None).unwrap().is_ok());
+    }
 }
@@ -1,8 +1,6 @@
 use {
-    crate::PendingData,
     grug_app::AppError,
-    grug_types::StdError,
-    std::sync::{PoisonError, RwLockReadGuard, RwLockWriteGuard},
+    grug_types::{Hash, StdError},
     thiserror::Error,
 };
 
@@ -14,25 +12,88 @@ pub enum DbError {
     #[error(transparent)]
     RocksDb(#[from] rocksdb::Error),
 
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
     #[error("cannot flush when the in-memory write batch is already set")]
     PendingDataAlreadySet,
 
     #[error("cannot commit when the in-memory write batch is not set")]
     PendingDataNotSet,
 
-    #[error("rwlock for the write batch is poisoned")]
+    #[error("lock for the write batch is poisoned")]
     PendingDataPoisoned,
-}
 
-impl<'a> From<PoisonError<RwLockReadGuard<'a, Option<PendingData>>>> for DbError {
-    fn from(_: PoisonError<RwLockReadGuard<'a, Option<PendingData>>>) -> Self {
-        Self::PendingDataPoisoned
-    }
+    #[error("lock for the write batch being committed is poisoned")]
+    CommittingDataPoisoned,
+
+    #[error("lock for the background commit thread is poisoned")]
+    CommitThreadPoisoned,
+
+    #[error("lock for writing to the database is poisoned")]
+    WriteLockPoisoned,
+
+    #[error("lock for the background backup thread is poisoned")]
+    BackupThreadPoisoned,
+
+    #[error("background thread for writing the committed data panicked")]
+    CommitThreadPanicked,
+
+    #[error("cannot roll back to version {to_version} when latest version is {latest_version}")]
+    RollbackVersionNotOlder {
+        latest_version: u64,
+        to_version: u64,
+    },
+
+    #[error("cannot roll back from {latest_version} to {to_version}, window is {rollback_window}")]
+    RollbackBeyondWindow {
+        latest_version: u64,
+        to_version: u64,
+        rollback_window: u64,
+    },
+
+    #[error("cannot roll back to version {to_version}, whose root hash isn't found")]
+    RollbackRootNotFound { to_version: u64 },
+
+    #[error("found uncommitted data of version {found_version} in state commitment")]
+    UncommittedVersionFound { found_version: u64 },
+
+    #[error("background thread for backing up the database panicked")]
+    BackupThreadPanicked,
+
+    #[error("cannot back up an empty database")]
+    BackupEmptyDb,
+
+    #[error("backup of version {version} not found")]
+    BackupNotFound { version: u64 },
+
+    #[error("backup is expected to be of version {expected}, found {found:?}")]
+    BackupVersionMismatch { expected: u64, found: Option<u64> },
+
+    #[error("root hash of backup of version {version} is {found:?}, expected {expected:?}")]
+    BackupRootHashMismatch {
+        version: u64,
+        expected: Option<Hash>,
+        found: Option<Hash>,
+    },
+
+    #[error("backup of version {version} is corrupted")]
+    BackupCorrupted { version: u64 },
 }
 
-impl<'a> From<PoisonError<RwLockWriteGuard<'a, Option<PendingData>>>> for DbError {
-    fn from(_: PoisonError<RwLockWriteGuard<'a, Option<PendingData>>>) -> Self {
-        Self::PendingDataPoisoned
+impl DbError {
+    /// Whether opening the database failed because it's already open, e.g. by
+    /// a running node. RocksDB reports this as an I/O error on the `LOCK` file
+    /// in the data directory.
+    pub fn is_locked(&self) -> bool {
+        matches!(
+            self,
+            DbError::RocksDb(err)
+                if err.kind() == rocksdb::ErrorKind::IOError && err.as_ref().contains("/LOCK")
+        )
     }
 }
 
@@ -1,6 +1,9 @@
+mod backup;
+mod config;
 mod db;
 mod error;
 mod testing;
 mod timestamp;
+mod verify;
 
-pub use {db::*, error::*, testing::*, timestamp::*};
+pub use {backup::*, config::*, db::*, error::*, testing::*, timestamp::*, verify::*};
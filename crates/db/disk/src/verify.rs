@@ -0,0 +1,40 @@
+use grug_types::Hash;
+
+/// Result of checking the state commitment against the state storage at a
+/// version. See `DiskDb::verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub version: u64,
+    /// The root hash found in the state commitment.
+    pub stored_root_hash: Option<Hash>,
+    /// The root hash of a Merkle tree built from scratch out of the state
+    /// storage.
+    pub computed_root_hash: Option<Hash>,
+    /// Discrepancies between the leaves of the Merkle tree in the state
+    /// commitment and the key-value pairs in the state storage.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl VerifyReport {
+    /// Whether the state commitment and the state storage agree.
+    pub fn is_ok(&self) -> bool {
+        self.stored_root_hash == self.computed_root_hash && self.mismatches.is_empty()
+    }
+}
+
+/// A discrepancy between the state commitment and the state storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The key exists in the state storage, but there is no leaf for it.
+    MissingLeaf { key: Vec<u8> },
+    /// The key exists in both, but the leaf's value hash doesn't match the
+    /// hash of the value in the state storage.
+    WrongValueHash {
+        key: Vec<u8>,
+        expected: Hash,
+        found: Hash,
+    },
+    /// There is a leaf for a key that doesn't exist in the state storage. Only
+    /// the key hash is known in this case.
+    ExtraLeaf { key_hash: Hash },
+}
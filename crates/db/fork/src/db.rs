@@ -0,0 +1,773 @@
+use {
+    crate::{DbError, DbResult, ForkSource},
+    grug_app::{CacheStore, Db},
+    grug_jmt::{BatchProof, MerkleTree, Proof, ICS23_PROOF_TYPE},
+    grug_types::{
+        extend_one_byte, Batch, Hash, Merged, Op, Order, Record, Storage, MAX_PAGE_LIMIT,
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        iter,
+        ops::Bound,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+        vec,
+    },
+};
+
+const MERKLE_TREE: MerkleTree = MerkleTree::new_default();
+
+struct ChangeSet {
+    version: u64,
+    state_commitment: Batch,
+    state_storage: Batch,
+}
+
+struct ForkDbInner {
+    /// The height at which the chain is forked.
+    fork_height: u64,
+    /// Version of the DB. Initialized to the fork height when the DB instance
+    /// is created, and incremented by 1 each time a batch is committed.
+    latest_version: u64,
+    /// A key-value store backing the Merkle tree of the data written locally.
+    state_commitment: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Data written locally since the fork: key => (version => op)
+    state_storage: BTreeMap<Vec<u8>, BTreeMap<u64, Op<Vec<u8>>>>,
+    /// Data pulled from the source at the fork height.
+    cache: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    /// Uncommitted changes
+    changeset: Option<ChangeSet>,
+    /// The first error from the source since the last flush, if any.
+    source_error: Option<String>,
+}
+
+/// A database that forks a chain at a given height, for running tests against
+/// mainnet data ("mainnet forking").
+///
+/// Data written locally are kept in memory. Reads are served from the local
+/// data first; keys that haven't been written locally are pulled from the
+/// source at the fork height, and cached. Reads at versions older than the fork
+/// height are pulled from the source at those versions, and not cached.
+///
+/// Iterating the state storage merges the data written locally with the range
+/// pulled from the source, `MAX_PAGE_LIMIT` records at a time. Ranges aren't
+/// cached, so each iteration queries the source again.
+///
+/// Since the DB doesn't have the full state, the Merkle tree only contains data
+/// written locally. As such, root hashes don't match those of the forked chain,
+/// and Merkle proofs can't be generated.
+///
+/// `Storage` methods can't return errors, so if the source fails, reads return
+/// nothing and iterations end early. The error is kept, and returned by the
+/// next `flush_but_not_commit`, so that a block executed on incomplete data
+/// isn't committed. Queries performed in the meantime may return incomplete
+/// results.
+pub struct ForkDb<S> {
+    inner: Arc<RwLock<ForkDbInner>>,
+    source: Arc<S>,
+}
+
+impl<S> ForkDb<S> {
+    pub fn new(source: S, fork_height: u64) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(ForkDbInner {
+                fork_height,
+                latest_version: fork_height,
+                state_commitment: BTreeMap::new(),
+                state_storage: BTreeMap::new(),
+                cache: HashMap::new(),
+                changeset: None,
+                source_error: None,
+            })),
+            source: Arc::new(source),
+        }
+    }
+
+    /// Return the source from which data are pulled.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    fn with_read<C, T>(&self, callback: C) -> T
+    where
+        C: FnOnce(RwLockReadGuard<ForkDbInner>) -> T,
+    {
+        let lock = self.inner.read().unwrap_or_else(|err| {
+            panic!("ForkDb is poisoned: {err:?}");
+        });
+        callback(lock)
+    }
+
+    fn with_write<C, T>(&self, callback: C) -> T
+    where
+        C: FnOnce(RwLockWriteGuard<ForkDbInner>) -> T,
+    {
+        let lock = self.inner.write().unwrap_or_else(|err| {
+            panic!("ForkDb is poisoned: {err:?}");
+        });
+        callback(lock)
+    }
+
+    // keep the first error, which is likely the cause of any later ones
+    fn record_source_error(&self, error: String) {
+        self.with_write(|mut inner| {
+            inner.source_error.get_or_insert(error);
+        });
+    }
+}
+
+impl<S> Clone for ForkDb<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            source: Arc::clone(&self.source),
+        }
+    }
+}
+
+impl<S> Db for ForkDb<S>
+where
+    S: ForkSource + 'static,
+{
+    type BatchProof = BatchProof;
+    type Error = DbError;
+    type Proof = Proof;
+
+    const ICS23_PROOF_TYPE: &'static str = ICS23_PROOF_TYPE;
+
+    fn state_commitment(&self) -> impl Storage + Clone + 'static {
+        StateCommitment { db: self.clone() }
+    }
+
+    fn state_storage(&self, version: Option<u64>) -> impl Storage + Clone + 'static {
+        StateStorage {
+            db: self.clone(),
+            version: version.unwrap_or_else(|| self.with_read(|inner| inner.latest_version)),
+        }
+    }
+
+    fn latest_version(&self) -> Option<u64> {
+        self.with_read(|inner| Some(inner.latest_version))
+    }
+
+    fn root_hash(&self, version: Option<u64>) -> DbResult<Option<Hash>> {
+        let version = version.unwrap_or_else(|| self.with_read(|inner| inner.latest_version));
+        Ok(MERKLE_TREE.root_hash(&self.state_commitment(), version)?)
+    }
+
+    fn prove(&self, _key: &[u8], _version: Option<u64>) -> DbResult<Proof> {
+        Err(DbError::ProveUnsupported)
+    }
+
+    fn prove_ics23(&self, _key: &[u8], _version: Option<u64>) -> DbResult<Vec<u8>> {
+        Err(DbError::ProveUnsupported)
+    }
+
+    fn prove_many(&self, _keys: &[&[u8]], _version: Option<u64>) -> DbResult<BatchProof> {
+        Err(DbError::ProveUnsupported)
+    }
+
+    // same as in `MemDb`, do everything that requires a read lock first, and
+    // everything that requires a write lock in the end, to avoid deadlocks.
+    fn flush_but_not_commit(&self, batch: Batch) -> DbResult<(u64, Option<Hash>)> {
+        // the batch may have been produced from incomplete data. the error is
+        // cleared, so that the block can be retried.
+        if let Some(error) = self.with_write(|mut inner| inner.source_error.take()) {
+            return Err(DbError::SourceFailed { error });
+        }
+
+        let (new_version, root_hash, changeset) = self.with_read(|inner| {
+            if inner.changeset.is_some() {
+                return Err(DbError::ChangeSetAlreadySet);
+            }
+
+            let old_version = inner.latest_version;
+            let new_version = old_version + 1;
+
+            let mut cache = CacheStore::new(self.state_commitment(), None);
+            let root_hash = MERKLE_TREE.apply_raw(&mut cache, old_version, new_version, &batch)?;
+            let (_, changeset) = cache.disassemble();
+
+            Ok((new_version, root_hash, changeset))
+        })?;
+
+        self.with_write(|mut inner| {
+            inner.changeset = Some(ChangeSet {
+                version: new_version,
+                state_commitment: changeset,
+                state_storage: batch,
+            });
+        });
+
+        Ok((new_version, root_hash))
+    }
+
+    fn commit(&self) -> DbResult<()> {
+        self.with_write(|mut inner| {
+            let changeset = inner.changeset.take().ok_or(DbError::ChangeSetNotSet)?;
+
+            // update the version
+            inner.latest_version = changeset.version;
+
+            // write changes to state commitment
+            for (key, op) in changeset.state_commitment {
+                if let Op::Insert(value) = op {
+                    inner.state_commitment.insert(key, value);
+                } else {
+                    inner.state_commitment.remove(&key);
+                }
+            }
+
+            // write changes to state storage. note that deletions are recorded
+            // as well, so that deleted keys aren't pulled from the source.
+            for (key, op) in changeset.state_storage {
+                inner
+                    .state_storage
+                    .entry(key)
+                    .or_default()
+                    .insert(changeset.version, op);
+            }
+
+            Ok(())
+        })
+    }
+}
+
+// ----------------------------- state commitment ------------------------------
+
+pub struct StateCommitment<S> {
+    db: ForkDb<S>,
+}
+
+impl<S> Clone for StateCommitment<S> {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+        }
+    }
+}
+
+impl<S> Storage for StateCommitment<S>
+where
+    S: ForkSource,
+{
+    fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.db
+            .with_read(|inner| inner.state_commitment.get(key).cloned())
+    }
+
+    fn scan<'a>(
+        &'a self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        let Some(bounds) = bounds(min, max) else {
+            return Box::new(iter::empty());
+        };
+        let vec = self.db.with_read(|inner| {
+            inner
+                .state_commitment
+                .range::<[u8], _>(bounds)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>()
+        });
+        match order {
+            Order::Ascending => Box::new(vec.into_iter()),
+            Order::Descending => Box::new(vec.into_iter().rev()),
+        }
+    }
+
+    fn write(&mut self, _key: &[u8], _value: &[u8]) {
+        unreachable!("write function called on read-only storage");
+    }
+
+    fn remove(&mut self, _key: &[u8]) {
+        unreachable!("write function called on read-only storage");
+    }
+
+    fn remove_range(&mut self, _min: Option<&[u8]>, _max: Option<&[u8]>) {
+        unreachable!("write function called on read-only storage");
+    }
+}
+
+// ------------------------------- state storage -------------------------------
+
+pub struct StateStorage<S> {
+    db: ForkDb<S>,
+    version: u64,
+}
+
+impl<S> Clone for StateStorage<S> {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            version: self.version,
+        }
+    }
+}
+
+impl<S> Storage for StateStorage<S>
+where
+    S: ForkSource,
+{
+    fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let (fork_height, found) = self.db.with_read(|inner| {
+            // look for the key in the data written locally
+            let local = inner
+                .state_storage
+                .get(key)
+                .and_then(|ops| ops.range(..=self.version).next_back())
+                .map(|(_, op)| op.clone().into_option());
+            // if not found, look for it in the data pulled from the source
+            let found = if self.version >= inner.fork_height {
+                local.or_else(|| inner.cache.get(key).cloned())
+            } else {
+                None
+            };
+            (inner.fork_height, found)
+        });
+
+        if let Some(value) = found {
+            return value;
+        }
+
+        let height = self.version.min(fork_height);
+        let value = match self.db.source.read(key, height) {
+            Ok(value) => value,
+            Err(err) => {
+                self.db.record_source_error(err.to_string());
+                return None;
+            },
+        };
+
+        if height == fork_height {
+            self.db.with_write(|mut inner| {
+                inner.cache.insert(key.to_vec(), value.clone());
+            });
+        }
+
+        value
+    }
+
+    fn scan<'a>(
+        &'a self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        let Some(bounds) = bounds(min, max) else {
+            return Box::new(iter::empty());
+        };
+
+        // the latest local op of each key in the range, same as in `read`.
+        // local data are ignored at versions older than the fork height.
+        let (fork_height, mut local) = self.db.with_read(|inner| {
+            let local = if self.version >= inner.fork_height {
+                inner
+                    .state_storage
+                    .range::<[u8], _>(bounds)
+                    .filter_map(|(key, ops)| {
+                        let (_, op) = ops.range(..=self.version).next_back()?;
+                        Some((key.clone(), op.clone()))
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                vec![]
+            };
+            (inner.fork_height, local)
+        });
+
+        if order == Order::Descending {
+            local.reverse();
+        }
+
+        let remote = RemoteRange {
+            db: &self.db,
+            min: min.map(|bytes| bytes.to_vec()),
+            max: max.map(|bytes| bytes.to_vec()),
+            order,
+            height: self.version.min(fork_height),
+            page: Vec::new().into_iter(),
+            done: false,
+        };
+
+        Box::new(Merged::new(remote, local.into_iter(), order))
+    }
+
+    fn write(&mut self, _key: &[u8], _value: &[u8]) {
+        unreachable!("write function called on read-only storage");
+    }
+
+    fn remove(&mut self, _key: &[u8]) {
+        unreachable!("write function called on read-only storage");
+    }
+
+    fn remove_range(&mut self, _min: Option<&[u8]>, _max: Option<&[u8]>) {
+        unreachable!("write function called on read-only storage");
+    }
+}
+
+/// Iterates a range of the source's state storage at the given height, pulling
+/// `MAX_PAGE_LIMIT` records at a time.
+struct RemoteRange<'a, S> {
+    db: &'a ForkDb<S>,
+    min: Option<Vec<u8>>,
+    max: Option<Vec<u8>>,
+    order: Order,
+    height: u64,
+    page: vec::IntoIter<Record>,
+    done: bool,
+}
+
+impl<'a, S> Iterator for RemoteRange<'a, S>
+where
+    S: ForkSource,
+{
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.page.next() {
+                return Some(record);
+            }
+
+            if self.done {
+                return None;
+            }
+
+            let records = match self.db.source.scan(
+                self.min.as_deref(),
+                self.max.as_deref(),
+                self.order,
+                MAX_PAGE_LIMIT,
+                self.height,
+            ) {
+                Ok(records) => records,
+                Err(err) => {
+                    self.db.record_source_error(err.to_string());
+                    self.done = true;
+                    return None;
+                },
+            };
+
+            // the source may cap the page size lower than we ask for, so a page
+            // that isn't full isn't necessarily the last one. keep going until
+            // an empty page. the next page starts after the last record of this
+            // one.
+            self.done = records.is_empty();
+            if let Some((key, _)) = records.last() {
+                match self.order {
+                    Order::Ascending => self.min = Some(extend_one_byte(key.clone())),
+                    Order::Descending => self.max = Some(key.clone()),
+                }
+            }
+
+            self.page = records.into_iter();
+        }
+    }
+}
+
+// `min` is inclusive and `max` exclusive. `None` if the range is empty, since
+// `BTreeMap::range` panics if the start is greater than the end.
+#[allow(clippy::type_complexity)]
+fn bounds<'a>(
+    min: Option<&'a [u8]>,
+    max: Option<&'a [u8]>,
+) -> Option<(Bound<&'a [u8]>, Bound<&'a [u8]>)> {
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return None;
+        }
+    }
+
+    Some((
+        min.map_or(Bound::Unbounded, Bound::Included),
+        max.map_or(Bound::Unbounded, Bound::Excluded),
+    ))
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        grug_app::{App, AppError},
+        grug_db_memory::MemDb,
+        grug_vm_rust::RustVm,
+        std::sync::atomic::{AtomicUsize, Ordering::SeqCst},
+    };
+
+    // a stand-in for a remote node, which serves data from an in-process app,
+    // and counts the queries it receives
+    struct CountingSource {
+        app: App<MemDb, RustVm>,
+        queries: AtomicUsize,
+    }
+
+    impl CountingSource {
+        fn new(db: MemDb) -> Self {
+            Self {
+                app: App::new(db),
+                queries: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl ForkSource for CountingSource {
+        type Error = AppError;
+
+        fn read(&self, key: &[u8], height: u64) -> Result<Option<Vec<u8>>, AppError> {
+            self.queries.fetch_add(1, SeqCst);
+            self.app.read(key, height)
+        }
+
+        fn scan(
+            &self,
+            min: Option<&[u8]>,
+            max: Option<&[u8]>,
+            order: Order,
+            limit: u32,
+            height: u64,
+        ) -> Result<Vec<Record>, AppError> {
+            self.queries.fetch_add(1, SeqCst);
+            self.app.scan(min, max, order, limit, height)
+        }
+    }
+
+    // a source that always fails, as a remote node would if it went offline
+    struct FailingSource;
+
+    impl ForkSource for FailingSource {
+        type Error = String;
+
+        fn read(&self, _key: &[u8], _height: u64) -> Result<Option<Vec<u8>>, String> {
+            Err("node is offline".into())
+        }
+
+        fn scan(
+            &self,
+            _min: Option<&[u8]>,
+            _max: Option<&[u8]>,
+            _order: Order,
+            _limit: u32,
+            _height: u64,
+        ) -> Result<Vec<Record>, String> {
+            Err("node is offline".into())
+        }
+    }
+
+    fn insert(key: &str, value: &str) -> (Vec<u8>, Op<Vec<u8>>) {
+        (
+            key.as_bytes().to_vec(),
+            Op::Insert(value.as_bytes().to_vec()),
+        )
+    }
+
+    fn delete(key: &str) -> (Vec<u8>, Op<Vec<u8>>) {
+        (key.as_bytes().to_vec(), Op::Delete)
+    }
+
+    #[test]
+    fn forking_works() {
+        // the remote chain at versions 1, 2, and 3. nothing is written at
+        // version 0, since the `/store` query interprets height 0 as the
+        // latest height.
+        let remote_db = MemDb::new();
+        for batch in [
+            Batch::new(),
+            Batch::from([insert("donald", "trump"), insert("jake", "shepherd")]),
+            Batch::from([insert("donald", "duck"), insert("joe", "biden")]),
+            Batch::from([delete("joe"), insert("larry", "engineer")]),
+        ] {
+            remote_db.flush_and_commit(batch).unwrap();
+        }
+
+        // fork the chain at version 2
+        let db = ForkDb::new(CountingSource::new(remote_db.clone()), 2);
+        assert_eq!(db.latest_version(), Some(2));
+
+        // data are pulled from the source at the fork height
+        let storage = db.state_storage(None);
+        for (key, value) in [
+            ("donald", Some("duck")),
+            ("jake", Some("shepherd")),
+            ("joe", Some("biden")),
+            ("larry", None),
+        ] {
+            assert_eq!(
+                storage.read(key.as_bytes()),
+                value.map(|v| v.as_bytes().to_vec())
+            );
+        }
+        assert_eq!(db.source().queries.load(SeqCst), 4);
+
+        // reading the same keys again hits the cache, including for keys that
+        // don't exist
+        assert_eq!(storage.read(b"donald"), Some(b"duck".to_vec()));
+        assert_eq!(storage.read(b"larry"), None);
+        assert_eq!(db.source().queries.load(SeqCst), 4);
+
+        // write some data locally
+        let (version, root_hash) = db
+            .flush_and_commit(Batch::from([
+                insert("donald", "trump"),
+                delete("jake"),
+                insert("pumpkin", "cat"),
+            ]))
+            .unwrap();
+        assert_eq!(version, 3);
+        assert_eq!(db.latest_version(), Some(3));
+        assert_eq!(db.root_hash(None).unwrap(), root_hash);
+        assert!(root_hash.is_some());
+
+        // data written locally take precedence, and keys deleted locally are
+        // not pulled from the source
+        let storage = db.state_storage(None);
+        for (key, value) in [
+            ("donald", Some("trump")),
+            ("jake", None),
+            ("joe", Some("biden")),
+            ("pumpkin", Some("cat")),
+        ] {
+            assert_eq!(
+                storage.read(key.as_bytes()),
+                value.map(|v| v.as_bytes().to_vec())
+            );
+        }
+        assert_eq!(db.source().queries.load(SeqCst), 4);
+
+        // reading at the fork height ignores local data
+        let storage = db.state_storage(Some(2));
+        assert_eq!(storage.read(b"donald"), Some(b"duck".to_vec()));
+        assert_eq!(storage.read(b"pumpkin"), None);
+        assert_eq!(db.source().queries.load(SeqCst), 5);
+
+        // reading before the fork height queries the source at that height,
+        // without caching
+        let storage = db.state_storage(Some(1));
+        assert_eq!(storage.read(b"donald"), Some(b"trump".to_vec()));
+        assert_eq!(storage.read(b"donald"), Some(b"trump".to_vec()));
+        assert_eq!(db.source().queries.load(SeqCst), 7);
+
+        // the remote chain isn't affected
+        assert_eq!(remote_db.state_storage(None).read(b"pumpkin"), None);
+
+        // Merkle proofs are not supported
+        assert!(matches!(
+            db.prove(b"donald", None),
+            Err(DbError::ProveUnsupported)
+        ));
+    }
+
+    #[test]
+    fn scanning_works() {
+        // the remote chain has more records than fit in one page, so that
+        // scanning pulls multiple pages from the source
+        let remote_db = MemDb::new();
+        remote_db.flush_and_commit(Batch::new()).unwrap();
+        remote_db
+            .flush_and_commit(
+                (0..250_u16)
+                    .map(|i| (i.to_be_bytes().to_vec(), Op::Insert(vec![1])))
+                    .collect(),
+            )
+            .unwrap();
+
+        let db = ForkDb::new(CountingSource::new(remote_db), 1);
+        let storage = db.state_storage(None);
+
+        let records = storage
+            .scan(None, None, Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 250);
+        // 3 pages of records, then an empty one
+        assert_eq!(db.source().queries.load(SeqCst), 4);
+
+        // overwrite, delete, and insert some records locally
+        let key = |i: u16| i.to_be_bytes().to_vec();
+        db.flush_and_commit(Batch::from([
+            (key(0), Op::Delete),
+            (key(100), Op::Insert(vec![2])),
+            (key(199), Op::Delete),
+            (key(300), Op::Insert(vec![2])),
+        ]))
+        .unwrap();
+
+        let mut expected = (1..250_u16)
+            .filter(|i| *i != 199)
+            .map(|i| {
+                (key(i), vec![if i == 100 {
+                    2
+                } else {
+                    1
+                }])
+            })
+            .collect::<Vec<_>>();
+        expected.push((key(300), vec![2]));
+
+        // the local data are merged with the remote range, in both orders
+        let storage = db.state_storage(None);
+        let records = storage
+            .scan(None, None, Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(records, expected);
+
+        let records = storage
+            .scan(None, None, Order::Descending)
+            .collect::<Vec<_>>();
+        assert_eq!(records, expected.iter().rev().cloned().collect::<Vec<_>>());
+
+        // bounded ranges
+        let records = storage
+            .scan(Some(&key(99)), Some(&key(201)), Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(records, expected[98..199]);
+
+        let records = storage
+            .scan(Some(&key(201)), Some(&key(99)), Order::Ascending)
+            .collect::<Vec<_>>();
+        assert!(records.is_empty());
+
+        // scanning at the fork height ignores the local data
+        let records = db
+            .state_storage(Some(1))
+            .scan(None, Some(&key(2)), Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(records, [(key(0), vec![1]), (key(1), vec![1])]);
+
+        // the state commitment can be iterated as well, which contains the
+        // Merkle tree of the data written locally
+        let commitment = db.state_commitment();
+        let asc = commitment
+            .scan(None, None, Order::Ascending)
+            .collect::<Vec<_>>();
+        let desc = commitment
+            .scan(None, None, Order::Descending)
+            .collect::<Vec<_>>();
+        assert!(!asc.is_empty());
+        assert_eq!(asc, desc.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn source_errors_are_reported_on_flush() {
+        let db = ForkDb::new(FailingSource, 1);
+
+        // reads and iterations can't return the error, so they come up empty
+        let storage = db.state_storage(None);
+        assert_eq!(storage.read(b"donald"), None);
+        assert_eq!(storage.scan(None, None, Order::Ascending).count(), 0);
+
+        // the block executed on the incomplete data can't be flushed
+        assert!(matches!(
+            db.flush_but_not_commit(Batch::from([insert("donald", "trump")])),
+            Err(DbError::SourceFailed { error }) if error == "node is offline"
+        ));
+
+        // the error is cleared, so a retry goes through
+        let (version, _) = db
+            .flush_and_commit(Batch::from([insert("donald", "trump")]))
+            .unwrap();
+        assert_eq!(version, 2);
+    }
+}
@@ -0,0 +1,47 @@
+use {grug_app::AppError, grug_types::StdError, thiserror::Error};
+
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error(transparent)]
+    Std(#[from] StdError),
+
+    #[error("cannot flush when changeset is already set")]
+    ChangeSetAlreadySet,
+
+    #[error("cannot commit when changeset is not yet set")]
+    ChangeSetNotSet,
+
+    #[error("cannot generate Merkle proofs without the full state")]
+    ProveUnsupported,
+
+    #[error("failed to pull data from the fork source: {error}")]
+    SourceFailed { error: String },
+
+    #[cfg(feature = "rpc")]
+    #[error(transparent)]
+    Rpc(#[from] tendermint_rpc::Error),
+
+    #[cfg(feature = "rpc")]
+    #[error(transparent)]
+    Tendermint(#[from] tendermint::Error),
+
+    #[cfg(feature = "rpc")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[cfg(feature = "rpc")]
+    #[error("query failed! codespace = {codespace}, code = {code}, log = {log}")]
+    QueryFailed {
+        codespace: String,
+        code: u32,
+        log: String,
+    },
+}
+
+impl From<DbError> for AppError {
+    fn from(err: DbError) -> Self {
+        AppError::Db(err.to_string())
+    }
+}
+
+pub type DbResult<T> = std::result::Result<T, DbError>;
@@ -0,0 +1,9 @@
+mod db;
+mod error;
+#[cfg(feature = "rpc")]
+mod rpc;
+mod source;
+
+#[cfg(feature = "rpc")]
+pub use rpc::*;
+pub use {db::*, error::*, source::*};
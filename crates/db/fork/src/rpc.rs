@@ -0,0 +1,291 @@
+use {
+    crate::{DbError, DbResult, ForkSource},
+    grug_types::{
+        extend_one_byte, from_json_slice, to_json_vec, Binary, Order, Record, StoreRangeRequest,
+    },
+    tendermint::block::Height,
+    tendermint_rpc::{Client, HttpClient},
+    tokio::runtime::{Builder, Runtime},
+};
+
+/// Pulls data from a remote node using the `/store/range` ABCI query.
+///
+/// `Db` methods are synchronous, so the client comes with its own runtime to
+/// block on requests. As such, this must not be used from within an async
+/// context.
+pub struct RpcSource {
+    client: HttpClient,
+    runtime: Runtime,
+}
+
+impl RpcSource {
+    pub fn connect(endpoint: &str) -> DbResult<Self> {
+        let client = HttpClient::new(endpoint)?;
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self { client, runtime })
+    }
+
+    fn query(&self, path: &str, data: Vec<u8>, height: u64) -> DbResult<Vec<u8>> {
+        let height = Height::try_from(height)?;
+        let res = self.runtime.block_on(self.client.abci_query(
+            Some(path.into()),
+            data,
+            Some(height),
+            false,
+        ))?;
+
+        if res.code.is_err() {
+            return Err(DbError::QueryFailed {
+                codespace: res.codespace,
+                code: res.code.value(),
+                log: res.log,
+            });
+        }
+
+        Ok(res.value)
+    }
+}
+
+impl ForkSource for RpcSource {
+    type Error = DbError;
+
+    fn read(&self, key: &[u8], height: u64) -> DbResult<Option<Vec<u8>>> {
+        // the `/store` query returns an empty value both if the key doesn't
+        // exist and if its value is empty. instead, scan a range containing
+        // only the key, which tells the two apart.
+        let max = extend_one_byte(key.to_vec());
+        let record = self
+            .scan(Some(key), Some(&max), Order::Ascending, 1, height)?
+            .pop();
+
+        Ok(record.map(|(_, value)| value))
+    }
+
+    fn scan(
+        &self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+        height: u64,
+    ) -> DbResult<Vec<Record>> {
+        let req = StoreRangeRequest {
+            min: min.map(|bytes| bytes.to_vec().into()),
+            max: max.map(|bytes| bytes.to_vec().into()),
+            order,
+            limit: Some(limit),
+        };
+        let res = self.query("/store/range", to_json_vec(&req)?, height)?;
+        let records: Vec<(Binary, Binary)> = from_json_slice(res)?;
+
+        Ok(records
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect())
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::ForkDb,
+        grug_app::{App, Db},
+        grug_db_memory::MemDb,
+        grug_types::{to_json_value, Batch, Json, Op, Storage},
+        grug_vm_rust::RustVm,
+        serde_json::json,
+        std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::{TcpListener, TcpStream},
+            thread,
+        },
+    };
+
+    // nothing listens on this port, so requests fail without a network
+    const UNREACHABLE: &str = "http://127.0.0.1:1";
+
+    // a stand-in for a CometBFT node, which serves the `abci_query` JSON-RPC
+    // method over HTTP from an in-process app. return the endpoint to connect
+    // to. the node runs until the test process exits.
+    fn start_mock_node(app: App<MemDb, RustVm>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                handle_request(&app, stream.unwrap());
+            }
+        });
+
+        endpoint
+    }
+
+    // handle a single HTTP request, then close the connection
+    fn handle_request(app: &App<MemDb, RustVm>, mut stream: TcpStream) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        // the params of `abci_query`, with the data in hex and the height as a
+        // string
+        let req: Json = serde_json::from_slice(&body).unwrap();
+        let params = &req["params"];
+        let path = params["path"].as_str().unwrap();
+        let data = hex::decode(params["data"].as_str().unwrap()).unwrap();
+        let height: u64 = params["height"].as_str().unwrap().parse().unwrap();
+
+        let res = match path {
+            "/store/range" => app.do_query_store_range_raw(&data, height),
+            _ => unimplemented!("the mock node only serves `/store/range` queries"),
+        };
+        let (code, value, log) = match res {
+            Ok(value) => (0, value, String::new()),
+            Err(err) => (1, vec![], err.to_string()),
+        };
+
+        let res = json!({
+            "jsonrpc": "2.0",
+            "id": req["id"],
+            "result": {
+                "response": {
+                    "code": code,
+                    "log": log,
+                    "info": "",
+                    "index": "0",
+                    "key": "",
+                    "value": to_json_value(&Binary::from(value)).unwrap(),
+                    "proofOps": null,
+                    "height": height.to_string(),
+                    "codespace": "",
+                },
+            },
+        })
+        .to_string();
+
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{res}",
+            res.len()
+        )
+        .unwrap();
+    }
+
+    // a remote chain at versions 1 and 2, with `num_records` extra records at
+    // version 2, for making scans span multiple pages. nothing is written at
+    // version 0, since queries interpret height 0 as the latest height.
+    fn mock_remote(num_records: u16) -> App<MemDb, RustVm> {
+        let db = MemDb::new();
+        db.flush_and_commit(Batch::new()).unwrap();
+        db.flush_and_commit(Batch::from([
+            (b"donald".to_vec(), Op::Insert(b"trump".to_vec())),
+            (b"empty".to_vec(), Op::Insert(vec![])),
+        ]))
+        .unwrap();
+        db.flush_and_commit(
+            (0..num_records)
+                .map(|i| {
+                    (
+                        [b"page".as_slice(), &i.to_be_bytes()].concat(),
+                        Op::Insert(vec![1]),
+                    )
+                })
+                .chain([(b"donald".to_vec(), Op::Insert(b"duck".to_vec()))])
+                .collect(),
+        )
+        .unwrap();
+        App::new(db)
+    }
+
+    #[test]
+    fn reading_works() {
+        let endpoint = start_mock_node(mock_remote(0));
+        let source = RpcSource::connect(&endpoint).unwrap();
+
+        assert_eq!(source.read(b"donald", 1).unwrap(), Some(b"trump".to_vec()));
+        assert_eq!(source.read(b"donald", 2).unwrap(), Some(b"duck".to_vec()));
+
+        // an empty value is told apart from a missing one
+        assert_eq!(source.read(b"empty", 2).unwrap(), Some(vec![]));
+        assert_eq!(source.read(b"larry", 2).unwrap(), None);
+
+        // keys that the requested key is a prefix of aren't mistaken for it
+        assert_eq!(source.read(b"don", 2).unwrap(), None);
+    }
+
+    #[test]
+    fn scanning_works() {
+        // more records than fit in one page
+        let endpoint = start_mock_node(mock_remote(250));
+        let source = RpcSource::connect(&endpoint).unwrap();
+
+        // a single request returns at most one page
+        let records = source
+            .scan(Some(b"page"), Some(b"pagf"), Order::Ascending, 10, 2)
+            .unwrap();
+        assert_eq!(records.len(), 10);
+        assert_eq!(records[0], (b"page\0\0".to_vec(), vec![1]));
+
+        // the fork pulls all pages, in both orders
+        let db = ForkDb::new(source, 2);
+        let storage = db.state_storage(None);
+        for order in [Order::Ascending, Order::Descending] {
+            let records = storage
+                .scan(Some(b"page"), Some(b"pagf"), order)
+                .collect::<Vec<_>>();
+            assert_eq!(records.len(), 250);
+        }
+
+        // the records of other heights aren't included
+        let records = db
+            .state_storage(Some(1))
+            .scan(None, None, Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(records, [
+            (b"donald".to_vec(), b"trump".to_vec()),
+            (b"empty".to_vec(), vec![]),
+        ]);
+    }
+
+    #[test]
+    fn connecting_to_invalid_endpoint() {
+        assert!(matches!(
+            RpcSource::connect("not a url"),
+            Err(DbError::Rpc(_))
+        ));
+    }
+
+    #[test]
+    fn failing_requests_return_errors() {
+        let source = RpcSource::connect(UNREACHABLE).unwrap();
+
+        assert!(matches!(source.read(b"foo", 1), Err(DbError::Rpc(_))));
+        assert!(matches!(
+            source.scan(None, None, Order::Ascending, 10, 1),
+            Err(DbError::Rpc(_))
+        ));
+
+        // heights are checked before sending the request
+        assert!(matches!(
+            source.read(b"foo", u64::MAX),
+            Err(DbError::Tendermint(_))
+        ));
+    }
+}
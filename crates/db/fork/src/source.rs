@@ -0,0 +1,53 @@
+use {
+    grug_app::{App, AppError, Db, Vm},
+    grug_types::{Order, Record},
+};
+
+/// A source of the chain's state, from which `ForkDb` pulls the data it
+/// doesn't have locally.
+///
+/// This mirrors the `/store` and `/store/range` ABCI queries: given raw keys
+/// and a block height, return the raw values in the state storage.
+pub trait ForkSource: Send + Sync {
+    type Error: ToString;
+
+    /// Read the value of a raw key in the state storage at the given height.
+    fn read(&self, key: &[u8], height: u64) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Read at most `limit` records in a range of the state storage at the
+    /// given height. `min` is inclusive and `max` exclusive.
+    fn scan(
+        &self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+        height: u64,
+    ) -> Result<Vec<Record>, Self::Error>;
+}
+
+/// An in-process app can be used as the source, which is useful in tests.
+impl<DB, VM> ForkSource for App<DB, VM>
+where
+    DB: Db + Send + Sync,
+    VM: Vm + Send + Sync,
+    AppError: From<DB::Error> + From<VM::Error>,
+{
+    type Error = AppError;
+
+    fn read(&self, key: &[u8], height: u64) -> Result<Option<Vec<u8>>, AppError> {
+        let (value, _) = self.do_query_store(key, height, false)?;
+        Ok(value)
+    }
+
+    fn scan(
+        &self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+        height: u64,
+    ) -> Result<Vec<Record>, AppError> {
+        self.do_query_store_range(min, max, order, Some(limit), height)
+    }
+}
@@ -1,17 +1,37 @@
 use {
-    crate::{DbError, DbResult, VersionedMap},
+    crate::{DbError, DbResult, PersistentStorage, VersionedMap},
+    borsh::{BorshDeserialize, BorshSerialize},
     grug_app::{CacheStore, Db},
-    grug_jmt::{MerkleTree, Proof},
-    grug_types::{hash, Batch, Hash, Op, Order, Record, Storage},
+    grug_jmt::{BatchProof, MerkleTree, Proof, ICS23_PROOF_TYPE},
+    grug_types::{hash, Batch, Hash, Order, Record, Storage},
+    prost::Message,
     std::{
-        collections::HashMap,
+        fs::File,
+        io::{BufReader, BufWriter, Read, Write},
         ops::Bound,
+        path::Path,
         sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
     },
 };
 
 const MERKLE_TREE: MerkleTree = MerkleTree::new_default();
 
+/// Bytes that a dump file starts with, followed by the format version.
+const DUMP_MAGIC: &[u8] = b"grug-memdb";
+
+const DUMP_FORMAT_VERSION: u8 = 1;
+
+/// Content of a dump file. The data are encoded in Borsh, following the magic
+/// bytes and the format version.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct Dump {
+    /// The root hash at the latest version, used to check the integrity of
+    /// the data when loading.
+    root_hash: Option<Hash>,
+    state_commitment: Vec<Record>,
+    state_storage: VersionedMap<Vec<u8>, Vec<u8>>,
+}
+
 struct ChangeSet {
     version: u64,
     state_commitment: Batch,
@@ -25,9 +45,12 @@ struct MemDbInner {
     latest_version: Option<u64>,
     /// A key-value store backing the Merkle tree.
     ///
-    /// A HashMap is chosen over BTreeMap because our Merkle tree implementation
-    /// does not need to iterate raw keys in this store.
-    state_commitment: HashMap<Vec<u8>, Vec<u8>>,
+    /// This needs to be ordered, because reverting the Merkle tree requires
+    /// iterating nodes by version.
+    ///
+    /// Both this and the state storage are persistent data structures, which
+    /// share their data with forks of the DB (see `MemDb::fork`).
+    state_commitment: PersistentStorage,
     /// A versioned key-value storage: key => (version => value)
     state_storage: VersionedMap<Vec<u8>, Vec<u8>>,
     /// Uncommitted changes
@@ -43,13 +66,144 @@ impl MemDb {
         Self {
             inner: Arc::new(RwLock::new(MemDbInner {
                 latest_version: None,
-                state_commitment: HashMap::new(),
+                state_commitment: PersistentStorage::new(),
                 state_storage: VersionedMap::new(),
                 changeset: None,
             })),
         }
     }
 
+    /// Create an independent copy of the DB, which shares its history up to
+    /// this point with the original.
+    ///
+    /// Unlike `clone`, which returns another handle to the same DB, data
+    /// written to the fork are not visible to the original, and vice versa.
+    /// Forking is cheap regardless of the amount of data: the two DBs share
+    /// the same data structures, and writing to either only copies the parts
+    /// that are written to.
+    ///
+    /// Uncommitted changes are not carried over to the fork.
+    pub fn fork(&self) -> Self {
+        self.with_read(|inner| Self {
+            inner: Arc::new(RwLock::new(MemDbInner {
+                latest_version: inner.latest_version,
+                state_commitment: inner.state_commitment.clone(),
+                state_storage: inner.state_storage.clone(),
+                changeset: None,
+            })),
+        })
+    }
+
+    /// Revert the DB to an earlier version, discarding all versions after it.
+    /// Return the root hash at that version.
+    ///
+    /// Forks of the DB are not affected.
+    pub fn revert_to(&self, version: u64) -> DbResult<Option<Hash>> {
+        self.with_write(|mut inner| {
+            if inner.changeset.is_some() {
+                return Err(DbError::RevertWithChangeSet);
+            }
+
+            match inner.latest_version {
+                Some(latest_version) if version <= latest_version => (),
+                latest_version => {
+                    return Err(DbError::RevertVersionNewer {
+                        latest_version,
+                        version,
+                    });
+                },
+            }
+
+            MERKLE_TREE.rollback(&mut inner.state_commitment, version)?;
+            inner.state_storage.truncate(version);
+            inner.latest_version = Some(version);
+
+            Ok(MERKLE_TREE.root_hash(&inner.state_commitment, version)?)
+        })
+    }
+
+    /// Delete Merkle tree nodes that are no longer part of the tree as of the
+    /// given version. Afterwards, proofs can't be generated for versions older
+    /// than it.
+    ///
+    /// Forks of the DB are not affected.
+    pub fn prune(&self, up_to_version: u64) -> DbResult<()> {
+        self.with_write(|mut inner| {
+            Ok(MERKLE_TREE.prune(&mut inner.state_commitment, Some(up_to_version))?)
+        })
+    }
+
+    /// Write the committed state of the DB, including all versions of it, to a
+    /// file, which can be loaded by `MemDb::load`.
+    ///
+    /// This is intended for test fixtures, e.g. a chain state that takes a
+    /// long time to set up from genesis.
+    pub fn dump(&self, path: impl AsRef<Path>) -> DbResult<()> {
+        let dump = self.with_read(|inner| -> DbResult<_> {
+            let version = inner.latest_version.unwrap_or(0);
+            Ok(Dump {
+                root_hash: MERKLE_TREE.root_hash(&inner.state_commitment, version)?,
+                state_commitment: inner
+                    .state_commitment
+                    .scan(None, None, Order::Ascending)
+                    .collect(),
+                state_storage: inner.state_storage.clone(),
+            })
+        })?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(DUMP_MAGIC)?;
+        writer.write_all(&[DUMP_FORMAT_VERSION])?;
+        borsh::to_writer(&mut writer, &dump)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Load a DB from a file created by `MemDb::dump`.
+    ///
+    /// Error if the root hash computed from the loaded data doesn't match the
+    /// one recorded in the file.
+    pub fn load(path: impl AsRef<Path>) -> DbResult<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut header = [0; DUMP_MAGIC.len() + 1];
+        reader.read_exact(&mut header)?;
+        if &header[..DUMP_MAGIC.len()] != DUMP_MAGIC {
+            return Err(DbError::NotDumpFile);
+        }
+        if header[DUMP_MAGIC.len()] != DUMP_FORMAT_VERSION {
+            return Err(DbError::UnsupportedDumpFormat {
+                version: header[DUMP_MAGIC.len()],
+            });
+        }
+
+        let dump: Dump = borsh::from_reader(&mut reader)?;
+
+        let state_commitment = dump
+            .state_commitment
+            .into_iter()
+            .collect::<PersistentStorage>();
+
+        let latest_version = dump.state_storage.latest_version;
+        let root_hash = MERKLE_TREE.root_hash(&state_commitment, latest_version.unwrap_or(0))?;
+        if root_hash != dump.root_hash {
+            return Err(DbError::DumpRootHashMismatch {
+                expected: dump.root_hash,
+                found: root_hash,
+            });
+        }
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(MemDbInner {
+                latest_version,
+                state_commitment,
+                state_storage: dump.state_storage,
+                changeset: None,
+            })),
+        })
+    }
+
     fn with_read<C, T>(&self, callback: C) -> T
     where
         C: FnOnce(RwLockReadGuard<MemDbInner>) -> T,
@@ -86,9 +240,12 @@ impl Clone for MemDb {
 }
 
 impl Db for MemDb {
+    type BatchProof = BatchProof;
     type Error = DbError;
     type Proof = Proof;
 
+    const ICS23_PROOF_TYPE: &'static str = ICS23_PROOF_TYPE;
+
     fn state_commitment(&self) -> impl Storage + Clone + 'static {
         StateCommitment { db: self.clone() }
     }
@@ -114,6 +271,18 @@ impl Db for MemDb {
         Ok(MERKLE_TREE.prove(&self.state_commitment(), &hash(key), version)?)
     }
 
+    fn prove_ics23(&self, key: &[u8], version: Option<u64>) -> DbResult<Vec<u8>> {
+        let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
+        let proof = MERKLE_TREE.prove_ics23(&self.state_commitment(), &hash(key), version)?;
+        Ok(proof.encode_to_vec())
+    }
+
+    fn prove_many(&self, keys: &[&[u8]], version: Option<u64>) -> DbResult<BatchProof> {
+        let version = version.unwrap_or_else(|| self.latest_version().unwrap_or(0));
+        let key_hashes = keys.iter().map(hash).collect::<Vec<_>>();
+        Ok(MERKLE_TREE.prove_batch(&self.state_commitment(), &key_hashes, version)?)
+    }
+
     // Note on implementing this function: We must make sure that we don't
     // attempt to lock the DB (either read or write) inside the `with_write`
     // callback. Doing so will result in error:
@@ -160,13 +329,7 @@ impl Db for MemDb {
             inner.latest_version = Some(changeset.version);
 
             // write changes to state commitment
-            for (key, op) in changeset.state_commitment {
-                if let Op::Insert(value) = op {
-                    inner.state_commitment.insert(key, value);
-                } else {
-                    inner.state_commitment.remove(&key);
-                }
-            }
+            inner.state_commitment.flush(changeset.state_commitment);
 
             // write changes to state storage
             inner.state_storage.write_batch(changeset.state_storage);
@@ -175,7 +338,33 @@ impl Db for MemDb {
         })
     }
 
-    // TODO: add a more performant implementation of `flush_and_commit`
+    // Unlike `flush_but_not_commit`, which applies the batch to the Merkle
+    // tree through a cache that then needs to be copied into the DB, here we
+    // apply it directly to the state commitment, under a single write lock.
+    fn flush_and_commit(&self, batch: Batch) -> DbResult<(u64, Option<Hash>)> {
+        self.with_write(|mut inner| {
+            if inner.changeset.is_some() {
+                return Err(DbError::ChangeSetAlreadySet);
+            }
+
+            let (old_version, new_version) = match inner.latest_version {
+                Some(v) => (v, v + 1),
+                None => (0, 0),
+            };
+
+            let root_hash = MERKLE_TREE.apply_raw(
+                &mut inner.state_commitment,
+                old_version,
+                new_version,
+                &batch,
+            )?;
+
+            inner.state_storage.write_batch(batch);
+            inner.latest_version = Some(new_version);
+
+            Ok((new_version, root_hash))
+        })
+    }
 }
 
 // ----------------------------- state commitment ------------------------------
@@ -187,8 +376,7 @@ pub struct StateCommitment {
 
 impl Storage for StateCommitment {
     fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
-        self.db
-            .with_read(|inner| inner.state_commitment.get(key).cloned())
+        self.db.with_read(|inner| inner.state_commitment.read(key))
     }
 
     fn scan<'a>(
@@ -207,6 +395,10 @@ impl Storage for StateCommitment {
     fn remove(&mut self, _key: &[u8]) {
         unreachable!("write function called on read-only storage");
     }
+
+    fn remove_range(&mut self, _min: Option<&[u8]>, _max: Option<&[u8]>) {
+        unreachable!("write function called on read-only storage");
+    }
 }
 
 // ------------------------------- state storage -------------------------------
@@ -254,4 +446,131 @@ impl Storage for StateStorage {
     fn remove(&mut self, _key: &[u8]) {
         unreachable!("write function called on read-only storage");
     }
+
+    fn remove_range(&mut self, _min: Option<&[u8]>, _max: Option<&[u8]>) {
+        unreachable!("write function called on read-only storage");
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {super::*, grug_types::Op};
+
+    fn insert(key: &str, value: &str) -> (Vec<u8>, Op<Vec<u8>>) {
+        (
+            key.as_bytes().to_vec(),
+            Op::Insert(value.as_bytes().to_vec()),
+        )
+    }
+
+    fn delete(key: &str) -> (Vec<u8>, Op<Vec<u8>>) {
+        (key.as_bytes().to_vec(), Op::Delete)
+    }
+
+    fn read(db: &MemDb, version: Option<u64>, key: &str) -> Option<String> {
+        db.state_storage(version)
+            .read(key.as_bytes())
+            .map(|value| String::from_utf8(value).unwrap())
+    }
+
+    #[test]
+    fn forking_and_reverting() {
+        let batch0 = Batch::from([insert("donald", "trump"), insert("jake", "shepherd")]);
+        let batch1 = Batch::from([insert("donald", "duck"), insert("joe", "biden")]);
+        let batch2 = Batch::from([insert("larry", "engineer")]);
+        let batch2_fork = Batch::from([delete("jake"), insert("pumpkin", "cat")]);
+
+        let db = MemDb::new();
+        db.flush_and_commit(batch0.clone()).unwrap();
+        let (_, root_hash1) = db.flush_and_commit(batch1.clone()).unwrap();
+
+        // the two DBs share history up to the fork point, but diverge after it
+        let fork = db.fork();
+        let (_, root_hash2) = db.flush_and_commit(batch2.clone()).unwrap();
+        let (_, root_hash2_fork) = fork.flush_and_commit(batch2_fork.clone()).unwrap();
+        assert_ne!(root_hash2, root_hash2_fork);
+
+        assert_eq!(read(&db, None, "larry"), Some("engineer".into()));
+        assert_eq!(read(&db, None, "pumpkin"), None);
+        assert_eq!(read(&db, None, "jake"), Some("shepherd".into()));
+        assert_eq!(read(&fork, None, "larry"), None);
+        assert_eq!(read(&fork, None, "pumpkin"), Some("cat".into()));
+        assert_eq!(read(&fork, None, "jake"), None);
+
+        for db in [&db, &fork] {
+            assert_eq!(db.latest_version(), Some(2));
+            assert_eq!(db.root_hash(Some(1)).unwrap(), root_hash1);
+            assert_eq!(read(db, Some(1), "donald"), Some("duck".into()));
+        }
+
+        // the fork is identical to a DB that's gone through the same history.
+        // also check that `flush_and_commit` agrees with flushing and
+        // committing separately.
+        let other = MemDb::new();
+        for batch in [batch0, batch1, batch2_fork] {
+            other.flush_but_not_commit(batch).unwrap();
+            other.commit().unwrap();
+        }
+        assert_eq!(other.root_hash(None).unwrap(), root_hash2_fork);
+
+        // revert the original DB. the fork isn't affected.
+        assert_eq!(db.revert_to(1).unwrap(), root_hash1);
+        assert_eq!(db.latest_version(), Some(1));
+        assert_eq!(read(&db, None, "larry"), None);
+        assert_eq!(read(&fork, None, "pumpkin"), Some("cat".into()));
+        assert_eq!(fork.root_hash(None).unwrap(), root_hash2_fork);
+
+        // applying the same batch again gives the same root hash
+        assert_eq!(db.flush_and_commit(batch2).unwrap(), (2, root_hash2));
+
+        // can't revert to a version newer than the latest
+        assert!(matches!(
+            db.revert_to(3),
+            Err(DbError::RevertVersionNewer {
+                latest_version: Some(2),
+                version: 3,
+            })
+        ));
+    }
+
+    #[test]
+    fn dumping_and_loading() {
+        let db = MemDb::new();
+        db.flush_and_commit(Batch::from([
+            insert("donald", "trump"),
+            insert("jake", "shepherd"),
+        ]))
+        .unwrap();
+        let (_, root_hash) = db
+            .flush_and_commit(Batch::from([insert("donald", "duck"), delete("jake")]))
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.bin");
+        db.dump(&path).unwrap();
+
+        // the loaded DB has the same root hash and the same history
+        let loaded = MemDb::load(&path).unwrap();
+        assert_eq!(loaded.latest_version(), Some(1));
+        assert_eq!(loaded.root_hash(None).unwrap(), root_hash);
+        assert_eq!(read(&loaded, Some(0), "donald"), Some("trump".into()));
+        assert_eq!(read(&loaded, Some(0), "jake"), Some("shepherd".into()));
+        assert_eq!(read(&loaded, None, "donald"), Some("duck".into()));
+        assert_eq!(read(&loaded, None, "jake"), None);
+
+        // the loaded DB can be written to as usual
+        let batch = Batch::from([insert("pumpkin", "cat")]);
+        assert_eq!(
+            loaded.flush_and_commit(batch.clone()).unwrap(),
+            db.flush_and_commit(batch).unwrap()
+        );
+
+        // a file that isn't a dump is rejected
+        std::fs::write(&path, b"hello").unwrap();
+        assert!(matches!(MemDb::load(&path), Err(DbError::Io(_))));
+        std::fs::write(&path, b"hello world!").unwrap();
+        assert!(matches!(MemDb::load(&path), Err(DbError::NotDumpFile)));
+    }
 }
@@ -1,15 +1,43 @@
-use {grug_app::AppError, grug_types::StdError, thiserror::Error};
+use {
+    grug_app::AppError,
+    grug_types::{Hash, StdError},
+    thiserror::Error,
+};
 
 #[derive(Debug, Error)]
 pub enum DbError {
     #[error(transparent)]
     Std(#[from] StdError),
 
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     #[error("cannot flush when changeset is already set")]
     ChangeSetAlreadySet,
 
     #[error("cannot commit when changeset is not yet set")]
     ChangeSetNotSet,
+
+    #[error("cannot revert when changeset is set")]
+    RevertWithChangeSet,
+
+    #[error("cannot revert to version {version} which is newer than the latest version {latest_version:?}")]
+    RevertVersionNewer {
+        latest_version: Option<u64>,
+        version: u64,
+    },
+
+    #[error("not a MemDb dump file")]
+    NotDumpFile,
+
+    #[error("unsupported dump file format version {version}")]
+    UnsupportedDumpFormat { version: u8 },
+
+    #[error("root hash mismatch in dump file! expected: {expected:?}, found: {found:?}")]
+    DumpRootHashMismatch {
+        expected: Option<Hash>,
+        found: Option<Hash>,
+    },
 }
 
 impl From<DbError> for AppError {
@@ -1,5 +1,6 @@
 mod db;
 mod error;
+mod persistent_storage;
 mod versioned_map;
 
-pub use {db::*, error::*, versioned_map::*};
+pub use {db::*, error::*, persistent_storage::*, versioned_map::*};
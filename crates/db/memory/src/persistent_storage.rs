@@ -0,0 +1,174 @@
+use {
+    grug_types::{Order, Record, Storage},
+    im::OrdMap,
+    std::{iter, ops::Bound},
+};
+
+/// An in-memory KV store backed by a persistent (in the functional programming
+/// sense) map.
+///
+/// Cloning it is cheap, regardless of the amount of data: the clones share the
+/// same underlying tree, and when one of them is written to, only the nodes on
+/// the path to the written key are copied.
+#[derive(Default, Clone)]
+pub struct PersistentStorage {
+    data: OrdMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PersistentStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FromIterator<Record> for PersistentStorage {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        Self {
+            data: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Storage for PersistentStorage {
+    fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    fn scan<'a>(
+        &'a self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        // same as with `MockStorage`, return an empty iterator instead of
+        // panicking if min > max
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Box::new(iter::empty());
+            }
+        }
+
+        let min = min.map_or(Bound::Unbounded, Bound::Included);
+        let max = max.map_or(Bound::Unbounded, Bound::Excluded);
+        let iter = self
+            .data
+            .range::<_, [u8]>((min, max))
+            .map(|(k, v)| (k.clone(), v.clone()));
+
+        if order == Order::Ascending {
+            Box::new(iter)
+        } else {
+            Box::new(iter.rev())
+        }
+    }
+
+    fn write(&mut self, key: &[u8], value: &[u8]) {
+        self.data.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.data.remove(key);
+    }
+
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return;
+            }
+        }
+
+        // split the map into three parts: below, within, and above the range;
+        // then put the parts below and above back together. splitting takes
+        // logarithmic time, without visiting the records in the range.
+        let (below, within) = match min {
+            Some(min) => {
+                let (below, found, mut within) = self.data.split_lookup(min);
+                if let Some(value) = found {
+                    within.insert(min.to_vec(), value);
+                }
+                (below, within)
+            },
+            None => (OrdMap::new(), self.data.clone()),
+        };
+        let above = match max {
+            Some(max) => {
+                let (_, found, mut above) = within.split_lookup(max);
+                if let Some(value) = found {
+                    above.insert(max.to_vec(), value);
+                }
+                above
+            },
+            None => OrdMap::new(),
+        };
+        self.data = below.union(above);
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_are_independent() {
+        let mut storage = PersistentStorage::new();
+        for i in 0..100_u8 {
+            storage.write(&[i], &[i]);
+        }
+
+        let mut clone = storage.clone();
+        clone.write(&[1], &[0]);
+        clone.remove(&[2]);
+        clone.remove_range(Some(&[10]), Some(&[20]));
+
+        assert_eq!(storage.read(&[1]), Some(vec![1]));
+        assert_eq!(storage.read(&[2]), Some(vec![2]));
+        assert_eq!(storage.scan(None, None, Order::Ascending).count(), 100);
+
+        assert_eq!(clone.read(&[1]), Some(vec![0]));
+        assert_eq!(clone.read(&[2]), None);
+        assert!(clone
+            .scan(Some(&[9]), Some(&[21]), Order::Descending)
+            .eq([(vec![20], vec![20]), (vec![9], vec![9])]));
+
+        // min > max gives an empty iterator
+        assert_eq!(
+            clone.scan(Some(&[5]), Some(&[4]), Order::Ascending).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn removing_ranges() {
+        let mut storage = (0..10_u8)
+            .map(|i| (vec![i], vec![i]))
+            .collect::<PersistentStorage>();
+        let keys = |storage: &PersistentStorage| {
+            storage
+                .scan(None, None, Order::Ascending)
+                .map(|(key, _)| key[0])
+                .collect::<Vec<_>>()
+        };
+
+        // min is inclusive, max is exclusive
+        storage.remove_range(Some(&[2]), Some(&[4]));
+        assert_eq!(keys(&storage), [0, 1, 4, 5, 6, 7, 8, 9]);
+
+        // bounds don't need to be existing keys
+        storage.remove_range(Some(&[3]), Some(&[5, 0]));
+        assert_eq!(keys(&storage), [0, 1, 6, 7, 8, 9]);
+
+        // min > max removes nothing
+        storage.remove_range(Some(&[8]), Some(&[7]));
+        assert_eq!(keys(&storage), [0, 1, 6, 7, 8, 9]);
+
+        // unbounded on either side
+        storage.remove_range(None, Some(&[1]));
+        storage.remove_range(Some(&[9]), None);
+        assert_eq!(keys(&storage), [1, 6, 7, 8]);
+    }
+}
@@ -1,27 +1,35 @@
 use {
+    borsh::{BorshDeserialize, BorshSerialize},
     grug_types::Op,
+    im::OrdMap,
     std::{
         borrow::Borrow,
         collections::BTreeMap,
+        io::{self, Read, Write},
         marker::PhantomData,
         ops::{Bound, RangeBounds},
     },
 };
 
+/// A versioned key-value map: key => (version => op).
+///
+/// The outer map is persistent (in the functional programming sense), so that
+/// cloning the map is cheap, and the clones only copy the parts they write to.
+#[derive(Clone)]
 pub struct VersionedMap<K, V> {
     // initialized to None
     // set to 0 the first time a batch is written
     // incremented by 1 each following batch write
     pub latest_version: Option<u64>,
     // key => (version => op)
-    nested_map: BTreeMap<K, BTreeMap<u64, Op<V>>>,
+    nested_map: OrdMap<K, BTreeMap<u64, Op<V>>>,
 }
 
 impl<K, V> VersionedMap<K, V> {
     pub fn new() -> Self {
         Self {
             latest_version: None,
-            nested_map: BTreeMap::new(),
+            nested_map: OrdMap::new(),
         }
     }
 }
@@ -32,9 +40,41 @@ impl<K, V> Default for VersionedMap<K, V> {
     }
 }
 
+// the nested map is encoded the same way Borsh encodes a `BTreeMap`: the number
+// of entries, followed by the entries in ascending order of keys.
+impl<K, V> BorshSerialize for VersionedMap<K, V>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.latest_version.serialize(writer)?;
+        self.nested_map.iter().collect::<Vec<_>>().serialize(writer)
+    }
+}
+
+impl<K, V> BorshDeserialize for VersionedMap<K, V>
+where
+    K: BorshDeserialize + Ord + Clone,
+    V: BorshDeserialize + Clone,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let latest_version = BorshDeserialize::deserialize_reader(reader)?;
+        let nested_map = Vec::<(K, BTreeMap<u64, Op<V>>)>::deserialize_reader(reader)?
+            .into_iter()
+            .collect();
+
+        Ok(Self {
+            latest_version,
+            nested_map,
+        })
+    }
+}
+
 impl<K, V> VersionedMap<K, V>
 where
-    K: Ord,
+    K: Ord + Clone,
+    V: Clone,
 {
     pub fn write_batch<B>(&mut self, batch: B)
     where
@@ -56,6 +96,34 @@ where
         }
     }
 
+    /// Discard all versions after the given one.
+    pub fn truncate(&mut self, version: u64) {
+        // only touch the keys written to after the version, so that the rest
+        // of the map isn't copied if it's shared with a clone
+        let keys = self
+            .nested_map
+            .iter()
+            .filter(|(_, inner_map)| {
+                inner_map
+                    .last_key_value()
+                    .is_some_and(|(last_version, _)| *last_version > version)
+            })
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in keys {
+            let inner_map = self.nested_map.get_mut(&key).unwrap();
+            inner_map.split_off(&(version + 1));
+            if inner_map.is_empty() {
+                self.nested_map.remove(&key);
+            }
+        }
+
+        if let Some(latest_version) = self.latest_version.as_mut() {
+            *latest_version = (*latest_version).min(version);
+        }
+    }
+
     pub fn get<T>(&self, key: &T, version: u64) -> Option<&V>
     where
         T: Ord + ?Sized,
@@ -94,7 +162,7 @@ where
 }
 
 pub struct VersionedIterator<'a, K, V, R, T: ?Sized> {
-    nested_map: &'a BTreeMap<K, BTreeMap<u64, Op<V>>>,
+    nested_map: &'a OrdMap<K, BTreeMap<u64, Op<V>>>,
     range: R,
     version: u64,
     last_visited_key: Option<&'a K>,
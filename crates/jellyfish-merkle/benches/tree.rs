@@ -0,0 +1,188 @@
+use {
+    criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput},
+    grug_jmt::{MerkleTree, NodeCache},
+    grug_types::{hash, Batch, MockStorage, Op},
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    std::num::NonZeroUsize,
+};
+
+const TREE: MerkleTree = MerkleTree::new_default();
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+// number of keys to prove in each iteration of the proving benchmark
+const NUM_PROOFS: usize = 1_000;
+
+// number of consecutive versions applied in each iteration of the multi-version
+// benchmark, and the number of keys changed in each version
+const NUM_VERSIONS: u64 = 10;
+const VERSION_SIZE: usize = 1_000;
+
+// memory taken by the node cache, same as the default of the disk DB
+const NODE_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+fn random_batch(rng: &mut StdRng, size: usize) -> Batch {
+    (0..size)
+        .map(|_| {
+            let key = rng.gen::<[u8; 32]>().to_vec();
+            let value = rng.gen::<[u8; 32]>().to_vec();
+            (key, Op::Insert(value))
+        })
+        .collect()
+}
+
+// a batch that updates random existing keys, and inserts as many new ones
+fn update_batch(rng: &mut StdRng, keys: &[Vec<u8>]) -> Batch {
+    let mut batch = random_batch(rng, VERSION_SIZE / 2);
+    for _ in 0..VERSION_SIZE / 2 {
+        let key = keys[rng.gen_range(0..keys.len())].clone();
+        let value = rng.gen::<[u8; 32]>().to_vec();
+        batch.insert(key, Op::Insert(value));
+    }
+    batch
+}
+
+// build a tree of the given number of keys at version 1, and return the keys
+fn build_tree(rng: &mut StdRng, size: usize) -> (MockStorage, Vec<Vec<u8>>) {
+    let batch = random_batch(rng, size);
+    let mut storage = MockStorage::new();
+    TREE.apply_raw(&mut storage, 0, 1, &batch).unwrap();
+    let keys = batch.into_keys().collect();
+    (storage, keys)
+}
+
+// apply the batches on top of version 1, one version each
+fn apply_versions(tree: &MerkleTree, storage: &mut MockStorage, batches: &[Batch]) {
+    for (version, batch) in (1..).zip(batches) {
+        tree.apply_raw(storage, version, version + 1, batch)
+            .unwrap();
+    }
+}
+
+fn applying(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let mut rng = StdRng::seed_from_u64(size as u64);
+        let batch = random_batch(&mut rng, size);
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &batch, |b, batch| {
+            b.iter_batched(
+                MockStorage::new,
+                |mut storage| TREE.apply_raw(&mut storage, 0, 1, batch).unwrap(),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+fn applying_versions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_versions");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let mut rng = StdRng::seed_from_u64(size as u64);
+        let (storage, keys) = build_tree(&mut rng, size);
+        let batches = (0..NUM_VERSIONS)
+            .map(|_| update_batch(&mut rng, &keys))
+            .collect::<Vec<_>>();
+
+        group.throughput(Throughput::Elements(NUM_VERSIONS * VERSION_SIZE as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &batches, |b, batches| {
+            b.iter_batched(
+                || storage.clone(),
+                |mut storage| apply_versions(&TREE, &mut storage, batches),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+fn proving(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prove");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let mut rng = StdRng::seed_from_u64(size as u64);
+        let (storage, keys) = build_tree(&mut rng, size);
+        let key_hashes = (0..NUM_PROOFS)
+            .map(|_| hash(&keys[rng.gen_range(0..size)]))
+            .collect::<Vec<_>>();
+
+        group.throughput(Throughput::Elements(NUM_PROOFS as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &key_hashes,
+            |b, key_hashes| {
+                b.iter(|| {
+                    for key_hash in key_hashes {
+                        TREE.prove(&storage, key_hash, 1).unwrap();
+                    }
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// same as the multi-version and proving benchmarks, but reading nodes through a
+// cache, which is warmed up by the earlier iterations
+fn with_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("with_cache");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let mut rng = StdRng::seed_from_u64(size as u64);
+        let (storage, keys) = build_tree(&mut rng, size);
+        let batches = (0..NUM_VERSIONS)
+            .map(|_| update_batch(&mut rng, &keys))
+            .collect::<Vec<_>>();
+        let key_hashes = (0..NUM_PROOFS)
+            .map(|_| hash(&keys[rng.gen_range(0..size)]))
+            .collect::<Vec<_>>();
+
+        // each iteration applies the same batches to a fresh copy of the same
+        // tree, which writes the same nodes, so the cache stays valid across
+        // iterations
+        let cache = NodeCache::new(NonZeroUsize::new(NODE_CACHE_SIZE).unwrap());
+        let tree = TREE.with_cache(&cache);
+
+        group.throughput(Throughput::Elements(NUM_VERSIONS * VERSION_SIZE as u64));
+        group.bench_with_input(
+            BenchmarkId::new("apply_versions", size),
+            &batches,
+            |b, batches| {
+                b.iter_batched(
+                    || storage.clone(),
+                    |mut storage| apply_versions(&tree, &mut storage, batches),
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        group.throughput(Throughput::Elements(NUM_PROOFS as u64));
+        group.bench_with_input(
+            BenchmarkId::new("prove", size),
+            &key_hashes,
+            |b, key_hashes| {
+                b.iter(|| {
+                    for key_hash in key_hashes {
+                        tree.prove(&storage, key_hash, 1).unwrap();
+                    }
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, applying, applying_versions, proving, with_cache);
+criterion_main!(benches);
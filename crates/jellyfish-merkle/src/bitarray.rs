@@ -4,7 +4,7 @@ use {
     std::fmt,
 };
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct BitArray {
     pub num_bits: usize,
     /// We opt for the stack-allocated `[u8; N]` over heap-allocated `Vec<u8>`.
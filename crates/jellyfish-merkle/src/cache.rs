@@ -0,0 +1,77 @@
+use {
+    crate::{BitArray, Node},
+    lru::LruCache,
+    std::{
+        mem::size_of,
+        num::NonZeroUsize,
+        sync::{Mutex, MutexGuard},
+    },
+};
+
+/// Approximate memory taken by each node in the cache. Neither the key nor the
+/// node allocate on the heap, so this is the same for all nodes: the key and
+/// the node themselves, plus the LRU cache's bookkeeping, which is a hash table
+/// entry and the two pointers of a linked list, each pointer-sized.
+const ENTRY_SIZE: usize = size_of::<(u64, BitArray)>() + size_of::<Node>() + 4 * size_of::<usize>();
+
+/// An in-memory cache of tree nodes, evicting the least recently used ones
+/// once full.
+///
+/// Nodes are keyed by version and bit path, and a node is never modified once
+/// written, so a single cache can be shared across versions. Nodes are put
+/// into the cache as they're read, and as they're committed (see
+/// `MerkleTree::cache_nodes`), but not as they're written, since the writes
+/// may not end up being committed.
+pub struct NodeCache {
+    inner: Mutex<LruCache<(u64, BitArray), Node>>,
+}
+
+impl NodeCache {
+    /// Create an empty cache that takes up to approximately `size` bytes of
+    /// memory. It holds at least one node regardless.
+    pub fn new(size: NonZeroUsize) -> Self {
+        let capacity = NonZeroUsize::new(size.get() / ENTRY_SIZE).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            inner: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Return the maximum number of nodes the cache can hold.
+    pub fn capacity(&self) -> usize {
+        self.lock().cap().get()
+    }
+
+    /// Return the number of nodes in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Return whether the cache holds no node.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Remove all nodes from the cache. This must be called if nodes are
+    /// deleted or overwritten other than through `MerkleTree`.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub(crate) fn get(&self, version: u64, bits: &BitArray) -> Option<Node> {
+        self.lock().get(&(version, bits.clone())).cloned()
+    }
+
+    pub(crate) fn put(&self, version: u64, bits: &BitArray, node: Node) {
+        self.lock().put((version, bits.clone()), node);
+    }
+
+    pub(crate) fn pop(&self, version: u64, bits: &BitArray) {
+        self.lock().pop(&(version, bits.clone()));
+    }
+
+    // a panic while holding the lock can't leave the cache in an inconsistent
+    // state, so we simply ignore poisoning.
+    fn lock(&self) -> MutexGuard<LruCache<(u64, BitArray), Node>> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
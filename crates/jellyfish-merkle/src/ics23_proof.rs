@@ -0,0 +1,215 @@
+use {
+    crate::{
+        node::{INTERNAL_NODE_HASH_PREFIX, LEAF_NODE_HASH_PERFIX},
+        BitArray, LeafNode, MembershipProof,
+    },
+    grug_types::{Hash, Order},
+    ics23::{ExistenceProof, HashOp, InnerOp, InnerSpec, LeafOp, LengthOp, ProofSpec},
+};
+
+/// Type of the proof op under which ICS-23 proofs of this tree are returned,
+/// analogous to `ics23:iavl` used by the Cosmos SDK.
+pub const ICS23_PROOF_TYPE: &str = "ics23:jmt";
+
+/// The [ICS-23](https://github.com/cosmos/ics23) proof spec describing the
+/// Jellyfish Merkle tree.
+///
+/// Our tree doesn't store the raw keys, so a non-membership proof can't contain
+/// the raw keys of the neighboring leaves. Therefore, the spec treats the key
+/// hash and value hash as the key and value. That is, to verify a proof,
+/// verifiers need to hash the raw key and value with SHA-256 first.
+pub fn ics23_spec() -> ProofSpec {
+    ProofSpec {
+        leaf_spec: Some(ics23_leaf_op()),
+        inner_spec: Some(InnerSpec {
+            // left child goes first, then the right child
+            child_order: vec![0, 1],
+            child_size: Hash::LENGTH as i32,
+            min_prefix_length: INTERNAL_NODE_HASH_PREFIX.len() as i32,
+            max_prefix_length: INTERNAL_NODE_HASH_PREFIX.len() as i32,
+            empty_child: Hash::ZERO.to_vec(),
+            hash: HashOp::Sha256.into(),
+        }),
+        max_depth: BitArray::MAX_BIT_LENGTH as i32,
+        min_depth: 0,
+        prehash_key_before_comparison: false,
+    }
+}
+
+/// Convert a membership proof into an ICS-23 existence proof.
+///
+/// Our membership proof doesn't include the value hash, so it needs to be
+/// provided here.
+pub fn ics23_existence_proof(
+    key_hash: &Hash,
+    value_hash: &Hash,
+    proof: &MembershipProof,
+) -> ExistenceProof {
+    let bitarray = BitArray::from_bytes(key_hash);
+    let num_siblings = proof.sibling_hashes.len();
+
+    // both our sibling hashes and ICS-23 inner ops go from bottom up.
+    // if the node on the path is a left child, its sibling goes after it (the
+    // suffix); otherwise, the sibling goes before it (the prefix).
+    let path = bitarray
+        .range(None, Some(num_siblings), Order::Descending)
+        .zip(&proof.sibling_hashes)
+        .map(|(bit, sibling_hash)| {
+            let sibling_hash = sibling_hash.as_ref().unwrap_or(&Hash::ZERO);
+            if bit == 0 {
+                InnerOp {
+                    hash: HashOp::Sha256.into(),
+                    prefix: INTERNAL_NODE_HASH_PREFIX.to_vec(),
+                    suffix: sibling_hash.to_vec(),
+                }
+            } else {
+                InnerOp {
+                    hash: HashOp::Sha256.into(),
+                    prefix: [INTERNAL_NODE_HASH_PREFIX, sibling_hash].concat(),
+                    suffix: vec![],
+                }
+            }
+        })
+        .collect();
+
+    ExistenceProof {
+        key: key_hash.to_vec(),
+        value: value_hash.to_vec(),
+        leaf: Some(ics23_leaf_op()),
+        path,
+    }
+}
+
+/// Convert a membership proof of a leaf node into an ICS-23 existence proof.
+pub(crate) fn leaf_existence_proof(leaf: &LeafNode, proof: &MembershipProof) -> ExistenceProof {
+    ics23_existence_proof(&leaf.key_hash, &leaf.value_hash, proof)
+}
+
+fn ics23_leaf_op() -> LeafOp {
+    LeafOp {
+        hash: HashOp::Sha256.into(),
+        prehash_key: HashOp::NoHash.into(),
+        prehash_value: HashOp::NoHash.into(),
+        length: LengthOp::NoPrefix.into(),
+        prefix: LEAF_NODE_HASH_PERFIX.to_vec(),
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::MerkleTree,
+        grug_types::{hash, Batch, MockStorage, Op, StdResult},
+        ics23::{verify_membership, verify_non_membership, HostFunctionsManager},
+        prost::Message,
+        test_case::test_case,
+    };
+
+    const TREE: MerkleTree = MerkleTree::new_default();
+
+    fn build_tree() -> StdResult<(MockStorage, Hash)> {
+        let mut storage = MockStorage::new();
+        let batch = ["a", "b", "c", "d", "e", "f", "g", "h"]
+            .into_iter()
+            .map(|key| {
+                (
+                    key.as_bytes().to_vec(),
+                    Op::Insert(key.repeat(2).into_bytes()),
+                )
+            })
+            .collect::<Batch>();
+        let root_hash = TREE.apply_raw(&mut storage, 0, 1, &batch)?.unwrap();
+        Ok((storage, root_hash))
+    }
+
+    #[test_case("a", "aa"; "a")]
+    #[test_case("d", "dd"; "d")]
+    #[test_case("h", "hh"; "h")]
+    fn proving_membership(key: &str, value: &str) {
+        let (storage, root_hash) = build_tree().unwrap();
+        let key_hash = hash(key);
+        let value_hash = hash(value);
+
+        // round-trip the proof through protobuf, as a light client would
+        let proof = TREE.prove_ics23(&storage, &key_hash, 1).unwrap();
+        let proof = ics23::CommitmentProof::decode(proof.encode_to_vec().as_slice()).unwrap();
+
+        let spec = ics23_spec();
+        let root = root_hash.to_vec();
+        assert!(verify_membership::<HostFunctionsManager>(
+            &proof,
+            &spec,
+            &root,
+            &key_hash,
+            &value_hash
+        ));
+        assert!(!verify_membership::<HostFunctionsManager>(
+            &proof,
+            &spec,
+            &root,
+            &key_hash,
+            &hash("wrong")
+        ));
+        assert!(!verify_non_membership::<HostFunctionsManager>(
+            &proof, &spec, &root, &key_hash
+        ));
+    }
+
+    // pick keys such that we cover the cases where the path ends at a leaf
+    // and at an empty child, as well as keys left or right of all leaves.
+    #[test_case("i"; "i")]
+    #[test_case("j"; "j")]
+    #[test_case("k"; "k")]
+    #[test_case("l"; "l")]
+    #[test_case("m"; "m")]
+    #[test_case("n"; "n")]
+    #[test_case("o"; "o")]
+    #[test_case("p"; "p")]
+    fn proving_non_membership(key: &str) {
+        let (storage, root_hash) = build_tree().unwrap();
+        let key_hash = hash(key);
+
+        let proof = TREE.prove_ics23(&storage, &key_hash, 1).unwrap();
+        let proof = ics23::CommitmentProof::decode(proof.encode_to_vec().as_slice()).unwrap();
+
+        let spec = ics23_spec();
+        let root = root_hash.to_vec();
+        assert!(verify_non_membership::<HostFunctionsManager>(
+            &proof, &spec, &root, &key_hash
+        ));
+        assert!(!verify_non_membership::<HostFunctionsManager>(
+            &proof,
+            &spec,
+            &root,
+            &hash("a")
+        ));
+    }
+
+    #[test]
+    fn converting_membership_proof() {
+        let (storage, root_hash) = build_tree().unwrap();
+        let key_hash = hash("b");
+        let value_hash = hash("bb");
+
+        let proof = match TREE.prove(&storage, &key_hash, 1).unwrap() {
+            crate::Proof::Membership(proof) => proof,
+            _ => panic!("expecting membership proof"),
+        };
+        let proof = ics23::CommitmentProof {
+            proof: Some(ics23::commitment_proof::Proof::Exist(
+                ics23_existence_proof(&key_hash, &value_hash, &proof),
+            )),
+        };
+
+        assert!(verify_membership::<HostFunctionsManager>(
+            &proof,
+            &ics23_spec(),
+            &root_hash.to_vec(),
+            &key_hash,
+            &value_hash
+        ));
+    }
+}
@@ -1,14 +1,19 @@
 mod bitarray;
+mod cache;
+mod ics23_proof;
 mod node;
 mod proof;
 mod tree;
 
 pub use crate::{
     bitarray::{BitArray, BitIterator},
+    cache::NodeCache,
+    ics23_proof::{ics23_existence_proof, ics23_spec, ICS23_PROOF_TYPE},
     node::{hash_internal_node, hash_leaf_node, Child, InternalNode, LeafNode, Node},
     proof::{
-        verify_membership_proof, verify_non_membership_proof, verify_proof, MembershipProof,
-        NonMembershipProof, Proof, ProofError, ProofNode,
+        verify_batch_proof, verify_membership_proof, verify_non_membership_proof, verify_proof,
+        BatchProof, BatchProofNode, MembershipProof, NonMembershipProof, Proof, ProofError,
+        ProofNode,
     },
     tree::{MerkleTree, DEFAULT_NODE_NAMESPACE, DEFAULT_ORPHAN_NAMESPACE},
 };
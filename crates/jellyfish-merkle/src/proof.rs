@@ -24,6 +24,22 @@ pub enum ProofError {
     // TODO: add more details to the error message?
     #[error("expecting bitarrays to share a common prefix but they do not")]
     NotCommonPrefix,
+
+    #[error("value hash mismatch for key hash {key_hash}! expect: {expect}, actual: {actual}")]
+    ValueHashMismatch {
+        key_hash: Hash,
+        expect: Hash,
+        actual: Hash,
+    },
+
+    #[error("key hash {key_hash} is not covered by the batch proof")]
+    NotInBatchProof { key_hash: Hash },
+
+    #[error("batch proof is deeper than the length of key hashes")]
+    BatchProofTooDeep,
+
+    #[error("batch proof doesn't contain any node")]
+    EmptyBatchProof,
 }
 
 #[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
@@ -58,6 +74,35 @@ pub enum ProofNode {
     },
 }
 
+/// A proof of the membership or non-membership of multiple keys.
+///
+/// Instead of a list of sibling hashes for each key, it consists of the part
+/// of the tree that contains the paths from the root to each of the keys, such
+/// that nodes shared by multiple paths are only included once. Subtrees off
+/// these paths are represented only by their hashes.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BatchProof {
+    pub root: BatchProofNode,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum BatchProofNode {
+    /// A subtree that isn't on the path to any of the keys being proven.
+    Pruned {
+        hash: Hash,
+    },
+    /// A child that doesn't exist.
+    Empty,
+    Internal {
+        left: Box<BatchProofNode>,
+        right: Box<BatchProofNode>,
+    },
+    Leaf {
+        key_hash: Hash,
+        value_hash: Hash,
+    },
+}
+
 pub fn verify_proof(
     root_hash: &Hash,
     key_hash: &Hash,
@@ -140,6 +185,136 @@ pub fn verify_non_membership_proof(
     compute_and_compare_root_hash(root_hash, &bitarray, &proof.sibling_hashes, hash)
 }
 
+/// Verify a batch proof, given key hashes and their respective value hashes.
+/// A value hash of `None` means the key is to be proven to not exist.
+pub fn verify_batch_proof(
+    root_hash: &Hash,
+    items: &[(Hash, Option<Hash>)],
+    proof: &BatchProof,
+) -> Result<(), ProofError> {
+    for (key_hash, value_hash) in items {
+        verify_batch_proof_item(key_hash, value_hash.as_ref(), &proof.root)?;
+    }
+
+    let Some(hash) = hash_batch_proof_node(&proof.root, 0)? else {
+        return Err(ProofError::EmptyBatchProof);
+    };
+
+    if hash != root_hash {
+        return Err(ProofError::RootHashMismatch {
+            computed: hash,
+            actual: root_hash.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+fn verify_batch_proof_item(
+    key_hash: &Hash,
+    value_hash: Option<&Hash>,
+    mut node: &BatchProofNode,
+) -> Result<(), ProofError> {
+    let bitarray = BitArray::from_bytes(key_hash);
+    let mut iter = bitarray.range(None, None, Order::Ascending);
+    let mut depth = 0;
+
+    // walk down the tree following the bits of the key hash, until reaching a
+    // node that isn't an internal node
+    while let BatchProofNode::Internal { left, right } = node {
+        node = match iter.next() {
+            Some(0) => left,
+            Some(_) => right,
+            None => return Err(ProofError::BatchProofTooDeep),
+        };
+        depth += 1;
+    }
+
+    match (node, value_hash) {
+        (
+            BatchProofNode::Leaf {
+                key_hash: leaf_key_hash,
+                value_hash: leaf_value_hash,
+            },
+            Some(value_hash),
+        ) if leaf_key_hash == key_hash => {
+            if leaf_value_hash != value_hash {
+                return Err(ProofError::ValueHashMismatch {
+                    key_hash: key_hash.clone(),
+                    expect: value_hash.clone(),
+                    actual: leaf_value_hash.clone(),
+                });
+            }
+        },
+        (
+            BatchProofNode::Leaf {
+                key_hash: leaf_key_hash,
+                ..
+            },
+            None,
+        ) if leaf_key_hash == key_hash => {
+            return Err(ProofError::IncorrectProofType {
+                expect: "non-membership",
+                actual: "membership",
+            });
+        },
+        // same as with non-membership proofs, if the node reached is a leaf of
+        // another key, the two keys must share a common prefix.
+        (
+            BatchProofNode::Leaf {
+                key_hash: leaf_key_hash,
+                ..
+            },
+            None,
+        ) => {
+            let leaf_bitarray = BitArray::from_bytes(leaf_key_hash);
+            let bits = bitarray.range(None, Some(depth), Order::Ascending);
+            let leaf_bits = leaf_bitarray.range(None, Some(depth), Order::Ascending);
+            if bits.zip(leaf_bits).any(|(a, b)| a != b) {
+                return Err(ProofError::NotCommonPrefix);
+            }
+        },
+        (BatchProofNode::Empty, None) => (),
+        (BatchProofNode::Leaf { .. } | BatchProofNode::Empty, Some(_)) => {
+            return Err(ProofError::IncorrectProofType {
+                expect: "membership",
+                actual: "non-membership",
+            });
+        },
+        (BatchProofNode::Pruned { .. }, _) => {
+            return Err(ProofError::NotInBatchProof {
+                key_hash: key_hash.clone(),
+            });
+        },
+        (BatchProofNode::Internal { .. }, _) => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn hash_batch_proof_node(node: &BatchProofNode, depth: usize) -> Result<Option<Hash>, ProofError> {
+    if depth > BitArray::MAX_BIT_LENGTH {
+        return Err(ProofError::BatchProofTooDeep);
+    }
+
+    match node {
+        BatchProofNode::Pruned { hash } => Ok(Some(hash.clone())),
+        BatchProofNode::Empty => Ok(None),
+        BatchProofNode::Internal { left, right } => {
+            let left_hash = hash_batch_proof_node(left, depth + 1)?;
+            let right_hash = hash_batch_proof_node(right, depth + 1)?;
+            Ok(Some(hash_internal_node(
+                left_hash.as_ref(),
+                right_hash.as_ref(),
+            )))
+        },
+        BatchProofNode::Leaf {
+            key_hash,
+            value_hash,
+        } => Ok(Some(hash_leaf_node(key_hash, value_hash))),
+    }
+}
+
 fn compute_and_compare_root_hash(
     root_hash: &Hash,
     bitarray: &BitArray,
@@ -297,5 +472,84 @@ mod tests {
         assert!(verify_non_membership_proof(&HASH_ROOT, &hash(key.as_bytes()), &proof,).is_ok());
     }
 
+    #[test]
+    fn verifying_batch() {
+        // the batch proof of r, m, b, and o, same as in tree.rs
+        let mut proof = BatchProof {
+            root: BatchProofNode::Internal {
+                left: Box::new(BatchProofNode::Internal {
+                    left: Box::new(BatchProofNode::Empty),
+                    right: Box::new(BatchProofNode::Internal {
+                        left: Box::new(BatchProofNode::Leaf {
+                            key_hash: hash(b"r"),
+                            value_hash: hash(b"foo"),
+                        }),
+                        right: Box::new(BatchProofNode::Internal {
+                            left: Box::new(BatchProofNode::Leaf {
+                                key_hash: HASH_M,
+                                value_hash: HASH_BAR,
+                            }),
+                            right: Box::new(BatchProofNode::Pruned { hash: HASH_0111 }),
+                        }),
+                    }),
+                }),
+                right: Box::new(BatchProofNode::Pruned { hash: HASH_1 }),
+            },
+        };
+
+        let item = |key: &str, value: Option<&str>| {
+            (
+                hash(key.as_bytes()),
+                value.map(|value| hash(value.as_bytes())),
+            )
+        };
+
+        assert!(verify_batch_proof(
+            &HASH_ROOT,
+            &[
+                item("r", Some("foo")),
+                item("m", Some("bar")),
+                item("b", None),
+                item("o", None),
+            ],
+            &proof,
+        )
+        .is_ok());
+
+        // wrong value
+        assert!(matches!(
+            verify_batch_proof(&HASH_ROOT, &[item("r", Some("bar"))], &proof),
+            Err(ProofError::ValueHashMismatch { .. })
+        ));
+
+        // proving non-membership of a key that exists
+        assert!(matches!(
+            verify_batch_proof(&HASH_ROOT, &[item("m", None)], &proof),
+            Err(ProofError::IncorrectProofType { .. })
+        ));
+
+        // proving membership of a key that doesn't exist
+        assert!(matches!(
+            verify_batch_proof(&HASH_ROOT, &[item("b", Some("foo"))], &proof),
+            Err(ProofError::IncorrectProofType { .. })
+        ));
+
+        // a key that isn't covered by the proof (it's under node 1)
+        assert!(matches!(
+            verify_batch_proof(&HASH_ROOT, &[item("a", Some("buzz"))], &proof),
+            Err(ProofError::NotInBatchProof { .. })
+        ));
+
+        // tampering with the proof changes the root hash
+        let BatchProofNode::Internal { right, .. } = &mut proof.root else {
+            unreachable!();
+        };
+        **right = BatchProofNode::Pruned { hash: HASH_0 };
+        assert!(matches!(
+            verify_batch_proof(&HASH_ROOT, &[item("r", Some("foo"))], &proof),
+            Err(ProofError::RootHashMismatch { .. })
+        ));
+    }
+
     // TODO: add fail cases for proofs
 }
@@ -1,10 +1,13 @@
 use {
     crate::{
-        BitArray, Child, InternalNode, LeafNode, MembershipProof, Node, NonMembershipProof, Proof,
+        ics23_proof::leaf_existence_proof, BatchProof, BatchProofNode, BitArray, Child,
+        InternalNode, LeafNode, MembershipProof, Node, NodeCache, NonMembershipProof, Proof,
         ProofNode,
     },
-    grug_storage::{Map, Set},
+    grug_storage::{Bound, Map, Set},
     grug_types::{hash, Batch, Hash, Op, Order, StdResult, Storage},
+    ics23::{commitment_proof, CommitmentProof, ExistenceProof, NonExistenceProof},
+    std::cmp::Ordering,
 };
 
 // default storage namespaces
@@ -14,6 +17,11 @@ pub const DEFAULT_ORPHAN_NAMESPACE: &str = "o";
 /// The bit path of the root node, which is just empty
 pub const ROOT_BITS: &BitArray = &BitArray::new_empty();
 
+/// When applying a batch, the two children of an internal node are worked on
+/// in parallel if each of them has at least this many ops to apply. Below this
+/// the overhead of spawning a task outweighs the gain.
+const PARALLEL_THRESHOLD: usize = 128;
+
 /// Describes what happens after applying ops (a slice of `HashedPair`) at a
 /// node and its subtree.
 #[derive(Debug)]
@@ -27,6 +35,36 @@ enum Outcome {
     Deleted,
 }
 
+/// Nodes to be saved, and nodes to be marked as orphaned, as the result of
+/// applying a batch. They are collected first, and written to the storage in
+/// the end, so that subtrees can be worked on in parallel with only read
+/// access to the storage.
+#[derive(Default)]
+struct Changes {
+    nodes: Vec<(BitArray, Node)>,
+    orphans: Vec<(u64, BitArray)>,
+}
+
+impl Changes {
+    fn append(&mut self, mut other: Changes) {
+        self.nodes.append(&mut other.nodes);
+        self.orphans.append(&mut other.orphans);
+    }
+}
+
+/// Describes the result of looking for a key in the tree.
+#[derive(Debug)]
+enum Search {
+    /// The key exists. Return its leaf node.
+    Found(LeafNode),
+    /// The key doesn't exist. Return the leaf nodes immediately left and right
+    /// of where it would be, if any.
+    NotFound {
+        left: Option<LeafNode>,
+        right: Option<LeafNode>,
+    },
+}
+
 /// Jellyfish Merkle tree (JMT).
 ///
 /// Adapted from Diem's work:
@@ -43,6 +81,7 @@ enum Outcome {
 pub struct MerkleTree<'a> {
     nodes: Map<'a, (u64, &'a BitArray), Node>,
     orphans: Set<'a, (u64, u64, &'a BitArray)>,
+    cache: Option<&'a NodeCache>,
 }
 
 impl<'a> Default for MerkleTree<'a> {
@@ -57,6 +96,7 @@ impl<'a> MerkleTree<'a> {
         Self {
             nodes: Map::new(node_namespace),
             orphans: Set::new(orphan_namespace),
+            cache: None,
         }
     }
 
@@ -67,16 +107,39 @@ impl<'a> MerkleTree<'a> {
         Self::new(DEFAULT_NODE_NAMESPACE, DEFAULT_ORPHAN_NAMESPACE)
     }
 
+    /// Read nodes through the given cache.
+    ///
+    /// The cache must only be used with one storage, and the nodes in that
+    /// storage must only be modified through Merkle trees using the cache.
+    /// Nodes written by `apply` must be put into the cache with `cache_nodes`
+    /// once they have been committed.
+    pub const fn with_cache(self, cache: &'a NodeCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
     /// Get the root hash at the given version. Use latest version if unspecified.
     ///
     /// If the root node is not found at the version, return None. There are two
     /// possible reasons that it's not found: either no data has ever been
     /// written to the tree yet, or the version is old and has been pruned.
     pub fn root_hash(&self, storage: &dyn Storage, version: u64) -> StdResult<Option<Hash>> {
-        let root_node = self.nodes.may_load(storage, (version, ROOT_BITS))?;
+        let root_node = self.may_load_node(storage, version, ROOT_BITS)?;
         Ok(root_node.map(|node| node.hash()))
     }
 
+    /// Get the newest version of which the tree has any node. Return None if
+    /// the tree has no node at all.
+    pub fn latest_version(&self, storage: &dyn Storage) -> StdResult<Option<u64>> {
+        self.nodes
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .transpose()
+            .map(|maybe_key| maybe_key.map(|(version, _)| version))
+    }
+
     /// Apply a batch of ops to the tree. Return the new root hash.
     ///
     /// If the tree isn't changed, the version isn't incremented.
@@ -131,76 +194,115 @@ impl<'a> MerkleTree<'a> {
             "version is not incremental"
         );
 
+        let mut changes = Changes::default();
+
         // if an old root node exists (i.e. tree isn't empty at the old version),
         // mark it as orphaned
-        if self.nodes.has(storage, (old_version, ROOT_BITS)) {
-            self.mark_node_as_orphaned(storage, new_version, old_version, ROOT_BITS)?;
+        let old_root_node = self.may_load_node(storage, old_version, ROOT_BITS)?;
+        if old_root_node.is_some() {
+            changes.orphans.push((old_version, ROOT_BITS.clone()));
         }
 
         // recursively apply the ops, starting at the old root
-        match self.apply_at(storage, new_version, old_version, ROOT_BITS, batch)? {
+        let outcome = self.apply_at(
+            storage,
+            new_version,
+            old_version,
+            ROOT_BITS,
+            batch,
+            &mut changes,
+        )?;
+
+        let root_hash = match outcome {
             // if the new tree is non-empty (i.e. it has a root node), save this
             // new root node and return its hash
             Outcome::Updated(new_root_node) | Outcome::Unchanged(Some(new_root_node)) => {
-                self.save_node(storage, new_version, ROOT_BITS, &new_root_node)?;
-                Ok(Some(new_root_node.hash()))
+                let root_hash = new_root_node.hash();
+                changes.nodes.push((ROOT_BITS.clone(), new_root_node));
+                Some(root_hash)
             },
             // the new tree is empty. do nothing and just return None.
-            Outcome::Deleted | Outcome::Unchanged(None) => Ok(None),
+            Outcome::Deleted | Outcome::Unchanged(None) => None,
+        };
+
+        for (bits, node) in changes.nodes {
+            self.save_node(storage, new_version, &bits, node)?;
         }
+
+        for (version, bits) in changes.orphans {
+            self.mark_node_as_orphaned(storage, new_version, version, &bits)?;
+        }
+
+        Ok(root_hash)
     }
 
     fn apply_at(
         &self,
-        storage: &mut dyn Storage,
+        storage: &dyn Storage,
         new_version: u64,
         old_version: u64,
         bits: &BitArray,
         batch: Vec<(Hash, Op<Hash>)>,
+        changes: &mut Changes,
     ) -> StdResult<Outcome> {
-        match self.nodes.may_load(storage, (old_version, bits))? {
+        match self.may_load_node(storage, old_version, bits)? {
             Some(Node::Leaf(leaf_node)) => {
-                self.apply_at_leaf(storage, new_version, bits, leaf_node, batch)
+                self.apply_at_leaf(new_version, bits, leaf_node, batch, changes)
             },
             Some(Node::Internal(internal_node)) => {
-                self.apply_at_internal(storage, new_version, bits, internal_node, batch)
+                self.apply_at_internal(storage, new_version, bits, internal_node, batch, changes)
             },
             None => {
                 let (batch, op) = prepare_batch_for_subtree(batch, None);
                 debug_assert!(op.is_none());
-                self.create_subtree(storage, new_version, bits, batch, None)
+                self.create_subtree(new_version, bits, batch, None, changes)
             },
         }
     }
 
     fn apply_at_internal(
         &self,
-        storage: &mut dyn Storage,
+        storage: &dyn Storage,
         new_version: u64,
         bits: &BitArray,
         mut internal_node: InternalNode,
         batch: Vec<(Hash, Op<Hash>)>,
+        changes: &mut Changes,
     ) -> StdResult<Outcome> {
         // split the batch into two, one for left child, one for right
         let (batch_for_left, batch_for_right) = partition_batch(batch, bits);
+        let parallel = batch_for_left.len().min(batch_for_right.len()) >= PARALLEL_THRESHOLD;
 
         // apply at the two children, respectively
-        let left_outcome = self.apply_at_child(
-            storage,
-            new_version,
-            bits,
-            true,
-            internal_node.left_child.as_ref(),
-            batch_for_left,
-        )?;
-        let right_outcome = self.apply_at_child(
-            storage,
-            new_version,
-            bits,
-            false,
-            internal_node.right_child.as_ref(),
-            batch_for_right,
-        )?;
+        let left_child = internal_node.left_child.as_ref();
+        let right_child = internal_node.right_child.as_ref();
+        let (left_outcome, right_outcome) = join(
+            parallel,
+            changes,
+            |changes| {
+                self.apply_at_child(
+                    storage,
+                    new_version,
+                    bits,
+                    true,
+                    left_child,
+                    batch_for_left,
+                    changes,
+                )
+            },
+            |changes| {
+                self.apply_at_child(
+                    storage,
+                    new_version,
+                    bits,
+                    false,
+                    right_child,
+                    batch_for_right,
+                    changes,
+                )
+            },
+        );
+        let (left_outcome, right_outcome) = (left_outcome?, right_outcome?);
 
         match (left_outcome, right_outcome) {
             // neither children is changed. this node is unchanged as well
@@ -250,36 +352,46 @@ impl<'a> MerkleTree<'a> {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[inline]
     fn apply_at_child(
         &self,
-        storage: &mut dyn Storage,
+        storage: &dyn Storage,
         new_version: u64,
         parent_bits: &BitArray,
         is_left: bool,
         child: Option<&Child>,
         batch: Vec<(Hash, Op<Hash>)>,
+        changes: &mut Changes,
     ) -> StdResult<Outcome> {
         let child_bits = parent_bits.extend_one_bit(is_left);
         match (batch.is_empty(), child) {
             // child exists, but there is no op to apply
             (true, Some(child)) => {
-                let child_node = self.nodes.load(storage, (child.version, &child_bits))?;
+                let child_node = self.load_node(storage, child.version, &child_bits)?;
                 Ok(Outcome::Unchanged(Some(child_node)))
             },
             // child doesn't exist, and there is no op to apply
             (true, None) => Ok(Outcome::Unchanged(None)),
             // child exists, and there are ops to apply
             (false, Some(child)) => {
-                let outcome =
-                    self.apply_at(storage, new_version, child.version, &child_bits, batch)?;
+                let outcome = self.apply_at(
+                    storage,
+                    new_version,
+                    child.version,
+                    &child_bits,
+                    batch,
+                    changes,
+                )?;
                 // if the child has been updated, save the updated node
                 if let Outcome::Updated(new_child_node) = &outcome {
-                    self.save_node(storage, new_version, &child_bits, new_child_node)?;
+                    changes
+                        .nodes
+                        .push((child_bits.clone(), new_child_node.clone()));
                 }
                 // if the child has been deleted or updated, mark it as orphaned
                 if let Outcome::Deleted | Outcome::Updated(_) = &outcome {
-                    self.mark_node_as_orphaned(storage, new_version, child.version, &child_bits)?;
+                    changes.orphans.push((child.version, child_bits));
                 }
                 Ok(outcome)
             },
@@ -287,18 +399,18 @@ impl<'a> MerkleTree<'a> {
             (false, None) => {
                 let (batch, op) = prepare_batch_for_subtree(batch, None);
                 debug_assert!(op.is_none());
-                self.create_subtree(storage, new_version, &child_bits, batch, None)
+                self.create_subtree(new_version, &child_bits, batch, None, changes)
             },
         }
     }
 
     fn apply_at_leaf(
         &self,
-        storage: &mut dyn Storage,
         new_version: u64,
         bits: &BitArray,
         mut leaf_node: LeafNode,
         batch: Vec<(Hash, Op<Hash>)>,
+        changes: &mut Changes,
     ) -> StdResult<Outcome> {
         let (batch, op) = prepare_batch_for_subtree(batch, Some(&leaf_node));
         match (batch.is_empty(), op) {
@@ -315,24 +427,24 @@ impl<'a> MerkleTree<'a> {
             (true, None) => Ok(Outcome::Unchanged(Some(Node::Leaf(leaf_node)))),
             (false, Some(Op::Insert(value_hash))) => {
                 leaf_node.value_hash = value_hash;
-                self.create_subtree(storage, new_version, bits, batch, Some(leaf_node))
+                self.create_subtree(new_version, bits, batch, Some(leaf_node), changes)
             },
             (false, Some(Op::Delete)) => {
-                self.create_subtree(storage, new_version, bits, batch, None)
+                self.create_subtree(new_version, bits, batch, None, changes)
             },
             (false, None) => {
-                self.create_subtree(storage, new_version, bits, batch, Some(leaf_node))
+                self.create_subtree(new_version, bits, batch, Some(leaf_node), changes)
             },
         }
     }
 
     fn create_subtree(
         &self,
-        storage: &mut dyn Storage,
         version: u64,
         bits: &BitArray,
         batch: Vec<(Hash, Hash)>,
         existing_leaf: Option<LeafNode>,
+        changes: &mut Changes,
     ) -> StdResult<Outcome> {
         let new_node = match (batch.len(), existing_leaf) {
             (0, None) => {
@@ -349,28 +461,38 @@ impl<'a> MerkleTree<'a> {
             (_, existing_leaf) => {
                 let (batch_for_left, batch_for_right) = partition_batch(batch, bits);
                 let (leaf_for_left, leaf_for_right) = partition_leaf(existing_leaf, bits);
-                let left_outcome = self.create_subtree(
-                    storage,
-                    version,
-                    &bits.extend_one_bit(true),
-                    batch_for_left,
-                    leaf_for_left,
-                )?;
-                let right_outcome = self.create_subtree(
-                    storage,
-                    version,
-                    &bits.extend_one_bit(false),
-                    batch_for_right,
-                    leaf_for_right,
-                )?;
+                let parallel =
+                    batch_for_left.len().min(batch_for_right.len()) >= PARALLEL_THRESHOLD;
+                let (left_outcome, right_outcome) = join(
+                    parallel,
+                    changes,
+                    |changes| {
+                        self.create_subtree(
+                            version,
+                            &bits.extend_one_bit(true),
+                            batch_for_left,
+                            leaf_for_left,
+                            changes,
+                        )
+                    },
+                    |changes| {
+                        self.create_subtree(
+                            version,
+                            &bits.extend_one_bit(false),
+                            batch_for_right,
+                            leaf_for_right,
+                            changes,
+                        )
+                    },
+                );
                 Node::Internal(InternalNode {
-                    left_child: into_child(version, left_outcome),
-                    right_child: into_child(version, right_outcome),
+                    left_child: into_child(version, left_outcome?),
+                    right_child: into_child(version, right_outcome?),
                 })
             },
         };
 
-        self.save_node(storage, version, bits, &new_node)?;
+        changes.nodes.push((bits.clone(), new_node.clone()));
 
         Ok(Outcome::Updated(new_node))
     }
@@ -402,7 +524,7 @@ impl<'a> MerkleTree<'a> {
         let mut bits = ROOT_BITS.clone();
         let bitarray = BitArray::from_bytes(key_hash);
         let mut iter = bitarray.range(None, None, Order::Ascending);
-        let mut node = self.nodes.load(storage, (version, &bits))?;
+        let mut node = self.load_node(storage, version, &bits)?;
         let mut proof_node = None;
         let mut sibling_hashes = vec![];
 
@@ -431,12 +553,12 @@ impl<'a> MerkleTree<'a> {
                         (Some(0), Some(child), sibling) => {
                             sibling_hashes.push(hash_of(sibling));
                             bits.push(0);
-                            node = self.nodes.load(storage, (child.version, &bits))?;
+                            node = self.load_node(storage, child.version, &bits)?;
                         },
                         (Some(1), sibling, Some(child)) => {
                             sibling_hashes.push(hash_of(sibling));
                             bits.push(1);
-                            node = self.nodes.load(storage, (child.version, &bits))?;
+                            node = self.load_node(storage, child.version, &bits)?;
                         },
                         (Some(0), None, sibling) => {
                             proof_node = Some(ProofNode::Internal {
@@ -480,11 +602,380 @@ impl<'a> MerkleTree<'a> {
         }
     }
 
+    /// Generate an [ICS-23](https://github.com/cosmos/ics23) proof of the
+    /// membership or non-membership of a key, to be verified against the spec
+    /// given by `ics23_spec`.
+    ///
+    /// Unlike our own non-membership proof, an ICS-23 non-existence proof
+    /// consists of existence proofs of the key's left and right neighbors, so
+    /// we need to find them in the tree first.
+    pub fn prove_ics23(
+        &self,
+        storage: &dyn Storage,
+        key_hash: &Hash,
+        version: u64,
+    ) -> StdResult<CommitmentProof> {
+        let proof = match self.search(storage, key_hash, version)? {
+            Search::Found(leaf) => {
+                commitment_proof::Proof::Exist(self.prove_leaf(storage, &leaf, version)?)
+            },
+            Search::NotFound { left, right } => {
+                commitment_proof::Proof::Nonexist(NonExistenceProof {
+                    key: key_hash.to_vec(),
+                    left: left
+                        .map(|leaf| self.prove_leaf(storage, &leaf, version))
+                        .transpose()?,
+                    right: right
+                        .map(|leaf| self.prove_leaf(storage, &leaf, version))
+                        .transpose()?,
+                })
+            },
+        };
+
+        Ok(CommitmentProof { proof: Some(proof) })
+    }
+
+    fn prove_leaf(
+        &self,
+        storage: &dyn Storage,
+        leaf: &LeafNode,
+        version: u64,
+    ) -> StdResult<ExistenceProof> {
+        match self.prove(storage, &leaf.key_hash, version)? {
+            Proof::Membership(proof) => Ok(leaf_existence_proof(leaf, &proof)),
+            // the leaf was just found in the tree, so it must exist
+            Proof::NonMembership(_) => unreachable!("leaf not found: {}", leaf.key_hash),
+        }
+    }
+
+    /// Look for the leaf of the given key hash. If it doesn't exist, find the
+    /// leaves immediately left and right of it instead.
+    fn search(&self, storage: &dyn Storage, key_hash: &Hash, version: u64) -> StdResult<Search> {
+        let mut bits = ROOT_BITS.clone();
+        let bitarray = BitArray::from_bytes(key_hash);
+        let mut iter = bitarray.range(None, None, Order::Ascending);
+        let mut node = self.load_node(storage, version, &bits)?;
+        // the deepest subtrees branching off the path to the left and to the
+        // right. the neighbors are the rightmost and leftmost leaves in them.
+        let mut left_subtree = None;
+        let mut right_subtree = None;
+
+        loop {
+            match node {
+                // the path ends at the leaf of either the key itself or another
+                // key. in the latter case, the leaf is one of the neighbors, as
+                // it's closer to the key than the subtree on the same side.
+                Node::Leaf(leaf) => {
+                    return match leaf.key_hash.cmp(key_hash) {
+                        Ordering::Equal => Ok(Search::Found(leaf)),
+                        Ordering::Less => Ok(Search::NotFound {
+                            left: Some(leaf),
+                            right: self.edge_leaf(storage, right_subtree, true)?,
+                        }),
+                        Ordering::Greater => Ok(Search::NotFound {
+                            left: self.edge_leaf(storage, left_subtree, false)?,
+                            right: Some(leaf),
+                        }),
+                    };
+                },
+                Node::Internal(InternalNode {
+                    left_child,
+                    right_child,
+                }) => {
+                    let (bit, child, sibling) = match iter.next() {
+                        Some(0) => (0, left_child, right_child),
+                        Some(1) => (1, right_child, left_child),
+                        bit => unreachable!("unexpected next bit: {bit:?}"),
+                    };
+                    if let Some(sibling) = sibling {
+                        let sibling_bits = bits.extend_one_bit(bit == 1);
+                        if bit == 0 {
+                            right_subtree = Some((sibling_bits, sibling));
+                        } else {
+                            left_subtree = Some((sibling_bits, sibling));
+                        }
+                    }
+                    // the path ends at an empty child
+                    let Some(child) = child else {
+                        break;
+                    };
+                    bits.push(bit);
+                    node = self.load_node(storage, child.version, &bits)?;
+                },
+            }
+        }
+
+        Ok(Search::NotFound {
+            left: self.edge_leaf(storage, left_subtree, false)?,
+            right: self.edge_leaf(storage, right_subtree, true)?,
+        })
+    }
+
+    /// Find the leftmost or rightmost leaf in the given subtree.
+    fn edge_leaf(
+        &self,
+        storage: &dyn Storage,
+        subtree: Option<(BitArray, Child)>,
+        leftmost: bool,
+    ) -> StdResult<Option<LeafNode>> {
+        let Some((mut bits, child)) = subtree else {
+            return Ok(None);
+        };
+
+        let mut node = self.load_node(storage, child.version, &bits)?;
+
+        loop {
+            match node {
+                Node::Leaf(leaf) => return Ok(Some(leaf)),
+                Node::Internal(InternalNode {
+                    left_child,
+                    right_child,
+                }) => {
+                    let left_child = left_child.map(|child| (true, child));
+                    let right_child = right_child.map(|child| (false, child));
+                    let (is_left, child) = if leftmost {
+                        left_child.or(right_child)
+                    } else {
+                        right_child.or(left_child)
+                    }
+                    .expect("internal node has no children");
+                    bits = bits.extend_one_bit(is_left);
+                    node = self.load_node(storage, child.version, &bits)?;
+                },
+            }
+        }
+    }
+
+    /// Generate a proof of the membership or non-membership of multiple keys
+    /// at once. Nodes shared by the paths to the keys are only included once,
+    /// so this is much smaller than a proof for each key.
+    pub fn prove_batch(
+        &self,
+        storage: &dyn Storage,
+        key_hashes: &[Hash],
+        version: u64,
+    ) -> StdResult<BatchProof> {
+        // the key hashes need to be sorted, so that at each internal node, we
+        // can split them into those going left and right.
+        let mut key_hashes = key_hashes.to_vec();
+        key_hashes.sort();
+        key_hashes.dedup();
+
+        let root_node = self.load_node(storage, version, ROOT_BITS)?;
+        let root = self.prove_subtree(storage, ROOT_BITS.clone(), root_node, &key_hashes)?;
+
+        Ok(BatchProof { root })
+    }
+
+    fn prove_subtree(
+        &self,
+        storage: &dyn Storage,
+        bits: BitArray,
+        node: Node,
+        key_hashes: &[Hash],
+    ) -> StdResult<BatchProofNode> {
+        match node {
+            Node::Leaf(LeafNode {
+                key_hash,
+                value_hash,
+            }) => Ok(BatchProofNode::Leaf {
+                key_hash,
+                value_hash,
+            }),
+            Node::Internal(InternalNode {
+                left_child,
+                right_child,
+            }) => {
+                let partition_point = key_hashes
+                    .partition_point(|key_hash| bit_at_index(key_hash, bits.num_bits) == 0);
+                let (left_key_hashes, right_key_hashes) = key_hashes.split_at(partition_point);
+                let left = self.prove_child(
+                    storage,
+                    bits.extend_one_bit(true),
+                    left_child,
+                    left_key_hashes,
+                )?;
+                let right = self.prove_child(
+                    storage,
+                    bits.extend_one_bit(false),
+                    right_child,
+                    right_key_hashes,
+                )?;
+                Ok(BatchProofNode::Internal {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                })
+            },
+        }
+    }
+
+    fn prove_child(
+        &self,
+        storage: &dyn Storage,
+        bits: BitArray,
+        child: Option<Child>,
+        key_hashes: &[Hash],
+    ) -> StdResult<BatchProofNode> {
+        match child {
+            None => Ok(BatchProofNode::Empty),
+            // none of the keys is in this subtree, so we only need its hash
+            Some(child) if key_hashes.is_empty() => Ok(BatchProofNode::Pruned { hash: child.hash }),
+            Some(child) => {
+                let node = self.load_node(storage, child.version, &bits)?;
+                self.prove_subtree(storage, bits, node, key_hashes)
+            },
+        }
+    }
+
+    /// Collect the leaf nodes of the tree at the given version, in ascending
+    /// order by key hash. Return an empty vector if the tree is empty.
+    pub fn leaves(&self, storage: &dyn Storage, version: u64) -> StdResult<Vec<LeafNode>> {
+        let mut leaves = vec![];
+        if let Some(root_node) = self.may_load_node(storage, version, ROOT_BITS)? {
+            self.collect_leaves(storage, ROOT_BITS, root_node, &mut leaves)?;
+        }
+        Ok(leaves)
+    }
+
+    fn collect_leaves(
+        &self,
+        storage: &dyn Storage,
+        bits: &BitArray,
+        node: Node,
+        leaves: &mut Vec<LeafNode>,
+    ) -> StdResult<()> {
+        match node {
+            Node::Leaf(leaf_node) => leaves.push(leaf_node),
+            Node::Internal(InternalNode {
+                left_child,
+                right_child,
+            }) => {
+                // visit the left child first, so that leaves are in order
+                for (is_left, child) in [(true, left_child), (false, right_child)] {
+                    if let Some(child) = child {
+                        let child_bits = bits.extend_one_bit(is_left);
+                        let child_node = self.load_node(storage, child.version, &child_bits)?;
+                        self.collect_leaves(storage, &child_bits, child_node, leaves)?;
+                    }
+                }
+            },
+        }
+
+        Ok(())
+    }
+
     /// Delete nodes that are no longer part of the tree as of `up_to_version`.
     /// If no `up_to_version` is provided then delete all orphans.
-    pub fn prune(&self, _store: &mut dyn Storage, _up_to_version: Option<u64>) -> StdResult<()> {
-        // we should first implement a `range_remove` method on Storage trait
-        todo!()
+    pub fn prune(&self, storage: &mut dyn Storage, up_to_version: Option<u64>) -> StdResult<()> {
+        // orphans are keyed by the version since which they're orphaned first,
+        // so the ones to be pruned are all in a contiguous range. the root bits
+        // is the smallest bit array, so this bound excludes all orphans newer
+        // than `up_to_version`.
+        let max = || up_to_version.map(|version| Bound::Exclusive((version + 1, 0, ROOT_BITS)));
+        let orphans = self
+            .orphans
+            .range(storage, None, max(), Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (_, version, bits) in orphans {
+            self.nodes.remove(storage, (version, &bits));
+            if let Some(cache) = self.cache {
+                cache.pop(version, &bits);
+            }
+        }
+
+        self.orphans.clear(storage, None, max(), None);
+
+        Ok(())
+    }
+
+    /// Revert the tree to an earlier version, discarding all versions after it.
+    ///
+    /// Nodes created after `to_version` are deleted. Nodes that were orphaned
+    /// after `to_version` are unmarked, as they're once again part of the tree.
+    ///
+    /// Note that this only works if `to_version` hasn't been pruned.
+    pub fn rollback(&self, storage: &mut dyn Storage, to_version: u64) -> StdResult<()> {
+        // the root bits is the smallest bit array, so this bound includes all
+        // nodes of versions newer than `to_version`.
+        let min = Bound::Inclusive((to_version + 1, ROOT_BITS));
+        let nodes = self
+            .nodes
+            .keys(storage, Some(min), None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (version, bits) in nodes {
+            self.nodes.remove(storage, (version, &bits));
+            if let Some(cache) = self.cache {
+                cache.pop(version, &bits);
+            }
+        }
+
+        let min = Bound::Inclusive((to_version + 1, 0, ROOT_BITS));
+        self.orphans.clear(storage, Some(min), None, None);
+
+        Ok(())
+    }
+
+    /// Put the nodes written by a batch of changes to the storage, as produced
+    /// by `apply`, into the cache, and evict the nodes deleted by it. Do
+    /// nothing if there's no cache.
+    ///
+    /// Call this once the batch has been committed to the storage.
+    pub fn cache_nodes(&self, batch: &Batch) -> StdResult<()> {
+        let Some(cache) = self.cache else {
+            return Ok(());
+        };
+
+        let (min, max) = self.nodes.storage_bounds(None, None);
+        for (key, op) in batch.range(min..max) {
+            if let Op::Insert(value) = op {
+                let ((version, bits), node) = self.nodes.decode_record(key, value)?;
+                cache.put(version, &bits, node);
+            } else {
+                let (version, bits) = self.nodes.decode_key(key)?;
+                cache.pop(version, &bits);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_node(&self, storage: &dyn Storage, version: u64, bits: &BitArray) -> StdResult<Node> {
+        if let Some(cache) = self.cache {
+            if let Some(node) = cache.get(version, bits) {
+                return Ok(node);
+            }
+        }
+
+        let node = self.nodes.load(storage, (version, bits))?;
+
+        if let Some(cache) = self.cache {
+            cache.put(version, bits, node.clone());
+        }
+
+        Ok(node)
+    }
+
+    fn may_load_node(
+        &self,
+        storage: &dyn Storage,
+        version: u64,
+        bits: &BitArray,
+    ) -> StdResult<Option<Node>> {
+        if let Some(cache) = self.cache {
+            if let Some(node) = cache.get(version, bits) {
+                return Ok(Some(node));
+            }
+        }
+
+        let node = self.nodes.may_load(storage, (version, bits))?;
+
+        if let (Some(cache), Some(node)) = (self.cache, &node) {
+            cache.put(version, bits, node.clone());
+        }
+
+        Ok(node)
     }
 
     #[inline]
@@ -493,9 +984,11 @@ impl<'a> MerkleTree<'a> {
         storage: &mut dyn Storage,
         version: u64,
         bits: &BitArray,
-        node: &Node,
+        node: Node,
     ) -> StdResult<()> {
-        self.nodes.save(storage, (version, bits), node)
+        // the node isn't put into the cache here, because the write may not
+        // end up being committed. see `cache_nodes`.
+        self.nodes.save(storage, (version, bits), &node)
     }
 
     #[inline]
@@ -511,6 +1004,28 @@ impl<'a> MerkleTree<'a> {
     }
 }
 
+/// Run the two closures, each with its own changes, in parallel if needed.
+/// The changes are then merged into the given changes.
+fn join<A, B>(
+    parallel: bool,
+    changes: &mut Changes,
+    a: A,
+    b: B,
+) -> (StdResult<Outcome>, StdResult<Outcome>)
+where
+    A: FnOnce(&mut Changes) -> StdResult<Outcome> + Send,
+    B: FnOnce(&mut Changes) -> StdResult<Outcome> + Send,
+{
+    if !parallel {
+        return (a(changes), b(changes));
+    }
+
+    let mut changes_b = Changes::default();
+    let outcomes = rayon::join(|| a(changes), || b(&mut changes_b));
+    changes.append(changes_b);
+    outcomes
+}
+
 #[allow(clippy::type_complexity)]
 #[inline]
 fn partition_batch<T>(
@@ -666,7 +1181,10 @@ fn into_child(version: u64, outcome: Outcome) -> Option<Child> {
 
 #[cfg(test)]
 mod tests {
-    use {super::*, grug_types::MockStorage, hex_literal::hex, test_case::test_case};
+    use {
+        super::*, grug_types::MockStorage, hex_literal::hex, std::num::NonZeroUsize,
+        test_case::test_case,
+    };
 
     const TREE: MerkleTree = MerkleTree::new_default();
 
@@ -840,6 +1358,101 @@ mod tests {
         }
     }
 
+    // delete every node in version 2, then roll back to version 1. the tree
+    // should be exactly as it was before version 2 was applied.
+    #[test]
+    fn rolling_back() {
+        let (mut storage, _) = build_test_case().unwrap();
+
+        let batch = Batch::from([(b"r".to_vec(), Op::Delete), (b"m".to_vec(), Op::Delete)]);
+        let new_root_hash = TREE.apply_raw(&mut storage, 1, 2, &batch).unwrap();
+        assert_eq!(TREE.latest_version(&storage).unwrap(), Some(2));
+
+        TREE.rollback(&mut storage, 1).unwrap();
+        assert_eq!(TREE.latest_version(&storage).unwrap(), Some(1));
+
+        // the root at version 1 is intact, while that at version 2 is gone
+        assert_eq!(TREE.root_hash(&storage, 1).unwrap(), Some(HASH_ROOT));
+        assert_eq!(TREE.root_hash(&storage, 2).unwrap(), None);
+
+        // no node of version 2 remains, and no node is marked as orphaned
+        for item in TREE.nodes.keys(&storage, None, None, Order::Ascending) {
+            let (version, _) = item.unwrap();
+            assert_eq!(version, 1);
+        }
+        assert!(TREE.orphans.is_empty(&storage));
+
+        // applying the same batch again should give the same root hash
+        let root_hash = TREE.apply_raw(&mut storage, 1, 2, &batch).unwrap();
+        assert_eq!(root_hash, new_root_hash);
+    }
+
+    // delete some nodes in version 2, then prune up to version 2. the nodes
+    // orphaned in version 2 should be gone, while the tree at version 2 should
+    // be intact.
+    #[test]
+    fn pruning() {
+        let (mut storage, _) = build_test_case().unwrap();
+
+        let batch = Batch::from([(b"r".to_vec(), Op::Delete), (b"m".to_vec(), Op::Delete)]);
+        let new_root_hash = TREE.apply_raw(&mut storage, 1, 2, &batch).unwrap();
+
+        // pruning up to version 1 does nothing, since the nodes were orphaned
+        // in version 2
+        TREE.prune(&mut storage, Some(1)).unwrap();
+        assert_eq!(TREE.root_hash(&storage, 1).unwrap(), Some(HASH_ROOT));
+
+        let orphans = TREE
+            .orphans
+            .range(&storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert!(!orphans.is_empty());
+
+        TREE.prune(&mut storage, Some(2)).unwrap();
+        assert_eq!(TREE.root_hash(&storage, 1).unwrap(), None);
+        assert_eq!(TREE.root_hash(&storage, 2).unwrap(), new_root_hash);
+        assert!(TREE.orphans.is_empty(&storage));
+
+        for (_, version, bits) in orphans {
+            assert!(!TREE.nodes.has(&storage, (version, &bits)));
+        }
+
+        // the remaining keys can still be proven at version 2
+        for key in [b"L", b"a"] {
+            let proof = TREE.prove(&storage, &hash(key), 2).unwrap();
+            assert!(matches!(proof, Proof::Membership(_)));
+        }
+    }
+
+    #[test]
+    fn collecting_leaves() {
+        let (mut storage, _) = build_test_case().unwrap();
+
+        let batch = Batch::from([(b"m".to_vec(), Op::Delete)]);
+        TREE.apply_raw(&mut storage, 1, 2, &batch).unwrap();
+
+        // leaves are in the order of the key hashes: r (010), m (0110), L (0111), a (1)
+        let leaf = |key: &str, value: &str| LeafNode {
+            key_hash: hash(key),
+            value_hash: hash(value),
+        };
+        assert_eq!(TREE.leaves(&storage, 1).unwrap(), [
+            leaf("r", "foo"),
+            leaf("m", "bar"),
+            leaf("L", "fuzz"),
+            leaf("a", "buzz"),
+        ]);
+        assert_eq!(TREE.leaves(&storage, 2).unwrap(), [
+            leaf("r", "foo"),
+            leaf("L", "fuzz"),
+            leaf("a", "buzz"),
+        ]);
+
+        // there's no tree at version 3
+        assert!(TREE.leaves(&storage, 3).unwrap().is_empty());
+    }
+
     #[test_case(
         "r",
         Proof::Membership(MembershipProof {
@@ -916,4 +1529,131 @@ mod tests {
             proof
         );
     }
+
+    #[test]
+    fn proving_batch() {
+        let (storage, _) = build_test_case().unwrap();
+
+        // prove r, m (members), and b, o (non-members). the paths to r, m, and
+        // o share nodes 0 and 01, and those to m and o end at the same leaf.
+        // keys may be duplicated.
+        let key_hashes = ["r", "m", "b", "o", "m"].map(|key| hash(key.as_bytes()));
+        let proof = TREE.prove_batch(&storage, &key_hashes, 1).unwrap();
+        assert_eq!(proof, BatchProof {
+            root: BatchProofNode::Internal {
+                left: Box::new(BatchProofNode::Internal {
+                    left: Box::new(BatchProofNode::Empty),
+                    right: Box::new(BatchProofNode::Internal {
+                        left: Box::new(BatchProofNode::Leaf {
+                            key_hash: hash(b"r"),
+                            value_hash: hash(b"foo"),
+                        }),
+                        right: Box::new(BatchProofNode::Internal {
+                            left: Box::new(BatchProofNode::Leaf {
+                                key_hash: HASH_M,
+                                value_hash: HASH_BAR,
+                            }),
+                            right: Box::new(BatchProofNode::Pruned { hash: HASH_0111 }),
+                        }),
+                    }),
+                }),
+                right: Box::new(BatchProofNode::Pruned { hash: HASH_1 }),
+            },
+        });
+    }
+
+    // a batch big enough to be applied in parallel should give the same tree
+    // as applying it in chunks small enough to be applied sequentially. node
+    // hashes don't depend on versions, so the root hashes should match.
+    #[test]
+    fn applying_in_parallel() {
+        let batch = (0..PARALLEL_THRESHOLD * 16)
+            .map(|i| {
+                (
+                    i.to_be_bytes().to_vec(),
+                    Op::Insert(i.to_le_bytes().to_vec()),
+                )
+            })
+            .collect::<Batch>();
+
+        let mut storage = MockStorage::new();
+        let root_hash = TREE.apply_raw(&mut storage, 0, 1, &batch).unwrap();
+
+        let mut chunked_storage = MockStorage::new();
+        let mut chunked_root_hash = None;
+        let mut version = 0;
+        let items = batch.into_iter().collect::<Vec<_>>();
+        for chunk in items.chunks(PARALLEL_THRESHOLD) {
+            let chunk = chunk.iter().cloned().collect::<Batch>();
+            chunked_root_hash = TREE
+                .apply_raw(&mut chunked_storage, version, version + 1, &chunk)
+                .unwrap();
+            version += 1;
+        }
+
+        assert!(root_hash.is_some());
+        assert_eq!(root_hash, chunked_root_hash);
+        assert_eq!(
+            TREE.leaves(&storage, 1).unwrap(),
+            TREE.leaves(&chunked_storage, version).unwrap()
+        );
+    }
+
+    #[test]
+    fn caching_nodes() {
+        let cache = NodeCache::new(NonZeroUsize::new(1024 * 1024).unwrap());
+        let tree = MerkleTree::new_default().with_cache(&cache);
+
+        // nodes aren't put into the cache as they're written, but only once
+        // they're committed
+        let mut storage = MockStorage::new();
+        let batch = Batch::from([
+            (b"r".to_vec(), Op::Insert(b"foo".to_vec())),
+            (b"m".to_vec(), Op::Insert(b"bar".to_vec())),
+            (b"L".to_vec(), Op::Insert(b"fuzz".to_vec())),
+            (b"a".to_vec(), Op::Insert(b"buzz".to_vec())),
+        ]);
+        tree.apply_raw(&mut storage, 0, 1, &batch).unwrap();
+        assert!(cache.is_empty());
+
+        let changes = storage
+            .scan(None, None, Order::Ascending)
+            .map(|(key, value)| (key, Op::Insert(value)))
+            .collect::<Batch>();
+        tree.cache_nodes(&changes).unwrap();
+        assert!(!cache.is_empty());
+
+        // reads are served from the cache, so they succeed even against an
+        // empty storage
+        let empty_storage = MockStorage::new();
+        assert_eq!(tree.root_hash(&empty_storage, 1).unwrap(), Some(HASH_ROOT));
+        assert_eq!(TREE.root_hash(&empty_storage, 1).unwrap(), None);
+
+        // nodes are put into the cache as they're read. nodes deleted by
+        // rolling back are evicted from the cache.
+        let batch = Batch::from([(b"r".to_vec(), Op::Delete)]);
+        tree.apply_raw(&mut storage, 1, 2, &batch).unwrap();
+        assert_eq!(tree.root_hash(&empty_storage, 2).unwrap(), None);
+        assert!(tree.root_hash(&storage, 2).unwrap().is_some());
+        assert!(tree.root_hash(&empty_storage, 2).unwrap().is_some());
+        tree.rollback(&mut storage, 1).unwrap();
+        assert_eq!(tree.root_hash(&empty_storage, 2).unwrap(), None);
+        assert_eq!(tree.root_hash(&empty_storage, 1).unwrap(), Some(HASH_ROOT));
+
+        // nodes deleted by committed changes are evicted from the cache too
+        let changes = Batch::from([(tree.nodes.storage_key((1, ROOT_BITS)), Op::Delete)]);
+        tree.cache_nodes(&changes).unwrap();
+        assert_eq!(tree.root_hash(&empty_storage, 1).unwrap(), None);
+
+        // once cleared, reads go to the storage again
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(tree.root_hash(&empty_storage, 1).unwrap(), None);
+        assert_eq!(tree.root_hash(&storage, 1).unwrap(), Some(HASH_ROOT));
+        assert_eq!(cache.len(), 1);
+
+        // the cache holds at least one node, however small it is
+        assert_eq!(NodeCache::new(NonZeroUsize::MIN).capacity(), 1);
+        assert!(cache.capacity() > 1);
+    }
 }
@@ -4,6 +4,11 @@
 // everything they need.
 pub use {grug_macros::*, grug_storage::*, grug_types::*, grug_wasm::*};
 
+// `Json` is both a JSON value type in `grug_types` and a storage encoding
+// scheme in `grug_storage`. The former is far more often used by contracts, so
+// it keeps the name, while the encoding scheme is renamed.
+pub use {grug_storage::Json as JsonEncoding, grug_types::Json};
+
 // The testing crate must be excluded if the target is Wasm, because it contains
 // Wasm-incompatible operators, e.g. in `MockApi` which uses RNGs.
 #[cfg(not(target_arch = "wasm32"))]
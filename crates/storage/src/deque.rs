@@ -0,0 +1,220 @@
+use {
+    crate::{Borsh, Codec, Encoding, PathBuf, RawKey},
+    grug_types::{Order, StdResult, Storage},
+    std::marker::PhantomData,
+};
+
+/// A double-ended queue, where elements can be pushed to or popped from both
+/// the front and the back.
+///
+/// Storage layout:
+///
+/// - the positions of the head and the tail are stored together directly
+///   under the namespace;
+/// - each element is stored under the namespace with its position as the key.
+///
+/// Positions are `u32` numbers that wrap around, so elements can be pushed to
+/// the front of an empty queue.
+pub struct Deque<'a, T, E: Encoding = Borsh> {
+    namespace: &'a [u8],
+    data: PhantomData<T>,
+    encoding: PhantomData<E>,
+}
+
+impl<'a, T, E> Deque<'a, T, E>
+where
+    E: Encoding,
+{
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            namespace: namespace.as_bytes(),
+            data: PhantomData,
+            encoding: PhantomData,
+        }
+    }
+
+    fn meta_path(&self) -> PathBuf<(u32, u32)> {
+        PathBuf::new(self.namespace, &[], <Option<&RawKey>>::None)
+    }
+
+    fn path(&self, position: u32) -> PathBuf<T, E> {
+        element_path(self.namespace, position)
+    }
+
+    // returns the position of the head (the first element) and the tail (one
+    // after the last element)
+    fn load_meta(&self, storage: &dyn Storage) -> StdResult<(u32, u32)> {
+        self.meta_path()
+            .as_path()
+            .may_load(storage)
+            .map(Option::unwrap_or_default)
+    }
+
+    fn save_meta(&self, storage: &mut dyn Storage, head: u32, tail: u32) -> StdResult<()> {
+        self.meta_path().as_path().save(storage, &(head, tail))
+    }
+
+    pub fn len(&self, storage: &dyn Storage) -> StdResult<u32> {
+        let (head, tail) = self.load_meta(storage)?;
+        Ok(tail.wrapping_sub(head))
+    }
+
+    pub fn is_empty(&self, storage: &dyn Storage) -> StdResult<bool> {
+        self.len(storage).map(|len| len == 0)
+    }
+}
+
+impl<'a, T, E> Deque<'a, T, E>
+where
+    E: Codec<T>,
+{
+    pub fn push_back(&self, storage: &mut dyn Storage, data: &T) -> StdResult<()> {
+        let (head, tail) = self.load_meta(storage)?;
+        self.path(tail).as_path().save(storage, data)?;
+        self.save_meta(storage, head, tail.wrapping_add(1))
+    }
+
+    pub fn push_front(&self, storage: &mut dyn Storage, data: &T) -> StdResult<()> {
+        let (head, tail) = self.load_meta(storage)?;
+        let head = head.wrapping_sub(1);
+        self.path(head).as_path().save(storage, data)?;
+        self.save_meta(storage, head, tail)
+    }
+
+    pub fn pop_back(&self, storage: &mut dyn Storage) -> StdResult<Option<T>> {
+        let (head, tail) = self.load_meta(storage)?;
+        if head == tail {
+            return Ok(None);
+        }
+
+        let tail = tail.wrapping_sub(1);
+        let path = self.path(tail);
+        let data = path.as_path().load(storage)?;
+        path.as_path().remove(storage);
+        self.save_meta(storage, head, tail)?;
+
+        Ok(Some(data))
+    }
+
+    pub fn pop_front(&self, storage: &mut dyn Storage) -> StdResult<Option<T>> {
+        let (head, tail) = self.load_meta(storage)?;
+        if head == tail {
+            return Ok(None);
+        }
+
+        let path = self.path(head);
+        let data = path.as_path().load(storage)?;
+        path.as_path().remove(storage);
+        self.save_meta(storage, head.wrapping_add(1), tail)?;
+
+        Ok(Some(data))
+    }
+
+    pub fn front(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
+        self.get(storage, 0)
+    }
+
+    pub fn back(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
+        match self.len(storage)? {
+            0 => Ok(None),
+            len => self.get(storage, len - 1),
+        }
+    }
+
+    /// Load the element at the given index, counting from the front.
+    pub fn get(&self, storage: &dyn Storage, index: u32) -> StdResult<Option<T>> {
+        let (head, tail) = self.load_meta(storage)?;
+        if index >= tail.wrapping_sub(head) {
+            return Ok(None);
+        }
+
+        self.path(head.wrapping_add(index))
+            .as_path()
+            .load(storage)
+            .map(Some)
+    }
+
+    /// Iterate all elements, from front to back if the order is ascending, or
+    /// back to front if descending.
+    pub fn iter<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        order: Order,
+    ) -> StdResult<Box<dyn Iterator<Item = StdResult<T>> + 'b>> {
+        let (head, tail) = self.load_meta(storage)?;
+        let indexes = 0..tail.wrapping_sub(head);
+
+        // need to make a copy of the namespace and move it into the closure,
+        // so that the iterator can live longer than &self.
+        let namespace = self.namespace.to_vec();
+        let load = move |index: u32| {
+            element_path::<T, E>(&namespace, head.wrapping_add(index))
+                .as_path()
+                .load(storage)
+        };
+
+        Ok(match order {
+            Order::Ascending => Box::new(indexes.map(load)),
+            Order::Descending => Box::new(indexes.rev().map(load)),
+        })
+    }
+}
+
+fn element_path<T, E>(namespace: &[u8], position: u32) -> PathBuf<T, E>
+where
+    E: Encoding,
+{
+    PathBuf::new(namespace, &[], Some(&RawKey::Val32(position.to_be_bytes())))
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {super::*, grug_types::MockStorage};
+
+    const DEQUE: Deque<u64> = Deque::new("q");
+
+    fn collect(storage: &dyn Storage, order: Order) -> Vec<u64> {
+        DEQUE
+            .iter(storage, order)
+            .unwrap()
+            .collect::<StdResult<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn deque_works() {
+        let mut storage = MockStorage::new();
+
+        assert!(DEQUE.is_empty(&storage).unwrap());
+        assert_eq!(DEQUE.pop_front(&mut storage).unwrap(), None);
+        assert_eq!(DEQUE.pop_back(&mut storage).unwrap(), None);
+
+        // push to the front of an empty queue, so that the head wraps around
+        DEQUE.push_front(&mut storage, &2).unwrap();
+        DEQUE.push_front(&mut storage, &1).unwrap();
+        DEQUE.push_back(&mut storage, &3).unwrap();
+        DEQUE.push_back(&mut storage, &4).unwrap();
+
+        assert_eq!(DEQUE.len(&storage).unwrap(), 4);
+        assert_eq!(DEQUE.front(&storage).unwrap(), Some(1));
+        assert_eq!(DEQUE.back(&storage).unwrap(), Some(4));
+        assert_eq!(DEQUE.get(&storage, 2).unwrap(), Some(3));
+        assert_eq!(DEQUE.get(&storage, 4).unwrap(), None);
+        assert_eq!(collect(&storage, Order::Ascending), [1, 2, 3, 4]);
+        assert_eq!(collect(&storage, Order::Descending), [4, 3, 2, 1]);
+
+        assert_eq!(DEQUE.pop_front(&mut storage).unwrap(), Some(1));
+        assert_eq!(DEQUE.pop_back(&mut storage).unwrap(), Some(4));
+        assert_eq!(DEQUE.pop_back(&mut storage).unwrap(), Some(3));
+        assert_eq!(collect(&storage, Order::Ascending), [2]);
+
+        assert_eq!(DEQUE.pop_front(&mut storage).unwrap(), Some(2));
+        assert!(DEQUE.is_empty(&storage).unwrap());
+        assert_eq!(DEQUE.back(&storage).unwrap(), None);
+
+        // the only entry left in the storage should be the metadata
+        assert_eq!(storage.scan(None, None, Order::Ascending).count(), 1);
+    }
+}
@@ -1,12 +1,170 @@
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    grug_types::{
+        from_borsh_slice, from_json_slice, from_proto_slice, to_borsh_vec, to_json_vec,
+        to_proto_vec, StdError, StdResult,
+    },
+    prost::Message,
+    serde::{de::DeserializeOwned, ser::Serialize},
+};
+
 /// A marker that designates encoding schemes.
 pub trait Encoding {}
 
+/// An encoding scheme that is capable of encoding values of type `T` into
+/// bytes, and decoding them back.
+pub trait Codec<T>: Encoding {
+    fn encode(data: &T) -> StdResult<Vec<u8>>;
+
+    fn decode(bytes: &[u8]) -> StdResult<T>;
+}
+
 /// Represents the Borsh encoding scheme.
 pub struct Borsh;
 
 impl Encoding for Borsh {}
 
+impl<T> Codec<T> for Borsh
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn encode(data: &T) -> StdResult<Vec<u8>> {
+        to_borsh_vec(data)
+    }
+
+    fn decode(bytes: &[u8]) -> StdResult<T> {
+        from_borsh_slice(bytes)
+    }
+}
+
 /// Represents the Protobuf encoding scheme.
 pub struct Proto;
 
 impl Encoding for Proto {}
+
+impl<T> Codec<T> for Proto
+where
+    T: Message + Default,
+{
+    fn encode(data: &T) -> StdResult<Vec<u8>> {
+        Ok(to_proto_vec(data))
+    }
+
+    fn decode(bytes: &[u8]) -> StdResult<T> {
+        from_proto_slice(bytes)
+    }
+}
+
+/// Represents the JSON encoding scheme.
+///
+/// Useful for data that is to be read by off-chain indexers, which can't be
+/// expected to understand Borsh.
+pub struct Json;
+
+impl Encoding for Json {}
+
+impl<T> Codec<T> for Json
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(data: &T) -> StdResult<Vec<u8>> {
+        to_json_vec(data)
+    }
+
+    fn decode(bytes: &[u8]) -> StdResult<T> {
+        from_json_slice(bytes)
+    }
+}
+
+/// Decode a value loaded from the storage. In case of an error, include the
+/// namespace under which the value is stored in the error message, so that the
+/// corrupted storage object can be easily identified.
+pub(crate) fn decode_value<T, E>(namespace: &[u8], bytes: &[u8]) -> StdResult<T>
+where
+    E: Codec<T>,
+{
+    E::decode(bytes).map_err(|err| match err {
+        StdError::Deserialize { ty, reason } => StdError::Deserialize {
+            ty,
+            reason: format!(
+                "invalid value under namespace `{}`: {reason}",
+                String::from_utf8_lossy(namespace)
+            ),
+        },
+        err => err,
+    })
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Item, Map, Set},
+        grug_types::{MockStorage, Order, Storage},
+    };
+
+    const NUMBERS_BORSH: Map<&str, u64, Borsh> = Map::new("b");
+    const NUMBERS_PROTO: Map<&str, u64, Proto> = Map::new("p");
+    const NUMBERS_JSON: Map<&str, u64, Json> = Map::new("j");
+    const NAMES_JSON: Set<&str, Json> = Set::new("s");
+    const NAME_PROTO: Item<String, Proto> = Item::new("n");
+
+    #[test]
+    fn encoding_works() {
+        let mut storage = MockStorage::new();
+
+        NUMBERS_BORSH.save(&mut storage, "one", &1).unwrap();
+        NUMBERS_PROTO.save(&mut storage, "one", &1).unwrap();
+        NUMBERS_JSON.save(&mut storage, "one", &1).unwrap();
+        NUMBERS_JSON.save(&mut storage, "two", &2).unwrap();
+        NAMES_JSON.insert(&mut storage, "alice").unwrap();
+        NAME_PROTO.save(&mut storage, &"bob".to_string()).unwrap();
+
+        let path = NUMBERS_BORSH.path("one");
+        assert_eq!(
+            storage.read(path.storage_key()).unwrap(),
+            1u64.to_le_bytes()
+        );
+        let path = NUMBERS_PROTO.path("one");
+        assert_eq!(storage.read(path.storage_key()).unwrap(), [0x08, 0x01]);
+        let path = NUMBERS_JSON.path("one");
+        assert_eq!(storage.read(path.storage_key()).unwrap(), b"1");
+
+        assert_eq!(NUMBERS_PROTO.load(&storage, "one").unwrap(), 1);
+        assert_eq!(
+            NUMBERS_JSON
+                .range(&storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap(),
+            [("one".to_string(), 1), ("two".to_string(), 2)]
+        );
+        assert!(NAMES_JSON.has(&storage, "alice"));
+        assert_eq!(NAME_PROTO.load(&storage).unwrap(), "bob");
+    }
+
+    #[test]
+    fn decoding_errors_name_the_namespace() {
+        let mut storage = MockStorage::new();
+
+        // write a value that isn't valid JSON
+        storage.write(NUMBERS_JSON.path("one").storage_key(), b"hello");
+
+        let err = NUMBERS_JSON.load(&storage, "one").unwrap_err();
+        assert!(matches!(
+            err,
+            StdError::Deserialize { reason, .. } if reason.starts_with("invalid value under namespace `j`")
+        ));
+
+        let err = NUMBERS_JSON
+            .range(&storage, None, None, Order::Ascending)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            StdError::Deserialize { reason, .. } if reason.starts_with("invalid value under namespace `j`")
+        ));
+    }
+}
@@ -0,0 +1,421 @@
+use {
+    crate::{Borsh, Bound, Codec, Encoding, MapKey, Path, PathBuf, Prefix, RawKey},
+    grug_types::{
+        concat, increment_last_byte, nested_namespaces_with_key, Order, StdError, StdResult,
+        Storage,
+    },
+    std::marker::PhantomData,
+};
+
+/// A secondary index over data of type `T`.
+///
+/// Indexes are identified to the data by the primary key, which is provided
+/// here in its serialized form (see `MapKey::serialize`).
+pub trait Index<T> {
+    /// The namespace of the primary map, from which the index loads records.
+    ///
+    /// `IndexedMap` asserts that this is the same as its own namespace.
+    fn pk_namespace(&self) -> &[u8];
+
+    /// Return an error if the index entries for the data can't be created under
+    /// the given primary key, e.g. because they conflict with another record's.
+    ///
+    /// `IndexedMap` calls this for all indexes before writing anything, so that
+    /// a failed save leaves the storage untouched.
+    fn check(&self, _storage: &dyn Storage, _pk: &[u8], _data: &T) -> StdResult<()> {
+        Ok(())
+    }
+
+    /// Create the index entries for the data under the given primary key.
+    fn save(&self, storage: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()>;
+
+    /// Delete the index entries created for the data under the given primary key.
+    fn remove(&self, storage: &mut dyn Storage, pk: &[u8], old_data: &T);
+}
+
+/// A collection of indexes to be maintained by an `IndexedMap`.
+///
+/// Typically implemented for a struct that contains one or more indexes:
+///
+/// ```ignore
+/// struct AccountIndexes<'a> {
+///     pub public_key: MultiIndex<'a, Hash, Account, Addr>,
+/// }
+///
+/// impl<'a> IndexList<Account> for AccountIndexes<'a> {
+///     fn get_indexes(&self) -> Box<dyn Iterator<Item = &'_ dyn Index<Account>> + '_> {
+///         let indexes: Vec<&dyn Index<Account>> = vec![&self.public_key];
+///         Box::new(indexes.into_iter())
+///     }
+/// }
+/// ```
+pub trait IndexList<T> {
+    fn get_indexes(&self) -> Box<dyn Iterator<Item = &'_ dyn Index<T>> + '_>;
+}
+
+// ------------------------------- unique index --------------------------------
+
+/// An index where each index key corresponds to at most one primary key.
+///
+/// Attempting to save a record whose index key is already used by another
+/// record results in an error.
+///
+/// Storage layout: `len(namespace) | namespace | index_key => primary_key`
+pub struct UniqueIndex<'a, IK, T, PK, E: Encoding = Borsh> {
+    index: fn(&T) -> IK,
+    pk_namespace: &'a [u8],
+    idx_namespace: &'a [u8],
+    primary_key: PhantomData<PK>,
+    encoding: PhantomData<E>,
+}
+
+impl<'a, IK, T, PK, E> UniqueIndex<'a, IK, T, PK, E>
+where
+    E: Encoding,
+{
+    /// `pk_namespace` must be the same as the namespace of the `IndexedMap`
+    /// this index is used with, which is asserted when the map is written to.
+    pub const fn new(index: fn(&T) -> IK, pk_namespace: &'a str, idx_namespace: &'a str) -> Self {
+        Self {
+            index,
+            pk_namespace: pk_namespace.as_bytes(),
+            idx_namespace: idx_namespace.as_bytes(),
+            primary_key: PhantomData,
+            encoding: PhantomData,
+        }
+    }
+}
+
+impl<'a, IK, T, PK, E> UniqueIndex<'a, IK, T, PK, E>
+where
+    IK: MapKey,
+    E: Encoding,
+{
+    fn path(&self, idx_key: IK) -> PathBuf<PK> {
+        let mut raw_keys = idx_key.raw_keys();
+        let last_raw_key = raw_keys.pop();
+        PathBuf::new(self.idx_namespace, &raw_keys, last_raw_key.as_ref())
+    }
+}
+
+impl<'a, IK, T, PK, E> Index<T> for UniqueIndex<'a, IK, T, PK, E>
+where
+    IK: MapKey,
+    E: Encoding,
+{
+    fn pk_namespace(&self) -> &[u8] {
+        self.pk_namespace
+    }
+
+    // the index key may be used by the record itself, which keeps it when
+    // updated
+    fn check(&self, storage: &dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
+        let path = self.path((self.index)(data));
+        match storage.read(path.storage_key()) {
+            Some(existing_pk) if existing_pk != pk => Err(StdError::unique_index_violated(
+                self.idx_namespace,
+                path.storage_key(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn save(&self, storage: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
+        let path = self.path((self.index)(data));
+        if storage.read(path.storage_key()).is_some() {
+            return Err(StdError::unique_index_violated(
+                self.idx_namespace,
+                path.storage_key(),
+            ));
+        }
+
+        storage.write(path.storage_key(), pk);
+
+        Ok(())
+    }
+
+    fn remove(&self, storage: &mut dyn Storage, _pk: &[u8], old_data: &T) {
+        self.path((self.index)(old_data)).as_path().remove(storage)
+    }
+}
+
+impl<'a, IK, T, PK, E> UniqueIndex<'a, IK, T, PK, E>
+where
+    IK: MapKey,
+    PK: MapKey,
+    E: Codec<T>,
+{
+    fn no_prefix(&self) -> IndexPrefix<IK, T, PK, E> {
+        IndexPrefix::new(self.pk_namespace, self.idx_namespace, &[])
+    }
+
+    pub fn prefix(&self, prefix: IK::Prefix) -> IndexPrefix<IK::Suffix, T, PK, E> {
+        IndexPrefix::new(self.pk_namespace, self.idx_namespace, &prefix.raw_keys())
+    }
+
+    pub fn may_load(
+        &self,
+        storage: &dyn Storage,
+        idx_key: IK,
+    ) -> StdResult<Option<(PK::Output, T)>> {
+        let Some(pk_raw) = storage.read(self.path(idx_key).storage_key()) else {
+            return Ok(None);
+        };
+
+        load_primary::<PK, T, E>(storage, &primary_prefix(self.pk_namespace), &pk_raw).map(Some)
+    }
+
+    pub fn load(&self, storage: &dyn Storage, idx_key: IK) -> StdResult<(PK::Output, T)> {
+        let path = self.path(idx_key);
+        let Some(pk_raw) = storage.read(path.storage_key()) else {
+            return Err(StdError::data_not_found::<T>(path.storage_key()));
+        };
+
+        load_primary::<PK, T, E>(storage, &primary_prefix(self.pk_namespace), &pk_raw)
+    }
+
+    pub fn keys<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<IK>>,
+        max: Option<Bound<IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<PK::Output>> + 'b> {
+        self.no_prefix().keys(storage, min, max, order)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn range<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<IK>>,
+        max: Option<Bound<IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'b> {
+        self.no_prefix().range(storage, min, max, order)
+    }
+}
+
+// -------------------------------- multi index --------------------------------
+
+/// An index where each index key may correspond to any number of primary keys.
+///
+/// Storage layout: `len(namespace) | namespace | len(index_key) | index_key | primary_key => primary_key`
+///
+/// For compound index keys, each element of the index key is length-prefixed.
+pub struct MultiIndex<'a, IK, T, PK, E: Encoding = Borsh> {
+    index: fn(&T) -> IK,
+    pk_namespace: &'a [u8],
+    idx_namespace: &'a [u8],
+    primary_key: PhantomData<PK>,
+    encoding: PhantomData<E>,
+}
+
+impl<'a, IK, T, PK, E> MultiIndex<'a, IK, T, PK, E>
+where
+    E: Encoding,
+{
+    /// `pk_namespace` must be the same as the namespace of the `IndexedMap`
+    /// this index is used with, which is asserted when the map is written to.
+    pub const fn new(index: fn(&T) -> IK, pk_namespace: &'a str, idx_namespace: &'a str) -> Self {
+        Self {
+            index,
+            pk_namespace: pk_namespace.as_bytes(),
+            idx_namespace: idx_namespace.as_bytes(),
+            primary_key: PhantomData,
+            encoding: PhantomData,
+        }
+    }
+}
+
+impl<'a, IK, T, PK, E> MultiIndex<'a, IK, T, PK, E>
+where
+    IK: MapKey,
+    E: Encoding,
+{
+    fn path(&self, idx_key: IK, pk: &[u8]) -> PathBuf<PK> {
+        PathBuf::new(
+            self.idx_namespace,
+            &idx_key.raw_keys(),
+            Some(&RawKey::Ref(pk)),
+        )
+    }
+}
+
+impl<'a, IK, T, PK, E> Index<T> for MultiIndex<'a, IK, T, PK, E>
+where
+    IK: MapKey,
+    E: Encoding,
+{
+    fn pk_namespace(&self) -> &[u8] {
+        self.pk_namespace
+    }
+
+    fn save(&self, storage: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
+        let path = self.path((self.index)(data), pk);
+        storage.write(path.storage_key(), pk);
+
+        Ok(())
+    }
+
+    fn remove(&self, storage: &mut dyn Storage, pk: &[u8], old_data: &T) {
+        self.path((self.index)(old_data), pk)
+            .as_path()
+            .remove(storage)
+    }
+}
+
+impl<'a, IK, T, PK, E> MultiIndex<'a, IK, T, PK, E>
+where
+    IK: MapKey,
+    PK: MapKey,
+    E: Codec<T>,
+{
+    fn no_prefix(&self) -> IndexPrefix<IK, T, PK, E> {
+        IndexPrefix::new(self.pk_namespace, self.idx_namespace, &[])
+    }
+
+    /// Iterate the records that have the given index key, in the order of their
+    /// primary keys.
+    pub fn prefix(&self, idx_key: IK) -> IndexPrefix<PK, T, PK, E> {
+        IndexPrefix::new(self.pk_namespace, self.idx_namespace, &idx_key.raw_keys())
+    }
+
+    pub fn keys<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<IK>>,
+        max: Option<Bound<IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<PK::Output>> + 'b> {
+        let (min, max) = multi_index_bounds(min, max);
+        self.no_prefix().keys(storage, min, max, order)
+    }
+
+    /// Iterate records whose index keys are within the given bounds, in the
+    /// order of their index keys, then primary keys.
+    #[allow(clippy::type_complexity)]
+    pub fn range<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<IK>>,
+        max: Option<Bound<IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'b> {
+        let (min, max) = multi_index_bounds(min, max);
+        self.no_prefix().range(storage, min, max, order)
+    }
+}
+
+// in a multi index, the storage keys are the index keys with the primary keys
+// appended. unlike in a map, all elements of the index key are length-prefixed.
+// convert the bounds accordingly, such that an inclusive bound includes all
+// primary keys under the index key, and an exclusive bound excludes them all.
+fn multi_index_bounds<K: MapKey>(
+    min: Option<Bound<K>>,
+    max: Option<Bound<K>>,
+) -> (Option<Bound<K>>, Option<Bound<K>>) {
+    let prefixed =
+        |key: K| nested_namespaces_with_key(None, &key.raw_keys(), <Option<&RawKey>>::None);
+    let min = min.map(|bound| match bound {
+        Bound::Inclusive(key) => Bound::InclusiveRaw(prefixed(key)),
+        Bound::Exclusive(key) => Bound::InclusiveRaw(increment_last_byte(prefixed(key))),
+        raw => raw,
+    });
+    let max = max.map(|bound| match bound {
+        Bound::Inclusive(key) => Bound::ExclusiveRaw(increment_last_byte(prefixed(key))),
+        Bound::Exclusive(key) => Bound::ExclusiveRaw(prefixed(key)),
+        raw => raw,
+    });
+
+    (min, max)
+}
+
+// ------------------------------- index prefix --------------------------------
+
+/// Iterate index entries under a prefix, loading the corresponding records from
+/// the primary map.
+pub struct IndexPrefix<K, T, PK, E: Encoding = Borsh> {
+    pk_prefix: Vec<u8>,
+    inner: Prefix<K, PK>,
+    data: PhantomData<T>,
+    encoding: PhantomData<E>,
+}
+
+impl<K, T, PK, E> IndexPrefix<K, T, PK, E>
+where
+    E: Encoding,
+{
+    pub fn new(pk_namespace: &[u8], idx_namespace: &[u8], prefixes: &[RawKey]) -> Self {
+        Self {
+            pk_prefix: primary_prefix(pk_namespace),
+            inner: Prefix::new(idx_namespace, prefixes),
+            data: PhantomData,
+            encoding: PhantomData,
+        }
+    }
+}
+
+impl<K, T, PK, E> IndexPrefix<K, T, PK, E>
+where
+    K: MapKey,
+    PK: MapKey,
+    E: Codec<T>,
+{
+    pub fn keys<'a>(
+        &self,
+        storage: &'a dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<PK::Output>> + 'a> {
+        let iter = self
+            .inner
+            .range_raw(storage, min, max, order)
+            .map(|(_, pk_raw)| PK::deserialize(&pk_raw));
+
+        Box::new(iter)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn range<'a>(
+        &self,
+        storage: &'a dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'a> {
+        let pk_prefix = self.pk_prefix.clone();
+        let iter = self
+            .inner
+            .range_raw(storage, min, max, order)
+            .map(move |(_, pk_raw)| load_primary::<PK, T, E>(storage, &pk_prefix, &pk_raw));
+
+        Box::new(iter)
+    }
+}
+
+// the prefix of the storage keys of all records in the primary map
+fn primary_prefix(pk_namespace: &[u8]) -> Vec<u8> {
+    nested_namespaces_with_key(
+        Some(pk_namespace),
+        &<[RawKey; 0]>::default(),
+        <Option<&RawKey>>::None,
+    )
+}
+
+fn load_primary<PK, T, E>(
+    storage: &dyn Storage,
+    pk_prefix: &[u8],
+    pk_raw: &[u8],
+) -> StdResult<(PK::Output, T)>
+where
+    PK: MapKey,
+    E: Codec<T>,
+{
+    let pk = PK::deserialize(pk_raw)?;
+    // the prefix is the namespace, prefixed by its length in two bytes
+    let storage_key = concat(pk_prefix, pk_raw);
+    let data = Path::<T, E>::from_raw(&pk_prefix[2..], &storage_key).load(storage)?;
+    Ok((pk, data))
+}
@@ -0,0 +1,404 @@
+use {
+    crate::{Borsh, Bound, Codec, Encoding, Index, IndexList, Map, MapKey, PathBuf, Prefix},
+    grug_types::{Order, StdError, StdResult, Storage},
+};
+
+/// A `Map` with secondary indexes.
+///
+/// The indexes are declared by a type `I` implementing the `IndexList` trait,
+/// and are updated automatically as records are saved, updated, or removed.
+/// Query the indexes via the `idx` field.
+///
+/// Index entries only reference the records by their primary keys, so the
+/// records themselves are stored only once, in the primary map.
+pub struct IndexedMap<'a, K, T, I, E: Encoding = Borsh> {
+    primary: Map<'a, K, T, E>,
+    pub idx: I,
+}
+
+impl<'a, K, T, I, E> IndexedMap<'a, K, T, I, E>
+where
+    E: Encoding,
+{
+    pub const fn new(pk_namespace: &'a str, indexes: I) -> Self {
+        Self {
+            primary: Map::new(pk_namespace),
+            idx: indexes,
+        }
+    }
+}
+
+impl<'a, K, T, I, E> IndexedMap<'a, K, T, I, E>
+where
+    K: MapKey,
+    E: Encoding,
+{
+    pub fn prefix(&self, prefix: K::Prefix) -> Prefix<K::Suffix, T, E> {
+        self.primary.prefix(prefix)
+    }
+
+    pub fn is_empty(&self, storage: &dyn Storage) -> bool {
+        self.primary.is_empty(storage)
+    }
+
+    pub fn has(&self, storage: &dyn Storage, k: K) -> bool {
+        self.primary.has(storage, k)
+    }
+
+    pub fn keys<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'b> {
+        self.primary.keys(storage, min, max, order)
+    }
+}
+
+impl<'a, K, T, I, E> IndexedMap<'a, K, T, I, E>
+where
+    K: MapKey,
+    E: Codec<T>,
+{
+    pub fn may_load(&self, storage: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        self.primary.may_load(storage, k)
+    }
+
+    pub fn load(&self, storage: &dyn Storage, k: K) -> StdResult<T> {
+        self.primary.load(storage, k)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn range<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'b> {
+        self.primary.range(storage, min, max, order)
+    }
+}
+
+impl<'a, K, T, I, E> IndexedMap<'a, K, T, I, E>
+where
+    K: MapKey,
+    I: IndexList<T>,
+    E: Codec<T>,
+{
+    pub fn save(&self, storage: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
+        let pk = k.serialize();
+        let path = self.primary.path(k);
+        let old_data = path.as_path().may_load(storage)?;
+        self.replace(storage, &pk, &path, Some(data), old_data.as_ref())
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage, k: K) -> StdResult<()> {
+        let pk = k.serialize();
+        let path = self.primary.path(k);
+        let old_data = path.as_path().may_load(storage)?;
+        self.replace(storage, &pk, &path, None, old_data.as_ref())
+    }
+
+    pub fn update<A, Error>(
+        &self,
+        storage: &mut dyn Storage,
+        k: K,
+        action: A,
+    ) -> Result<Option<T>, Error>
+    where
+        T: Clone,
+        A: FnOnce(Option<T>) -> Result<Option<T>, Error>,
+        Error: From<StdError>,
+    {
+        let pk = k.serialize();
+        let path = self.primary.path(k);
+        let old_data = path.as_path().may_load(storage)?;
+        let new_data = action(old_data.clone())?;
+        self.replace(storage, &pk, &path, new_data.as_ref(), old_data.as_ref())?;
+        Ok(new_data)
+    }
+
+    // the indexes load records from the primary map by its namespace, which
+    // they're given separately, so make sure it's the right one before writing.
+    fn indexes(&self) -> impl Iterator<Item = &'_ dyn Index<T>> + '_ {
+        self.idx.get_indexes().inspect(|index| {
+            assert_eq!(
+                index.pk_namespace(),
+                self.primary.namespace(),
+                "index refers to a different primary namespace than the indexed map"
+            );
+        })
+    }
+
+    // everything that may fail, i.e. checking the indexes for conflicts and
+    // encoding the data, is done before anything is written, so that a failed
+    // save doesn't leave the record and its index entries out of sync.
+    //
+    // the old index entries are removed before the new ones are created, so
+    // that a record may keep its own unique index keys.
+    fn replace(
+        &self,
+        storage: &mut dyn Storage,
+        pk: &[u8],
+        path: &PathBuf<T, E>,
+        data: Option<&T>,
+        old_data: Option<&T>,
+    ) -> StdResult<()> {
+        let data = data
+            .map(|data| -> StdResult<_> {
+                for index in self.indexes() {
+                    index.check(storage, pk, data)?;
+                }
+                Ok((data, E::encode(data)?))
+            })
+            .transpose()?;
+
+        if let Some(old_data) = old_data {
+            for index in self.indexes() {
+                index.remove(storage, pk, old_data);
+            }
+        }
+
+        if let Some((data, bytes)) = data {
+            for index in self.indexes() {
+                index.save(storage, pk, data)?;
+            }
+            storage.write(path.storage_key(), &bytes);
+        } else {
+            path.as_path().remove(storage);
+        }
+
+        Ok(())
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Index, MultiIndex, UniqueIndex},
+        borsh::{BorshDeserialize, BorshSerialize},
+        grug_types::MockStorage,
+    };
+
+    #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+    struct Student {
+        id: u32,
+        class: u8,
+        age: u8,
+    }
+
+    struct StudentIndexes<'a> {
+        id: UniqueIndex<'a, u32, Student, &'a str>,
+        class: MultiIndex<'a, u8, Student, &'a str>,
+        class_age: MultiIndex<'a, (u8, u8), Student, &'a str>,
+    }
+
+    impl<'a> IndexList<Student> for StudentIndexes<'a> {
+        fn get_indexes(&self) -> Box<dyn Iterator<Item = &'_ dyn Index<Student>> + '_> {
+            let indexes: Vec<&dyn Index<Student>> = vec![&self.id, &self.class, &self.class_age];
+            Box::new(indexes.into_iter())
+        }
+    }
+
+    const STUDENTS: IndexedMap<&str, Student, StudentIndexes> =
+        IndexedMap::new("s", StudentIndexes {
+            id: UniqueIndex::new(|s| s.id, "s", "s__id"),
+            class: MultiIndex::new(|s| s.class, "s", "s__class"),
+            class_age: MultiIndex::new(|s| (s.class, s.age), "s", "s__class_age"),
+        });
+
+    fn student(id: u32, class: u8, age: u8) -> Student {
+        Student { id, class, age }
+    }
+
+    fn setup() -> MockStorage {
+        let mut storage = MockStorage::new();
+        for (name, student) in [
+            ("alice", student(1, 1, 10)),
+            ("bob", student(2, 2, 11)),
+            ("carol", student(3, 1, 11)),
+            ("dave", student(4, 2, 10)),
+            ("eve", student(5, 1, 10)),
+        ] {
+            STUDENTS.save(&mut storage, name, &student).unwrap();
+        }
+        storage
+    }
+
+    fn names<T>(iter: Box<dyn Iterator<Item = StdResult<(String, T)>> + '_>) -> Vec<String> {
+        iter.map(|res| res.unwrap().0).collect()
+    }
+
+    #[test]
+    fn unique_index_works() {
+        let mut storage = setup();
+
+        assert_eq!(
+            STUDENTS.idx.id.load(&storage, 3).unwrap(),
+            ("carol".to_string(), student(3, 1, 11))
+        );
+        assert_eq!(STUDENTS.idx.id.may_load(&storage, 6).unwrap(), None);
+
+        let ids = STUDENTS.idx.id.range(
+            &storage,
+            Some(Bound::Exclusive(2)),
+            Some(Bound::Inclusive(4)),
+            Order::Descending,
+        );
+        assert_eq!(names(ids), ["dave", "carol"]);
+
+        // saving another student with an existing ID should fail
+        assert!(matches!(
+            STUDENTS.save(&mut storage, "frank", &student(2, 1, 10)),
+            Err(StdError::UniqueIndexViolated { .. })
+        ));
+
+        // updating a student to an existing ID should fail, leaving the record
+        // and its index entries untouched
+        assert!(matches!(
+            STUDENTS.save(&mut storage, "bob", &student(3, 1, 12)),
+            Err(StdError::UniqueIndexViolated { .. })
+        ));
+        assert_eq!(
+            STUDENTS.idx.id.load(&storage, 2).unwrap(),
+            ("bob".to_string(), student(2, 2, 11))
+        );
+        assert_eq!(
+            STUDENTS.idx.id.load(&storage, 3).unwrap().0,
+            "carol".to_string()
+        );
+        let class_2 = STUDENTS
+            .idx
+            .class
+            .prefix(2)
+            .range(&storage, None, None, Order::Ascending);
+        assert_eq!(names(class_2), ["bob", "dave"]);
+
+        // a student can keep its own ID when updated
+        STUDENTS
+            .save(&mut storage, "bob", &student(2, 1, 12))
+            .unwrap();
+        assert_eq!(
+            STUDENTS.idx.id.load(&storage, 2).unwrap().1,
+            student(2, 1, 12)
+        );
+
+        // once removed, the ID can be taken by someone else
+        STUDENTS.remove(&mut storage, "bob").unwrap();
+        assert_eq!(STUDENTS.idx.id.may_load(&storage, 2).unwrap(), None);
+        STUDENTS
+            .save(&mut storage, "frank", &student(2, 1, 10))
+            .unwrap();
+        assert_eq!(STUDENTS.idx.id.load(&storage, 2).unwrap().0, "frank");
+    }
+
+    #[test]
+    fn multi_index_works() {
+        let mut storage = setup();
+
+        let class_1 = STUDENTS
+            .idx
+            .class
+            .prefix(1)
+            .range(&storage, None, None, Order::Ascending);
+        assert_eq!(names(class_1), ["alice", "carol", "eve"]);
+
+        let class_1 = STUDENTS.idx.class.prefix(1).range(
+            &storage,
+            Some(Bound::Exclusive("alice")),
+            None,
+            Order::Ascending,
+        );
+        assert_eq!(names(class_1), ["carol", "eve"]);
+
+        // iterating over index keys, records are ordered by index key, then by
+        // primary key
+        let all = STUDENTS
+            .idx
+            .class
+            .range(&storage, None, None, Order::Descending);
+        assert_eq!(names(all), ["dave", "bob", "eve", "carol", "alice"]);
+
+        let class_2 = STUDENTS.idx.class.range(
+            &storage,
+            Some(Bound::Exclusive(1)),
+            Some(Bound::Inclusive(2)),
+            Order::Ascending,
+        );
+        assert_eq!(names(class_2), ["bob", "dave"]);
+
+        let class_1 = STUDENTS.idx.class.keys(
+            &storage,
+            Some(Bound::Inclusive(1)),
+            Some(Bound::Exclusive(2)),
+            Order::Ascending,
+        );
+        assert_eq!(class_1.map(Result::unwrap).collect::<Vec<_>>(), [
+            "alice", "carol", "eve"
+        ]);
+
+        // compound index keys
+        let aged_10 =
+            STUDENTS
+                .idx
+                .class_age
+                .prefix((1, 10))
+                .range(&storage, None, None, Order::Ascending);
+        assert_eq!(names(aged_10), ["alice", "eve"]);
+
+        // update a record; the index should follow
+        STUDENTS
+            .update(&mut storage, "alice", |maybe_student| -> StdResult<_> {
+                let mut student = maybe_student.unwrap();
+                student.class = 2;
+                Ok(Some(student))
+            })
+            .unwrap();
+        let class_1 = STUDENTS
+            .idx
+            .class
+            .prefix(1)
+            .range(&storage, None, None, Order::Ascending);
+        assert_eq!(names(class_1), ["carol", "eve"]);
+        let class_2 = STUDENTS
+            .idx
+            .class
+            .prefix(2)
+            .range(&storage, None, None, Order::Ascending);
+        assert_eq!(names(class_2), ["alice", "bob", "dave"]);
+
+        // remove a record via update
+        STUDENTS
+            .update(&mut storage, "dave", |_| -> StdResult<_> { Ok(None) })
+            .unwrap();
+        assert!(!STUDENTS.has(&storage, "dave"));
+        let class_2 = STUDENTS
+            .idx
+            .class
+            .prefix(2)
+            .range(&storage, None, None, Order::Ascending);
+        assert_eq!(names(class_2), ["alice", "bob"]);
+        assert_eq!(STUDENTS.idx.id.may_load(&storage, 4).unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "index refers to a different primary namespace")]
+    fn mismatched_pk_namespace_panics() {
+        const STUDENTS_TYPO: IndexedMap<&str, Student, StudentIndexes> =
+            IndexedMap::new("s", StudentIndexes {
+                id: UniqueIndex::new(|s| s.id, "s", "s__id"),
+                class: MultiIndex::new(|s| s.class, "t", "s__class"),
+                class_age: MultiIndex::new(|s| (s.class, s.age), "s", "s__class_age"),
+            });
+
+        let mut storage = MockStorage::new();
+        STUDENTS_TYPO
+            .save(&mut storage, "alice", &student(1, 1, 10))
+            .unwrap();
+    }
+}
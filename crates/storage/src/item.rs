@@ -1,8 +1,6 @@
 use {
-    crate::{Borsh, Encoding, Path, Proto},
-    borsh::{BorshDeserialize, BorshSerialize},
+    crate::{decode_value, Borsh, Codec, Encoding, Path},
     grug_types::{StdError, StdResult, Storage},
-    prost::Message,
     std::marker::PhantomData,
 };
 
@@ -24,8 +22,17 @@ where
         }
     }
 
+    /// The raw key under which the item is stored.
+    ///
+    /// Together with `decode`, this allows reading the item off-chain, e.g.
+    /// with a `WasmRaw` query.
+    pub fn storage_key(&self) -> &[u8] {
+        self.storage_key
+    }
+
     fn path(&self) -> Path<T, E> {
-        Path::from_raw(self.storage_key)
+        // for an item, the storage key is the namespace itself
+        Path::from_raw(self.storage_key, self.storage_key)
     }
 
     pub fn exists(&self, storage: &dyn Storage) -> bool {
@@ -37,58 +44,19 @@ where
     }
 }
 
-// ----------------------------------- borsh -----------------------------------
-
-impl<'a, T> Item<'a, T, Borsh>
-where
-    T: BorshSerialize,
-{
-    pub fn save(&self, storage: &mut dyn Storage, data: &T) -> StdResult<()> {
-        self.path().save(storage, data)
-    }
-}
-
-impl<'a, T> Item<'a, T, Borsh>
-where
-    T: BorshDeserialize,
-{
-    pub fn may_load(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
-        self.path().may_load(storage)
-    }
-
-    pub fn load(&self, storage: &dyn Storage) -> StdResult<T> {
-        self.path().load(storage)
-    }
-}
-
-impl<'a, T> Item<'a, T>
+impl<'a, T, E> Item<'a, T, E>
 where
-    T: BorshSerialize + BorshDeserialize,
+    E: Codec<T>,
 {
-    pub fn update<A, E>(&self, storage: &mut dyn Storage, action: A) -> Result<Option<T>, E>
-    where
-        A: FnOnce(Option<T>) -> Result<Option<T>, E>,
-        E: From<StdError>,
-    {
-        self.path().update(storage, action)
+    /// Decode the item from the raw bytes stored under `storage_key`.
+    pub fn decode(&self, bytes: &[u8]) -> StdResult<T> {
+        decode_value::<T, E>(self.storage_key, bytes)
     }
-}
-
-// ----------------------------------- proto -----------------------------------
 
-impl<'a, T> Item<'a, T, Proto>
-where
-    T: Message,
-{
-    pub fn save(&self, storage: &mut dyn Storage, data: &T) {
+    pub fn save(&self, storage: &mut dyn Storage, data: &T) -> StdResult<()> {
         self.path().save(storage, data)
     }
-}
 
-impl<'a, T> Item<'a, T, Proto>
-where
-    T: Message + Default,
-{
     pub fn may_load(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
         self.path().may_load(storage)
     }
@@ -97,10 +65,10 @@ where
         self.path().load(storage)
     }
 
-    pub fn update<A, E>(&self, storage: &mut dyn Storage, action: A) -> Result<Option<T>, E>
+    pub fn update<A, Error>(&self, storage: &mut dyn Storage, action: A) -> Result<Option<T>, Error>
     where
-        A: FnOnce(Option<T>) -> Result<Option<T>, E>,
-        E: From<StdError>,
+        A: FnOnce(Option<T>) -> Result<Option<T>, Error>,
+        Error: From<StdError>,
     {
         self.path().update(storage, action)
     }
@@ -1,5 +1,9 @@
 use {
-    grug_types::{nested_namespaces_with_key, split_one_key, Addr, Hash, StdError, StdResult},
+    grug_types::{
+        nested_namespaces_with_key, split_one_key, Addr, Bytable, Decimal128, Decimal256, Hash,
+        Int, Int128, Int256, Int512, Int64, SignedDecimal128, SignedDecimal256, StdError,
+        StdResult, Timestamp, Uint128, Uint256, Uint512, Uint64,
+    },
     std::mem,
 };
 
@@ -11,6 +15,8 @@ pub enum RawKey<'a> {
     Val32([u8; 4]),
     Val64([u8; 8]),
     Val128([u8; 16]),
+    Val256([u8; 32]),
+    Val512([u8; 64]),
 }
 
 impl<'a> AsRef<[u8]> for RawKey<'a> {
@@ -23,6 +29,8 @@ impl<'a> AsRef<[u8]> for RawKey<'a> {
             RawKey::Val32(slice) => slice,
             RawKey::Val64(slice) => slice,
             RawKey::Val128(slice) => slice,
+            RawKey::Val256(slice) => slice,
+            RawKey::Val512(slice) => slice,
         }
     }
 }
@@ -106,6 +114,34 @@ impl MapKey for &str {
     }
 }
 
+impl MapKey for Vec<u8> {
+    type Output = Vec<u8>;
+    type Prefix = ();
+    type Suffix = ();
+
+    fn raw_keys(&self) -> Vec<RawKey> {
+        vec![RawKey::Ref(self)]
+    }
+
+    fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl MapKey for String {
+    type Output = String;
+    type Prefix = ();
+    type Suffix = ();
+
+    fn raw_keys(&self) -> Vec<RawKey> {
+        vec![RawKey::Ref(self.as_bytes())]
+    }
+
+    fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
+        String::from_utf8(bytes.to_vec()).map_err(StdError::deserialize::<Self::Output>)
+    }
+}
+
 impl MapKey for &Addr {
     type Output = Addr;
     type Prefix = ();
@@ -134,19 +170,59 @@ impl MapKey for &Hash {
     }
 }
 
+// owned versions of the above, so that they can be returned by functions that
+// derive index keys from data (see `UniqueIndex` and `MultiIndex`).
+impl MapKey for Addr {
+    type Output = Addr;
+    type Prefix = ();
+    type Suffix = ();
+
+    fn raw_keys(&self) -> Vec<RawKey> {
+        vec![RawKey::Ref(self.as_ref())]
+    }
+
+    fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
+        bytes.try_into()
+    }
+}
+
+impl MapKey for Hash {
+    type Output = Hash;
+    type Prefix = ();
+    type Suffix = ();
+
+    fn raw_keys(&self) -> Vec<RawKey> {
+        vec![RawKey::Ref(self.as_ref())]
+    }
+
+    fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
+        bytes.try_into()
+    }
+}
+
+// integers are encoded in big endian, so that the byte order is the same as the
+// numeric order. for signed integers, the sign bit is additionally flipped, so
+// that negative numbers come before positive ones.
+//
+// note: signed integers used to be encoded without flipping the sign bit. see
+// the changelog on how to migrate records written that way.
 macro_rules! impl_integer_map_key {
-    ($($t:ty, $v:tt),+ $(,)?) => {
+    ($($t:ty => $v:ident, $signed:literal),+ $(,)?) => {
         $(impl MapKey for $t {
             type Prefix = ();
             type Suffix = ();
             type Output = $t;
 
             fn raw_keys(&self) -> Vec<RawKey> {
-                vec![RawKey::$v(self.to_be_bytes())]
+                let mut bytes = self.to_be_bytes();
+                if $signed {
+                    bytes[0] ^= 0x80;
+                }
+                vec![RawKey::$v(bytes)]
             }
 
             fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
-                let Ok(bytes) = <[u8; mem::size_of::<Self>()]>::try_from(bytes) else {
+                let Ok(mut bytes) = <[u8; mem::size_of::<Self>()]>::try_from(bytes) else {
                     return Err(StdError::deserialize::<Self::Output>(format!(
                         "wrong number of bytes: expecting {}, got {}",
                         mem::size_of::<Self>(),
@@ -154,6 +230,10 @@ macro_rules! impl_integer_map_key {
                     )));
                 };
 
+                if $signed {
+                    bytes[0] ^= 0x80;
+                }
+
                 Ok(Self::from_be_bytes(bytes))
             }
         })*
@@ -161,10 +241,82 @@ macro_rules! impl_integer_map_key {
 }
 
 impl_integer_map_key!(
-    i8, Val8, u8, Val8, i16, Val16, u16, Val16, i32, Val32, u32, Val32, i64, Val64, u64, Val64,
-    i128, Val128, u128, Val128,
+    u8 => Val8, false,
+    u16 => Val16, false,
+    u32 => Val32, false,
+    u64 => Val64, false,
+    u128 => Val128, false,
+    i8 => Val8, true,
+    i16 => Val16, true,
+    i32 => Val32, true,
+    i64 => Val64, true,
+    i128 => Val128, true,
+    Uint64 => Val64, false,
+    Uint128 => Val128, false,
+    Uint256 => Val256, false,
+    Uint512 => Val512, false,
+    Int64 => Val64, true,
+    Int128 => Val128, true,
+    Int256 => Val256, true,
+    Int512 => Val512, true,
 );
 
+// decimals are encoded the same way as their numerators.
+macro_rules! impl_decimal_map_key {
+    ($($t:ty => $v:ident, $signed:literal),+ $(,)?) => {
+        $(impl MapKey for $t {
+            type Prefix = ();
+            type Suffix = ();
+            type Output = $t;
+
+            fn raw_keys(&self) -> Vec<RawKey> {
+                let mut bytes = self.numerator().to_be_bytes();
+                if $signed {
+                    bytes[0] ^= 0x80;
+                }
+                vec![RawKey::$v(bytes)]
+            }
+
+            fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
+                let Ok(mut bytes) = <[u8; mem::size_of::<Self>()]>::try_from(bytes) else {
+                    return Err(StdError::deserialize::<Self::Output>(format!(
+                        "wrong number of bytes: expecting {}, got {}",
+                        mem::size_of::<Self>(),
+                        bytes.len(),
+                    )));
+                };
+
+                if $signed {
+                    bytes[0] ^= 0x80;
+                }
+
+                Ok(Self::raw(Int::from_be_bytes(bytes)))
+            }
+        })*
+    }
+}
+
+impl_decimal_map_key!(
+    Decimal128 => Val128, false,
+    Decimal256 => Val256, false,
+    SignedDecimal128 => Val128, true,
+    SignedDecimal256 => Val256, true,
+);
+
+impl MapKey for Timestamp {
+    type Output = Timestamp;
+    type Prefix = ();
+    type Suffix = ();
+
+    fn raw_keys(&self) -> Vec<RawKey> {
+        vec![RawKey::Val64(self.nanos().to_be_bytes())]
+    }
+
+    fn deserialize(bytes: &[u8]) -> StdResult<Self::Output> {
+        u64::deserialize(bytes).map(Timestamp::from_nanos)
+    }
+}
+
 impl<A, B> MapKey for (A, B)
 where
     A: MapKey,
@@ -216,3 +368,108 @@ where
         Ok((a, b, c))
     }
 }
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {super::*, proptest::prelude::*, std::fmt::Debug};
+
+    /// Serializing two keys, the order of the bytes must match the order of
+    /// the keys; and each key must deserialize back to itself.
+    fn assert_order_preserved<K>(a: K, b: K) -> Result<(), TestCaseError>
+    where
+        K: MapKey<Output = K> + Ord + Debug,
+    {
+        let a_raw = a.serialize();
+        let b_raw = b.serialize();
+        prop_assert_eq!(a_raw.cmp(&b_raw), a.cmp(&b));
+        prop_assert_eq!(K::deserialize(&a_raw).unwrap(), a);
+        prop_assert_eq!(K::deserialize(&b_raw).unwrap(), b);
+        Ok(())
+    }
+
+    fn uint512(bytes: ([u8; 32], [u8; 32])) -> Uint512 {
+        Uint512::from_be_bytes([bytes.0, bytes.1].concat().try_into().unwrap())
+    }
+
+    fn int512(bytes: ([u8; 32], [u8; 32])) -> Int512 {
+        Int512::from_be_bytes([bytes.0, bytes.1].concat().try_into().unwrap())
+    }
+
+    proptest! {
+        #[test]
+        fn std_integers_preserve_order(
+            a in any::<(u8, u16, u32, u64, u128)>(),
+            b in any::<(u8, u16, u32, u64, u128)>(),
+            c in any::<(i8, i16, i32, i64, i128)>(),
+            d in any::<(i8, i16, i32, i64, i128)>(),
+        ) {
+            assert_order_preserved(a.0, b.0)?;
+            assert_order_preserved(a.1, b.1)?;
+            assert_order_preserved(a.2, b.2)?;
+            assert_order_preserved(a.3, b.3)?;
+            assert_order_preserved(a.4, b.4)?;
+            assert_order_preserved(c.0, d.0)?;
+            assert_order_preserved(c.1, d.1)?;
+            assert_order_preserved(c.2, d.2)?;
+            assert_order_preserved(c.3, d.3)?;
+            assert_order_preserved(c.4, d.4)?;
+        }
+
+        #[test]
+        fn math_integers_preserve_order(
+            a in any::<(u64, u128, [u8; 32], ([u8; 32], [u8; 32]))>(),
+            b in any::<(u64, u128, [u8; 32], ([u8; 32], [u8; 32]))>(),
+            c in any::<(i64, i128, [u8; 32], ([u8; 32], [u8; 32]))>(),
+            d in any::<(i64, i128, [u8; 32], ([u8; 32], [u8; 32]))>(),
+        ) {
+            assert_order_preserved(Uint64::new(a.0), Uint64::new(b.0))?;
+            assert_order_preserved(Uint128::new(a.1), Uint128::new(b.1))?;
+            assert_order_preserved(Uint256::from_be_bytes(a.2), Uint256::from_be_bytes(b.2))?;
+            assert_order_preserved(uint512(a.3), uint512(b.3))?;
+            assert_order_preserved(Int64::new(c.0), Int64::new(d.0))?;
+            assert_order_preserved(Int128::new(c.1), Int128::new(d.1))?;
+            assert_order_preserved(Int256::from_be_bytes(c.2), Int256::from_be_bytes(d.2))?;
+            assert_order_preserved(int512(c.3), int512(d.3))?;
+        }
+
+        #[test]
+        fn decimals_preserve_order(
+            a in any::<(u128, [u8; 32])>(),
+            b in any::<(u128, [u8; 32])>(),
+            c in any::<(i128, [u8; 32])>(),
+            d in any::<(i128, [u8; 32])>(),
+        ) {
+            assert_order_preserved(
+                Decimal128::raw(Uint128::new(a.0)),
+                Decimal128::raw(Uint128::new(b.0)),
+            )?;
+            assert_order_preserved(
+                Decimal256::raw(Uint256::from_be_bytes(a.1)),
+                Decimal256::raw(Uint256::from_be_bytes(b.1)),
+            )?;
+            assert_order_preserved(
+                SignedDecimal128::raw(Int128::new(c.0)),
+                SignedDecimal128::raw(Int128::new(d.0)),
+            )?;
+            assert_order_preserved(
+                SignedDecimal256::raw(Int256::from_be_bytes(c.1)),
+                SignedDecimal256::raw(Int256::from_be_bytes(d.1)),
+            )?;
+        }
+
+        #[test]
+        fn timestamps_preserve_order(a: u64, b: u64) {
+            assert_order_preserved(Timestamp::from_nanos(a), Timestamp::from_nanos(b))?;
+        }
+    }
+
+    #[test]
+    fn signed_integer_bounds() {
+        assert_eq!(i32::MIN.serialize(), [0x00, 0x00, 0x00, 0x00]);
+        assert_eq!((-1i32).serialize(), [0x7f, 0xff, 0xff, 0xff]);
+        assert_eq!(0i32.serialize(), [0x80, 0x00, 0x00, 0x00]);
+        assert_eq!(i32::MAX.serialize(), [0xff, 0xff, 0xff, 0xff]);
+    }
+}
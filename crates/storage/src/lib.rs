@@ -1,13 +1,19 @@
 mod bound;
+mod deque;
 mod encoding;
 mod incrementor;
+mod index;
+mod indexed_map;
 mod item;
 mod key;
 mod map;
 mod path;
 mod prefix;
+mod priority_queue;
 mod set;
+mod snapshot_map;
 
 pub use {
-    bound::*, encoding::*, incrementor::*, item::*, key::*, map::*, path::*, prefix::*, set::*,
+    bound::*, deque::*, encoding::*, incrementor::*, index::*, indexed_map::*, item::*, key::*,
+    map::*, path::*, prefix::*, priority_queue::*, set::*, snapshot_map::*,
 };
@@ -1,8 +1,6 @@
 use {
-    crate::{Borsh, Bound, Encoding, MapKey, PathBuf, Prefix, Proto},
-    borsh::{BorshDeserialize, BorshSerialize},
-    grug_types::{Order, StdError, StdResult, Storage},
-    prost::Message,
+    crate::{decode_value, Borsh, Bound, Codec, Encoding, MapKey, PathBuf, Prefix},
+    grug_types::{Order, Page, PageRequest, StdError, StdResult, Storage},
     std::marker::PhantomData,
 };
 
@@ -13,7 +11,10 @@ pub struct Map<'a, K, T, E: Encoding = Borsh> {
     encoding: PhantomData<E>,
 }
 
-impl<'a, K, T> Map<'a, K, T> {
+impl<'a, K, T, E> Map<'a, K, T, E>
+where
+    E: Encoding,
+{
     pub const fn new(namespace: &'a str) -> Self {
         // TODO: add a maximum length for namespace
         // see comments of increment_last_byte function for rationale
@@ -31,12 +32,42 @@ where
     K: MapKey,
     E: Encoding,
 {
-    fn path(&self, key: K) -> PathBuf<T, E> {
+    pub(crate) fn namespace(&self) -> &[u8] {
+        self.namespace
+    }
+
+    pub fn path(&self, key: K) -> PathBuf<T, E> {
+        self.path_ref(&key)
+    }
+
+    pub(crate) fn path_ref(&self, key: &K) -> PathBuf<T, E> {
         let mut raw_keys = key.raw_keys();
         let last_raw_key = raw_keys.pop();
         PathBuf::new(self.namespace, &raw_keys, last_raw_key.as_ref())
     }
 
+    /// The raw key under which the value of the given key is stored.
+    ///
+    /// Together with `decode`, this allows reading the map off-chain, e.g.
+    /// with a `WasmRaw` query.
+    pub fn storage_key(&self, k: K) -> Vec<u8> {
+        self.path(k).storage_key().to_vec()
+    }
+
+    /// The raw storage bounds for iterating the map. See `Prefix::storage_bounds`.
+    pub fn storage_bounds(
+        &self,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+    ) -> (Vec<u8>, Vec<u8>) {
+        self.no_prefix().storage_bounds(min, max)
+    }
+
+    /// Decode a key from its raw storage key. See `Prefix::decode_key`.
+    pub fn decode_key(&self, storage_key: &[u8]) -> StdResult<K::Output> {
+        self.no_prefix().decode_key(storage_key)
+    }
+
     fn no_prefix(&self) -> Prefix<K, T, E> {
         Prefix::new(self.namespace, &[])
     }
@@ -99,76 +130,36 @@ where
     ) {
         self.no_prefix().clear(storage, min, max, limit)
     }
-}
-
-// ----------------------------------- borsh -----------------------------------
-
-impl<'a, K, T> Map<'a, K, T, Borsh>
-where
-    K: MapKey,
-    T: BorshSerialize,
-{
-    pub fn save(&self, storage: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
-        self.path(k).as_path().save(storage, data)
-    }
-}
 
-impl<'a, K, T> Map<'a, K, T, Borsh>
-where
-    K: MapKey,
-    T: BorshDeserialize,
-{
-    pub fn may_load(&self, storage: &dyn Storage, k: K) -> StdResult<Option<T>> {
-        self.path(k).as_path().may_load(storage)
-    }
-
-    pub fn load(&self, storage: &dyn Storage, k: K) -> StdResult<T> {
-        self.path(k).as_path().load(storage)
-    }
-
-    #[allow(clippy::type_complexity)]
-    pub fn range<'b>(
+    /// Return a page of keys in ascending order. See `Prefix::paginate`.
+    pub fn paginate_keys(
         &self,
-        storage: &'b dyn Storage,
-        min: Option<Bound<K>>,
-        max: Option<Bound<K>>,
-        order: Order,
-    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'b> {
-        self.no_prefix().range(storage, min, max, order)
+        storage: &dyn Storage,
+        req: PageRequest,
+    ) -> StdResult<Page<K::Output>> {
+        self.no_prefix().paginate_keys(storage, req)
     }
 }
 
-impl<'a, K, T> Map<'a, K, T, Borsh>
+impl<'a, K, T, E> Map<'a, K, T, E>
 where
     K: MapKey,
-    T: BorshSerialize + BorshDeserialize,
+    E: Codec<T>,
 {
-    pub fn update<A, E>(&self, storage: &mut dyn Storage, k: K, action: A) -> Result<Option<T>, E>
-    where
-        A: FnOnce(Option<T>) -> Result<Option<T>, E>,
-        E: From<StdError>,
-    {
-        self.path(k).as_path().update(storage, action)
+    /// Decode a value from the raw bytes stored under one of the map's keys.
+    pub fn decode(&self, bytes: &[u8]) -> StdResult<T> {
+        decode_value::<T, E>(self.namespace, bytes)
     }
-}
 
-// ----------------------------------- proto -----------------------------------
+    /// Decode a raw record in the map. See `Prefix::decode_record`.
+    pub fn decode_record(&self, storage_key: &[u8], bytes: &[u8]) -> StdResult<(K::Output, T)> {
+        self.no_prefix().decode_record(storage_key, bytes)
+    }
 
-impl<'a, K, T> Map<'a, K, T, Proto>
-where
-    K: MapKey,
-    T: Message,
-{
-    pub fn save(&self, storage: &mut dyn Storage, k: K, data: &T) {
+    pub fn save(&self, storage: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
         self.path(k).as_path().save(storage, data)
     }
-}
 
-impl<'a, K, T> Map<'a, K, T, Proto>
-where
-    K: MapKey,
-    T: Message + Default,
-{
     pub fn may_load(&self, storage: &dyn Storage, k: K) -> StdResult<Option<T>> {
         self.path(k).as_path().may_load(storage)
     }
@@ -177,10 +168,15 @@ where
         self.path(k).as_path().load(storage)
     }
 
-    pub fn update<A, E>(&self, storage: &mut dyn Storage, k: K, action: A) -> Result<Option<T>, E>
+    pub fn update<A, Error>(
+        &self,
+        storage: &mut dyn Storage,
+        k: K,
+        action: A,
+    ) -> Result<Option<T>, Error>
     where
-        A: FnOnce(Option<T>) -> Result<Option<T>, E>,
-        E: From<StdError>,
+        A: FnOnce(Option<T>) -> Result<Option<T>, Error>,
+        Error: From<StdError>,
     {
         self.path(k).as_path().update(storage, action)
     }
@@ -195,4 +191,13 @@ where
     ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'b> {
         self.no_prefix().range(storage, min, max, order)
     }
+
+    /// Return a page of records in ascending order. See `Prefix::paginate`.
+    pub fn paginate(
+        &self,
+        storage: &dyn Storage,
+        req: PageRequest,
+    ) -> StdResult<Page<(K::Output, T)>> {
+        self.no_prefix().paginate(storage, req)
+    }
 }
@@ -1,16 +1,12 @@
 use {
-    crate::{Borsh, Encoding, Proto, RawKey},
-    borsh::{BorshDeserialize, BorshSerialize},
-    grug_types::{
-        from_borsh_slice, from_proto_slice, nested_namespaces_with_key, to_borsh_vec, to_proto_vec,
-        StdError, StdResult, Storage,
-    },
-    prost::Message,
+    crate::{decode_value, Borsh, Codec, Encoding, RawKey},
+    grug_types::{nested_namespaces_with_key, StdError, StdResult, Storage},
     std::marker::PhantomData,
 };
 
 pub struct PathBuf<T, E: Encoding = Borsh> {
     storage_key: Vec<u8>,
+    namespace_len: usize,
     data: PhantomData<T>,
     encoding: PhantomData<E>,
 }
@@ -22,14 +18,23 @@ where
     pub fn new(namespace: &[u8], prefixes: &[RawKey], maybe_key: Option<&RawKey>) -> Self {
         Self {
             storage_key: nested_namespaces_with_key(Some(namespace), prefixes, maybe_key),
+            namespace_len: namespace.len(),
             data: PhantomData,
             encoding: PhantomData,
         }
     }
 
+    /// The full, raw key under which the data is stored.
+    pub fn storage_key(&self) -> &[u8] {
+        self.storage_key.as_slice()
+    }
+
     pub fn as_path(&self) -> Path<'_, T, E> {
         Path {
             storage_key: self.storage_key.as_slice(),
+            // the storage key starts with the namespace, prefixed by its
+            // length in two bytes
+            namespace: &self.storage_key[2..2 + self.namespace_len],
             data: self.data,
             encoding: self.encoding,
         }
@@ -38,6 +43,7 @@ where
 
 pub struct Path<'a, T, E: Encoding = Borsh> {
     storage_key: &'a [u8],
+    namespace: &'a [u8],
     data: PhantomData<T>,
     encoding: PhantomData<E>,
 }
@@ -46,14 +52,20 @@ impl<'a, T, E> Path<'a, T, E>
 where
     E: Encoding,
 {
-    pub(crate) fn from_raw(storage_key: &'a [u8]) -> Self {
+    pub(crate) fn from_raw(namespace: &'a [u8], storage_key: &'a [u8]) -> Self {
         Self {
             storage_key,
+            namespace,
             data: PhantomData,
             encoding: PhantomData,
         }
     }
 
+    /// The full, raw key under which the data is stored.
+    pub fn storage_key(&self) -> &[u8] {
+        self.storage_key
+    }
+
     pub fn exists(&self, storage: &dyn Storage) -> bool {
         storage.read(self.storage_key).is_some()
     }
@@ -63,27 +75,20 @@ where
     }
 }
 
-// ----------------------------------- borsh -----------------------------------
-
-impl<'a, T> Path<'a, T, Borsh>
+impl<'a, T, E> Path<'a, T, E>
 where
-    T: BorshSerialize,
+    E: Codec<T>,
 {
     pub fn save(&self, storage: &mut dyn Storage, data: &T) -> StdResult<()> {
-        let bytes = to_borsh_vec(data)?;
+        let bytes = E::encode(data)?;
         storage.write(self.storage_key, &bytes);
         Ok(())
     }
-}
 
-impl<'a, T> Path<'a, T, Borsh>
-where
-    T: BorshDeserialize,
-{
     pub fn may_load(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
         storage
             .read(self.storage_key)
-            .map(from_borsh_slice)
+            .map(|bytes| decode_value::<T, E>(self.namespace, &bytes))
             .transpose()
     }
 
@@ -91,20 +96,15 @@ where
         storage
             .read(self.storage_key)
             .ok_or_else(|| StdError::data_not_found::<T>(self.storage_key))
-            .and_then(from_borsh_slice)
+            .and_then(|bytes| decode_value::<T, E>(self.namespace, &bytes))
     }
-}
 
-impl<'a, T> Path<'a, T, Borsh>
-where
-    T: BorshSerialize + BorshDeserialize,
-{
     // compared to the original cosmwasm, we require `action` to return an
     // option, which in case of None leads to the record being deleted.
-    pub fn update<A, E>(&self, storage: &mut dyn Storage, action: A) -> Result<Option<T>, E>
+    pub fn update<A, Error>(&self, storage: &mut dyn Storage, action: A) -> Result<Option<T>, Error>
     where
-        A: FnOnce(Option<T>) -> Result<Option<T>, E>,
-        E: From<StdError>,
+        A: FnOnce(Option<T>) -> Result<Option<T>, Error>,
+        Error: From<StdError>,
     {
         let maybe_data = action(self.may_load(storage)?)?;
 
@@ -117,54 +117,3 @@ where
         Ok(maybe_data)
     }
 }
-
-// ----------------------------------- proto -----------------------------------
-
-impl<'a, T> Path<'a, T, Proto>
-where
-    T: Message,
-{
-    // Note that for Protobuf, this function doesn't need to return a Result,
-    // because proto serialization always succeeds.
-    pub fn save(&self, storage: &mut dyn Storage, data: &T) {
-        let bytes = to_proto_vec(data);
-        storage.write(self.storage_key, &bytes);
-    }
-}
-
-impl<'a, T> Path<'a, T, Proto>
-where
-    T: Message + Default,
-{
-    pub fn may_load(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
-        storage
-            .read(self.storage_key)
-            .map(from_proto_slice)
-            .transpose()
-    }
-
-    pub fn load(&self, storage: &dyn Storage) -> StdResult<T> {
-        storage
-            .read(self.storage_key)
-            .ok_or_else(|| StdError::data_not_found::<T>(self.storage_key))
-            .and_then(from_proto_slice)
-    }
-
-    // compared to the original cosmwasm, we require `action` to return an
-    // option, which in case of None leads to the record being deleted.
-    pub fn update<A, E>(&self, storage: &mut dyn Storage, action: A) -> Result<Option<T>, E>
-    where
-        A: FnOnce(Option<T>) -> Result<Option<T>, E>,
-        E: From<StdError>,
-    {
-        let maybe_data = action(self.may_load(storage)?)?;
-
-        if let Some(data) = &maybe_data {
-            self.save(storage, data);
-        } else {
-            self.remove(storage);
-        }
-
-        Ok(maybe_data)
-    }
-}
@@ -1,16 +1,15 @@
 use {
-    crate::{Borsh, Bound, Encoding, MapKey, Proto, RawBound, RawKey},
-    borsh::BorshDeserialize,
+    crate::{decode_value, Borsh, Bound, Codec, Encoding, MapKey, RawBound, RawKey},
     grug_types::{
-        concat, extend_one_byte, from_borsh_slice, from_proto_slice, increment_last_byte,
-        nested_namespaces_with_key, trim, Order, StdResult, Storage,
+        concat, extend_one_byte, increment_last_byte, nested_namespaces_with_key, trim, Binary,
+        Order, Page, PageRequest, StdError, StdResult, Storage, MAX_COUNT_TOTAL,
     },
-    prost::Message,
     std::marker::PhantomData,
 };
 
 pub struct Prefix<K, T, E: Encoding = Borsh> {
     prefix: Vec<u8>,
+    namespace_len: usize,
     suffix: PhantomData<K>,
     data: PhantomData<T>,
     encoding: PhantomData<E>,
@@ -23,11 +22,18 @@ where
     pub fn new(namespace: &[u8], prefixes: &[RawKey]) -> Self {
         Self {
             prefix: nested_namespaces_with_key(Some(namespace), prefixes, <Option<&RawKey>>::None),
+            namespace_len: namespace.len(),
             suffix: PhantomData,
             data: PhantomData,
             encoding: PhantomData,
         }
     }
+
+    fn namespace(&self) -> &[u8] {
+        // the prefix starts with the namespace, prefixed by its length in two
+        // bytes
+        &self.prefix[2..2 + self.namespace_len]
+    }
 }
 
 impl<K, T, E> Prefix<K, T, E>
@@ -35,6 +41,33 @@ where
     K: MapKey,
     E: Encoding,
 {
+    /// The raw storage keys between which the records under the given bounds
+    /// are stored. The min bound is inclusive and the max bound exclusive.
+    ///
+    /// This allows iterating the records off-chain, e.g. with a `WasmRawRange`
+    /// query, and decoding them with `decode_record`.
+    pub fn storage_bounds(
+        &self,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+    ) -> (Vec<u8>, Vec<u8>) {
+        range_bounds(&self.prefix, min, max)
+    }
+
+    /// Decode a key from the full, raw storage key of its record.
+    pub fn decode_key(&self, storage_key: &[u8]) -> StdResult<K::Output> {
+        let key_raw = storage_key
+            .strip_prefix(self.prefix.as_slice())
+            .ok_or_else(|| {
+                StdError::deserialize::<K::Output>(format!(
+                    "storage key is not under namespace `{}`",
+                    String::from_utf8_lossy(self.namespace())
+                ))
+            })?;
+
+        K::deserialize(key_raw)
+    }
+
     #[allow(clippy::type_complexity)]
     pub fn range_raw<'a>(
         &self,
@@ -97,21 +130,90 @@ where
         Box::new(iter)
     }
 
+    /// Remove all records under the given bounds, or if a limit is given, up
+    /// to that many records, starting from the smallest key.
     pub fn clear(
         &self,
-        _storage: &mut dyn Storage,
-        _min: Option<Bound<K>>,
-        _max: Option<Bound<K>>,
-        _limit: Option<usize>,
+        storage: &mut dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        limit: Option<usize>,
     ) {
-        todo!() // TODO: implement this after we're added a `remove_range` method to Storage
+        let (min, mut max) = range_bounds(&self.prefix, min, max);
+
+        // if there's a limit, find the first key that is not to be removed, and
+        // use it as the max bound.
+        if let Some(limit) = limit {
+            if let Some((key, _)) = storage
+                .scan(Some(&min), Some(&max), Order::Ascending)
+                .nth(limit)
+            {
+                max = key;
+            }
+        }
+
+        storage.remove_range(Some(&min), Some(&max));
+    }
+
+    /// Same as `paginate`, but only return the keys.
+    pub fn paginate_keys(
+        &self,
+        storage: &dyn Storage,
+        req: PageRequest,
+    ) -> StdResult<Page<K::Output>> {
+        let page = self.paginate_raw(storage, req);
+        let items = page
+            .items
+            .into_iter()
+            .map(|(key_raw, _)| K::deserialize(&key_raw))
+            .collect::<StdResult<_>>()?;
+
+        Ok(Page {
+            items,
+            next: page.next,
+            total: page.total,
+        })
+    }
+
+    fn paginate_raw(&self, storage: &dyn Storage, req: PageRequest) -> Page<(Vec<u8>, Vec<u8>)> {
+        let limit = req.effective_limit() as usize;
+        let min = req.cursor.map(|cursor| Bound::ExclusiveRaw(cursor.into()));
+
+        // count at most `MAX_COUNT_TOTAL` items, so that the cost of the query
+        // is bounded; if there are more, the total is left unknown
+        let total = req
+            .count_total
+            .then(|| {
+                self.keys_raw(storage, None, None, Order::Ascending)
+                    .take(MAX_COUNT_TOTAL as usize + 1)
+                    .count() as u64
+            })
+            .filter(|total| *total <= MAX_COUNT_TOTAL);
+
+        // fetch one more record than the limit, to find out whether there are
+        // more pages
+        let mut items = self
+            .range_raw(storage, min, None, Order::Ascending)
+            .take(limit + 1)
+            .collect::<Vec<_>>();
+
+        let next = if items.len() > limit {
+            items.truncate(limit);
+            items
+                .last()
+                .map(|(key_raw, _)| Binary::from(key_raw.clone()))
+        } else {
+            None
+        };
+
+        Page { items, next, total }
     }
 }
 
-impl<K, T> Prefix<K, T, Borsh>
+impl<K, T, E> Prefix<K, T, E>
 where
     K: MapKey,
-    T: BorshDeserialize,
+    E: Codec<T>,
 {
     #[allow(clippy::type_complexity)]
     pub fn range<'a>(
@@ -121,40 +223,53 @@ where
         max: Option<Bound<K>>,
         order: Order,
     ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'a> {
+        let namespace = self.namespace().to_vec();
         let iter = self
             .range_raw(storage, min, max, order)
-            .map(|(key_raw, value_raw)| {
+            .map(move |(key_raw, value_raw)| {
                 let key = K::deserialize(&key_raw)?;
-                let value = from_borsh_slice(value_raw)?;
+                let value = decode_value::<T, E>(&namespace, &value_raw)?;
                 Ok((key, value))
             });
 
         Box::new(iter)
     }
-}
 
-impl<K, T> Prefix<K, T, Proto>
-where
-    K: MapKey,
-    T: Message + Default,
-{
-    #[allow(clippy::type_complexity)]
-    pub fn range<'a>(
+    /// Decode a record from its full, raw storage key and value.
+    pub fn decode_record(&self, storage_key: &[u8], bytes: &[u8]) -> StdResult<(K::Output, T)> {
+        let key = self.decode_key(storage_key)?;
+        let value = decode_value::<T, E>(self.namespace(), bytes)?;
+
+        Ok((key, value))
+    }
+
+    /// Return a page of records in ascending order, starting after the cursor
+    /// in the request, if any.
+    ///
+    /// The cursor is the raw key of the last record in the page. It's only
+    /// returned if there are more records after it.
+    pub fn paginate(
         &self,
-        storage: &'a dyn Storage,
-        min: Option<Bound<K>>,
-        max: Option<Bound<K>>,
-        order: Order,
-    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'a> {
-        let iter = self
-            .range_raw(storage, min, max, order)
+        storage: &dyn Storage,
+        req: PageRequest,
+    ) -> StdResult<Page<(K::Output, T)>> {
+        let namespace = self.namespace();
+        let page = self.paginate_raw(storage, req);
+        let items = page
+            .items
+            .into_iter()
             .map(|(key_raw, value_raw)| {
                 let key = K::deserialize(&key_raw)?;
-                let value = from_proto_slice(value_raw)?;
+                let value = decode_value::<T, E>(namespace, &value_raw)?;
                 Ok((key, value))
-            });
+            })
+            .collect::<StdResult<_>>()?;
 
-        Box::new(iter)
+        Ok(Page {
+            items,
+            next: page.next,
+            total: page.total,
+        })
     }
 }
 
@@ -176,3 +291,145 @@ fn range_bounds<K: MapKey>(
 
     (min, max)
 }
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{Bound, Map},
+        grug_types::{
+            MockStorage, Order, PageRequest, StdResult, Storage, MAX_COUNT_TOTAL, MAX_PAGE_LIMIT,
+        },
+    };
+
+    const FOOS: Map<(u8, u32), String> = Map::new("foo");
+
+    fn keys(storage: &dyn Storage) -> Vec<(u8, u32)> {
+        FOOS.keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn clearing_works() {
+        let mut storage = MockStorage::new();
+        for (a, b) in [(1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (2, 3), (3, 1)] {
+            FOOS.save(&mut storage, (a, b), &format!("{a}-{b}"))
+                .unwrap();
+        }
+
+        // clear with a limit, within a prefix
+        FOOS.prefix(2).clear(&mut storage, None, None, Some(2));
+        assert_eq!(keys(&storage), [(1, 1), (1, 2), (1, 3), (2, 3), (3, 1)]);
+
+        // clear with bounds
+        FOOS.clear(
+            &mut storage,
+            Some(Bound::Exclusive((1, 1))),
+            Some(Bound::Inclusive((2, 3))),
+            None,
+        );
+        assert_eq!(keys(&storage), [(1, 1), (3, 1)]);
+
+        // clear everything; records outside the map are untouched
+        storage.write(b"bar", b"bar");
+        FOOS.clear(&mut storage, None, None, None);
+        assert!(FOOS.is_empty(&storage));
+        assert_eq!(storage.read(b"bar"), Some(b"bar".to_vec()));
+    }
+
+    #[test]
+    fn paginating_works() {
+        let mut storage = MockStorage::new();
+        for (a, b) in [(1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (2, 3), (3, 1)] {
+            FOOS.save(&mut storage, (a, b), &format!("{a}-{b}"))
+                .unwrap();
+        }
+
+        // first page, counting the total
+        let req = PageRequest::new().with_limit(3).with_count_total();
+        let page = FOOS.paginate(&storage, req.clone()).unwrap();
+        assert_eq!(page.items, [
+            ((1, 1), "1-1".to_string()),
+            ((1, 2), "1-2".to_string()),
+            ((1, 3), "1-3".to_string()),
+        ]);
+        assert_eq!(page.total, Some(7));
+
+        // second page
+        let req = page.next_request(&req).unwrap();
+        let page = FOOS.paginate_keys(&storage, req.clone()).unwrap();
+        assert_eq!(page.items, [(2, 1), (2, 2), (2, 3)]);
+
+        // last page, which has fewer items than the limit, so no cursor
+        let req = page.next_request(&req).unwrap();
+        let page = FOOS.paginate_keys(&storage, req.clone()).unwrap();
+        assert_eq!(page.items, [(3, 1)]);
+        assert!(page.next.is_none());
+
+        // within a prefix, where the last page is exactly full
+        let req = PageRequest::new().with_limit(2);
+        let page = FOOS.prefix(2).paginate_keys(&storage, req.clone()).unwrap();
+        assert_eq!(page.items, [1, 2]);
+        assert_eq!(page.total, None);
+        let req = page.next_request(&req).unwrap();
+        let page = FOOS.prefix(2).paginate_keys(&storage, req).unwrap();
+        assert_eq!(page.items, [3]);
+        assert!(page.next.is_none());
+
+        // the limit is capped
+        for b in 0..MAX_PAGE_LIMIT * 2 {
+            FOOS.save(&mut storage, (4, b), &String::new()).unwrap();
+        }
+        let req = PageRequest::new().with_limit(MAX_PAGE_LIMIT * 2);
+        let page = FOOS.prefix(4).paginate_keys(&storage, req).unwrap();
+        assert_eq!(page.items.len(), MAX_PAGE_LIMIT as usize);
+        assert!(page.next.is_some());
+    }
+
+    #[test]
+    fn counting_total_is_bounded() {
+        let mut storage = MockStorage::new();
+        for b in 0..MAX_COUNT_TOTAL as u32 {
+            FOOS.save(&mut storage, (1, b), &String::new()).unwrap();
+        }
+
+        // exactly at the bound, the total is counted
+        let req = PageRequest::new().with_count_total();
+        let page = FOOS.paginate_keys(&storage, req.clone()).unwrap();
+        assert_eq!(page.total, Some(MAX_COUNT_TOTAL));
+
+        // past the bound, it's left unknown
+        FOOS.save(&mut storage, (2, 0), &String::new()).unwrap();
+        let page = FOOS.paginate_keys(&storage, req).unwrap();
+        assert_eq!(page.total, None);
+        assert!(page.next.is_some());
+    }
+
+    #[test]
+    fn decoding_raw_records_works() {
+        let mut storage = MockStorage::new();
+        for (a, b) in [(1, 1), (1, 2), (2, 1), (2, 2)] {
+            FOOS.save(&mut storage, (a, b), &format!("{a}-{b}"))
+                .unwrap();
+        }
+
+        // the storage key of an entry is the one it's stored under
+        let key = FOOS.storage_key((1, 2));
+        let value = storage.read(&key).unwrap();
+        assert_eq!(FOOS.decode(&value).unwrap(), "1-2");
+        assert_eq!(FOOS.decode_key(&key).unwrap(), (1, 2));
+
+        // scanning the raw bounds yields the same records as iterating
+        let (min, max) = FOOS.prefix(2).storage_bounds(None, None);
+        let records = storage
+            .scan(Some(&min), Some(&max), Order::Ascending)
+            .map(|(key, value)| FOOS.prefix(2).decode_record(&key, &value).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records, [(1, "2-1".to_string()), (2, "2-2".to_string())]);
+
+        // a record under another prefix can't be decoded
+        assert!(FOOS.prefix(2).decode_record(&key, &value).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use {
+    crate::{decode_value, Borsh, Bound, Codec, Encoding, MapKey, PathBuf, Prefix, RawKey},
+    grug_types::{concat, Order, StdError, StdResult, Storage},
+    std::marker::PhantomData,
+};
+
+/// A queue where elements are popped in the order of their keys, either the
+/// smallest or the biggest first.
+///
+/// Elements with equal keys are ordered by when they were pushed, i.e. they are
+/// popped by `pop_min` first-in-first-out, and by `pop_max` last-in-first-out.
+///
+/// Storage layout:
+///
+/// - a sequence number and the number of elements are stored together directly
+///   under the namespace;
+/// - each element is stored under the namespace with the serialized key plus a
+///   sequence number as the key, so that elements can share the same key. Same
+///   as in `Map`, the key's last element isn't prefixed by its length, so that
+///   elements are ordered by the key's value rather than its length.
+///
+/// Because the sequence number directly follows the key's last element, if
+/// that element is a byte string (e.g. `&str` or `&[u8]`), keys that only
+/// differ by trailing `0x00` bytes, such as `"a"` and `"a\0"`, aren't
+/// guaranteed to be ordered by their values.
+pub struct PriorityQueue<'a, K, T, E: Encoding = Borsh> {
+    namespace: &'a [u8],
+    key: PhantomData<K>,
+    data: PhantomData<T>,
+    encoding: PhantomData<E>,
+}
+
+impl<'a, K, T, E> PriorityQueue<'a, K, T, E>
+where
+    E: Encoding,
+{
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            namespace: namespace.as_bytes(),
+            key: PhantomData,
+            data: PhantomData,
+            encoding: PhantomData,
+        }
+    }
+
+    fn meta_path(&self) -> PathBuf<(u64, u64)> {
+        PathBuf::new(self.namespace, &[], <Option<&RawKey>>::None)
+    }
+
+    // returns the sequence number of the next element to be pushed, and the
+    // number of elements in the queue
+    fn load_meta(&self, storage: &dyn Storage) -> StdResult<(u64, u64)> {
+        self.meta_path()
+            .as_path()
+            .may_load(storage)
+            .map(Option::unwrap_or_default)
+    }
+
+    fn save_meta(&self, storage: &mut dyn Storage, seq: u64, len: u64) -> StdResult<()> {
+        self.meta_path().as_path().save(storage, &(seq, len))
+    }
+
+    pub fn len(&self, storage: &dyn Storage) -> StdResult<u64> {
+        self.load_meta(storage).map(|(_, len)| len)
+    }
+
+    pub fn is_empty(&self, storage: &dyn Storage) -> StdResult<bool> {
+        self.len(storage).map(|len| len == 0)
+    }
+}
+
+impl<'a, K, T, E> PriorityQueue<'a, K, T, E>
+where
+    K: MapKey,
+    E: Codec<T>,
+{
+    fn no_prefix(&self) -> Prefix<(K, u64), T, E> {
+        Prefix::new(self.namespace, &[])
+    }
+
+    pub fn push(&self, storage: &mut dyn Storage, key: K, data: &T) -> StdResult<()> {
+        let (seq, len) = self.load_meta(storage)?;
+
+        let raw_key = RawKey::Owned(concat(&key.serialize(), &seq.to_be_bytes()));
+        PathBuf::<T, E>::new(self.namespace, &[], Some(&raw_key))
+            .as_path()
+            .save(storage, data)?;
+
+        self.save_meta(storage, seq + 1, len + 1)
+    }
+
+    pub fn peek_min(&self, storage: &dyn Storage) -> StdResult<Option<(K::Output, T)>> {
+        self.peek(storage, Order::Ascending)
+    }
+
+    pub fn peek_max(&self, storage: &dyn Storage) -> StdResult<Option<(K::Output, T)>> {
+        self.peek(storage, Order::Descending)
+    }
+
+    pub fn pop_min(&self, storage: &mut dyn Storage) -> StdResult<Option<(K::Output, T)>> {
+        self.pop(storage, Order::Ascending)
+    }
+
+    pub fn pop_max(&self, storage: &mut dyn Storage) -> StdResult<Option<(K::Output, T)>> {
+        self.pop(storage, Order::Descending)
+    }
+
+    /// Iterate all elements in the order of their keys.
+    pub fn iter<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'b> {
+        let namespace = self.namespace.to_vec();
+        let iter = self
+            .range_raw(storage, order)
+            .map(move |(key_raw, value_raw)| {
+                decode_element::<K, T, E>(&namespace, &key_raw, &value_raw)
+            });
+
+        Box::new(iter)
+    }
+
+    // iterate all elements, skipping the metadata, which is stored directly
+    // under the namespace and thus comes before all elements.
+    fn range_raw<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'b> {
+        let min = Bound::ExclusiveRaw(vec![]);
+        self.no_prefix().range_raw(storage, Some(min), None, order)
+    }
+
+    fn peek(&self, storage: &dyn Storage, order: Order) -> StdResult<Option<(K::Output, T)>> {
+        self.range_raw(storage, order)
+            .next()
+            .map(|(key_raw, value_raw)| {
+                decode_element::<K, T, E>(self.namespace, &key_raw, &value_raw)
+            })
+            .transpose()
+    }
+
+    fn pop(&self, storage: &mut dyn Storage, order: Order) -> StdResult<Option<(K::Output, T)>> {
+        let Some((key_raw, value_raw)) = self.range_raw(storage, order).next() else {
+            return Ok(None);
+        };
+
+        let element = decode_element::<K, T, E>(self.namespace, &key_raw, &value_raw)?;
+
+        let prefix = PathBuf::<(), Borsh>::new(self.namespace, &[], <Option<&RawKey>>::None);
+        storage.remove(&concat(prefix.storage_key(), &key_raw));
+
+        let (seq, len) = self.load_meta(storage)?;
+        self.save_meta(storage, seq, len - 1)?;
+
+        Ok(Some(element))
+    }
+}
+
+// the raw key of an element is the serialized key followed by the 8-byte
+// sequence number, which we strip before deserializing the key.
+fn decode_element<K, T, E>(
+    namespace: &[u8],
+    key_raw: &[u8],
+    value_raw: &[u8],
+) -> StdResult<(K::Output, T)>
+where
+    K: MapKey,
+    E: Codec<T>,
+{
+    let key_len = key_raw.len().checked_sub(8).ok_or_else(|| {
+        StdError::deserialize::<K::Output>(format!(
+            "raw key of {} bytes is too short to contain a sequence number",
+            key_raw.len()
+        ))
+    })?;
+    let key = K::deserialize(&key_raw[..key_len])?;
+    let data = decode_value::<T, E>(namespace, value_raw)?;
+
+    Ok((key, data))
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {super::*, grug_types::MockStorage};
+
+    const QUEUE: PriorityQueue<(u32, &str), String> = PriorityQueue::new("pq");
+
+    fn push(storage: &mut dyn Storage, priority: u32, name: &str, data: &str) {
+        QUEUE
+            .push(storage, (priority, name), &data.to_string())
+            .unwrap();
+    }
+
+    fn pair(priority: u32, name: &str, data: &str) -> ((u32, String), String) {
+        ((priority, name.to_string()), data.to_string())
+    }
+
+    #[test]
+    fn priority_queue_works() {
+        let mut storage = MockStorage::new();
+
+        assert!(QUEUE.is_empty(&storage).unwrap());
+        assert_eq!(QUEUE.pop_min(&mut storage).unwrap(), None);
+
+        push(&mut storage, 2, "b", "1st");
+        push(&mut storage, 1, "z", "2nd");
+        push(&mut storage, 3, "a", "3rd");
+        push(&mut storage, 1, "z", "4th");
+        push(&mut storage, 2, "a", "5th");
+
+        assert_eq!(QUEUE.len(&storage).unwrap(), 5);
+        assert_eq!(
+            QUEUE
+                .iter(&storage, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap(),
+            [
+                pair(1, "z", "2nd"),
+                pair(1, "z", "4th"),
+                pair(2, "a", "5th"),
+                pair(2, "b", "1st"),
+                pair(3, "a", "3rd"),
+            ]
+        );
+
+        assert_eq!(QUEUE.peek_min(&storage).unwrap(), Some(pair(1, "z", "2nd")));
+        assert_eq!(QUEUE.peek_max(&storage).unwrap(), Some(pair(3, "a", "3rd")));
+
+        // elements with the same key are popped in the order they're pushed
+        assert_eq!(
+            QUEUE.pop_min(&mut storage).unwrap(),
+            Some(pair(1, "z", "2nd"))
+        );
+        assert_eq!(
+            QUEUE.pop_min(&mut storage).unwrap(),
+            Some(pair(1, "z", "4th"))
+        );
+        assert_eq!(
+            QUEUE.pop_max(&mut storage).unwrap(),
+            Some(pair(3, "a", "3rd"))
+        );
+        assert_eq!(
+            QUEUE.pop_max(&mut storage).unwrap(),
+            Some(pair(2, "b", "1st"))
+        );
+        assert_eq!(QUEUE.len(&storage).unwrap(), 1);
+
+        assert_eq!(
+            QUEUE.pop_max(&mut storage).unwrap(),
+            Some(pair(2, "a", "5th"))
+        );
+        assert_eq!(QUEUE.pop_max(&mut storage).unwrap(), None);
+        assert!(QUEUE.is_empty(&storage).unwrap());
+    }
+
+    #[test]
+    fn keys_of_different_lengths() {
+        const NAMES: PriorityQueue<&str, u32> = PriorityQueue::new("names");
+
+        let mut storage = MockStorage::new();
+
+        for (name, data) in [("b", 1), ("aa", 2), ("ab", 3), ("a", 4)] {
+            NAMES.push(&mut storage, name, &data).unwrap();
+        }
+
+        // elements are ordered by the key's value, not its length
+        assert_eq!(
+            NAMES
+                .iter(&storage, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap(),
+            [
+                ("a".to_string(), 4),
+                ("aa".to_string(), 2),
+                ("ab".to_string(), 3),
+                ("b".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            NAMES.pop_min(&mut storage).unwrap(),
+            Some(("a".to_string(), 4))
+        );
+        assert_eq!(
+            NAMES.pop_max(&mut storage).unwrap(),
+            Some(("b".to_string(), 1))
+        );
+
+        // same for the last element of composite keys
+        push(&mut storage, 1, "b", "1st");
+        push(&mut storage, 1, "aa", "2nd");
+        assert_eq!(
+            QUEUE.pop_min(&mut storage).unwrap(),
+            Some(pair(1, "aa", "2nd"))
+        );
+        assert_eq!(
+            QUEUE.pop_min(&mut storage).unwrap(),
+            Some(pair(1, "b", "1st"))
+        );
+    }
+
+    #[test]
+    fn malformed_elements_are_rejected() {
+        let mut storage = MockStorage::new();
+
+        // an element whose raw key is too short to contain a sequence number,
+        // e.g. written to the namespace by something other than the queue
+        let prefix = PathBuf::<(), Borsh>::new(b"pq", &[], <Option<&RawKey>>::None);
+        storage.write(&concat(prefix.storage_key(), b"foo"), b"bar");
+
+        assert!(QUEUE.pop_min(&mut storage).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use {
-    crate::{Borsh, Bound, MapKey, PathBuf, Prefix},
+    crate::{Borsh, Bound, Codec, Encoding, MapKey, PathBuf, Prefix},
     grug_types::{Empty, Order, StdResult, Storage},
     std::marker::PhantomData,
 };
@@ -7,38 +7,41 @@ use {
 /// Mimic the behavior of HashSet or BTreeSet.
 ///
 /// Internally, this is basicaly a `Map<T, Empty>`.
-///
-/// We explicitly use Borsh here, because there's no benefit using any other
-/// encoding scheme.
-pub struct Set<'a, T> {
+pub struct Set<'a, T, E: Encoding = Borsh> {
     namespace: &'a [u8],
     item: PhantomData<T>,
+    encoding: PhantomData<E>,
 }
 
-impl<'a, T> Set<'a, T> {
+impl<'a, T, E> Set<'a, T, E>
+where
+    E: Encoding,
+{
     pub const fn new(namespace: &'a str) -> Self {
         Self {
             namespace: namespace.as_bytes(),
             item: PhantomData,
+            encoding: PhantomData,
         }
     }
 }
 
-impl<'a, T> Set<'a, T>
+impl<'a, T, E> Set<'a, T, E>
 where
     T: MapKey,
+    E: Codec<Empty>,
 {
-    fn path(&self, item: T) -> PathBuf<Empty> {
+    fn path(&self, item: T) -> PathBuf<Empty, E> {
         let mut raw_keys = item.raw_keys();
         let last_raw_key = raw_keys.pop();
-        PathBuf::<Empty, Borsh>::new(self.namespace, &raw_keys, last_raw_key.as_ref())
+        PathBuf::new(self.namespace, &raw_keys, last_raw_key.as_ref())
     }
 
-    fn no_prefix(&self) -> Prefix<T, Empty> {
+    fn no_prefix(&self) -> Prefix<T, Empty, E> {
         Prefix::new(self.namespace, &[])
     }
 
-    pub fn prefix(&self, prefix: T::Prefix) -> Prefix<T::Suffix, Empty> {
+    pub fn prefix(&self, prefix: T::Prefix) -> Prefix<T::Suffix, Empty, E> {
         Prefix::new(self.namespace, &prefix.raw_keys())
     }
 
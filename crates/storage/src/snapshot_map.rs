@@ -0,0 +1,431 @@
+use {
+    crate::{decode_value, Borsh, Bound, Codec, Encoding, Map, MapKey, PathBuf, Prefix, RawKey},
+    grug_types::{BlockInfo, Order, StdError, StdResult, Storage},
+};
+
+/// Describes at which heights a `SnapshotMap` records the values it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Record the values at every height where they are changed. Values can be
+    /// queried at any height.
+    EveryBlock,
+    /// Only record the values at the heights that have been checkpointed with
+    /// `SnapshotMap::add_checkpoint`. Values can only be queried at these
+    /// heights.
+    Selected,
+}
+
+/// A `Map` that also keeps the historical values, such that it can be queried
+/// what a value was at a given block height.
+///
+/// Whenever a value is changed, the value prior to the change is recorded in a
+/// changelog, keyed by the map key and the block height. Only the first change
+/// in each block (or in case of `Strategy::Selected`, since each checkpoint) is
+/// recorded.
+///
+/// The writing methods take the block during which the change is made; the
+/// changelog is keyed by its height.
+pub struct SnapshotMap<'a, K, T, E: Encoding = Borsh> {
+    primary: Map<'a, K, T, E>,
+    checkpoints: Map<'a, u64, u32>,
+    changelog_namespace: &'a [u8],
+    strategy: Strategy,
+}
+
+impl<'a, K, T, E> SnapshotMap<'a, K, T, E>
+where
+    E: Encoding,
+{
+    pub const fn new(
+        pk_namespace: &'a str,
+        checkpoints_namespace: &'a str,
+        changelog_namespace: &'a str,
+        strategy: Strategy,
+    ) -> Self {
+        Self {
+            primary: Map::new(pk_namespace),
+            checkpoints: Map::new(checkpoints_namespace),
+            changelog_namespace: changelog_namespace.as_bytes(),
+            strategy,
+        }
+    }
+
+    /// Mark the given height as checkpointed. Only relevant for
+    /// `Strategy::Selected`.
+    ///
+    /// Checkpoints are counted, such that the same height can be checkpointed
+    /// multiple times, e.g. by multiple governance proposals, and is only
+    /// removed once all of them are removed.
+    pub fn add_checkpoint(&self, storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+        self.checkpoints
+            .update(storage, height, |count| -> StdResult<_> {
+                Ok(Some(count.unwrap_or(0) + 1))
+            })
+            .map(|_| ())
+    }
+
+    pub fn remove_checkpoint(&self, storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+        self.checkpoints
+            .update(storage, height, |count| -> StdResult<_> {
+                Ok(count
+                    .and_then(|count| count.checked_sub(1))
+                    .filter(|count| *count > 0))
+            })
+            .map(|_| ())
+    }
+}
+
+impl<'a, K, T, E> SnapshotMap<'a, K, T, E>
+where
+    K: MapKey,
+    E: Encoding,
+{
+    fn changelog(&self, k: &K) -> Prefix<u64, T, E> {
+        Prefix::new(self.changelog_namespace, &k.raw_keys())
+    }
+
+    fn changelog_path(&self, k: &K, height: u64) -> PathBuf<T, E> {
+        let height_raw = RawKey::Val64(height.to_be_bytes());
+        PathBuf::new(self.changelog_namespace, &k.raw_keys(), Some(&height_raw))
+    }
+
+    // whether a change made to the key at the given height should be recorded.
+    // this is the case if no change has been recorded since the current height,
+    // or the last checkpoint, depending on the strategy.
+    fn should_record(&self, storage: &dyn Storage, k: &K, height: u64) -> StdResult<bool> {
+        let since = match self.strategy {
+            Strategy::EveryBlock => height,
+            Strategy::Selected => {
+                let last_checkpoint = self
+                    .checkpoints
+                    .keys(storage, None, None, Order::Descending)
+                    .next()
+                    .transpose()?;
+                let Some(last_checkpoint) = last_checkpoint else {
+                    return Ok(false);
+                };
+                // the last checkpoint may be ahead of the current height, in
+                // which case the first change in every block is recorded until
+                // it's reached, as with `Strategy::EveryBlock`. that's more
+                // than the checkpoints strictly need, but saves us from looking
+                // up the last one at or before the current height.
+                last_checkpoint.min(height)
+            },
+        };
+
+        Ok(self
+            .changelog(k)
+            .keys_raw(
+                storage,
+                Some(Bound::Inclusive(since)),
+                None,
+                Order::Ascending,
+            )
+            .next()
+            .is_none())
+    }
+
+    pub fn prefix(&self, prefix: K::Prefix) -> Prefix<K::Suffix, T, E> {
+        self.primary.prefix(prefix)
+    }
+
+    pub fn is_empty(&self, storage: &dyn Storage) -> bool {
+        self.primary.is_empty(storage)
+    }
+
+    pub fn has(&self, storage: &dyn Storage, k: K) -> bool {
+        self.primary.has(storage, k)
+    }
+
+    pub fn keys<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'b> {
+        self.primary.keys(storage, min, max, order)
+    }
+}
+
+impl<'a, K, T, E> SnapshotMap<'a, K, T, E>
+where
+    K: MapKey,
+    E: Codec<T>,
+{
+    // the changelog entry is the value prior to the change, prefixed by a byte
+    // indicating whether the value existed.
+    fn write_changelog(&self, storage: &mut dyn Storage, k: &K, height: u64) -> StdResult<()> {
+        if !self.should_record(storage, k, height)? {
+            return Ok(());
+        }
+
+        let change = match self.primary.path_ref(k).as_path().may_load(storage)? {
+            Some(old_data) => [&[1], E::encode(&old_data)?.as_slice()].concat(),
+            None => vec![0],
+        };
+
+        storage.write(self.changelog_path(k, height).storage_key(), &change);
+
+        Ok(())
+    }
+
+    pub fn save(
+        &self,
+        storage: &mut dyn Storage,
+        k: K,
+        data: &T,
+        block: &BlockInfo,
+    ) -> StdResult<()> {
+        self.write_changelog(storage, &k, block.height.number())?;
+        self.primary.save(storage, k, data)
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage, k: K, block: &BlockInfo) -> StdResult<()> {
+        self.write_changelog(storage, &k, block.height.number())?;
+        self.primary.remove(storage, k);
+        Ok(())
+    }
+
+    pub fn update<A, Error>(
+        &self,
+        storage: &mut dyn Storage,
+        k: K,
+        block: &BlockInfo,
+        action: A,
+    ) -> Result<Option<T>, Error>
+    where
+        A: FnOnce(Option<T>) -> Result<Option<T>, Error>,
+        Error: From<StdError>,
+    {
+        let maybe_data = action(self.primary.path_ref(&k).as_path().may_load(storage)?)?;
+
+        if let Some(data) = &maybe_data {
+            self.save(storage, k, data, block)?;
+        } else {
+            self.remove(storage, k, block)?;
+        }
+
+        Ok(maybe_data)
+    }
+
+    pub fn may_load(&self, storage: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        self.primary.may_load(storage, k)
+    }
+
+    pub fn load(&self, storage: &dyn Storage, k: K) -> StdResult<T> {
+        self.primary.load(storage, k)
+    }
+
+    /// Load the value as it was at the beginning of the given height, i.e.
+    /// before any change made during that block.
+    ///
+    /// In case of `Strategy::Selected`, errors if the height isn't checkpointed.
+    pub fn may_load_at_height(
+        &self,
+        storage: &dyn Storage,
+        k: K,
+        height: u64,
+    ) -> StdResult<Option<T>> {
+        if self.strategy == Strategy::Selected && !self.checkpoints.has(storage, height) {
+            return Err(StdError::not_checkpointed(self.changelog_namespace, height));
+        }
+
+        // find the first change made at or after the height. the value prior
+        // to that change is the value at the height. if there isn't any such
+        // change, the value hasn't changed since, so simply load the current
+        // value.
+        let change = self
+            .changelog(&k)
+            .range_raw(
+                storage,
+                Some(Bound::Inclusive(height)),
+                None,
+                Order::Ascending,
+            )
+            .next();
+
+        match change {
+            Some((_, change)) => match change.split_first() {
+                Some((1, old_data)) => {
+                    decode_value::<T, E>(self.changelog_namespace, old_data).map(Some)
+                },
+                _ => Ok(None),
+            },
+            None => self.primary.may_load(storage, k),
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn range<'b>(
+        &self,
+        storage: &'b dyn Storage,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'b> {
+        self.primary.range(storage, min, max, order)
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        grug_types::{Hash, MockStorage, Timestamp, Uint64},
+    };
+
+    const EVERY: SnapshotMap<&str, u64> =
+        SnapshotMap::new("e", "e__checkpoints", "e__changelog", Strategy::EveryBlock);
+
+    const SELECTED: SnapshotMap<&str, u64> =
+        SnapshotMap::new("s", "s__checkpoints", "s__changelog", Strategy::Selected);
+
+    // at each height, the changes to apply; `None` means removing the value
+    const CHANGES: &[(u64, &str, Option<u64>)] = &[
+        (1, "alice", Some(10)),
+        (1, "bob", Some(20)),
+        (1, "alice", Some(11)),
+        (3, "alice", Some(12)),
+        (3, "bob", None),
+        (5, "bob", Some(21)),
+    ];
+
+    fn mock_block(height: u64) -> BlockInfo {
+        BlockInfo {
+            height: Uint64::new(height),
+            timestamp: Timestamp::from_seconds(height),
+            hash: Hash::ZERO,
+        }
+    }
+
+    fn apply_changes(storage: &mut dyn Storage, map: &SnapshotMap<&str, u64>) {
+        for (height, k, data) in CHANGES {
+            match data {
+                Some(data) => map.save(storage, k, data, &mock_block(*height)).unwrap(),
+                None => map.remove(storage, k, &mock_block(*height)).unwrap(),
+            }
+        }
+    }
+
+    #[test]
+    fn every_block_works() {
+        let mut storage = MockStorage::new();
+        apply_changes(&mut storage, &EVERY);
+
+        for (k, height, expect) in [
+            ("alice", 1, None),
+            ("alice", 2, Some(11)),
+            ("alice", 3, Some(11)),
+            ("alice", 4, Some(12)),
+            ("alice", 100, Some(12)),
+            ("bob", 1, None),
+            ("bob", 2, Some(20)),
+            ("bob", 4, None),
+            ("bob", 5, None),
+            ("bob", 6, Some(21)),
+        ] {
+            assert_eq!(
+                EVERY.may_load_at_height(&storage, k, height).unwrap(),
+                expect
+            );
+        }
+
+        assert_eq!(EVERY.load(&storage, "alice").unwrap(), 12);
+        assert_eq!(EVERY.load(&storage, "bob").unwrap(), 21);
+    }
+
+    #[test]
+    fn selected_works() {
+        let mut storage = MockStorage::new();
+        SELECTED.add_checkpoint(&mut storage, 2).unwrap();
+        SELECTED.add_checkpoint(&mut storage, 3).unwrap();
+        SELECTED.add_checkpoint(&mut storage, 3).unwrap();
+        SELECTED.remove_checkpoint(&mut storage, 3).unwrap();
+
+        // add the checkpoints as we go, as a contract would do
+        for (height, k, data) in CHANGES {
+            if *height == 5 {
+                SELECTED.add_checkpoint(&mut storage, 5).unwrap();
+            }
+            match data {
+                Some(data) => SELECTED
+                    .save(&mut storage, k, data, &mock_block(*height))
+                    .unwrap(),
+                None => SELECTED
+                    .remove(&mut storage, k, &mock_block(*height))
+                    .unwrap(),
+            }
+        }
+
+        for (k, height, expect) in [
+            ("alice", 2, Some(11)),
+            ("alice", 3, Some(11)),
+            ("alice", 5, Some(12)),
+            ("bob", 2, Some(20)),
+            ("bob", 3, Some(20)),
+            ("bob", 5, None),
+        ] {
+            assert_eq!(
+                SELECTED.may_load_at_height(&storage, k, height).unwrap(),
+                expect
+            );
+        }
+
+        // heights that aren't checkpointed can't be queried
+        assert!(matches!(
+            SELECTED.may_load_at_height(&storage, "alice", 4),
+            Err(StdError::NotCheckpointed { height: 4, .. })
+        ));
+
+        // once all checkpoints at a height are removed, it can't be queried
+        SELECTED.remove_checkpoint(&mut storage, 3).unwrap();
+        assert!(matches!(
+            SELECTED.may_load_at_height(&storage, "alice", 3),
+            Err(StdError::NotCheckpointed { height: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn selected_with_future_checkpoint_works() {
+        let mut storage = MockStorage::new();
+
+        // checkpoints are added ahead of time, e.g. by a governance proposal
+        SELECTED.add_checkpoint(&mut storage, 2).unwrap();
+        SELECTED.add_checkpoint(&mut storage, 10).unwrap();
+
+        // changes are made in several blocks before the last checkpoint, then
+        // at and after it
+        apply_changes(&mut storage, &SELECTED);
+        for height in [10, 12] {
+            SELECTED
+                .save(&mut storage, "alice", &(height + 3), &mock_block(height))
+                .unwrap();
+        }
+
+        // until the last checkpoint is reached, the first change in every block
+        // is recorded. after that, only the first change since the checkpoint.
+        let heights = SELECTED
+            .changelog(&"alice")
+            .keys(&storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(heights, [1, 3, 10]);
+
+        for (k, height, expect) in [
+            ("alice", 2, Some(11)),
+            ("alice", 10, Some(12)),
+            ("bob", 2, Some(20)),
+            ("bob", 10, Some(21)),
+        ] {
+            assert_eq!(
+                SELECTED.may_load_at_height(&storage, k, height).unwrap(),
+                expect
+            );
+        }
+
+        assert_eq!(SELECTED.load(&storage, "alice").unwrap(), 15);
+    }
+}
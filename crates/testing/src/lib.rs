@@ -1,9 +1,12 @@
 use {
-    grug_app::App,
+    grug_app::{App, AppError, Db},
     grug_db_memory::MemDb,
     grug_types::{BlockInfo, GenesisState, Hash, QueryRequest, QueryResponse, Timestamp, Uint64},
     grug_vm_rust::RustVm,
-    std::time::{SystemTime, UNIX_EPOCH},
+    std::{
+        path::Path,
+        time::{SystemTime, UNIX_EPOCH},
+    },
 };
 
 fn current_time() -> Timestamp {
@@ -16,8 +19,14 @@ fn current_time() -> Timestamp {
     Timestamp::from_nanos(nanos)
 }
 
-pub struct MockApp {
-    inner: App<MemDb, RustVm>,
+/// A wrapper over `App` with the Rust VM, for testing.
+///
+/// By default, it uses an in-memory database starting from an empty state, or
+/// from a fixture file (see `MockApp::from_fixture`). To run tests against the
+/// state of a live chain, use a `ForkDb` instead.
+pub struct MockApp<DB = MemDb> {
+    inner: App<DB, RustVm>,
+    db: DB,
 }
 
 // need to implement this to make clippy not complain
@@ -30,8 +39,30 @@ impl Default for MockApp {
 
 impl MockApp {
     pub fn new() -> Self {
+        Self::new_with_db(MemDb::new())
+    }
+
+    /// Start from a chain state previously saved by `MockApp::dump_fixture`,
+    /// instead of initializing the chain from genesis.
+    pub fn from_fixture(path: impl AsRef<Path>) -> Self {
+        Self::new_with_db(MemDb::load(path).unwrap())
+    }
+
+    /// Save the chain state to a file, to be loaded by `MockApp::from_fixture`.
+    pub fn dump_fixture(&self, path: impl AsRef<Path>) {
+        self.db.dump(path).unwrap();
+    }
+}
+
+impl<DB> MockApp<DB>
+where
+    DB: Db + Clone,
+    AppError: From<DB::Error>,
+{
+    pub fn new_with_db(db: DB) -> Self {
         Self {
-            inner: App::new(MemDb::new()),
+            inner: App::new(db.clone()),
+            db,
         }
     }
 
@@ -46,90 +77,13 @@ impl MockApp {
             .unwrap();
     }
 
-    pub fn query(&self, req: QueryRequest) -> QueryResponse {
-        self.inner.do_query_app(req, 0, false).unwrap()
-    }
-}
-
-// ----------------------------------- tests -----------------------------------
-
-#[cfg(test)]
-mod tests {
-    use {
-        super::*,
-        grug_types::{
-            hash, to_borsh_vec, to_json_value, Addr, Coins, Config, Empty, Message, Permission,
-            Permissions, Response, StdResult,
-        },
-        grug_vm_rust::{ContractWrapper, ExecuteFn, MigrateFn, QueryFn, ReceiveFn, ReplyFn},
-        grug_wasm::MutableCtx,
-        std::collections::BTreeSet,
-    };
-
-    fn bank_instantiate(_ctx: MutableCtx, _msg: Empty) -> StdResult<Response> {
-        Ok(Response::new().add_attribute("action", "bank_instantiate"))
+    /// The wrapped app, for calling its methods directly.
+    pub fn inner(&self) -> &App<DB, RustVm> {
+        &self.inner
     }
 
-    #[test]
-    fn init_chain_works() {
-        let mut app = MockApp::new();
-        let bank_contract = ContractWrapper::new(
-            Box::new(bank_instantiate),
-            None::<ExecuteFn>,
-            None::<MigrateFn>,
-            None::<ReceiveFn>,
-            None::<ReplyFn>,
-            None::<QueryFn>,
-        );
-        let bank_code = to_borsh_vec(&bank_contract).unwrap();
-        let bank_code_hash = hash(&bank_code);
-        let genesis_state = GenesisState {
-            config: Config {
-                owner: None,
-                bank: Addr::mock(1),
-                begin_blockers: vec![],
-                end_blockers: vec![],
-                permissions: Permissions {
-                    upload: Permission::Everybody,
-                    instantiate: Permission::Everybody,
-                    create_client: Permission::Everybody,
-                    create_connection: Permission::Everybody,
-                    create_channel: Permission::Everybody,
-                },
-                allowed_clients: BTreeSet::new(),
-            },
-            msgs: vec![
-                Message::Upload {
-                    code: bank_code.into(),
-                },
-                Message::Instantiate {
-                    code_hash: bank_code_hash,
-                    msg: to_json_value(&Empty {}).unwrap(),
-                    salt: b"bank".to_vec().into(),
-                    funds: Coins::new_empty(),
-                    admin: None,
-                },
-            ],
-        };
-        app.init_chain("dev-1", genesis_state);
-
-        let info = app.query(QueryRequest::Info {}).as_info();
-        dbg!(&info);
-
-        let code_hashes = app
-            .query(QueryRequest::Codes {
-                start_after: None,
-                limit: None,
-            })
-            .as_codes();
-        dbg!(&code_hashes);
-
-        let accounts = app
-            .query(QueryRequest::Accounts {
-                start_after: None,
-                limit: None,
-            })
-            .as_accounts();
-        dbg!(&accounts);
+    pub fn query(&self, req: QueryRequest) -> QueryResponse {
+        let (res, _) = self.inner.do_query_app(req, 0, false).unwrap();
+        res.expect("response is only empty when a proof is requested")
     }
 }
@@ -0,0 +1,77 @@
+// each test file compiles this module separately, and none of them uses all of
+// the helpers
+#![allow(dead_code)]
+
+use {
+    grug_types::{
+        hash, to_borsh_vec, to_json_value, Addr, BlockInfo, Coins, Config, Empty, GenesisState,
+        Hash, Message, Permission, Permissions, Response, StdResult, Timestamp, Uint64,
+        GENESIS_SENDER,
+    },
+    grug_vm_rust::{
+        ContractWrapper, ExecuteFn, InstantiateFn, MigrateFn, QueryFn, ReceiveFn, ReplyFn,
+    },
+    grug_wasm::MutableCtx,
+    std::collections::BTreeSet,
+};
+
+pub fn mock_block(height: u64) -> BlockInfo {
+    BlockInfo {
+        height: Uint64::new(height),
+        timestamp: Timestamp::from_seconds(height),
+        hash: Hash::ZERO,
+    }
+}
+
+/// Messages that upload a contract with only an instantiate entry point, and
+/// instantiate it from the genesis sender, along with the contract's address.
+pub fn upload_and_instantiate(instantiate_fn: InstantiateFn, salt: &[u8]) -> ([Message; 2], Addr) {
+    let code = to_borsh_vec(&ContractWrapper::new(
+        instantiate_fn,
+        None::<ExecuteFn>,
+        None::<MigrateFn>,
+        None::<ReceiveFn>,
+        None::<ReplyFn>,
+        None::<QueryFn>,
+    ))
+    .unwrap();
+    let code_hash = hash(&code);
+    let salt = salt.to_vec().into();
+    let address = Addr::compute(&GENESIS_SENDER, &code_hash, &salt);
+    let msgs = [
+        Message::Upload { code: code.into() },
+        Message::Instantiate {
+            code_hash,
+            msg: to_json_value(&Empty {}).unwrap(),
+            salt,
+            funds: Coins::new_empty(),
+            admin: None,
+        },
+    ];
+    (msgs, address)
+}
+
+fn bank_instantiate(_ctx: MutableCtx, _msg: Empty) -> StdResult<Response> {
+    Ok(Response::new().add_attribute("action", "bank_instantiate"))
+}
+
+pub fn mock_genesis_state() -> GenesisState {
+    let (msgs, _) = upload_and_instantiate(Box::new(bank_instantiate), b"bank");
+    GenesisState {
+        config: Config {
+            owner: None,
+            bank: Addr::mock(1),
+            begin_blockers: vec![],
+            end_blockers: vec![],
+            permissions: Permissions {
+                upload: Permission::Everybody,
+                instantiate: Permission::Everybody,
+                create_client: Permission::Everybody,
+                create_connection: Permission::Everybody,
+                create_channel: Permission::Everybody,
+            },
+            allowed_clients: BTreeSet::new(),
+        },
+        msgs: msgs.into(),
+    }
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use {
+    common::{mock_block, mock_genesis_state, upload_and_instantiate},
+    grug_app::{export_state, App, AppError, CHAIN_ID, CONFIG, LAST_FINALIZED_BLOCK},
+    grug_db_memory::MemDb,
+    grug_testing::MockApp,
+    grug_types::{
+        to_json_vec, Empty, Message, MockStorage, Response, StdResult, Storage, Uint64,
+        GENESIS_SENDER,
+    },
+    grug_vm_rust::RustVm,
+    grug_wasm::MutableCtx,
+};
+
+fn counter_instantiate(ctx: MutableCtx, _msg: Empty) -> StdResult<Response> {
+    ctx.storage.write(b"counter", &0u32.to_be_bytes());
+    Ok(Response::new())
+}
+
+#[test]
+fn export_and_import_state_works() {
+    // in addition to the bank, instantiate a contract that writes to its
+    // storage, so that we have some contract storage to export
+    let (counter_msgs, _) = upload_and_instantiate(Box::new(counter_instantiate), b"counter");
+    let mut genesis_state = mock_genesis_state();
+    genesis_state.config.owner = Some(GENESIS_SENDER);
+    genesis_state.msgs.extend(counter_msgs);
+    genesis_state.msgs.push(Message::ScheduleUpgrade {
+        height: Uint64::new(100),
+        name: "v2".into(),
+        info: None,
+    });
+
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", genesis_state);
+
+    let exported = app.inner().export_state(None).unwrap();
+    assert_eq!(exported.chain_id, "dev-1");
+    assert_eq!(exported.height, Uint64::new(0));
+    assert_eq!(exported.codes.len(), 2);
+    assert_eq!(exported.accounts.len(), 2);
+    assert_eq!(exported.contract_storages.len(), 1);
+    assert_eq!(exported.next_upgrade.as_ref().unwrap().name, "v2");
+
+    // initialize a new chain from the exported state (JSON-encoded, as it
+    // would be in CometBFT's genesis file), then export it again. the two
+    // exports should be identical, except for the chain ID.
+    let new_app = App::<_, RustVm>::new(MemDb::new());
+    new_app
+        .do_init_chain_raw(
+            "dev-2".into(),
+            mock_block(0),
+            &to_json_vec(&exported).unwrap(),
+        )
+        .unwrap();
+
+    let reexported = new_app.export_state(None).unwrap();
+    assert_eq!(reexported.chain_id, "dev-2");
+    assert_eq!(reexported.config, exported.config);
+    assert_eq!(reexported.codes, exported.codes);
+    assert_eq!(reexported.accounts, exported.accounts);
+    assert_eq!(reexported.contract_storages, exported.contract_storages);
+    assert_eq!(reexported.next_upgrade, exported.next_upgrade);
+
+    // the next upgrade is rescheduled relative to the new chain's genesis.
+    // had the state been exported at height 90, the upgrade at height 100
+    // would take place 10 blocks after genesis.
+    let mut exported_later = exported.clone();
+    exported_later.height = Uint64::new(90);
+    let new_app = App::<_, RustVm>::new(MemDb::new());
+    new_app
+        .do_init_chain_raw(
+            "dev-3".into(),
+            mock_block(0),
+            &to_json_vec(&exported_later).unwrap(),
+        )
+        .unwrap();
+    let reexported = new_app.export_state(None).unwrap();
+    assert_eq!(reexported.next_upgrade.unwrap().height, Uint64::new(10));
+
+    // can't export at a height that isn't finalized yet
+    assert!(matches!(
+        app.inner().export_state(Some(1)),
+        Err(AppError::HeightNotFinalized {
+            latest: 0,
+            height: 1,
+        })
+    ));
+
+    // a contract storage key too short to contain an address is rejected,
+    // rather than causing a panic
+    let mut storage = MockStorage::new();
+    CHAIN_ID.save(&mut storage, &exported.chain_id).unwrap();
+    CONFIG.save(&mut storage, &exported.config).unwrap();
+    LAST_FINALIZED_BLOCK
+        .save(&mut storage, &mock_block(0))
+        .unwrap();
+    storage.write(b"wasmlarry", b"foo");
+    assert!(matches!(
+        export_state(&storage),
+        Err(AppError::ContractStorageKeyTooShort { .. })
+    ));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use {
+    common::{mock_block, mock_genesis_state},
+    grug_db_fork::ForkDb,
+    grug_testing::MockApp,
+    grug_types::{Addr, Message, PageRequest, QueryRequest, Uint64, GENESIS_SENDER},
+};
+
+#[test]
+fn init_chain_works() {
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", mock_genesis_state());
+
+    let info = app.query(QueryRequest::Info {}).as_info();
+    dbg!(&info);
+
+    let code_hashes = app
+        .query(QueryRequest::Codes {
+            page: PageRequest::new(),
+        })
+        .as_codes();
+    dbg!(&code_hashes);
+
+    let accounts = app
+        .query(QueryRequest::Accounts {
+            page: PageRequest::new(),
+        })
+        .as_accounts();
+    dbg!(&accounts);
+}
+
+#[test]
+fn forking_works() {
+    let genesis_state = mock_genesis_state();
+    let Message::Instantiate {
+        code_hash, salt, ..
+    } = genesis_state.msgs[1].clone()
+    else {
+        unreachable!();
+    };
+
+    let mut remote = MockApp::new();
+    remote.init_chain("dev-1", genesis_state);
+
+    // fork the chain at the latest height. the forked app pulls the state
+    // from the remote one, so it doesn't need to be initialized.
+    let (height, _) = remote.inner().do_info().unwrap();
+    let app = MockApp::new_with_db(ForkDb::new(remote.inner().clone(), height));
+
+    let info = app.query(QueryRequest::Info {}).as_info();
+    assert_eq!(info, remote.query(QueryRequest::Info {}).as_info());
+    assert_eq!(info.chain_id, "dev-1");
+
+    let req = QueryRequest::Code {
+        hash: code_hash.clone(),
+    };
+    assert_eq!(
+        app.query(req.clone()).as_code(),
+        remote.query(req).as_code()
+    );
+
+    let address = Addr::compute(&GENESIS_SENDER, &code_hash, &salt);
+    let req = QueryRequest::Account { address };
+    assert_eq!(
+        app.query(req.clone()).as_account(),
+        remote.query(req).as_account()
+    );
+
+    // execute a block on the fork. the last finalized block, which exists
+    // in the remote state, is overwritten locally, and the local value
+    // takes precedence.
+    app.inner()
+        .do_finalize_block(mock_block(height + 1), vec![])
+        .unwrap();
+    app.inner().do_commit().unwrap();
+
+    let info = app.query(QueryRequest::Info {}).as_info();
+    assert_eq!(info.last_finalized_block.height, Uint64::new(height + 1));
+    assert_eq!(info.chain_id, "dev-1");
+
+    // the remote chain isn't affected
+    let info = remote.query(QueryRequest::Info {}).as_info();
+    assert_eq!(info.last_finalized_block.height, Uint64::new(height));
+}
+
+#[test]
+fn loading_fixture_works() {
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", mock_genesis_state());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("genesis.bin");
+    app.dump_fixture(&path);
+
+    let loaded = MockApp::from_fixture(&path);
+    assert_eq!(
+        loaded.inner().do_info().unwrap(),
+        app.inner().do_info().unwrap()
+    );
+    assert_eq!(
+        loaded.query(QueryRequest::Info {}).as_info(),
+        app.query(QueryRequest::Info {}).as_info()
+    );
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use {
+    common::{mock_genesis_state, upload_and_instantiate},
+    grug_app::{AppError, ACCOUNTS, CODES},
+    grug_jmt::{verify_batch_proof, verify_proof, BatchProof, Proof},
+    grug_testing::MockApp,
+    grug_types::{
+        from_json_slice, hash, query_storage_key, to_borsh_vec, Addr, Empty, PageRequest,
+        QueryRequest, Response, StdResult, MAX_BATCH_PROOF_KEYS, MAX_MULTI_QUERIES,
+    },
+    grug_wasm::MutableCtx,
+};
+
+#[test]
+fn multi_query_works() {
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", mock_genesis_state());
+
+    let mut res = app
+        .query(QueryRequest::Multi(vec![
+            QueryRequest::Info {},
+            QueryRequest::Codes {
+                page: PageRequest::new(),
+            },
+            // this account doesn't exist, so this sub-query should fail,
+            // without failing the other ones.
+            QueryRequest::Account {
+                address: Addr::mock(2),
+            },
+        ]))
+        .as_multi()
+        .into_iter();
+
+    let info = res.next().unwrap().into_std_result().unwrap().as_info();
+    assert_eq!(info.chain_id, "dev-1");
+
+    let code_hashes = res.next().unwrap().into_std_result().unwrap().as_codes();
+    assert_eq!(code_hashes.items.len(), 1);
+
+    assert!(res.next().unwrap().into_std_result().is_err());
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn multi_query_limits_work() {
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", mock_genesis_state());
+
+    // nested multi queries are rejected
+    let req = QueryRequest::Multi(vec![
+        QueryRequest::Info {},
+        QueryRequest::Multi(vec![QueryRequest::Info {}]),
+    ]);
+    assert!(matches!(
+        app.inner().do_query_app(req, 0, false),
+        Err(AppError::NestedMultiQuery)
+    ));
+
+    // so are too many sub-queries
+    let req = QueryRequest::Multi(vec![QueryRequest::Info {}; MAX_MULTI_QUERIES + 1]);
+    assert!(matches!(
+        app.inner().do_query_app(req, 0, false),
+        Err(AppError::TooManyQueries { .. })
+    ));
+
+    // exactly the maximum is fine
+    let req = QueryRequest::Multi(vec![QueryRequest::Info {}; MAX_MULTI_QUERIES]);
+    assert_eq!(app.query(req).as_multi().len(), MAX_MULTI_QUERIES);
+}
+
+#[test]
+fn query_with_proof_works() {
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", mock_genesis_state());
+
+    // the version is pinned to the latest one, which is returned along with
+    // the proofs
+    let (height, root_hash) = app.inner().do_info().unwrap();
+    let code_hash = app
+        .query(QueryRequest::Codes {
+            page: PageRequest::new(),
+        })
+        .as_codes()
+        .items
+        .remove(0);
+
+    // membership proof of a code
+    let req = QueryRequest::Code {
+        hash: code_hash.clone(),
+    };
+    let (res, proof) = app.inner().do_query_app(req.clone(), 0, true).unwrap();
+    let (version, key, proof) = proof.unwrap();
+    assert_eq!(version, height);
+    let code = res.unwrap().as_code();
+    assert_eq!(hash(&code), code_hash);
+    assert_eq!(key, query_storage_key(&req).unwrap());
+    assert_eq!(key, CODES.path(&code_hash).storage_key());
+    assert_eq!(
+        query_storage_key(&QueryRequest::Account {
+            address: Addr::mock(1),
+        })
+        .unwrap(),
+        ACCOUNTS.path(&Addr::mock(1)).storage_key(),
+    );
+    let value = to_borsh_vec(&code.to_vec()).unwrap();
+    let proof: Proof = from_json_slice(proof).unwrap();
+    assert!(verify_proof(&root_hash, &hash(&key), Some(&hash(value)), &proof).is_ok());
+
+    // non-membership proof of a contract storage key
+    let req = QueryRequest::WasmRaw {
+        contract: Addr::mock(1),
+        key: b"larry".to_vec().into(),
+    };
+    let (res, proof) = app.inner().do_query_app(req.clone(), 0, true).unwrap();
+    let (version, key, proof) = proof.unwrap();
+    assert_eq!(version, height);
+    assert!(res.is_none());
+    assert_eq!(key, [b"wasm".as_slice(), &Addr::mock(1), b"larry"].concat());
+    let proof: Proof = from_json_slice(proof).unwrap();
+    assert!(verify_proof(&root_hash, &hash(&key), None, &proof).is_ok());
+
+    // non-membership proof of an account, which would fail to be queried
+    // without a proof
+    let req = QueryRequest::Account {
+        address: Addr::mock(255),
+    };
+    assert!(app.inner().do_query_app(req.clone(), 0, false).is_err());
+    let (res, proof) = app.inner().do_query_app(req, 0, true).unwrap();
+    let (version, key, proof) = proof.unwrap();
+    assert_eq!(version, height);
+    assert!(res.is_none());
+    assert_eq!(key, ACCOUNTS.path(&Addr::mock(255)).storage_key());
+    let proof: Proof = from_json_slice(proof).unwrap();
+    assert!(verify_proof(&root_hash, &hash(&key), None, &proof).is_ok());
+
+    // smart queries can't be proved
+    assert!(app
+        .inner()
+        .do_query_app(QueryRequest::Info {}, 0, true)
+        .is_err());
+}
+
+#[test]
+fn query_store_many_works() {
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", mock_genesis_state());
+
+    let (_, root_hash) = app.inner().do_info().unwrap();
+
+    // query a key that exists, and one that doesn't, with a single proof
+    let keys: [&[u8]; 2] = [b"config", b"larry"];
+    let (values, proof) = app.inner().do_query_store_many(&keys, 0, true).unwrap();
+    let (version, proof) = proof.unwrap();
+    assert_eq!(version, 0);
+    for (key, value) in keys.iter().zip(&values) {
+        assert_eq!(*value, app.inner().do_query_store(key, 0, false).unwrap().0);
+    }
+    assert!(values[0].is_some());
+    assert!(values[1].is_none());
+
+    let items = keys
+        .iter()
+        .zip(&values)
+        .map(|(key, value)| (hash(key), value.as_ref().map(hash)))
+        .collect::<Vec<_>>();
+    let proof: BatchProof = from_json_slice(proof).unwrap();
+    assert!(verify_batch_proof(&root_hash, &items, &proof).is_ok());
+
+    // too many keys are rejected
+    let keys = vec![b"config".as_slice(); MAX_BATCH_PROOF_KEYS + 1];
+    assert!(matches!(
+        app.inner().do_query_store_many(&keys, 0, true),
+        Err(AppError::TooManyKeys { .. })
+    ));
+}
+
+fn records_instantiate(ctx: MutableCtx, _msg: Empty) -> StdResult<Response> {
+    for key in [b"a", b"b", b"c", b"d"] {
+        ctx.storage.write(key, key);
+    }
+    Ok(Response::new())
+}
+
+#[test]
+fn wasm_raw_range_query_works() {
+    let (records_msgs, contract) =
+        upload_and_instantiate(Box::new(records_instantiate), b"records");
+    let mut genesis_state = mock_genesis_state();
+    genesis_state.msgs.extend(records_msgs);
+
+    let mut app = MockApp::new();
+    app.init_chain("dev-1", genesis_state);
+
+    let range = |min: Option<&[u8]>, max: Option<&[u8]>, page| QueryRequest::WasmRawRange {
+        contract: contract.clone(),
+        min: min.map(|bytes| bytes.to_vec().into()),
+        max: max.map(|bytes| bytes.to_vec().into()),
+        page,
+    };
+    let query = |min: Option<&[u8]>, max: Option<&[u8]>, page| {
+        let page = app.query(range(min, max, page)).as_wasm_raw_range();
+        let keys = page
+            .items
+            .iter()
+            .map(|(key, _)| key.to_vec())
+            .collect::<Vec<_>>();
+        (keys, page)
+    };
+
+    // min is inclusive, max is exclusive
+    let (keys, page) = query(Some(b"b"), Some(b"d"), PageRequest::new());
+    assert_eq!(keys, [b"b", b"c"]);
+    assert_eq!(page.next, None);
+
+    // records of other contracts aren't included
+    let (keys, _) = query(Some(b"c"), None, PageRequest::new());
+    assert_eq!(keys, [b"c", b"d"]);
+
+    // paginate through the range, counting the records in it
+    let req = PageRequest::new().with_limit(2).with_count_total();
+    let (keys, page) = query(Some(b"b"), None, req.clone());
+    assert_eq!(keys, [b"b", b"c"]);
+    assert_eq!(page.total, Some(3));
+
+    let (keys, page) = query(Some(b"b"), None, page.next_request(&req).unwrap());
+    assert_eq!(keys, [b"d"]);
+    assert_eq!(page.next, None);
+
+    // a cursor outside of the range is rejected, as the page following it
+    // would include records outside of the range
+    for cursor in [b"a", b"d"] {
+        let page = PageRequest::new().with_cursor(cursor.to_vec().into());
+        assert!(matches!(
+            app.inner()
+                .do_query_app(range(Some(b"b"), Some(b"d"), page), 0, false),
+            Err(AppError::CursorOutOfRange { .. })
+        ));
+    }
+}
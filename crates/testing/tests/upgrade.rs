@@ -0,0 +1,60 @@
+mod common;
+
+use {
+    common::{mock_block, mock_genesis_state},
+    grug_app::{App, AppError, AppResult, CONFIG},
+    grug_db_memory::MemDb,
+    grug_types::{BlockInfo, Message, Permission, QueryRequest, Storage, Uint64, GENESIS_SENDER},
+    grug_vm_rust::RustVm,
+};
+
+fn mock_upgrade_handler(mut storage: Box<dyn Storage>, _block: &BlockInfo) -> AppResult<()> {
+    let mut cfg = CONFIG.load(&storage)?;
+    cfg.permissions.upload = Permission::Nobody;
+    CONFIG.save(&mut storage, &cfg)?;
+    Ok(())
+}
+
+#[test]
+fn upgrade_works() {
+    let db = MemDb::new();
+    let old_app = App::<_, RustVm>::new(db.clone());
+    let new_app = App::<_, RustVm>::new(db).add_upgrade_handler("v2", mock_upgrade_handler);
+
+    let mut genesis_state = mock_genesis_state();
+    genesis_state.config.owner = Some(GENESIS_SENDER);
+    genesis_state.msgs.push(Message::ScheduleUpgrade {
+        height: Uint64::new(2),
+        name: "v2".into(),
+        info: None,
+    });
+    old_app
+        .do_init_chain("dev-1".into(), mock_block(0), genesis_state)
+        .unwrap();
+
+    // blocks before the upgrade height are processed as usual
+    old_app.do_finalize_block(mock_block(1), vec![]).unwrap();
+    old_app.do_commit().unwrap();
+
+    // the old software halts at the upgrade height
+    assert!(matches!(
+        old_app.do_finalize_block(mock_block(2), vec![]),
+        Err(AppError::UpgradeNeeded { name, height: 2 }) if name == "v2"
+    ));
+
+    // the new software runs the upgrade handler, and continues
+    new_app.do_finalize_block(mock_block(2), vec![]).unwrap();
+    new_app.do_commit().unwrap();
+
+    let (res, _) = new_app
+        .do_query_app(QueryRequest::Info {}, 0, false)
+        .unwrap();
+    assert_eq!(
+        res.unwrap().as_info().config.permissions.upload,
+        Permission::Nobody
+    );
+
+    // the scheduled upgrade is cleared, so the handler is only run once
+    let (value, _) = new_app.do_query_store(b"next_upgrade", 0, false).unwrap();
+    assert!(value.is_none());
+}
@@ -1,10 +1,10 @@
 use {
-    crate::{Addr, Hash, Message, Timestamp, Uint64},
+    crate::{Addr, Binary, Hash, Message, Timestamp, Uint64},
     borsh::{BorshDeserialize, BorshSerialize},
     hex_literal::hex,
-    serde::{Deserialize, Serialize},
+    serde::{de, Deserialize, Serialize},
     serde_with::skip_serializing_none,
-    std::collections::BTreeSet,
+    std::collections::{BTreeMap, BTreeSet},
 };
 
 /// Genesis messages don't have senders, so we use this mock up hash as the
@@ -19,8 +19,68 @@ pub const GENESIS_BLOCK_HASH: Hash = Hash(hex!(
     "d04b98f48e8f8bcc15c6ae5ac050801cd6dcfd428fb5f9e65c4e16e7807340fa"
 ));
 
-/// The chain's genesis state. To be included in the `app_state` field of
-/// CometBFT's `genesis.json`.
+/// Namespace under which Wasm byte codes are stored in the chain's state.
+pub const CODES_NAMESPACE: &str = "code";
+
+/// Namespace under which account metadata are stored in the chain's state.
+pub const ACCOUNTS_NAMESPACE: &str = "account";
+
+/// Each contract has its own storage space, which we term the "substore".
+/// A key in a contract's substore is prefixed by the word "wasm" + contract address.
+pub const CONTRACT_NAMESPACE: &[u8] = b"wasm";
+
+/// The chain's genesis. To be included in the `app_state` field of CometBFT's
+/// `genesis.json`.
+///
+/// The genesis can take either of two forms: a config and a list of messages
+/// to be executed, or a raw state exported from a live chain.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Genesis {
+    Msgs(GenesisState),
+    Raw(RawGenesisState),
+}
+
+// not derived, because an untagged enum fails to deserialize with the error
+// "data did not match any variant", which doesn't tell what's wrong with it.
+// instead, we try both forms, and report both errors if neither matches.
+impl<'de> de::Deserialize<'de> for Genesis {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        let msgs_err = match GenesisState::deserialize(&value) {
+            Ok(genesis_state) => return Ok(Self::Msgs(genesis_state)),
+            Err(err) => err,
+        };
+
+        let raw_err = match RawGenesisState::deserialize(&value) {
+            Ok(raw_genesis_state) => return Ok(Self::Raw(raw_genesis_state)),
+            Err(err) => err,
+        };
+
+        Err(de::Error::custom(format!(
+            "invalid genesis! as messages: {msgs_err}; as raw state: {raw_err}"
+        )))
+    }
+}
+
+impl From<GenesisState> for Genesis {
+    fn from(genesis_state: GenesisState) -> Self {
+        Self::Msgs(genesis_state)
+    }
+}
+
+impl From<RawGenesisState> for Genesis {
+    fn from(raw_genesis_state: RawGenesisState) -> Self {
+        Self::Raw(raw_genesis_state)
+    }
+}
+
+/// A genesis state consisting of the chain-level config and a list of messages
+/// to be executed during genesis.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct GenesisState {
@@ -28,6 +88,33 @@ pub struct GenesisState {
     pub msgs: Vec<Message>,
 }
 
+/// A genesis state consisting of the chain's raw state, typically exported from
+/// a live chain. This can be used to bootstrap a testnet fork, or to perform a
+/// hard-fork migration.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RawGenesisState {
+    /// The ID of the chain from which the state was exported. Note that when
+    /// initializing a chain from this state, the chain ID provided by CometBFT
+    /// is used instead.
+    pub chain_id: String,
+    /// The height at which the state was exported. Like the chain ID, this is
+    /// for reference only; the new chain starts from the height provided by
+    /// CometBFT.
+    pub height: Uint64,
+    pub config: Config,
+    /// Wasm byte codes, indexed by code hashes.
+    pub codes: BTreeMap<Hash, Binary>,
+    /// Account metadata, indexed by addresses.
+    pub accounts: BTreeMap<Addr, Account>,
+    /// Each contract's raw key-value pairs, indexed by contract addresses.
+    pub contract_storages: BTreeMap<Addr, BTreeMap<Binary, Binary>>,
+    /// The next scheduled software upgrade, if any.
+    #[serde(default)]
+    pub next_upgrade: Option<Upgrade>,
+}
+
 /// Chain-level configurations. Not to be confused with contract-level configs.
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
@@ -82,6 +169,22 @@ pub enum Permission {
     Somebodies(BTreeSet<Addr>),
 }
 
+/// A planned upgrade of the chain's software.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Upgrade {
+    /// The block height at which the upgrade takes place. Nodes running the
+    /// old software halt before processing this block.
+    pub height: Uint64,
+    /// Name of the upgrade. The new software must have an upgrade handler
+    /// registered under this name.
+    pub name: String,
+    /// Optional information about the upgrade, such as where to download the
+    /// new software.
+    pub info: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct BlockInfo {
@@ -97,3 +200,19 @@ pub struct Account {
     pub code_hash: Hash,
     pub admin: Option<Addr>,
 }
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::from_json_slice};
+
+    #[test]
+    fn invalid_genesis_reports_both_forms() {
+        let err = from_json_slice::<Genesis>(br#"{"chain_id":"dev-1","msgs":[]}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("as messages: unknown field `chain_id`"));
+        assert!(err.contains("as raw state: unknown field `msgs`"));
+    }
+}
@@ -28,7 +28,7 @@
 //! - Forward the funds to another account.
 
 use {
-    crate::{Addr, Coin, Coins},
+    crate::{Addr, Coin, Coins, Page, PageRequest},
     serde::{Deserialize, Serialize},
     serde_with::skip_serializing_none,
 };
@@ -59,24 +59,24 @@ pub enum BankQueryMsg {
     },
     Balances {
         address: Addr,
-        start_after: Option<String>,
-        limit: Option<u32>,
+        #[serde(default)]
+        page: PageRequest,
     },
     Supply {
         denom: String,
     },
     Supplies {
-        start_after: Option<String>,
-        limit: Option<u32>,
+        #[serde(default)]
+        page: PageRequest,
     },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum BankQueryResponse {
     Balance(Coin),
-    Balances(Coins),
+    Balances(Page<Coin>),
     Supply(Coin),
-    Supplies(Coins),
+    Supplies(Page<Coin>),
 }
 
 impl BankQueryResponse {
@@ -87,7 +87,7 @@ impl BankQueryResponse {
         coin
     }
 
-    pub fn as_balances(self) -> Coins {
+    pub fn as_balances(self) -> Page<Coin> {
         let BankQueryResponse::Balances(coins) = self else {
             panic!("BankQueryResponse is not Balances");
         };
@@ -101,7 +101,7 @@ impl BankQueryResponse {
         coin
     }
 
-    pub fn as_supplies(self) -> Coins {
+    pub fn as_supplies(self) -> Page<Coin> {
         let BankQueryResponse::Supplies(coins) = self else {
             panic!("BankQueryResponse is not Supplies");
         };
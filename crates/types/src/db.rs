@@ -1,7 +1,8 @@
 use {
     crate::{StdError, StdResult},
+    borsh::{BorshDeserialize, BorshSerialize},
     serde::{Deserialize, Serialize},
-    std::collections::BTreeMap,
+    std::{borrow::Borrow, cmp::Ordering, collections::BTreeMap, iter::Peekable},
 };
 
 /// A shorthand for an owned KV pair.
@@ -12,7 +13,7 @@ pub type Record = (Vec<u8>, Vec<u8>);
 pub type Batch<K = Vec<u8>, V = Vec<u8>> = BTreeMap<K, Op<V>>;
 
 /// Represents a database operation, either inserting a value or deleting one.
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum Op<V = Vec<u8>> {
     Insert(V),
@@ -45,7 +46,8 @@ impl<V> Op<V> {
 }
 
 /// Describing iteration order.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum Order {
     Ascending = 1,
     Descending = 2,
@@ -73,3 +75,77 @@ impl TryFrom<i32> for Order {
         }
     }
 }
+
+/// An iterator over records in a base store merged with pending ops on top of
+/// it, in the given order. The base iterator and the pending ops must be in the
+/// same order.
+///
+/// The pending ops can be either borrowed or owned, e.g. iterated from a
+/// `Batch` or collected into a `Vec`.
+pub struct Merged<B, P>
+where
+    B: Iterator<Item = Record>,
+    P: Iterator,
+{
+    base: Peekable<B>,
+    pending: Peekable<P>,
+    order: Order,
+}
+
+impl<B, P, K, O> Merged<B, P>
+where
+    B: Iterator<Item = Record>,
+    P: Iterator<Item = (K, O)>,
+    K: Borrow<Vec<u8>>,
+    O: Borrow<Op>,
+{
+    pub fn new(base: B, pending: P, order: Order) -> Self {
+        Self {
+            base: base.peekable(),
+            pending: pending.peekable(),
+            order,
+        }
+    }
+
+    fn take_pending(&mut self) -> Option<Record> {
+        let (key, op) = self.pending.next()?;
+        match op.borrow() {
+            Op::Insert(value) => Some((key.borrow().clone(), value.clone())),
+            Op::Delete => self.next(),
+        }
+    }
+}
+
+impl<B, P, K, O> Iterator for Merged<B, P>
+where
+    B: Iterator<Item = Record>,
+    P: Iterator<Item = (K, O)>,
+    K: Borrow<Vec<u8>>,
+    O: Borrow<Op>,
+{
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.base.peek(), self.pending.peek()) {
+            (Some((base_key, _)), Some((pending_key, _))) => {
+                let ordering_raw = base_key.cmp(pending_key.borrow());
+                let ordering = match self.order {
+                    Order::Ascending => ordering_raw,
+                    Order::Descending => ordering_raw.reverse(),
+                };
+
+                match ordering {
+                    Ordering::Less => self.base.next(),
+                    Ordering::Equal => {
+                        self.base.next();
+                        self.take_pending()
+                    },
+                    Ordering::Greater => self.take_pending(),
+                }
+            },
+            (None, Some(_)) => self.take_pending(),
+            (Some(_), None) => self.base.next(),
+            (None, None) => None,
+        }
+    }
+}
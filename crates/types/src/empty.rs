@@ -1,20 +1,59 @@
 use {
     borsh::{BorshDeserialize, BorshSerialize},
+    prost::{
+        bytes::{Buf, BufMut},
+        encoding::{skip_field, DecodeContext, WireType},
+        DecodeError, Message,
+    },
     serde::{Deserialize, Serialize},
 };
 
 /// When serializing to JSON, gives an pair of brackets: `{}`.
-/// When serializing with Borsh, gives empty bytes: ``.
+/// When serializing with Borsh or Protobuf, gives empty bytes: ``.
 /// Useful for use in contract messages when there isn't any intended inputs, or
 /// in contract storage to represent empty value (e.g. in `grug::Set`).
-#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(
+    Serialize, Deserialize, BorshSerialize, BorshDeserialize, Default, Debug, Clone, PartialEq, Eq,
+)]
 pub struct Empty {}
 
+// implemented manually, because `prost::Message` derive macro also derives
+// `Debug`, which conflicts with the one above.
+impl Message for Empty {
+    fn encode_raw<B>(&self, _buf: &mut B)
+    where
+        B: BufMut,
+    {
+    }
+
+    fn merge_field<B>(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut B,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        skip_field(wire_type, tag, buf, ctx)
+    }
+
+    fn encoded_len(&self) -> usize {
+        0
+    }
+
+    fn clear(&mut self) {}
+}
+
 #[cfg(test)]
 mod tests {
     use {
         super::*,
-        crate::{from_borsh_slice, from_json_value, to_borsh_vec, to_json_value},
+        crate::{
+            from_borsh_slice, from_json_value, from_proto_slice, to_borsh_vec, to_json_value,
+            to_proto_vec,
+        },
         serde_json::json,
     };
 
@@ -30,4 +69,10 @@ mod tests {
         assert!(to_borsh_vec(&Empty {}).unwrap().is_empty());
         assert_eq!(from_borsh_slice::<Empty>(&[]).unwrap(), Empty {});
     }
+
+    #[test]
+    fn encoding_with_proto() {
+        assert!(to_proto_vec(&Empty {}).is_empty());
+        assert_eq!(from_proto_slice::<Empty>(&[]).unwrap(), Empty {});
+    }
 }
@@ -41,6 +41,15 @@ pub enum StdError {
     #[error("Data not found! type: {ty}, storage key: {key}")]
     DataNotFound { ty: &'static str, key: String },
 
+    #[error("Unique index violated! namespace: {namespace}, key: {key}")]
+    UniqueIndexViolated { namespace: String, key: String },
+
+    #[error("Height {height} is not checkpointed! namespace: {namespace}")]
+    NotCheckpointed { namespace: String, height: u64 },
+
+    #[error("Page contains {actual} items, more than the limit of {limit}")]
+    PageTooLarge { limit: u32, actual: usize },
+
     #[error("Cannot find iterator with ID {iterator_id}")]
     IteratorNotFound { iterator_id: i32 },
 
@@ -97,10 +106,10 @@ pub enum StdError {
     #[error("Remainder by zero: {a} % 0")]
     RemainderByZero { a: String },
 
-    #[error("Failed to serialize into json! type: {ty}, reason: {reason}")]
+    #[error("Failed to serialize! type: {ty}, reason: {reason}")]
     Serialize { ty: &'static str, reason: String },
 
-    #[error("Failed to deserialize from json! type: {ty}, reason: {reason}")]
+    #[error("Failed to deserialize! type: {ty}, reason: {reason}")]
     Deserialize { ty: &'static str, reason: String },
 }
 
@@ -130,6 +139,24 @@ impl StdError {
         }
     }
 
+    pub fn unique_index_violated(namespace: &[u8], key: &[u8]) -> Self {
+        Self::UniqueIndexViolated {
+            namespace: String::from_utf8_lossy(namespace).into(),
+            key: BASE64.encode(key),
+        }
+    }
+
+    pub fn not_checkpointed(namespace: &[u8], height: u64) -> Self {
+        Self::NotCheckpointed {
+            namespace: String::from_utf8_lossy(namespace).into(),
+            height,
+        }
+    }
+
+    pub fn page_too_large(limit: u32, actual: usize) -> Self {
+        Self::PageTooLarge { limit, actual }
+    }
+
     pub fn overflow_conversion<A: ToString, B>(source: A) -> Self {
         Self::OverflowConversion {
             source_type: type_name::<A>(),
@@ -14,6 +14,7 @@ mod ibc;
 mod math;
 #[cfg(not(target_arch = "wasm32"))]
 mod mocks;
+mod pagination;
 mod query;
 mod response;
 mod result;
@@ -25,8 +26,8 @@ mod utils;
 
 pub use {
     address::*, app::*, bank::*, binary::*, coin::*, context::*, db::*, empty::*, error::*,
-    event::*, hash::*, ibc::*, math::*, query::*, response::*, result::*, serde::*, timestamp::*,
-    traits::*, tx::*, utils::*,
+    event::*, hash::*, ibc::*, math::*, pagination::*, query::*, response::*, result::*, serde::*,
+    timestamp::*, traits::*, tx::*, utils::*,
 };
 
 // Mocks need to be excluded in Wasm builds because they depend on k256/p256
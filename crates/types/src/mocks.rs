@@ -1,7 +1,7 @@
 use {
     crate::{Addr, Api, Order, Record, StdError, StdResult, Storage},
     grug_crypto::{secp256k1_verify, secp256r1_verify},
-    std::{collections::BTreeMap, iter, ops::Bound},
+    std::{collections::BTreeMap, iter, mem, ops::Bound},
 };
 
 // ---------------------------------- storage ----------------------------------
@@ -62,6 +62,26 @@ impl Storage for MockStorage {
     fn remove(&mut self, key: &[u8]) {
         self.data.remove(key);
     }
+
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return;
+            }
+        }
+
+        // split the map into three parts: below, within, and above the range;
+        // then put the parts below and above back together.
+        let mut within = match min {
+            Some(min) => self.data.split_off(min),
+            None => mem::take(&mut self.data),
+        };
+        let mut above = match max {
+            Some(max) => within.split_off(max),
+            None => BTreeMap::new(),
+        };
+        self.data.append(&mut above);
+    }
 }
 
 // ------------------------------------ api ------------------------------------
@@ -0,0 +1,167 @@
+use {
+    crate::{Binary, StdError, StdResult},
+    serde::{Deserialize, Deserializer, Serialize},
+    serde_with::skip_serializing_none,
+};
+
+/// Number of items to return in a page, if the request doesn't specify one.
+pub const DEFAULT_PAGE_LIMIT: u32 = 30;
+
+/// Maximum number of items to return in a page. Requests for larger pages are
+/// capped at this value.
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+/// Maximum number of items to count when a request asks for the total. If
+/// there are more items than this, the total is not returned.
+pub const MAX_COUNT_TOTAL: u64 = 10_000;
+
+/// The number of items to return in a page given the requested limit: the
+/// default one if not given, capped between 1 and `MAX_PAGE_LIMIT`.
+pub fn page_limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+}
+
+// cap the limit as the request is deserialized, so that whoever receives a
+// request, be it the host or a contract, never sees a limit that's too large
+fn deserialize_limit<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let limit = Option::<u32>::deserialize(deserializer)?;
+    Ok(limit.map(|limit| page_limit(Some(limit))))
+}
+
+/// Describes which page of a paginated query to return.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    /// The `next` cursor of the previous page. `None` to start from the first
+    /// item.
+    pub cursor: Option<Binary>,
+    /// Maximum number of items to return. Defaults to `DEFAULT_PAGE_LIMIT`,
+    /// and is capped by `MAX_PAGE_LIMIT`.
+    #[serde(default, deserialize_with = "deserialize_limit")]
+    pub limit: Option<u32>,
+    /// Whether to count the total number of items. This requires iterating
+    /// over them, so is off by default, and at most `MAX_COUNT_TOTAL` items
+    /// are counted.
+    #[serde(default)]
+    pub count_total: bool,
+}
+
+impl PageRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cursor(mut self, cursor: Binary) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(page_limit(Some(limit)));
+        self
+    }
+
+    pub fn with_count_total(mut self) -> Self {
+        self.count_total = true;
+        self
+    }
+
+    /// The number of items to actually return: the requested limit, or the
+    /// default one if not given, capped between 1 and `MAX_PAGE_LIMIT`.
+    pub fn effective_limit(&self) -> u32 {
+        page_limit(self.limit)
+    }
+
+    /// The same request, with the limit set to the effective one. Useful when
+    /// forwarding the request to a contract, so that the page size doesn't
+    /// depend on the contract's own default.
+    pub fn capped(self) -> Self {
+        Self {
+            limit: Some(self.effective_limit()),
+            ..self
+        }
+    }
+}
+
+/// A page of items returned by a paginated query.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor for requesting the next page. `None` if this is the last page.
+    ///
+    /// The cursor is opaque: its content is not to be interpreted by callers.
+    pub next: Option<Binary>,
+    /// Total number of items, if requested and there are no more than
+    /// `MAX_COUNT_TOTAL` of them.
+    pub total: Option<u64>,
+}
+
+impl<T> Page<T> {
+    /// Convert the items, keeping the cursor and total unchanged.
+    pub fn map<U, F>(self, f: F) -> Page<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next: self.next,
+            total: self.total,
+        }
+    }
+
+    /// Ensure the page doesn't contain more items than `req` allows. Useful
+    /// for checking a page returned by an untrusted party, e.g. a contract.
+    pub fn check_limit(&self, req: &PageRequest) -> StdResult<()> {
+        let limit = req.effective_limit();
+        if self.items.len() > limit as usize {
+            return Err(StdError::page_too_large(limit, self.items.len()));
+        }
+
+        Ok(())
+    }
+
+    /// Request for the page after this one, with the same limit and counting
+    /// option as `req`, or `None` if this is the last page.
+    pub fn next_request(&self, req: &PageRequest) -> Option<PageRequest> {
+        self.next.clone().map(|cursor| PageRequest {
+            cursor: Some(cursor),
+            ..req.clone()
+        })
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::from_json_value, serde_json::json};
+
+    #[test]
+    fn limit_is_capped() {
+        // when building a request
+        let req = PageRequest::new().with_limit(MAX_PAGE_LIMIT + 1);
+        assert_eq!(req.limit, Some(MAX_PAGE_LIMIT));
+
+        // when deserializing one
+        let req: PageRequest = from_json_value(json!({ "limit": MAX_PAGE_LIMIT + 1 })).unwrap();
+        assert_eq!(req.limit, Some(MAX_PAGE_LIMIT));
+        let req: PageRequest = from_json_value(json!({})).unwrap();
+        assert_eq!(req.limit, None);
+        assert_eq!(req.clone().capped().limit, Some(DEFAULT_PAGE_LIMIT));
+
+        // when checking a page
+        let page = Page {
+            items: vec![(); MAX_PAGE_LIMIT as usize + 1],
+            next: None,
+            total: None,
+        };
+        assert!(page
+            .check_limit(&PageRequest::new().with_limit(MAX_PAGE_LIMIT))
+            .is_err());
+        assert!(page.check_limit(&req).is_err());
+    }
+}
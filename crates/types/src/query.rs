@@ -1,9 +1,51 @@
 use {
-    crate::{Addr, Binary, BlockInfo, Coin, Coins, Config, Hash, Json},
+    crate::{
+        nested_namespaces_with_key, Addr, Binary, BlockInfo, Coin, Config, GenericResult, Hash,
+        Json, Order, Page, PageRequest, ACCOUNTS_NAMESPACE, CODES_NAMESPACE, CONTRACT_NAMESPACE,
+    },
     serde::{Deserialize, Serialize},
     serde_with::skip_serializing_none,
 };
 
+/// For queries whose responses can be Merkle-proved, return the raw key in the
+/// state storage that the query reads from. `None` for other queries.
+///
+/// The keys are derived as follows (`|` denotes concatenation, `len` is the
+/// length of a byte slice as a 2-byte big-endian integer):
+///
+/// - `Code { hash }`: `len("code") | "code" | hash`, with the value being the
+///   Wasm byte code, Borsh-encoded;
+/// - `Account { address }`: `len("account") | "account" | address`, with the
+///   value being the `Account`, Borsh-encoded;
+/// - `WasmRaw { contract, key }`: `"wasm" | contract | key`, with the value
+///   being the raw bytes written by the contract.
+///
+/// The Merkle tree is over the SHA-256 hashes of these keys and values.
+pub fn query_storage_key(req: &QueryRequest) -> Option<Vec<u8>> {
+    match req {
+        QueryRequest::Code { hash } => Some(nested_namespaces_with_key(
+            Some(CODES_NAMESPACE.as_bytes()),
+            &[] as &[&[u8]],
+            Some(hash),
+        )),
+        QueryRequest::Account { address } => Some(nested_namespaces_with_key(
+            Some(ACCOUNTS_NAMESPACE.as_bytes()),
+            &[] as &[&[u8]],
+            Some(address),
+        )),
+        QueryRequest::WasmRaw { contract, key } => {
+            Some([CONTRACT_NAMESPACE, contract.as_ref(), key.as_ref()].concat())
+        },
+        _ => None,
+    }
+}
+
+/// Maximum number of sub-queries in a `QueryRequest::Multi`.
+pub const MAX_MULTI_QUERIES: usize = 30;
+
+/// Maximum number of keys in a `/store/batch` query.
+pub const MAX_BATCH_PROOF_KEYS: usize = 100;
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -15,20 +57,20 @@ pub enum QueryRequest {
     /// Returns: `Coin`
     Balance { address: Addr, denom: String },
     /// Enumerate an account's balances in all denoms.
-    /// Returns: `Coins`
+    /// Returns: `Page<Coin>`
     Balances {
         address: Addr,
-        start_after: Option<String>,
-        limit: Option<u32>,
+        #[serde(default)]
+        page: PageRequest,
     },
     /// A token's total supply.
     /// Returns: `Coin`
     Supply { denom: String },
     /// Enumerate all tokens' total supplies.
-    /// Returns: `Coins`
+    /// Returns: `Page<Coin>`
     Supplies {
-        start_after: Option<String>,
-        limit: Option<u32>,
+        #[serde(default)]
+        page: PageRequest,
     },
     /// A single Wasm byte code.
     /// Returns: `Binary`
@@ -36,26 +78,42 @@ pub enum QueryRequest {
     /// Enumerate metadata of all codes.
     /// Note: to limit the size of return data, we only return the hashes.
     /// To download the actual Wasm byte code, use Query::Code.
-    /// Returns: `Vec<Hash>`
+    /// Returns: `Page<Hash>`
     Codes {
-        start_after: Option<Hash>,
-        limit: Option<u32>,
+        #[serde(default)]
+        page: PageRequest,
     },
     /// Metadata of a single account.
     /// Returns: `AccountResponse`
     Account { address: Addr },
     /// Enumerate metadata of all accounts.
-    /// Returns: `Vec<AccountResponse>`
+    /// Returns: `Page<AccountResponse>`
     Accounts {
-        start_after: Option<Addr>,
-        limit: Option<u32>,
+        #[serde(default)]
+        page: PageRequest,
     },
     /// A raw key-value pair in a contract's internal state.
     /// Returns: `WasmRawResponse`
     WasmRaw { contract: Addr, key: Binary },
+    /// Enumerate raw key-value pairs in a contract's internal state, in
+    /// ascending order. `min` is inclusive and `max` exclusive.
+    /// Returns: `Page<(Binary, Binary)>`
+    WasmRawRange {
+        contract: Addr,
+        min: Option<Binary>,
+        max: Option<Binary>,
+        #[serde(default)]
+        page: PageRequest,
+    },
     /// Call the contract's query entry point with the given message.
     /// Returns: `WasmSmartResponse`
     WasmSmart { contract: Addr, msg: Json },
+    /// Perform multiple queries at once, against the same state snapshot.
+    /// The sub-queries are evaluated in order; an error in one of them does
+    /// not abort the others. Sub-queries can't themselves be `Multi`, and
+    /// there can be at most `MAX_MULTI_QUERIES` of them.
+    /// Returns: `Vec<GenericResult<QueryResponse>>`
+    Multi(Vec<QueryRequest>),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -100,15 +158,17 @@ pub struct ClientResponse {
 pub enum QueryResponse {
     Info(InfoResponse),
     Balance(Coin),
-    Balances(Coins),
+    Balances(Page<Coin>),
     Supply(Coin),
-    Supplies(Coins),
+    Supplies(Page<Coin>),
     Code(Binary),
-    Codes(Vec<Hash>),
+    Codes(Page<Hash>),
     Account(AccountResponse),
-    Accounts(Vec<AccountResponse>),
+    Accounts(Page<AccountResponse>),
     WasmRaw(WasmRawResponse),
+    WasmRawRange(Page<(Binary, Binary)>),
     WasmSmart(WasmSmartResponse),
+    Multi(Vec<GenericResult<QueryResponse>>),
 }
 
 // TODO: can we use a macro to implement these?
@@ -127,7 +187,7 @@ impl QueryResponse {
         coin
     }
 
-    pub fn as_balances(self) -> Coins {
+    pub fn as_balances(self) -> Page<Coin> {
         let Self::Balances(coins) = self else {
             panic!("BankQueryResponse is not Balances");
         };
@@ -141,7 +201,7 @@ impl QueryResponse {
         coin
     }
 
-    pub fn as_supplies(self) -> Coins {
+    pub fn as_supplies(self) -> Page<Coin> {
         let Self::Supplies(coins) = self else {
             panic!("BankQueryResponse is not Supplies");
         };
@@ -155,7 +215,7 @@ impl QueryResponse {
         wasm_byte_code
     }
 
-    pub fn as_codes(self) -> Vec<Hash> {
+    pub fn as_codes(self) -> Page<Hash> {
         let Self::Codes(hashes) = self else {
             panic!("QueryResponse is not Codes");
         };
@@ -169,7 +229,7 @@ impl QueryResponse {
         resp
     }
 
-    pub fn as_accounts(self) -> Vec<AccountResponse> {
+    pub fn as_accounts(self) -> Page<AccountResponse> {
         let Self::Accounts(resp) = self else {
             panic!("QueryResponse is not Accounts");
         };
@@ -183,10 +243,38 @@ impl QueryResponse {
         resp
     }
 
+    pub fn as_wasm_raw_range(self) -> Page<(Binary, Binary)> {
+        let Self::WasmRawRange(resp) = self else {
+            panic!("QueryResponse is not WasmRawRange");
+        };
+        resp
+    }
+
     pub fn as_wasm_smart(self) -> WasmSmartResponse {
         let Self::WasmSmart(resp) = self else {
             panic!("QueryResponse is not WasmSmart");
         };
         resp
     }
+
+    pub fn as_multi(self) -> Vec<GenericResult<QueryResponse>> {
+        let Self::Multi(resps) = self else {
+            panic!("QueryResponse is not Multi");
+        };
+        resps
+    }
+}
+
+/// A range query of the app's underlying key-value store, for the
+/// `/store/range` ABCI query. `min` is inclusive and `max` exclusive. At most
+/// `limit` records are returned, capped by `MAX_PAGE_LIMIT`.
+///
+/// The response is a JSON array of `[key, value]` pairs.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoreRangeRequest {
+    pub min: Option<Binary>,
+    pub max: Option<Binary>,
+    pub order: Order,
+    pub limit: Option<u32>,
 }
@@ -33,6 +33,30 @@ pub trait Storage: DynClone + Send + Sync {
 
     fn remove(&mut self, key: &[u8]);
 
+    /// Remove all records under the given bounds. Same as with `scan`, minimum
+    /// bound is inclusive, maximum bound is exclusive, and nothing is removed
+    /// if min > max.
+    ///
+    /// The default implementation here is to collect the keys in the range and
+    /// remove them one by one. Overwrite this implementation if there are more
+    /// efficient approaches.
+    ///
+    /// Note that a `Db`'s state storage only receives ops on individual keys,
+    /// since the Merkle tree must be updated for each key removed, so
+    /// `CacheStore::disassemble` converts removed ranges into a deletion per
+    /// record. Range deletions reach the database only in the Merkle tree's
+    /// own storage, e.g. when pruning.
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        let keys = self
+            .scan(min, max, Order::Ascending)
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
     /// Perform a batch of writes and removes altogether, ideally atomically.
     ///
     /// The batch is provided by value instead of by reference (unlike other
@@ -78,6 +102,10 @@ impl Storage for Box<dyn Storage> {
         self.as_mut().remove(key)
     }
 
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        self.as_mut().remove_range(min, max)
+    }
+
     fn flush(&mut self, batch: Batch) {
         self.as_mut().flush(batch)
     }
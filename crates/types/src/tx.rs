@@ -1,5 +1,5 @@
 use {
-    crate::{Addr, Binary, Coins, Config, Hash, Json},
+    crate::{Addr, Binary, Coins, Config, Hash, Json, Uint64},
     serde::{Deserialize, Serialize},
     serde_with::skip_serializing_none,
 };
@@ -20,6 +20,18 @@ pub enum Message {
     /// Only the `owner` is authorized to do this. If the owner is set to `None`,
     /// no one can update the config.
     SetConfig { new_cfg: Config },
+    /// Schedule a software upgrade at the given block height.
+    ///
+    /// Only the `owner` is authorized to do this. Nodes running the current
+    /// software halt upon reaching the height. Nodes running the new software,
+    /// which has an upgrade handler registered under the given `name`, run the
+    /// handler and continue. Scheduling an upgrade overwrites any previously
+    /// scheduled one.
+    ScheduleUpgrade {
+        height: Uint64,
+        name: String,
+        info: Option<String>,
+    },
     /// Send coins to the given recipient address.
     Transfer { to: Addr, coins: Coins },
     /// Upload a Wasm binary code and store it in the chain's state.
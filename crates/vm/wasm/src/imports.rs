@@ -1,6 +1,9 @@
 use {
     crate::{read_from_memory, write_to_memory, Environment, Iterator, VmError, VmResult},
-    grug_types::{from_json_slice, to_json_vec, Addr, Querier, QueryRequest, Record, Storage},
+    grug_types::{
+        from_borsh_slice, from_json_slice, to_json_vec, Addr, Batch, Querier, QueryRequest, Record,
+        Storage,
+    },
     tracing::info,
     wasmer::FunctionEnvMut,
 };
@@ -104,6 +107,42 @@ pub fn db_remove(mut fe: FunctionEnvMut<Environment>, key_ptr: u32) -> VmResult<
     })
 }
 
+pub fn db_remove_range(
+    mut fe: FunctionEnvMut<Environment>,
+    min_ptr: u32,
+    max_ptr: u32,
+) -> VmResult<()> {
+    let (env, wasm_store) = fe.data_and_store_mut();
+
+    let min = if min_ptr != 0 {
+        Some(read_from_memory(env, &wasm_store, min_ptr)?)
+    } else {
+        None
+    };
+    let max = if max_ptr != 0 {
+        Some(read_from_memory(env, &wasm_store, max_ptr)?)
+    } else {
+        None
+    };
+
+    env.with_context_data_mut(|ctx| -> VmResult<_> {
+        ctx.storage.remove_range(min.as_deref(), max.as_deref());
+        Ok(())
+    })
+}
+
+pub fn db_write_batch(mut fe: FunctionEnvMut<Environment>, batch_ptr: u32) -> VmResult<()> {
+    let (env, wasm_store) = fe.data_and_store_mut();
+
+    let batch_bytes = read_from_memory(env, &wasm_store, batch_ptr)?;
+    let batch: Batch = from_borsh_slice(batch_bytes)?;
+
+    env.with_context_data_mut(|ctx| -> VmResult<_> {
+        ctx.storage.flush(batch);
+        Ok(())
+    })
+}
+
 pub fn debug(mut fe: FunctionEnvMut<Environment>, addr_ptr: u32, msg_ptr: u32) -> VmResult<()> {
     let (env, wasm_store) = fe.data_and_store_mut();
 
@@ -1,7 +1,8 @@
 use {
     crate::{
-        db_next, db_read, db_remove, db_scan, db_write, debug, query_chain, read_then_wipe,
-        secp256k1_verify, secp256r1_verify, write_to_memory, Environment, VmError, VmResult,
+        db_next, db_read, db_remove, db_remove_range, db_scan, db_write, db_write_batch, debug,
+        query_chain, read_then_wipe, secp256k1_verify, secp256r1_verify, write_to_memory,
+        Environment, VmError, VmResult,
     },
     grug_app::{PrefixStore, QueryProvider, Vm},
     grug_types::{to_borsh_vec, Context},
@@ -42,6 +43,8 @@ impl Vm for WasmVm {
                 "db_next" => Function::new_typed_with_env(&mut wasm_store, &fe, db_next),
                 "db_write" => Function::new_typed_with_env(&mut wasm_store, &fe, db_write),
                 "db_remove" => Function::new_typed_with_env(&mut wasm_store, &fe, db_remove),
+                "db_remove_range" => Function::new_typed_with_env(&mut wasm_store, &fe, db_remove_range),
+                "db_write_batch" => Function::new_typed_with_env(&mut wasm_store, &fe, db_write_batch),
                 "debug" => Function::new_typed_with_env(&mut wasm_store, &fe, debug),
                 "query_chain" => Function::new_typed_with_env(&mut wasm_store, &fe, query_chain),
                 "secp256k1_verify" => Function::new_typed_with_env(&mut wasm_store, &fe, secp256k1_verify),
@@ -122,3 +125,162 @@ impl Vm for WasmVm {
         read_then_wipe(env, &mut wasm_store, res_ptr)
     }
 }
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::read_from_memory,
+        grug_types::{
+            BlockInfo, MockStorage, Order, Record, Storage, Timestamp, GENESIS_BLOCK_HASH,
+        },
+    };
+
+    // a module that exposes the `db_scan` and `db_next` imports to the host,
+    // with a bump allocator that never frees memory
+    const MODULE: &str = r#"
+        (module
+            (import "env" "db_scan" (func $db_scan (param i32 i32 i32) (result i32)))
+            (import "env" "db_next" (func $db_next (param i32) (result i32)))
+            (memory (export "memory") 2)
+            (global $heap (mut i32) (i32.const 1024))
+            (func (export "allocate") (param $capacity i32) (result i32)
+                (local $region i32)
+                (local.set $region (global.get $heap))
+                (i32.store offset=0 (local.get $region) (i32.add (local.get $region) (i32.const 12)))
+                (i32.store offset=4 (local.get $region) (local.get $capacity))
+                (i32.store offset=8 (local.get $region) (i32.const 0))
+                ;; the next region starts after this one's data, aligned to 4 bytes
+                (global.set $heap
+                    (i32.and
+                        (i32.add
+                            (i32.add (local.get $region) (i32.const 15))
+                            (local.get $capacity))
+                        (i32.const -4)))
+                (local.get $region))
+            (func (export "deallocate") (param i32))
+            (func (export "scan") (param i32 i32 i32) (result i32)
+                (call $db_scan (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "next") (param i32) (result i32)
+                (call $db_next (local.get 0))))
+    "#;
+
+    const NAMESPACE: &[u8] = b"wasm";
+
+    fn key(i: u16) -> Vec<u8> {
+        i.to_be_bytes().to_vec()
+    }
+
+    fn build_vm(storage: MockStorage, contract: &[u8]) -> WasmVm {
+        let storage: Box<dyn Storage> = Box::new(storage);
+        let block = BlockInfo {
+            height: 1_u64.into(),
+            timestamp: Timestamp::from_seconds(1),
+            hash: GENESIS_BLOCK_HASH,
+        };
+        WasmVm::build_instance(
+            PrefixStore::new(storage.clone(), &[NAMESPACE, contract]),
+            QueryProvider::new(storage, block),
+            MODULE.as_bytes().to_vec(),
+        )
+        .unwrap()
+    }
+
+    // scan the contract's storage by calling `db_scan` and then `db_next` until
+    // the iterator is exhausted, decoding the records as the guest would
+    fn scan(vm: &mut WasmVm, min: Option<&[u8]>, max: Option<&[u8]>, order: Order) -> Vec<Record> {
+        let mut fe_mut = vm.fe.clone().into_mut(&mut vm.wasm_store);
+        let (env, mut wasm_store) = fe_mut.data_and_store_mut();
+
+        let mut bound_ptr = |bound: Option<&[u8]>| -> u32 {
+            bound.map_or(0, |bytes| {
+                write_to_memory(env, &mut wasm_store, bytes).unwrap()
+            })
+        };
+        let min_ptr = bound_ptr(min);
+        let max_ptr = bound_ptr(max);
+
+        let iterator_id: i32 = env
+            .call_function1(&mut wasm_store, "scan", &[
+                min_ptr.into(),
+                max_ptr.into(),
+                i32::from(order).into(),
+            ])
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        let mut records = vec![];
+        loop {
+            let record_ptr: u32 = env
+                .call_function1(&mut wasm_store, "next", &[iterator_id.into()])
+                .unwrap()
+                .try_into()
+                .unwrap();
+            if record_ptr == 0 {
+                break;
+            }
+
+            // key | value | len(key)
+            let mut bytes = read_from_memory(env, &wasm_store, record_ptr).unwrap();
+            let key_len = u16::from_be_bytes([bytes[bytes.len() - 2], bytes[bytes.len() - 1]]);
+            bytes.truncate(bytes.len() - 2);
+            let value = bytes.split_off(key_len as usize);
+            records.push((bytes, value));
+        }
+
+        records
+    }
+
+    #[test]
+    fn scanning_contract_storage() {
+        let contract = b"contract";
+
+        // more records than the host iterator pulls at once, so that the
+        // iteration spans multiple batches; plus records of other contracts
+        // right before and after this one's in the underlying storage
+        let mut storage = MockStorage::new();
+        for i in 0..100 {
+            storage.write(&[NAMESPACE, contract, &key(i)].concat(), &[1]);
+        }
+        storage.write(&[NAMESPACE, b"contrac", &key(0)].concat(), &[2]);
+        storage.write(&[NAMESPACE, b"contracu", &key(0)].concat(), &[2]);
+
+        let mut vm = build_vm(storage, contract);
+
+        // the keys are the same as the ones the contract wrote, without the
+        // namespace
+        let records = scan(&mut vm, None, None, Order::Ascending);
+        assert_eq!(
+            records,
+            (0..100).map(|i| (key(i), vec![1])).collect::<Vec<_>>()
+        );
+
+        let records = scan(&mut vm, None, None, Order::Descending);
+        assert_eq!(
+            records,
+            (0..100)
+                .rev()
+                .map(|i| (key(i), vec![1]))
+                .collect::<Vec<_>>()
+        );
+
+        // min is inclusive, max is exclusive
+        let records = scan(&mut vm, Some(&key(25)), Some(&key(75)), Order::Ascending);
+        assert_eq!(
+            records,
+            (25..75).map(|i| (key(i), vec![1])).collect::<Vec<_>>()
+        );
+
+        let records = scan(&mut vm, Some(&key(25)), Some(&key(75)), Order::Descending);
+        assert_eq!(
+            records,
+            (25..75)
+                .rev()
+                .map(|i| (key(i), vec![1]))
+                .collect::<Vec<_>>()
+        );
+    }
+}
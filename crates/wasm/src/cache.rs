@@ -0,0 +1,461 @@
+use {
+    crate::ExternalStorage,
+    grug_types::{Batch, Merged, Op, Order, Record, Storage},
+    std::{
+        collections::BTreeMap,
+        iter, mem,
+        ops::Bound,
+        sync::{Mutex, MutexGuard},
+    },
+};
+
+struct Cache {
+    /// Values that have been read from the host. `None` means the record
+    /// doesn't exist.
+    reads: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// Writes that haven't yet been sent to the host.
+    pending: Batch,
+}
+
+impl Cache {
+    const fn new() -> Self {
+        Self {
+            reads: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+// the cache is global, instead of being owned by `CachedStorage`, because the
+// buffered writes also need to be flushed by `ExternalQuerier` before querying
+// the chain. Wasm modules are single-threaded, so the lock is never contended.
+static CACHE: Mutex<Cache> = Mutex::new(Cache::new());
+
+// the global cache over the external storage
+fn external() -> Cached<'static, ExternalStorage> {
+    Cached {
+        cache: &CACHE,
+        base: &ExternalStorage,
+    }
+}
+
+/// Send the buffered writes to the host, in one `db_write_batch` call.
+pub(crate) fn flush_storage_cache() {
+    external().flush();
+}
+
+/// Clear the cache, discarding the buffered writes.
+pub(crate) fn reset_storage_cache() {
+    external().reset();
+}
+
+/// A caching layer over `ExternalStorage`, enabled by the `storage-cache`
+/// feature.
+///
+/// Reads are memoized and writes are buffered, so that a contract accessing
+/// the same records repeatedly only crosses the Wasm boundary once for each.
+/// The buffered writes are sent to the host in one call at the end of the
+/// entry point, if it succeeds, or before the contract queries the chain.
+#[derive(Clone)]
+pub struct CachedStorage;
+
+impl Storage for CachedStorage {
+    fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+        external().read(key)
+    }
+
+    fn scan<'a>(
+        &'a self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        external().scan(min, max, order)
+    }
+
+    fn write(&mut self, key: &[u8], value: &[u8]) {
+        external().write(key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        external().remove(key);
+    }
+
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        external().remove_range(min, max);
+    }
+
+    fn flush(&mut self, batch: Batch) {
+        external().extend(batch);
+    }
+}
+
+/// The caching logic, over any base storage. `CachedStorage` uses it with the
+/// global cache over the external storage; this separation allows it to be
+/// tested against a mock storage.
+struct Cached<'a, B> {
+    cache: &'a Mutex<Cache>,
+    base: &'a B,
+}
+
+// implemented manually, because the derive would require `B: Copy`
+impl<'a, B> Clone for Cached<'a, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, B> Copy for Cached<'a, B> {}
+
+impl<'a, B> Cached<'a, B>
+where
+    B: Storage + Clone,
+{
+    fn lock(self) -> MutexGuard<'a, Cache> {
+        // the lock can only be poisoned if the contract panicked, in which case
+        // the call is aborted anyways
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn read(self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut cache = self.lock();
+
+        if let Some(op) = cache.pending.get(key) {
+            return op.clone().into_option();
+        }
+
+        if let Some(value) = cache.reads.get(key) {
+            return value.clone();
+        }
+
+        let value = self.base.read(key);
+        cache.reads.insert(key.to_vec(), value.clone());
+
+        value
+    }
+
+    fn scan(
+        self,
+        min: Option<&[u8]>,
+        max: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Box::new(iter::empty());
+            }
+        }
+
+        let base = self.base.scan(min, max, order);
+
+        // the pending ops in the range are copied, so that the lock isn't held
+        // during iteration. this means writes made during the iteration aren't
+        // reflected, same as with the host's iterators.
+        let pending = {
+            let min = min.map_or(Bound::Unbounded, |bytes| Bound::Included(bytes.to_vec()));
+            let max = max.map_or(Bound::Unbounded, |bytes| Bound::Excluded(bytes.to_vec()));
+            let mut pending = self
+                .lock()
+                .pending
+                .range((min, max))
+                .map(|(key, op)| (key.clone(), op.clone()))
+                .collect::<Vec<_>>();
+            if order == Order::Descending {
+                pending.reverse();
+            }
+            pending
+        };
+
+        Box::new(Merged::new(base, pending.into_iter(), order))
+    }
+
+    fn write(self, key: &[u8], value: &[u8]) {
+        self.lock()
+            .pending
+            .insert(key.to_vec(), Op::Insert(value.to_vec()));
+    }
+
+    fn remove(self, key: &[u8]) {
+        self.lock().pending.insert(key.to_vec(), Op::Delete);
+    }
+
+    // the range is removed by the host in one call. pending ops and memoized
+    // reads in the range are discarded, while those outside it are unaffected.
+    fn remove_range(self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return;
+            }
+        }
+
+        {
+            let in_range = |key: &Vec<u8>| {
+                min.map_or(true, |min| key.as_slice() >= min)
+                    && max.map_or(true, |max| key.as_slice() < max)
+            };
+            let mut cache = self.lock();
+            cache.pending.retain(|key, _| !in_range(key));
+            cache.reads.retain(|key, _| !in_range(key));
+        }
+
+        self.base.clone().remove_range(min, max);
+    }
+
+    fn extend(self, batch: Batch) {
+        self.lock().pending.extend(batch);
+    }
+
+    fn flush(self) {
+        let pending = {
+            let mut cache = self.lock();
+            let pending = mem::take(&mut cache.pending);
+
+            // the values just written are what the host would return if read
+            for (key, op) in &pending {
+                cache.reads.insert(key.clone(), op.clone().into_option());
+            }
+
+            pending
+        };
+
+        if !pending.is_empty() {
+            self.base.clone().flush(pending);
+        }
+    }
+
+    fn reset(self) {
+        let mut cache = self.lock();
+        cache.reads.clear();
+        cache.pending.clear();
+    }
+}
+
+// ----------------------------------- tests -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        grug_types::MockStorage,
+        std::sync::{Arc, Mutex},
+    };
+
+    // a mock of the host's storage, recording the calls made to it
+    #[derive(Clone, Default)]
+    struct MockHost {
+        storage: Arc<Mutex<MockStorage>>,
+        calls: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl MockHost {
+        fn new(records: &[(&[u8], &[u8])]) -> Self {
+            let host = Self::default();
+            for (key, value) in records {
+                host.storage.lock().unwrap().write(key, value);
+            }
+            host
+        }
+
+        fn take_calls(&self) -> Vec<&'static str> {
+            mem::take(&mut self.calls.lock().unwrap())
+        }
+
+        fn record(&self, call: &'static str) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl Storage for MockHost {
+        fn read(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.record("read");
+            self.storage.lock().unwrap().read(key)
+        }
+
+        fn scan<'a>(
+            &'a self,
+            min: Option<&[u8]>,
+            max: Option<&[u8]>,
+            order: Order,
+        ) -> Box<dyn Iterator<Item = Record> + 'a> {
+            self.record("scan");
+            let records = self
+                .storage
+                .lock()
+                .unwrap()
+                .scan(min, max, order)
+                .collect::<Vec<_>>();
+            Box::new(records.into_iter())
+        }
+
+        fn write(&mut self, key: &[u8], value: &[u8]) {
+            self.record("write");
+            self.storage.lock().unwrap().write(key, value);
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.record("remove");
+            self.storage.lock().unwrap().remove(key);
+        }
+
+        fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+            self.record("remove_range");
+            self.storage.lock().unwrap().remove_range(min, max);
+        }
+
+        fn flush(&mut self, batch: Batch) {
+            self.record("flush");
+            self.storage.lock().unwrap().flush(batch);
+        }
+    }
+
+    fn records(records: &[(&[u8], &[u8])]) -> Vec<Record> {
+        records
+            .iter()
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect()
+    }
+
+    fn cached<'a>(cache: &'a Mutex<Cache>, host: &'a MockHost) -> Cached<'a, MockHost> {
+        Cached { cache, base: host }
+    }
+
+    #[test]
+    fn reading_own_writes() {
+        let host = MockHost::new(&[(b"a", b"1"), (b"b", b"2")]);
+        let cache = Mutex::new(Cache::new());
+        let cached = cached(&cache, &host);
+
+        // reads are memoized, including of records that don't exist
+        assert_eq!(cached.read(b"a"), Some(b"1".to_vec()));
+        assert_eq!(cached.read(b"a"), Some(b"1".to_vec()));
+        assert_eq!(cached.read(b"c"), None);
+        assert_eq!(cached.read(b"c"), None);
+        assert_eq!(host.take_calls(), ["read", "read"]);
+
+        // writes are visible to subsequent reads, without reaching the host
+        cached.write(b"a", b"10");
+        cached.remove(b"b");
+        cached.write(b"c", b"3");
+        assert_eq!(cached.read(b"a"), Some(b"10".to_vec()));
+        assert_eq!(cached.read(b"b"), None);
+        assert_eq!(cached.read(b"c"), Some(b"3".to_vec()));
+        assert!(host.take_calls().is_empty());
+        assert_eq!(host.read(b"a"), Some(b"1".to_vec()));
+
+        // resetting discards the writes
+        cached.reset();
+        host.take_calls();
+        assert_eq!(cached.read(b"a"), Some(b"1".to_vec()));
+        assert_eq!(host.take_calls(), ["read"]);
+    }
+
+    // illustration of this test case:
+    //
+    // host    : 1 2 _ 4 5 6 7 _
+    // pending :   D P _ _ P D 8  (P = put, D = delete)
+    // merged  : 1 _ 3 4 5 6 _ 8
+    #[test]
+    fn scanning_merges_pending_writes() {
+        let host = MockHost::new(&[
+            (&[1], &[1]),
+            (&[2], &[2]),
+            (&[4], &[4]),
+            (&[5], &[5]),
+            (&[6], &[6]),
+            (&[7], &[7]),
+        ]);
+        let cache = Mutex::new(Cache::new());
+        let cached = cached(&cache, &host);
+
+        cached.remove(&[2]);
+        cached.write(&[3], &[3]);
+        cached.write(&[6], &[255]);
+        cached.remove(&[7]);
+        cached.write(&[8], &[8]);
+
+        let merged = [
+            (vec![1], vec![1]),
+            (vec![3], vec![3]),
+            (vec![4], vec![4]),
+            (vec![5], vec![5]),
+            (vec![6], vec![255]),
+            (vec![8], vec![8]),
+        ];
+
+        let asc = cached
+            .scan(None, None, Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(asc, merged);
+
+        let desc = cached
+            .scan(None, None, Order::Descending)
+            .collect::<Vec<_>>();
+        assert_eq!(desc, merged.iter().rev().cloned().collect::<Vec<_>>());
+
+        let bounded = cached
+            .scan(Some(&[2]), Some(&[7]), Order::Ascending)
+            .collect::<Vec<_>>();
+        assert_eq!(bounded, merged[1..5]);
+    }
+
+    #[test]
+    fn removing_range() {
+        let host = MockHost::new(&[(b"a", b"1"), (b"b", b"2"), (b"c", b"3")]);
+        let cache = Mutex::new(Cache::new());
+        let cached = cached(&cache, &host);
+
+        // memoize a read and buffer some writes, in and out of the range
+        assert_eq!(cached.read(b"b"), Some(b"2".to_vec()));
+        cached.write(b"a", b"10");
+        cached.write(b"bb", b"20");
+        cached.write(b"d", b"4");
+        host.take_calls();
+
+        // the range is removed by the host in one call
+        cached.remove_range(Some(b"b"), Some(b"c"));
+        assert_eq!(host.take_calls(), ["remove_range"]);
+
+        // the memoized read and the write in the range are discarded, while
+        // those outside it are kept
+        assert_eq!(cached.read(b"b"), None);
+        assert_eq!(cached.read(b"bb"), None);
+        assert_eq!(cached.read(b"a"), Some(b"10".to_vec()));
+        assert_eq!(cached.read(b"d"), Some(b"4".to_vec()));
+        assert_eq!(host.take_calls(), ["read", "read"]);
+
+        assert_eq!(
+            cached
+                .scan(None, None, Order::Ascending)
+                .collect::<Vec<_>>(),
+            records(&[(b"a", b"10"), (b"c", b"3"), (b"d", b"4")])
+        );
+    }
+
+    #[test]
+    fn flushing_in_one_batch() {
+        let host = MockHost::new(&[(b"a", b"1"), (b"b", b"2")]);
+        let cache = Mutex::new(Cache::new());
+        let cached = cached(&cache, &host);
+
+        cached.write(b"a", b"10");
+        cached.remove(b"b");
+        cached.write(b"c", b"3");
+        cached.extend(Batch::from([(b"d".to_vec(), Op::Insert(b"4".to_vec()))]));
+
+        // nothing to send if there's no write
+        cached.flush();
+        cached.flush();
+        assert_eq!(host.take_calls(), ["flush"]);
+
+        assert_eq!(
+            host.scan(None, None, Order::Ascending).collect::<Vec<_>>(),
+            records(&[(b"a", b"10"), (b"c", b"3"), (b"d", b"4")])
+        );
+        host.take_calls();
+
+        // the flushed values are served from the cache afterwards
+        assert_eq!(cached.read(b"a"), Some(b"10".to_vec()));
+        assert_eq!(cached.read(b"b"), None);
+        assert!(host.take_calls().is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 use {
     grug_types::{
-        from_json_value, to_json_value, AccountResponse, Addr, Api, Binary, Coins, Hash,
-        InfoResponse, Querier, QueryRequest, StdResult, Storage, Timestamp, Uint128, Uint64,
+        from_json_value, to_json_value, AccountResponse, Addr, Api, Binary, Coin, Coins,
+        GenericResult, Hash, InfoResponse, Page, PageRequest, Querier, QueryRequest, QueryResponse,
+        StdResult, Storage, Timestamp, Uint128, Uint64,
     },
     serde::{de::DeserializeOwned, ser::Serialize},
 };
@@ -135,15 +136,10 @@ macro_rules! impl_methods {
             pub fn query_balances(
                 &self,
                 address: Addr,
-                start_after: Option<String>,
-                limit: Option<u32>,
-            ) -> StdResult<Coins> {
+                page: PageRequest,
+            ) -> StdResult<Page<Coin>> {
                 self.querier
-                    .query_chain(QueryRequest::Balances {
-                        address,
-                        start_after,
-                        limit,
-                    })
+                    .query_chain(QueryRequest::Balances { address, page })
                     .map(|res| res.as_balances())
             }
 
@@ -155,13 +151,9 @@ macro_rules! impl_methods {
             }
 
             #[inline]
-            pub fn query_supplies(
-                &self,
-                start_after: Option<String>,
-                limit: Option<u32>,
-            ) -> StdResult<Coins> {
+            pub fn query_supplies(&self, page: PageRequest) -> StdResult<Page<Coin>> {
                 self.querier
-                    .query_chain(QueryRequest::Supplies { start_after, limit })
+                    .query_chain(QueryRequest::Supplies { page })
                     .map(|res| res.as_supplies())
             }
 
@@ -173,13 +165,9 @@ macro_rules! impl_methods {
             }
 
             #[inline]
-            pub fn query_codes(
-                &self,
-                start_after: Option<Hash>,
-                limit: Option<u32>,
-            ) -> StdResult<Vec<Hash>> {
+            pub fn query_codes(&self, page: PageRequest) -> StdResult<Page<Hash>> {
                 self.querier
-                    .query_chain(QueryRequest::Codes { start_after, limit })
+                    .query_chain(QueryRequest::Codes { page })
                     .map(|res| res.as_codes())
             }
 
@@ -191,13 +179,9 @@ macro_rules! impl_methods {
             }
 
             #[inline]
-            pub fn query_accounts(
-                &self,
-                start_after: Option<Addr>,
-                limit: Option<u32>,
-            ) -> StdResult<Vec<AccountResponse>> {
+            pub fn query_accounts(&self, page: PageRequest) -> StdResult<Page<AccountResponse>> {
                 self.querier
-                    .query_chain(QueryRequest::Accounts { start_after, limit })
+                    .query_chain(QueryRequest::Accounts { page })
                     .map(|res| res.as_accounts())
             }
 
@@ -208,6 +192,24 @@ macro_rules! impl_methods {
                     .map(|res| res.as_wasm_raw().value)
             }
 
+            #[inline]
+            pub fn query_wasm_raw_range(
+                &self,
+                contract: Addr,
+                min: Option<Binary>,
+                max: Option<Binary>,
+                page: PageRequest,
+            ) -> StdResult<Page<(Binary, Binary)>> {
+                self.querier
+                    .query_chain(QueryRequest::WasmRawRange {
+                        contract,
+                        min,
+                        max,
+                        page,
+                    })
+                    .map(|res| res.as_wasm_raw_range())
+            }
+
             #[inline]
             pub fn query_wasm_smart<M: Serialize, R: DeserializeOwned>(
                 &self,
@@ -221,6 +223,16 @@ macro_rules! impl_methods {
                     })
                     .and_then(|res| from_json_value(res.as_wasm_smart().data))
             }
+
+            #[inline]
+            pub fn query_multi(
+                &self,
+                reqs: Vec<QueryRequest>,
+            ) -> StdResult<Vec<GenericResult<QueryResponse>>> {
+                self.querier
+                    .query_chain(QueryRequest::Multi(reqs))
+                    .map(|res| res.as_multi())
+            }
         }
     };
 }
@@ -1,8 +1,8 @@
 use {
     crate::{
         make_auth_ctx, make_immutable_ctx, make_mutable_ctx, make_sudo_ctx,
-        unwrap_into_generic_result, AuthCtx, ExternalApi, ExternalQuerier, ExternalStorage,
-        ImmutableCtx, MutableCtx, Region, SudoCtx,
+        unwrap_into_generic_result, AuthCtx, ExternalApi, ExternalQuerier, ImmutableCtx,
+        MutableCtx, Region, SudoCtx,
     },
     grug_types::{
         from_borsh_slice, from_json_slice, to_json_vec, BankQueryMsg, BankQueryResponse, Context,
@@ -12,6 +12,27 @@ use {
     serde::de::DeserializeOwned,
 };
 
+// the storage provided to the entry points. with the `storage-cache` feature,
+// this is a caching layer over the external storage.
+#[cfg(feature = "storage-cache")]
+use crate::CachedStorage as ContractStorage;
+#[cfg(not(feature = "storage-cache"))]
+use crate::ExternalStorage as ContractStorage;
+
+// called at the end of each entry point. with the `storage-cache` feature, the
+// buffered writes are sent to the host if the call succeeded, and the cache is
+// cleared either way, so that nothing is carried over to the next call.
+#[cfg(feature = "storage-cache")]
+fn finalize<T>(res: &GenericResult<T>) {
+    if let GenericResult::Ok(_) = res {
+        crate::flush_storage_cache();
+    }
+    crate::reset_storage_cache();
+}
+
+#[cfg(not(feature = "storage-cache"))]
+fn finalize<T>(_res: &GenericResult<T>) {}
+
 // ----------------------------------- alloc -----------------------------------
 
 /// Reserve a region in Wasm memory of the given number of bytes. Return the
@@ -47,6 +68,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_instantiate(instantiate_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -62,7 +84,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let mutable_ctx = make_mutable_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let mutable_ctx = make_mutable_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     instantiate_fn(mutable_ctx, msg).into()
@@ -83,6 +105,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_execute(execute_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -98,7 +121,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let mutable_ctx = make_mutable_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let mutable_ctx = make_mutable_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     execute_fn(mutable_ctx, msg).into()
@@ -119,6 +142,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_query(query_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -134,7 +158,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let immutable_ctx = make_immutable_ctx!(ctx, &ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let immutable_ctx = make_immutable_ctx!(ctx, &ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     query_fn(immutable_ctx, msg).into()
@@ -155,6 +179,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_migrate(migrate_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -170,7 +195,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let mutable_ctx = make_mutable_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let mutable_ctx = make_mutable_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     migrate_fn(mutable_ctx, msg).into()
@@ -193,6 +218,7 @@ where
     let events_bytes = unsafe { Region::consume(events_ptr as *mut Region) };
 
     let res = _do_reply(reply_fn, &ctx_bytes, &msg_bytes, &events_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -209,7 +235,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let sudo_ctx = make_sudo_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let sudo_ctx = make_sudo_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
     let events = unwrap_into_generic_result!(from_json_slice(events_bytes));
 
@@ -228,6 +254,7 @@ where
     let ctx_bytes = unsafe { Region::consume(ctx_ptr as *mut Region) };
 
     let res = _do_receive(receive_fn, &ctx_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -241,7 +268,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let mutable_ctx = make_mutable_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let mutable_ctx = make_mutable_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
 
     receive_fn(mutable_ctx).into()
 }
@@ -258,6 +285,7 @@ where
     let ctx_bytes = unsafe { Region::consume(ctx_ptr as *mut Region) };
 
     let res = _do_before_block(before_block_fn, &ctx_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -271,7 +299,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let sudo_ctx = make_sudo_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let sudo_ctx = make_sudo_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
 
     before_block_fn(sudo_ctx).into()
 }
@@ -288,6 +316,7 @@ where
     let ctx_bytes = unsafe { Region::consume(ctx_ptr as *mut Region) };
 
     let res = _do_after_block(after_block_fn, &ctx_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -301,7 +330,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let sudo_ctx = make_sudo_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let sudo_ctx = make_sudo_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
 
     after_block_fn(sudo_ctx).into()
 }
@@ -320,6 +349,7 @@ where
     let tx_bytes = unsafe { Region::consume(tx_ptr as *mut Region) };
 
     let res = _do_before_tx(before_tx_fn, &ctx_bytes, &tx_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -334,7 +364,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let auth_ctx = make_auth_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let auth_ctx = make_auth_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let tx = unwrap_into_generic_result!(from_json_slice(tx_bytes));
 
     before_tx_fn(auth_ctx, tx).into()
@@ -354,6 +384,7 @@ where
     let tx_bytes = unsafe { Region::consume(tx_ptr as *mut Region) };
 
     let res = _do_after_tx(after_tx_fn, &ctx_bytes, &tx_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -368,7 +399,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let auth_ctx = make_auth_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let auth_ctx = make_auth_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let tx = unwrap_into_generic_result!(from_json_slice(tx_bytes));
 
     after_tx_fn(auth_ctx, tx).into()
@@ -388,6 +419,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_bank_transfer(transfer_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -402,7 +434,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let sudo_ctx = make_sudo_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let sudo_ctx = make_sudo_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     transfer_fn(sudo_ctx, msg).into()
@@ -422,6 +454,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_bank_query(query_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -436,7 +469,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let immutable_ctx = make_immutable_ctx!(ctx, &ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let immutable_ctx = make_immutable_ctx!(ctx, &ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     query_fn(immutable_ctx, msg).into()
@@ -463,6 +496,7 @@ where
         &client_state_bytes,
         &consensus_state_bytes,
     );
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -478,7 +512,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let sudo_ctx = make_sudo_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let sudo_ctx = make_sudo_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let client_state_bytes = unwrap_into_generic_result!(from_json_slice(client_state_bytes));
     let consensus_state_bytes = unwrap_into_generic_result!(from_json_slice(consensus_state_bytes));
 
@@ -499,6 +533,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_ibc_client_update(update_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -513,7 +548,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let sudo_ctx = make_sudo_ctx!(ctx, &mut ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let sudo_ctx = make_sudo_ctx!(ctx, &mut ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     update_fn(sudo_ctx, msg).into()
@@ -533,6 +568,7 @@ where
     let msg_bytes = unsafe { Region::consume(msg_ptr as *mut Region) };
 
     let res = _do_ibc_client_verify(verify_fn, &ctx_bytes, &msg_bytes);
+    finalize(&res);
     let res_bytes = to_json_vec(&res).unwrap();
 
     Region::release_buffer(res_bytes) as usize
@@ -547,7 +583,7 @@ where
     E: ToString,
 {
     let ctx: Context = unwrap_into_generic_result!(from_borsh_slice(ctx_bytes));
-    let immutable_ctx = make_immutable_ctx!(ctx, &ExternalStorage, &ExternalApi, &ExternalQuerier);
+    let immutable_ctx = make_immutable_ctx!(ctx, &ContractStorage, &ExternalApi, &ExternalQuerier);
     let msg = unwrap_into_generic_result!(from_json_slice(msg_bytes));
 
     verify_fn(immutable_ctx, msg).into()
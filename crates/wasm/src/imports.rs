@@ -1,8 +1,8 @@
 use {
     crate::Region,
     grug_types::{
-        from_json_slice, to_json_vec, Addr, Api, GenericResult, Order, Querier, QueryRequest,
-        QueryResponse, Record, StdError, StdResult, Storage,
+        from_json_slice, to_borsh_vec, to_json_vec, Addr, Api, Batch, GenericResult, Order,
+        Querier, QueryRequest, QueryResponse, Record, StdError, StdResult, Storage,
     },
 };
 
@@ -24,6 +24,8 @@ extern "C" {
     // write ops (mutate the state):
     fn db_write(key_ptr: usize, value_ptr: usize);
     fn db_remove(key_ptr: usize);
+    fn db_remove_range(min_ptr: usize, max_ptr: usize);
+    fn db_write_batch(batch_ptr: usize);
 
     // print a debug message to the client's CLI output.
     fn debug(addr_ptr: usize, msg_ptr: usize);
@@ -108,6 +110,25 @@ impl Storage for ExternalStorage {
 
         unsafe { db_remove(key_ptr as usize) }
     }
+
+    fn remove_range(&mut self, min: Option<&[u8]>, max: Option<&[u8]>) {
+        let min_region = min.map(Region::build);
+        let min_ptr = get_optional_region_ptr(min_region.as_ref());
+
+        let max_region = max.map(Region::build);
+        let max_ptr = get_optional_region_ptr(max_region.as_ref());
+
+        unsafe { db_remove_range(min_ptr, max_ptr) }
+    }
+
+    // the batch is sent to the host in one call, in Borsh encoding.
+    fn flush(&mut self, batch: Batch) {
+        let batch_bytes = to_borsh_vec(&batch).unwrap();
+        let batch_region = Region::build(&batch_bytes);
+        let batch_ptr = &*batch_region as *const Region;
+
+        unsafe { db_write_batch(batch_ptr as usize) }
+    }
 }
 
 pub struct ExternalIterator {
@@ -231,6 +252,11 @@ pub struct ExternalQuerier;
 
 impl Querier for ExternalQuerier {
     fn query_chain(&self, req: QueryRequest) -> StdResult<QueryResponse> {
+        // the host must see the buffered writes, in case the contract queries
+        // itself.
+        #[cfg(feature = "storage-cache")]
+        crate::flush_storage_cache();
+
         let req_bytes = to_json_vec(&req)?;
         let req_region = Region::build(&req_bytes);
         let req_ptr = &*req_region as *const Region;
@@ -1,3 +1,5 @@
+#[cfg(feature = "storage-cache")]
+mod cache;
 mod contexts;
 mod exports;
 mod imports;
@@ -7,3 +9,6 @@ mod memory;
 // Note: We don't need to `pub use macros::*` here because the `#[macro_export]`
 // annotation alrady does that. Rust macros work in quirky ways.
 pub use {contexts::*, exports::*, imports::*, memory::*};
+
+#[cfg(feature = "storage-cache")]
+pub use cache::*;
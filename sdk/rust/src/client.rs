@@ -3,11 +3,14 @@ use {
     anyhow::{bail, ensure},
     grug::{
         from_json_slice, from_json_value, hash, to_json_value, to_json_vec, AccountResponse, Addr,
-        Binary, Coin, Coins, Config, Hash, InfoResponse, Message, QueryRequest, QueryResponse, Tx,
+        Binary, Bound, Codec, Coin, Coins, Config, GenericResult, Hash, InfoResponse, Item, Map,
+        MapKey, Message, Page, PageRequest, QueryRequest, QueryResponse, StdResult, Tx, Uint64,
         WasmRawResponse,
     },
     grug_account::{QueryMsg, StateResponse},
-    grug_jmt::Proof,
+    grug_jmt::{BatchProof, Proof, ICS23_PROOF_TYPE},
+    ics23::CommitmentProof,
+    prost::Message,
     serde::{de::DeserializeOwned, ser::Serialize},
     std::any::type_name,
     tendermint::block::Height,
@@ -92,7 +95,7 @@ impl Client {
         key: Vec<u8>,
         height: Option<u64>,
         prove: bool,
-    ) -> anyhow::Result<(Option<Vec<u8>>, Option<Proof>)> {
+    ) -> anyhow::Result<(Option<Vec<u8>>, Option<CommitmentProof>)> {
         let res = self.query("/store", key.clone(), height, prove).await?;
         let value = if res.value.is_empty() {
             None
@@ -103,15 +106,43 @@ impl Client {
             ensure!(res.proof.is_some());
             let proof = res.proof.unwrap();
             ensure!(proof.ops.len() == 1);
-            ensure!(proof.ops[0].field_type == type_name::<Proof>());
+            ensure!(proof.ops[0].field_type == ICS23_PROOF_TYPE);
             ensure!(proof.ops[0].key == key);
-            Some(from_json_slice(&proof.ops[0].data)?)
+            Some(CommitmentProof::decode(proof.ops[0].data.as_slice())?)
         } else {
             None
         };
         Ok((value, proof))
     }
 
+    /// Query multiple raw keys at once, at most `MAX_BATCH_PROOF_KEYS` of them.
+    /// If a proof is requested, a single Merkle proof is returned for all the
+    /// keys.
+    pub async fn query_store_many(
+        &self,
+        keys: Vec<Binary>,
+        height: Option<u64>,
+        prove: bool,
+    ) -> anyhow::Result<(Vec<Option<Binary>>, Option<BatchProof>)> {
+        let data = to_json_vec(&keys)?;
+        let res = self
+            .query("/store/batch", data.clone(), height, prove)
+            .await?;
+        let values: Vec<Option<Binary>> = from_json_slice(&res.value)?;
+        ensure!(values.len() == keys.len());
+        let proof = if prove {
+            ensure!(res.proof.is_some());
+            let proof = res.proof.unwrap();
+            ensure!(proof.ops.len() == 1);
+            ensure!(proof.ops[0].field_type == type_name::<BatchProof>());
+            ensure!(proof.ops[0].key == data);
+            Some(from_json_slice(&proof.ops[0].data)?)
+        } else {
+            None
+        };
+        Ok((values, proof))
+    }
+
     pub async fn query_app(
         &self,
         req: &QueryRequest,
@@ -123,6 +154,42 @@ impl Client {
         Ok(from_json_slice(res.value)?)
     }
 
+    /// Perform a query, and request a Merkle proof of the response. Only
+    /// `Code`, `Account`, and `WasmRaw` queries can be proved.
+    ///
+    /// The response is `None` if the key being proved doesn't exist, in which
+    /// case the proof is a non-membership proof.
+    ///
+    /// Also returns the height the query was performed at, which is the latest
+    /// height if unspecified. Use `verify_query_proof` to verify the proof
+    /// against the header of the block after it.
+    ///
+    /// Unlike `query_store`, which returns an ICS-23 proof, this returns a
+    /// JSON-encoded Merkle proof in our own format.
+    pub async fn query_app_with_proof(
+        &self,
+        req: &QueryRequest,
+        height: Option<u64>,
+    ) -> anyhow::Result<(Option<QueryResponse>, Proof, u64)> {
+        let res = self
+            .query("/app", to_json_vec(req)?.to_vec(), height, true)
+            .await?;
+        let value = if res.value.is_empty() {
+            None
+        } else {
+            Some(from_json_slice(res.value)?)
+        };
+        ensure!(res.proof.is_some());
+        let proof = res.proof.unwrap();
+        ensure!(proof.ops.len() == 1);
+        ensure!(proof.ops[0].field_type == type_name::<Proof>());
+        Ok((
+            value,
+            from_json_slice(&proof.ops[0].data)?,
+            res.height.value(),
+        ))
+    }
+
     pub async fn query_info(&self, height: Option<u64>) -> anyhow::Result<InfoResponse> {
         let res = self.query_app(&QueryRequest::Info {}, height).await?;
         Ok(res.as_info())
@@ -143,19 +210,11 @@ impl Client {
     pub async fn query_balances(
         &self,
         address: Addr,
-        start_after: Option<String>,
-        limit: Option<u32>,
+        page: PageRequest,
         height: Option<u64>,
-    ) -> anyhow::Result<Coins> {
+    ) -> anyhow::Result<Page<Coin>> {
         let res = self
-            .query_app(
-                &QueryRequest::Balances {
-                    address,
-                    start_after,
-                    limit,
-                },
-                height,
-            )
+            .query_app(&QueryRequest::Balances { address, page }, height)
             .await?;
         Ok(res.as_balances())
     }
@@ -169,12 +228,11 @@ impl Client {
 
     pub async fn query_supplies(
         &self,
-        start_after: Option<String>,
-        limit: Option<u32>,
+        page: PageRequest,
         height: Option<u64>,
-    ) -> anyhow::Result<Coins> {
+    ) -> anyhow::Result<Page<Coin>> {
         let res = self
-            .query_app(&QueryRequest::Supplies { start_after, limit }, height)
+            .query_app(&QueryRequest::Supplies { page }, height)
             .await?;
         Ok(res.as_supplies())
     }
@@ -186,12 +244,11 @@ impl Client {
 
     pub async fn query_codes(
         &self,
-        start_after: Option<Hash>,
-        limit: Option<u32>,
+        page: PageRequest,
         height: Option<u64>,
-    ) -> anyhow::Result<Vec<Hash>> {
+    ) -> anyhow::Result<Page<Hash>> {
         let res = self
-            .query_app(&QueryRequest::Codes { start_after, limit }, height)
+            .query_app(&QueryRequest::Codes { page }, height)
             .await?;
         Ok(res.as_codes())
     }
@@ -209,12 +266,11 @@ impl Client {
 
     pub async fn query_accounts(
         &self,
-        start_after: Option<Addr>,
-        limit: Option<u32>,
+        page: PageRequest,
         height: Option<u64>,
-    ) -> anyhow::Result<Vec<AccountResponse>> {
+    ) -> anyhow::Result<Page<AccountResponse>> {
         let res = self
-            .query_app(&QueryRequest::Accounts { start_after, limit }, height)
+            .query_app(&QueryRequest::Accounts { page }, height)
             .await?;
         Ok(res.as_accounts())
     }
@@ -231,6 +287,92 @@ impl Client {
         Ok(res.as_wasm_raw())
     }
 
+    pub async fn query_wasm_raw_range(
+        &self,
+        contract: Addr,
+        min: Option<Binary>,
+        max: Option<Binary>,
+        page: PageRequest,
+        height: Option<u64>,
+    ) -> anyhow::Result<Page<(Binary, Binary)>> {
+        let req = QueryRequest::WasmRawRange {
+            contract,
+            min,
+            max,
+            page,
+        };
+        let res = self.query_app(&req, height).await?;
+        Ok(res.as_wasm_raw_range())
+    }
+
+    /// Load an `Item` from a contract's storage, without having to go through
+    /// the contract's query entry point.
+    pub async fn query_item<T, E>(
+        &self,
+        contract: Addr,
+        item: &Item<'_, T, E>,
+        height: Option<u64>,
+    ) -> anyhow::Result<Option<T>>
+    where
+        E: Codec<T>,
+    {
+        let key = item.storage_key().to_vec().into();
+        let res = self.query_wasm_raw(contract, key, height).await?;
+        Ok(res.value.map(|bytes| item.decode(&bytes)).transpose()?)
+    }
+
+    /// Load a single entry of a `Map` from a contract's storage, without
+    /// having to go through the contract's query entry point.
+    pub async fn query_map_entry<K, T, E>(
+        &self,
+        contract: Addr,
+        map: &Map<'_, K, T, E>,
+        key: K,
+        height: Option<u64>,
+    ) -> anyhow::Result<Option<T>>
+    where
+        K: MapKey,
+        E: Codec<T>,
+    {
+        let key = map.storage_key(key).into();
+        let res = self.query_wasm_raw(contract, key, height).await?;
+        Ok(res.value.map(|bytes| map.decode(&bytes)).transpose()?)
+    }
+
+    /// Enumerate entries of a `Map` in a contract's storage, in ascending
+    /// order, without having to go through the contract's query entry point.
+    ///
+    /// To get the next page, pass the same bounds with `Page::next_request`.
+    #[allow(clippy::type_complexity)]
+    pub async fn query_map_range<K, T, E>(
+        &self,
+        contract: Addr,
+        map: &Map<'_, K, T, E>,
+        min: Option<Bound<K>>,
+        max: Option<Bound<K>>,
+        page: PageRequest,
+        height: Option<u64>,
+    ) -> anyhow::Result<Page<(K::Output, T)>>
+    where
+        K: MapKey,
+        E: Codec<T>,
+    {
+        let (min, max) = map.storage_bounds(min, max);
+        let res = self
+            .query_wasm_raw_range(contract, Some(min.into()), Some(max.into()), page, height)
+            .await?;
+        let items = res
+            .items
+            .into_iter()
+            .map(|(key, value)| map.decode_record(&key, &value))
+            .collect::<StdResult<_>>()?;
+        Ok(Page {
+            items,
+            next: res.next,
+            total: res.total,
+        })
+    }
+
     pub async fn query_wasm_smart<M: Serialize, R: DeserializeOwned>(
         &self,
         contract: Addr,
@@ -244,6 +386,18 @@ impl Client {
         Ok(from_json_value(res.as_wasm_smart().data)?)
     }
 
+    /// Perform multiple queries in a single request. All queries are evaluated
+    /// against the same state snapshot. The results are returned in the same
+    /// order as the requests.
+    pub async fn query_multi(
+        &self,
+        reqs: Vec<QueryRequest>,
+        height: Option<u64>,
+    ) -> anyhow::Result<Vec<GenericResult<QueryResponse>>> {
+        let res = self.query_app(&QueryRequest::Multi(reqs), height).await?;
+        Ok(res.as_multi())
+    }
+
     // ------------------------------ tx methods -------------------------------
 
     /// Create, sign, and broadcast a transaction without confirmation.
@@ -309,6 +463,21 @@ impl Client {
             .await
     }
 
+    pub async fn schedule_upgrade(
+        &self,
+        height: u64,
+        name: impl Into<String>,
+        info: Option<String>,
+        sign_opts: &SigningOptions,
+    ) -> anyhow::Result<tx_sync::Response> {
+        let msg = Message::ScheduleUpgrade {
+            height: Uint64::new(height),
+            name: name.into(),
+            info,
+        };
+        self.send_tx(vec![msg], sign_opts).await
+    }
+
     pub async fn transfer(
         &self,
         to: Addr,
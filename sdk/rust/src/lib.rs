@@ -1,5 +1,6 @@
 mod client;
+mod proof;
 mod signing_key;
 mod types;
 
-pub use crate::{client::*, signing_key::*, types::*};
+pub use crate::{client::*, proof::*, signing_key::*, types::*};
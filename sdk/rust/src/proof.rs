@@ -0,0 +1,54 @@
+use {
+    anyhow::{anyhow, bail, ensure},
+    grug::{hash, query_storage_key, to_borsh_vec, Account, Hash, QueryRequest, QueryResponse},
+    grug_jmt::{verify_proof, Proof},
+    tendermint::block::Header,
+};
+
+/// Verify the Merkle proof of a query response, as returned by
+/// `Client::query_app_with_proof`, against the app hash in a CometBFT block
+/// header.
+///
+/// An empty response (`None`) is verified against a non-membership proof.
+///
+/// Note that the app hash in the header of block `N + 1` is the root hash of
+/// the state _after_ block `N` is finalized. To verify a query performed at
+/// height `N`, provide the header of block `N + 1`.
+pub fn verify_query_proof(
+    header: &Header,
+    req: &QueryRequest,
+    res: Option<&QueryResponse>,
+    proof: &Proof,
+) -> anyhow::Result<()> {
+    let root_hash = Hash::try_from(header.app_hash.as_bytes())?;
+    let key = query_storage_key(req).ok_or_else(|| anyhow!("query can't be Merkle proved"))?;
+
+    // recover the raw value stored under the key from the response. we also
+    // make sure the response is indeed for the request, so that a malicious
+    // node can't trick us into verifying a proof of a different key.
+    let value = match (req, res) {
+        // the key doesn't exist
+        (_, None) => None,
+        (QueryRequest::Code { hash: code_hash }, Some(QueryResponse::Code(code))) => {
+            ensure!(hash(code) == *code_hash, "code doesn't match the hash");
+            Some(to_borsh_vec(&code.to_vec())?)
+        },
+        (QueryRequest::Account { address }, Some(QueryResponse::Account(account))) => {
+            ensure!(account.address == *address, "account address mismatch");
+            Some(to_borsh_vec(&Account {
+                code_hash: account.code_hash.clone(),
+                admin: account.admin.clone(),
+            })?)
+        },
+        (QueryRequest::WasmRaw { contract, key }, Some(QueryResponse::WasmRaw(res))) => {
+            ensure!(res.contract == *contract, "contract address mismatch");
+            ensure!(res.key == *key, "storage key mismatch");
+            res.value.as_ref().map(|value| value.to_vec())
+        },
+        _ => bail!("query response doesn't match the request"),
+    };
+
+    verify_proof(&root_hash, &hash(&key), value.map(hash).as_ref(), proof)?;
+
+    Ok(())
+}